@@ -0,0 +1,65 @@
+// Times Mat::dot against the plain loop it replaced on layer sizes from small to
+// MNIST-sized, run with
+//
+//     cargo run --release --example matmul
+//
+// (add `--features blas` to time the OpenBLAS path for the big ones)
+use std::time::Instant;
+
+use framework::{rand_float, seeded_rng, Mat};
+
+// Multiplications per size, a forward pass does one per layer and sample
+const REPEATS: usize = 2000;
+
+fn random(rows: usize, cols: usize) -> Mat {
+    let mut rng = seeded_rng(0);
+    Mat {
+        rows,
+        cols,
+        data: (0..rows)
+            .map(|_| (0..cols).map(|_| rand_float(&mut rng, -1.0, 1.0)).collect())
+            .collect(),
+    }
+}
+
+fn time(dot: fn(&mut Mat, &Mat, &Mat), dst: &mut Mat, a: &Mat, b: &Mat) -> f64 {
+    let start = Instant::now();
+    for _ in 0..REPEATS {
+        dot(dst, a, b);
+    }
+    start.elapsed().as_secs_f64() * 1e6 / REPEATS as f64
+}
+
+fn main() {
+    println!(
+        "{:>10} {:>12} {:>12} {:>8}",
+        "layer", "scalar (us)", "simd (us)", "speedup"
+    );
+    for (inputs, outputs) in [
+        (2, 4),
+        (16, 16),
+        (64, 64),
+        (256, 256),
+        (784, 256),
+        (784, 512),
+    ] {
+        // one sample times the weights, like NN::forward
+        let a = random(1, inputs);
+        let b = random(inputs, outputs);
+        let mut dst = Mat {
+            rows: 1,
+            cols: outputs,
+            data: vec![vec![0.0; outputs]],
+        };
+
+        let scalar = time(Mat::dot_scalar, &mut dst, &a, &b);
+        let simd = time(Mat::dot, &mut dst, &a, &b);
+        println!(
+            "{:>10} {:>12.2} {:>12.2} {:>7.1}x",
+            format!("{}x{}", inputs, outputs),
+            scalar,
+            simd,
+            scalar / simd
+        );
+    }
+}
@@ -0,0 +1,87 @@
+// Trains a small convolutional network on MNIST and prints the learned filters (the
+// visualizer only trains fully connected networks), run from the repository root with
+//
+//     cargo run --release -p framework --example mnist_cnn [images] [labels]
+//
+// (the paths default to the files in datasets/, the same the visualizer reads)
+use std::env;
+
+use framework::{
+    argmax, batches, seeded_rng, Activation, Conv2D, Dataset, Dense, Float, Init, Loss, MaxPool2D,
+    Sequential,
+};
+
+const SAMPLES: usize = 1000;
+const EPOCHS: usize = 10;
+const BATCH_SIZE: usize = 32;
+const LEARNING_RATE: Float = 0.5;
+const FILTERS: usize = 4;
+const KERNEL: usize = 5;
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let images = args
+        .next()
+        .unwrap_or("datasets/train-images-idx3-ubyte".to_owned());
+    let labels = args
+        .next()
+        .unwrap_or("datasets/train-labels-idx1-ubyte".to_owned());
+    let dataset = match Dataset::load_mnist(&images, &labels, SAMPLES) {
+        Ok(dataset) => dataset,
+        Err(e) => {
+            println!("Loading MNIST from {} failed: {}", images, e);
+            return;
+        }
+    };
+    let (t_input, t_output) = (dataset.t_input, dataset.t_output);
+    let mut rng = seeded_rng(0);
+
+    // 28x28 -> 4 maps of 24x24 -> pooled to 12x12 -> 10 digits
+    let mut conv = Conv2D::new(1, FILTERS, KERNEL, 1, (28, 28), Activation::Relu);
+    Conv2D::init(&mut conv, Init::Auto, &mut rng);
+    let pool = MaxPool2D::new(FILTERS, 2, Conv2D::output_size(&conv));
+    let mut dense = Dense::new(MaxPool2D::outputs(&pool), 10, Activation::Sigmoid);
+    Dense::init(&mut dense, Init::Auto, &mut rng);
+
+    let mut model = Sequential::new(Loss::CrossEntropy);
+    Sequential::push(&mut model, conv);
+    Sequential::push(&mut model, pool);
+    Sequential::push(&mut model, dense);
+
+    for epoch in 1..=EPOCHS {
+        for (x, y) in batches(&t_input, &t_output, BATCH_SIZE, &mut rng) {
+            Sequential::backprop(&mut model, &x, &y, &mut rng);
+            Sequential::learn(&mut model, LEARNING_RATE);
+        }
+        let output = Sequential::predict(&mut model, &t_input, &mut rng);
+        let correct = (0..t_input.rows)
+            .filter(|&i| argmax(&output.data[i]) == argmax(&t_output.data[i]))
+            .count();
+        println!(
+            "Epoch {}: cost {}, accuracy {:.1}%",
+            epoch,
+            Sequential::cost(&mut model, &t_input, &t_output, &mut rng),
+            correct as f32 * 100.0 / t_input.rows as f32
+        );
+    }
+
+    // the filters from most negative (' ') to most positive ('@')
+    let conv = &model.layers[0].params()[0];
+    let max = conv
+        .data
+        .iter()
+        .flatten()
+        .fold(0.0, |max: Float, w| max.max(w.abs()));
+    let shades = [' ', '.', ':', '-', '=', '+', '*', '#', '@'];
+    for y in 0..KERNEL {
+        let mut line = String::new();
+        for f in 0..FILTERS {
+            for x in 0..KERNEL {
+                let w = conv.data[y * KERNEL + x][f] / max;
+                line.push(shades[((w + 1.0) / 2.0 * (shades.len() - 1) as Float).round() as usize]);
+            }
+            line += "   ";
+        }
+        println!("{}", line);
+    }
+}
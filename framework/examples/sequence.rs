@@ -0,0 +1,45 @@
+// Teaches an Elman network to continue a repeating bit pattern and prints what it
+// predicts from its own outputs afterwards, run with
+//
+//     cargo run --release -p framework --example sequence
+use framework::{seeded_rng, Float, Init, Mat, Rnn, Sequence};
+
+const PATTERN: &str = "1001101";
+const STEPS: usize = 70;
+const HIDDEN: usize = 8;
+// truncated backprop through time window
+const WINDOW: usize = 10;
+const EPOCHS: usize = 3000;
+const LEARNING_RATE: Float = 2.0;
+
+fn main() {
+    let pattern: Vec<bool> = PATTERN.chars().map(|c| c == '1').collect();
+    let seq = Sequence::repeating(&pattern, STEPS);
+    let mut rnn = Rnn::new(1, HIDDEN, 1);
+    Rnn::init(&mut rnn, Init::Auto, &mut seeded_rng(0));
+
+    for epoch in 0..=EPOCHS {
+        Rnn::backprop(&mut rnn, &seq, WINDOW);
+        Rnn::learn(&mut rnn, LEARNING_RATE);
+        if epoch % 500 == 0 {
+            println!("Epoch {}: cost {}", epoch, Rnn::cost(&rnn, &seq));
+        }
+    }
+
+    // prime the state with one period, then feed the network its own guesses
+    Rnn::reset(&mut rnn);
+    let mut bit = Mat::new(&[&[0.0]]);
+    let mut y = bit.clone();
+    for &b in &pattern {
+        bit.data[0][0] = if b { 1.0 } else { 0.0 };
+        y = Rnn::step(&mut rnn, &bit);
+    }
+    let mut bits = String::new();
+    for _ in 0..STEPS {
+        bit.data[0][0] = y.data[0][0].round();
+        bits.push(if bit.data[0][0] > 0.5 { '1' } else { '0' });
+        y = Rnn::step(&mut rnn, &bit);
+    }
+    println!("Pattern   {}", PATTERN.repeat(STEPS / PATTERN.len() + 1));
+    println!("Generated {}", bits);
+}
@@ -0,0 +1,161 @@
+use std::sync::{Arc, RwLock};
+
+use serde::{Deserialize, Serialize};
+
+use super::{sigmoidf, tanhf, Float};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(into = "Named", try_from = "Named")]
+pub enum Activation {
+    Sigmoid,
+    Tanh,
+    Relu,
+    // One of the functions added with Activation::register, by registration order
+    Custom(usize),
+}
+
+// How an Activation is saved, custom ones by the name they were registered under so
+// a model loads again in a program that registers them in another order
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "Activation")]
+enum Named {
+    Sigmoid,
+    Tanh,
+    Relu,
+    Custom(String),
+}
+
+type Function = Arc<dyn Fn(Float) -> Float + Send + Sync>;
+
+// (name, f, f') of the registered activations
+static CUSTOM: RwLock<Vec<(String, Function, Function)>> = RwLock::new(Vec::new());
+
+impl From<Activation> for Named {
+    fn from(act: Activation) -> Named {
+        match act {
+            Activation::Sigmoid => Named::Sigmoid,
+            Activation::Tanh => Named::Tanh,
+            Activation::Relu => Named::Relu,
+            Activation::Custom(_) => Named::Custom(Activation::name(act)),
+        }
+    }
+}
+
+impl TryFrom<Named> for Activation {
+    type Error = String;
+
+    fn try_from(named: Named) -> Result<Activation, String> {
+        match named {
+            Named::Sigmoid => Ok(Activation::Sigmoid),
+            Named::Tanh => Ok(Activation::Tanh),
+            Named::Relu => Ok(Activation::Relu),
+            Named::Custom(name) => Activation::from_name(&name).ok_or_else(|| {
+                format!(
+                    "activation `{}` isn't registered, see Activation::register",
+                    name
+                )
+            }),
+        }
+    }
+}
+
+impl Activation {
+    // Makes `f` usable as an activation anywhere a built-in one is, e.g.
+    //
+    //     let softplus = Activation::register("softplus", |x| x.exp().ln_1p(), |a| 1.0 - (-a).exp());
+    //     NNBuilder::new().input(2).dense(8, softplus)
+    //
+    // `deriv` gets the activation's output like Activation::deriv, backprop doesn't
+    // keep the inputs. Registering a name again replaces its functions.
+    pub fn register(
+        name: &str,
+        f: impl Fn(Float) -> Float + Send + Sync + 'static,
+        deriv: impl Fn(Float) -> Float + Send + Sync + 'static,
+    ) -> Activation {
+        let mut custom = CUSTOM.write().unwrap();
+        let entry = (
+            name.to_owned(),
+            Arc::new(f) as Function,
+            Arc::new(deriv) as Function,
+        );
+        match custom.iter().position(|(n, _, _)| n == name) {
+            Some(i) => {
+                custom[i] = entry;
+                Activation::Custom(i)
+            }
+            None => {
+                custom.push(entry);
+                Activation::Custom(custom.len() - 1)
+            }
+        }
+    }
+
+    // sigmoid, tanh, relu or a registered name
+    pub fn from_name(name: &str) -> Option<Activation> {
+        match name {
+            "sigmoid" => Some(Activation::Sigmoid),
+            "tanh" => Some(Activation::Tanh),
+            "relu" => Some(Activation::Relu),
+            _ => CUSTOM
+                .read()
+                .unwrap()
+                .iter()
+                .position(|(n, _, _)| n == name)
+                .map(Activation::Custom),
+        }
+    }
+
+    pub fn name(act: Activation) -> String {
+        match act {
+            Activation::Sigmoid => "sigmoid".to_owned(),
+            Activation::Tanh => "tanh".to_owned(),
+            Activation::Relu => "relu".to_owned(),
+            Activation::Custom(i) => CUSTOM.read().unwrap()[i].0.clone(),
+        }
+    }
+
+    pub fn apply(act: Activation, x: Float) -> Float {
+        match act {
+            Activation::Sigmoid => sigmoidf(x),
+            Activation::Tanh => tanhf(x),
+            Activation::Relu => x.max(0.0),
+            Activation::Custom(i) => (CUSTOM.read().unwrap()[i].1)(x),
+        }
+    }
+
+    // Derivative expressed through the activation's output `a`,
+    // which is what backprop has at hand
+    pub fn deriv(act: Activation, a: Float) -> Float {
+        match act {
+            Activation::Sigmoid => a * (1.0 - a),
+            Activation::Tanh => 1.0 - a * a,
+            Activation::Relu => {
+                if a > 0.0 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Activation::Custom(i) => (CUSTOM.read().unwrap()[i].2)(a),
+        }
+    }
+}
+
+// The [7/6] continued fraction of tanh, from -5 to 5 and clamped to -1..1: within
+// 1e-4 of tanh(x) for every x (the most is where it gets clamped, around 5), with
+// half as much for a sigmoid through it. A division and a few multiplies instead of
+// an exp, see set_fast_activations.
+#[cfg(feature = "fast-activations")]
+pub(crate) fn fast_tanh(x: Float) -> Float {
+    let x = x.clamp(-5.0, 5.0);
+    let x2 = x * x;
+    let p = x * (135135.0 + x2 * (17325.0 + x2 * (378.0 + x2)));
+    let q = 135135.0 + x2 * (62370.0 + x2 * (3150.0 + x2 * 28.0));
+    (p / q).clamp(-1.0, 1.0)
+}
+
+// sigmoid(x) = (1 + tanh(x / 2)) / 2, within 5e-5
+#[cfg(feature = "fast-activations")]
+pub(crate) fn fast_sigmoid(x: Float) -> Float {
+    0.5 + 0.5 * fast_tanh(0.5 * x)
+}
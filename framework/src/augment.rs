@@ -0,0 +1,55 @@
+use rand::Rng;
+
+use super::{init::rand_normal, Float, Mat, StdRng};
+
+// Random changes to the training inputs, drawn anew every epoch so the network never
+// sees exactly the same sample twice and can't fit the noise in them
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Augment {
+    // standard deviation of the gaussian noise added to every input, in the inputs'
+    // own units (before Normalization), 0.0 adds none
+    pub noise: Float,
+    // (width, height) when every sample is an image, row by row
+    pub image: Option<(usize, usize)>,
+    // images move by up to this many pixels in x and in y, filling in with 0
+    pub shift: usize,
+}
+
+impl Augment {
+    pub fn is_off(aug: &Augment) -> bool {
+        aug.noise == 0.0 && (aug.image.is_none() || aug.shift == 0)
+    }
+
+    // A changed copy of the samples in `input`, one per row
+    pub fn apply(aug: &Augment, input: &Mat, rng: &mut StdRng) -> Mat {
+        let mut out = input.clone();
+        if let (Some((width, height)), true) = (aug.image, aug.shift > 0) {
+            assert_eq!(input.cols, width * height, "image size mismatch");
+            let shift = aug.shift as isize;
+            for row in &mut out.data {
+                let (dx, dy) = (rng.gen_range(-shift..=shift), rng.gen_range(-shift..=shift));
+                *row = shifted(row, width, height, dx, dy);
+            }
+        }
+        if aug.noise > 0.0 {
+            for value in out.data.iter_mut().flatten() {
+                *value += rand_normal(rng) * aug.noise;
+            }
+        }
+        out
+    }
+}
+
+// The picture moved right by dx and down by dy pixels
+fn shifted(pixels: &[Float], width: usize, height: usize, dx: isize, dy: isize) -> Vec<Float> {
+    let mut out = vec![0.0; pixels.len()];
+    for y in 0..height {
+        for x in 0..width {
+            let (from_x, from_y) = (x as isize - dx, y as isize - dy);
+            if (0..width as isize).contains(&from_x) && (0..height as isize).contains(&from_y) {
+                out[y * width + x] = pixels[from_y as usize * width + from_x as usize];
+            }
+        }
+    }
+    out
+}
@@ -0,0 +1,25 @@
+// Where NN::backprop computes the gradient, see set_backend
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Backend {
+    #[default]
+    Cpu,
+    // wgpu compute shaders, with the `gpu` feature
+    Gpu,
+}
+
+impl Backend {
+    pub fn from_name(name: &str) -> Option<Backend> {
+        match name {
+            "cpu" => Some(Backend::Cpu),
+            "gpu" => Some(Backend::Gpu),
+            _ => None,
+        }
+    }
+
+    pub fn name(backend: Backend) -> &'static str {
+        match backend {
+            Backend::Cpu => "cpu",
+            Backend::Gpu => "gpu",
+        }
+    }
+}
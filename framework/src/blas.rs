@@ -0,0 +1,63 @@
+use std::os::raw::c_int;
+
+use super::{Float, Mat};
+
+// Mat::dot through the system's OpenBLAS, built with `--features blas`. The rows
+// are copied into one buffer for every call, so it only pays off for big layers
+// and batches, smaller products stay on the SIMD loop.
+
+const ROW_MAJOR: c_int = 101;
+const NO_TRANS: c_int = 111;
+
+// Multiply-adds from which BLAS is used
+pub(crate) const MIN_WORK: usize = 128 * 128;
+
+#[link(name = "openblas")]
+extern "C" {
+    #[cfg_attr(not(feature = "f64"), link_name = "cblas_sgemm")]
+    #[cfg_attr(feature = "f64", link_name = "cblas_dgemm")]
+    fn gemm(
+        layout: c_int,
+        trans_a: c_int,
+        trans_b: c_int,
+        m: c_int,
+        n: c_int,
+        k: c_int,
+        alpha: Float,
+        a: *const Float,
+        lda: c_int,
+        b: *const Float,
+        ldb: c_int,
+        beta: Float,
+        c: *mut Float,
+        ldc: c_int,
+    );
+}
+
+pub(crate) fn dot(dst: &mut Mat, a: &Mat, b: &Mat) {
+    let a_flat = a.data.concat();
+    let b_flat = b.data.concat();
+    let mut c = vec![0.0; dst.rows * dst.cols];
+    // SAFETY: the buffers are row-major m x k, k x n and m x n like the sizes say
+    unsafe {
+        gemm(
+            ROW_MAJOR,
+            NO_TRANS,
+            NO_TRANS,
+            a.rows as c_int,
+            b.cols as c_int,
+            a.cols as c_int,
+            1.0,
+            a_flat.as_ptr(),
+            a.cols as c_int,
+            b_flat.as_ptr(),
+            b.cols as c_int,
+            0.0,
+            c.as_mut_ptr(),
+            b.cols as c_int,
+        );
+    }
+    for (row, values) in dst.data.iter_mut().zip(c.chunks(dst.cols)) {
+        row.copy_from_slice(values);
+    }
+}
@@ -0,0 +1,100 @@
+use super::{Activation, Float, Loss, Normalization, Optimizer, Regularization, NN};
+
+// Fluent construction of a network and its optimizer:
+//   NNBuilder::new().input(2).dense(4, Activation::Tanh).dense(1, Activation::Sigmoid)
+//       .loss(Loss::Mse).optimizer(Optimizer::adam(0.01)).build()
+// The weights are zero like NN::new, initialize them with Init::apply.
+#[derive(Clone, Debug)]
+pub struct NNBuilder {
+    arch: Vec<usize>,
+    act: Vec<Activation>,
+    dropout: Vec<Float>,
+    loss: Loss,
+    reg: Regularization,
+    norm: Normalization,
+    optimizer: Optimizer,
+}
+
+impl Default for NNBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NNBuilder {
+    pub fn new() -> NNBuilder {
+        NNBuilder {
+            arch: Vec::new(),
+            act: Vec::new(),
+            dropout: Vec::new(),
+            loss: Loss::default(),
+            reg: Regularization::default(),
+            norm: Normalization::default(),
+            optimizer: Optimizer::sgd(0.1),
+        }
+    }
+
+    pub fn input(mut self, size: usize) -> NNBuilder {
+        assert!(
+            self.arch.is_empty(),
+            "input() has to come first and only once"
+        );
+        self.arch.push(size);
+        self.dropout.push(0.0);
+        self
+    }
+
+    pub fn dense(mut self, size: usize, act: Activation) -> NNBuilder {
+        assert!(!self.arch.is_empty(), "dense() before input()");
+        self.arch.push(size);
+        self.act.push(act);
+        self.dropout.push(0.0);
+        self
+    }
+
+    // Dropout of the last dense layer added, which must not end up as the output one
+    pub fn dropout(mut self, p: Float) -> NNBuilder {
+        assert!((0.0..1.0).contains(&p));
+        assert!(self.arch.len() > 1, "dropout() before the first dense()");
+        *self.dropout.last_mut().unwrap() = p;
+        self
+    }
+
+    pub fn loss(mut self, loss: Loss) -> NNBuilder {
+        self.loss = loss;
+        self
+    }
+
+    pub fn regularization(mut self, reg: Regularization) -> NNBuilder {
+        self.reg = reg;
+        self
+    }
+
+    pub fn normalization(mut self, norm: Normalization) -> NNBuilder {
+        self.norm = norm;
+        self
+    }
+
+    pub fn optimizer(mut self, optimizer: Optimizer) -> NNBuilder {
+        self.optimizer = optimizer;
+        self
+    }
+
+    pub fn build(self) -> (NN, Optimizer) {
+        assert!(
+            self.arch.len() > 1,
+            "a network needs an input and a dense layer"
+        );
+        assert!(
+            *self.dropout.last().unwrap() == 0.0,
+            "the output layer can't have dropout"
+        );
+        let mut nn = NN::new(&self.arch);
+        nn.act = self.act;
+        nn.dropout = self.dropout;
+        nn.loss = self.loss;
+        nn.reg = self.reg;
+        nn.norm = self.norm;
+        (nn, self.optimizer)
+    }
+}
@@ -0,0 +1,106 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{io::read, io::write, Float, OptimizerState, NN};
+
+// A snapshot of the network with the epoch and cost it was taken at
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub epoch: usize,
+    pub cost: Float,
+    pub nn: NN,
+}
+
+impl Checkpoint {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Checkpoint> {
+        read(path.as_ref())
+    }
+}
+
+// What NN::save leaves out to continue training later: how far it got, the
+// optimizer's accumulated state and the cost history
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TrainingState {
+    // epochs trained so far, training resumes with this one
+    pub epoch: usize,
+    pub optimizer: OptimizerState,
+    // (epoch, cost per sample) on the training and on the validation samples
+    pub history: Vec<(usize, Float)>,
+    pub validation: Vec<(usize, Float)>,
+}
+
+impl TrainingState {
+    // Saved next to the model: model.json -> model.state.json, model.bin -> model.state.bin
+    pub fn path(model: impl AsRef<Path>) -> PathBuf {
+        let model = model.as_ref();
+        let ext = model
+            .extension()
+            .map_or("bin".into(), |ext| ext.to_string_lossy());
+        model.with_extension(format!("state.{}", ext))
+    }
+
+    pub fn save(state: &TrainingState, path: impl AsRef<Path>) -> io::Result<()> {
+        write(state, path.as_ref())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<TrainingState> {
+        read(path.as_ref())
+    }
+}
+
+// Every `every` epochs writes dir/epoch_<epoch>.<ext>, and with keep_best also
+// dir/best.<ext> whenever that snapshot has the lowest cost seen so far.
+#[derive(Clone, Debug)]
+pub struct Checkpointer {
+    pub dir: PathBuf,
+    pub every: usize,
+    pub keep_best: bool,
+    // "json" or "bin", see NN::save
+    pub ext: String,
+    best: Float,
+}
+
+impl Checkpointer {
+    pub fn new(dir: impl Into<PathBuf>, every: usize, keep_best: bool) -> Checkpointer {
+        Checkpointer {
+            dir: dir.into(),
+            every,
+            keep_best,
+            ext: "bin".to_owned(),
+            best: Float::INFINITY,
+        }
+    }
+
+    // Returns the paths written, if any
+    pub fn update(
+        cp: &mut Checkpointer,
+        nn: &NN,
+        epoch: usize,
+        cost: Float,
+    ) -> io::Result<Vec<PathBuf>> {
+        if cp.every == 0 || !epoch.is_multiple_of(cp.every) {
+            return Ok(vec![]);
+        }
+
+        fs::create_dir_all(&cp.dir)?;
+        let checkpoint = Checkpoint {
+            epoch,
+            cost,
+            nn: nn.clone(),
+        };
+
+        let mut written = vec![cp.dir.join(format!("epoch_{:06}.{}", epoch, cp.ext))];
+        if cp.keep_best && cost < cp.best {
+            cp.best = cost;
+            written.push(cp.dir.join(format!("best.{}", cp.ext)));
+        }
+        for path in &written {
+            write(&checkpoint, path)?;
+        }
+        Ok(written)
+    }
+}
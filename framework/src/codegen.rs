@@ -0,0 +1,112 @@
+use std::{fmt::Write, fs, io, path::Path};
+
+use super::{Activation, Float, NN};
+
+// Custom activations are closures, there is no source to write for them
+pub(crate) fn fn_body(act: Activation) -> io::Result<String> {
+    match act {
+        Activation::Sigmoid => Ok("1.0 / (1.0 + (-x).exp())".to_owned()),
+        Activation::Tanh => Ok("x.tanh()".to_owned()),
+        Activation::Relu => Ok("x.max(0.0)".to_owned()),
+        Activation::Custom(_) => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "the custom activation `{}` is a closure, it can't be written as Rust",
+                Activation::name(act)
+            ),
+        )),
+    }
+}
+
+pub(crate) fn floats(values: &[Float]) -> String {
+    let values: Vec<String> = values.iter().map(|v| format!("{:?}", v)).collect();
+    format!("[{}]", values.join(", "))
+}
+
+impl NN {
+    // Standalone Rust source with the weights as consts and a `forward` function,
+    // without any dependency on this crate. Fails for custom activations.
+    pub fn to_rust(nn: &NN) -> io::Result<String> {
+        let nn = &NN::fold_normalization(nn);
+        let arch = NN::arch(nn);
+        let mut src = String::new();
+
+        writeln!(src, "// Generated by nn-rust, architecture {:?}", arch).unwrap();
+        writeln!(src).unwrap();
+
+        for l in 0..nn.count - 1 {
+            let (rows, cols) = (nn.weights[l].rows, nn.weights[l].cols);
+            writeln!(src, "pub const W{}: [[f32; {}]; {}] = [", l, cols, rows).unwrap();
+            for row in &nn.weights[l].data {
+                writeln!(src, "    {},", floats(row)).unwrap();
+            }
+            writeln!(src, "];").unwrap();
+            writeln!(
+                src,
+                "pub const B{}: [f32; {}] = {};",
+                l,
+                cols,
+                floats(&nn.biases[l].data[0])
+            )
+            .unwrap();
+            writeln!(src).unwrap();
+        }
+
+        writeln!(
+            src,
+            "pub fn forward(input: [f32; {}]) -> [f32; {}] {{",
+            arch[0],
+            arch[nn.count - 1]
+        )
+        .unwrap();
+        writeln!(src, "    let a0 = input;").unwrap();
+        for l in 0..nn.count - 1 {
+            let act = Activation::name(nn.act[l]);
+            let call = format!("layer(&a{}, &W{}, &B{}, {})", l, l, l, act);
+            if l + 1 < nn.count - 1 {
+                writeln!(src, "    let a{} = {};", l + 1, call).unwrap();
+            } else {
+                writeln!(src, "    {}", call).unwrap();
+            }
+        }
+        writeln!(src, "}}").unwrap();
+        writeln!(src).unwrap();
+
+        src.push_str(
+            "fn layer<const I: usize, const O: usize>(
+    input: &[f32; I],
+    w: &[[f32; O]; I],
+    b: &[f32; O],
+    act: fn(f32) -> f32,
+) -> [f32; O] {
+    let mut out = *b;
+    for (i, x) in input.iter().enumerate() {
+        for (o, val) in out.iter_mut().enumerate() {
+            *val += x * w[i][o];
+        }
+    }
+    out.map(act)
+}
+",
+        );
+
+        // only the activations that are used, so the output has no dead code
+        let mut emitted = vec![];
+        for &act in &nn.act {
+            if emitted.contains(&act) {
+                continue;
+            }
+            emitted.push(act);
+            writeln!(src).unwrap();
+            writeln!(src, "fn {}(x: f32) -> f32 {{", Activation::name(act)).unwrap();
+            writeln!(src, "    {}", fn_body(act)?).unwrap();
+            writeln!(src, "}}").unwrap();
+        }
+
+        Ok(src)
+    }
+
+    pub fn export_rust(nn: &NN, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, Self::to_rust(nn)?)
+    }
+}
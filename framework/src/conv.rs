@@ -0,0 +1,253 @@
+use super::{init, layer::zeros, Activation, Init, Layer, Mat, StdRng};
+
+// Image layers for Sequential. A sample is still one row, holding the channels one
+// after another and each channel row by row: index = (c * height + y) * width + x.
+
+// 2D convolution without padding, output = act(filters * input + bias) per channel
+pub struct Conv2D {
+    // one column per output channel, with the in_channels x kernel x kernel inputs
+    // of the filter as rows (ordered like a sample)
+    pub weights: Mat,
+    pub biases: Mat,
+    pub act: Activation,
+    pub in_channels: usize,
+    pub out_channels: usize,
+    pub kernel: usize,
+    pub stride: usize,
+    // (width, height) of the input image
+    pub size: (usize, usize),
+    gw: Mat,
+    gb: Mat,
+    input: Mat,
+    output: Mat,
+}
+
+impl Conv2D {
+    // Zero filters, initialize them with Conv2D::init
+    pub fn new(
+        in_channels: usize,
+        out_channels: usize,
+        kernel: usize,
+        stride: usize,
+        size: (usize, usize),
+        act: Activation,
+    ) -> Conv2D {
+        assert!(kernel <= size.0 && kernel <= size.1 && stride > 0);
+        let inputs = in_channels * kernel * kernel;
+        Conv2D {
+            weights: zeros(inputs, out_channels),
+            biases: zeros(1, out_channels),
+            act,
+            in_channels,
+            out_channels,
+            kernel,
+            stride,
+            size,
+            gw: zeros(inputs, out_channels),
+            gb: zeros(1, out_channels),
+            input: zeros(0, 0),
+            output: zeros(0, 0),
+        }
+    }
+
+    pub fn init(layer: &mut Conv2D, init: Init, rng: &mut StdRng) {
+        let init = match init {
+            Init::Auto => Init::for_activation(layer.act),
+            init => init,
+        };
+        init::init_layer(init, &mut layer.weights, rng);
+        Mat::fill(&mut layer.biases, 0.0);
+    }
+
+    // (width, height) of every output channel
+    pub fn output_size(layer: &Conv2D) -> (usize, usize) {
+        let (w, h) = layer.size;
+        (
+            (w - layer.kernel) / layer.stride + 1,
+            (h - layer.kernel) / layer.stride + 1,
+        )
+    }
+
+    // Number of outputs per sample, the input size of the next layer
+    pub fn outputs(layer: &Conv2D) -> usize {
+        let (w, h) = Self::output_size(layer);
+        layer.out_channels * w * h
+    }
+
+    // The kernel x kernel weights one output channel applies to one input channel,
+    // e.g. to show the learned filters (the visualizer doesn't, it only trains NN)
+    pub fn filter(layer: &Conv2D, out_channel: usize, in_channel: usize) -> Mat {
+        let k = layer.kernel;
+        Mat {
+            rows: k,
+            cols: k,
+            data: (0..k)
+                .map(|y| {
+                    (0..k)
+                        .map(|x| layer.weights.data[(in_channel * k + y) * k + x][out_channel])
+                        .collect()
+                })
+                .collect(),
+        }
+    }
+
+    // Calls f(output index, input index, weight row) for every weight of every output
+    fn each_tap(layer: &Conv2D, mut f: impl FnMut(usize, usize, usize)) {
+        let (w, h) = layer.size;
+        let (ow, oh) = Self::output_size(layer);
+        let k = layer.kernel;
+        for oc in 0..layer.out_channels {
+            for oy in 0..oh {
+                for ox in 0..ow {
+                    let out = (oc * oh + oy) * ow + ox;
+                    for ic in 0..layer.in_channels {
+                        for ky in 0..k {
+                            for kx in 0..k {
+                                let y = oy * layer.stride + ky;
+                                let x = ox * layer.stride + kx;
+                                f(out, (ic * h + y) * w + x, (ic * k + ky) * k + kx);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn channel(layer: &Conv2D, out: usize) -> usize {
+        let (ow, oh) = Self::output_size(layer);
+        out / (ow * oh)
+    }
+}
+
+impl Layer for Conv2D {
+    fn forward(&mut self, input: &Mat, train: bool, _rng: &mut StdRng) -> Mat {
+        let (w, h) = self.size;
+        assert_eq!(input.cols, self.in_channels * w * h);
+        let mut output = zeros(input.rows, Self::outputs(self));
+        for (row, out_row) in input.data.iter().zip(&mut output.data) {
+            Self::each_tap(self, |out, i, r| {
+                out_row[out] += row[i] * self.weights.data[r][Self::channel(self, out)];
+            });
+            for (out, val) in out_row.iter_mut().enumerate() {
+                let b = self.biases.data[0][Self::channel(self, out)];
+                *val = Activation::apply(self.act, *val + b);
+            }
+        }
+        if train {
+            self.input = input.clone();
+            self.output = output.clone();
+        }
+        output
+    }
+
+    fn backward(&mut self, grad: &Mat) -> Mat {
+        let mut grad_input = zeros(grad.rows, self.input.cols);
+        let mut gw = std::mem::replace(&mut self.gw, zeros(0, 0));
+        for s in 0..grad.rows {
+            let delta: Vec<_> = (0..grad.cols)
+                .map(|out| {
+                    grad.data[s][out] * Activation::deriv(self.act, self.output.data[s][out])
+                })
+                .collect();
+            for (out, d) in delta.iter().enumerate() {
+                let c = Self::channel(self, out);
+                self.gb.data[0][c] += d;
+            }
+            Self::each_tap(self, |out, i, r| {
+                let c = Self::channel(self, out);
+                gw.data[r][c] += delta[out] * self.input.data[s][i];
+                grad_input.data[s][i] += delta[out] * self.weights.data[r][c];
+            });
+        }
+        self.gw = gw;
+        grad_input
+    }
+
+    fn params(&self) -> Vec<&Mat> {
+        vec![&self.weights, &self.biases]
+    }
+
+    fn grads(&self) -> Vec<&Mat> {
+        vec![&self.gw, &self.gb]
+    }
+
+    fn update(&mut self, f: &mut dyn FnMut(&mut Mat, &mut Mat)) {
+        f(&mut self.weights, &mut self.gw);
+        f(&mut self.biases, &mut self.gb);
+    }
+}
+
+// Max over non-overlapping size x size windows of every channel, leftover
+// rows and columns at the edges are dropped
+pub struct MaxPool2D {
+    pub channels: usize,
+    pub pool: usize,
+    // (width, height) of the input image
+    pub size: (usize, usize),
+    inputs: usize,
+    // input index each output was taken from in the last training pass
+    picked: Vec<Vec<usize>>,
+}
+
+impl MaxPool2D {
+    pub fn new(channels: usize, pool: usize, size: (usize, usize)) -> MaxPool2D {
+        assert!(pool > 0 && pool <= size.0 && pool <= size.1);
+        MaxPool2D {
+            channels,
+            pool,
+            size,
+            inputs: channels * size.0 * size.1,
+            picked: Vec::new(),
+        }
+    }
+
+    pub fn output_size(layer: &MaxPool2D) -> (usize, usize) {
+        (layer.size.0 / layer.pool, layer.size.1 / layer.pool)
+    }
+
+    pub fn outputs(layer: &MaxPool2D) -> usize {
+        let (w, h) = Self::output_size(layer);
+        layer.channels * w * h
+    }
+}
+
+impl Layer for MaxPool2D {
+    fn forward(&mut self, input: &Mat, train: bool, _rng: &mut StdRng) -> Mat {
+        assert_eq!(input.cols, self.inputs);
+        let (w, h) = self.size;
+        let (ow, oh) = Self::output_size(self);
+        let p = self.pool;
+        let mut output = zeros(input.rows, Self::outputs(self));
+        let mut picked = vec![vec![0; output.cols]; input.rows];
+        for (s, row) in input.data.iter().enumerate() {
+            for c in 0..self.channels {
+                for oy in 0..oh {
+                    for ox in 0..ow {
+                        let out = (c * oh + oy) * ow + ox;
+                        let best = (0..p * p)
+                            .map(|t| (c * h + oy * p + t / p) * w + ox * p + t % p)
+                            .max_by(|a, b| row[*a].total_cmp(&row[*b]))
+                            .unwrap();
+                        output.data[s][out] = row[best];
+                        picked[s][out] = best;
+                    }
+                }
+            }
+        }
+        if train {
+            self.picked = picked;
+        }
+        output
+    }
+
+    fn backward(&mut self, grad: &Mat) -> Mat {
+        let mut grad_input = zeros(grad.rows, self.inputs);
+        for (s, row) in grad.data.iter().enumerate() {
+            for (out, val) in row.iter().enumerate() {
+                grad_input.data[s][self.picked[s][out]] += val;
+            }
+        }
+        grad_input
+    }
+}
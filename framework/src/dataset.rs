@@ -0,0 +1,203 @@
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::Path,
+};
+
+use super::{Float, Mat};
+
+// Training data with the column names it was loaded with
+#[derive(Clone, Debug)]
+pub struct Dataset {
+    pub input_names: Vec<String>,
+    pub output_names: Vec<String>,
+    pub t_input: Mat,
+    pub t_output: Mat,
+}
+
+// One row per label with a 1.0 in the label's column
+pub fn one_hot(labels: &[usize], classes: usize) -> Mat {
+    Mat {
+        rows: labels.len(),
+        cols: classes,
+        data: labels
+            .iter()
+            .map(|&label| {
+                let mut row = vec![0.0; classes];
+                row[label] = 1.0;
+                row
+            })
+            .collect(),
+    }
+}
+
+// Index of the largest value, the predicted class of a one-hot output
+pub fn argmax(values: &[Float]) -> usize {
+    let mut best = 0;
+    for (i, val) in values.iter().enumerate() {
+        if *val > values[best] {
+            best = i;
+        }
+    }
+    best
+}
+
+// The predicted class of every row of one-hot outputs, see argmax
+pub fn argmax_rows(outputs: &Mat) -> Vec<usize> {
+    outputs.data.iter().map(|row| argmax(row)).collect()
+}
+
+// Class indices for arbitrary labels and the label of each class, in numeric order
+// when every label is an integer and alphabetical otherwise. Turn the indices into
+// outputs with one_hot and predictions back with argmax_rows.
+pub fn encode_labels(labels: &[&str]) -> (Vec<usize>, Vec<String>) {
+    let mut classes: Vec<String> = labels.iter().map(|label| label.to_string()).collect();
+    if labels.iter().all(|label| label.parse::<i64>().is_ok()) {
+        classes.sort_by_key(|label| label.parse::<i64>().unwrap());
+    } else {
+        classes.sort();
+    }
+    classes.dedup();
+    let indices = labels
+        .iter()
+        .map(|label| classes.iter().position(|class| class == label).unwrap())
+        .collect();
+    (indices, classes)
+}
+
+fn invalid(line: usize, msg: String) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, format!("line {}: {}", line, msg))
+}
+
+impl Dataset {
+    // CSV with a header naming every column `in:<name>` (input) or `out:<name>` (target), e.g.
+    //
+    //     in:a,in:b,out:xor
+    //     0,0,0
+    //     0,1,1
+    //
+    // A `class:<name>` column holds labels instead, one-hot encoded into an output per
+    // label named `<name>=<label>` after the out: columns (see encode_labels), e.g.
+    //
+    //     in:length,in:width,class:species
+    //     5.1,3.5,setosa
+    //     7.0,3.2,versicolor
+    //
+    // Empty lines and lines starting with # are skipped.
+    pub fn from_csv(text: &str) -> io::Result<Dataset> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+
+        let (header_line, header) = lines
+            .next()
+            .ok_or_else(|| invalid(0, "missing header".to_owned()))?;
+
+        // true for in:, false for out:, None for the class: column
+        let mut is_input = vec![];
+        let mut input_names = vec![];
+        let mut output_names = vec![];
+        let mut class_name = None;
+        for column in header.split(',').map(str::trim) {
+            if let Some(name) = column.strip_prefix("in:") {
+                is_input.push(Some(true));
+                input_names.push(name.to_owned());
+            } else if let Some(name) = column.strip_prefix("out:") {
+                is_input.push(Some(false));
+                output_names.push(name.to_owned());
+            } else if let Some(name) = column.strip_prefix("class:") {
+                if class_name.is_some() {
+                    return Err(invalid(
+                        header_line,
+                        "only one class: column is supported".to_owned(),
+                    ));
+                }
+                is_input.push(None);
+                class_name = Some(name.to_owned());
+            } else {
+                return Err(invalid(
+                    header_line,
+                    format!("column `{}` needs an in:, out: or class: prefix", column),
+                ));
+            }
+        }
+        if input_names.is_empty() || (output_names.is_empty() && class_name.is_none()) {
+            return Err(invalid(
+                header_line,
+                "need at least one in: and one out: or class: column".to_owned(),
+            ));
+        }
+
+        let mut inputs = vec![];
+        let mut outputs = vec![];
+        let mut labels = vec![];
+        for (line_no, line) in lines {
+            let values: Vec<&str> = line.split(',').map(str::trim).collect();
+            if values.len() != is_input.len() {
+                return Err(invalid(
+                    line_no,
+                    format!("expected {} values, got {}", is_input.len(), values.len()),
+                ));
+            }
+
+            let mut input = vec![];
+            let mut output = vec![];
+            for (value, &is_input) in values.iter().zip(&is_input) {
+                let Some(is_input) = is_input else {
+                    labels.push(*value);
+                    continue;
+                };
+                let value: Float = value
+                    .parse()
+                    .map_err(|_| invalid(line_no, format!("`{}` is not a number", value)))?;
+                if is_input {
+                    input.push(value);
+                } else {
+                    output.push(value);
+                }
+            }
+            inputs.push(input);
+            outputs.push(output);
+        }
+        if inputs.is_empty() {
+            return Err(invalid(header_line, "no samples".to_owned()));
+        }
+
+        if let Some(name) = class_name {
+            let (indices, classes) = encode_labels(&labels);
+            let encoded = one_hot(&indices, classes.len());
+            for (output, row) in outputs.iter_mut().zip(encoded.data) {
+                output.extend(row);
+            }
+            output_names.extend(classes.iter().map(|class| format!("{}={}", name, class)));
+        }
+
+        Ok(Dataset {
+            t_input: Mat {
+                rows: inputs.len(),
+                cols: input_names.len(),
+                data: inputs,
+            },
+            t_output: Mat {
+                rows: outputs.len(),
+                cols: output_names.len(),
+                data: outputs,
+            },
+            input_names,
+            output_names,
+        })
+    }
+
+    pub fn load_csv(path: impl AsRef<Path>) -> io::Result<Dataset> {
+        Self::parse_csv(&fs::read(path)?)
+    }
+
+    // The bytes of a CSV file, e.g. one dropped on the browser window
+    pub fn parse_csv(bytes: &[u8]) -> io::Result<Dataset> {
+        let text =
+            std::str::from_utf8(bytes).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+        Self::from_csv(text)
+    }
+}
@@ -0,0 +1,41 @@
+// How many samples every gradient step is computed from, the batch size of batches()
+// by name: the whole training set takes one smooth step an epoch, a sample at a time
+// as many noisy ones as there are samples and mini-batches are in between
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Descent {
+    Full,
+    // shuffled batches of this many samples
+    MiniBatch(usize),
+    // stochastic gradient descent on one sample at a time
+    Online,
+}
+
+impl Descent {
+    // For batches(), where 0 is the whole training set
+    pub fn batch_size(d: Descent) -> usize {
+        match d {
+            Descent::Full => 0,
+            Descent::MiniBatch(size) => size,
+            Descent::Online => 1,
+        }
+    }
+
+    // What batches of `batch_size` amount to with this many samples
+    pub fn of(batch_size: usize, samples: usize) -> Descent {
+        match batch_size {
+            0 => Descent::Full,
+            size if size >= samples => Descent::Full,
+            1 => Descent::Online,
+            size => Descent::MiniBatch(size),
+        }
+    }
+
+    // Full batch, mini-batches of `mini`, online and full batch again
+    pub fn next(d: Descent, mini: usize) -> Descent {
+        match d {
+            Descent::Full => Descent::MiniBatch(mini),
+            Descent::MiniBatch(_) => Descent::Online,
+            Descent::Online => Descent::Full,
+        }
+    }
+}
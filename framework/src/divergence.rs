@@ -0,0 +1,43 @@
+use super::Float;
+
+// Notices the cost blowing up between two epochs, usually a learning rate too high,
+// so training can stop while the weights are still numbers
+#[derive(Clone, Debug)]
+pub struct Divergence {
+    // a cost more than this many times the last epoch's is a jump, 0.0 never jumps
+    pub factor: Float,
+    // last finite cost
+    last: Option<Float>,
+}
+
+impl Divergence {
+    pub fn new(factor: Float) -> Divergence {
+        Divergence { factor, last: None }
+    }
+
+    // Feed the cost of the current epoch, returns how many times the last one it is
+    // when it jumped, infinity for a NaN or infinite cost
+    pub fn update(d: &mut Divergence, cost: Float) -> Option<Float> {
+        if !cost.is_finite() {
+            return Some(Float::INFINITY);
+        }
+        match d.last.replace(cost) {
+            Some(last) if d.factor > 0.0 && last > 0.0 && cost > last * d.factor => {
+                Some(cost / last)
+            }
+            _ => None,
+        }
+    }
+
+    // Forgets the last cost, a rewound or loaded network starts over
+    pub fn reset(d: &mut Divergence) {
+        d.last = None;
+    }
+
+    // Index of the largest of the layers' gradient norms (a NaN one before any), the
+    // layer the jump most likely came from
+    pub fn worst_layer(norms: &[Float]) -> Option<usize> {
+        let key = |norm: Float| if norm.is_nan() { Float::INFINITY } else { norm };
+        (0..norms.len()).max_by(|&a, &b| key(norms[a]).total_cmp(&key(norms[b])))
+    }
+}
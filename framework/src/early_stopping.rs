@@ -0,0 +1,42 @@
+use super::Float;
+
+// Decides when training can stop before running out of epochs
+#[derive(Clone, Debug)]
+pub struct EarlyStopping {
+    // stop once the cost is at or below this
+    pub target_cost: Float,
+    // stop after this many epochs without the cost improving by more than min_delta,
+    // 0 disables it
+    pub patience: usize,
+    pub min_delta: Float,
+    best: Float,
+    since_best: usize,
+}
+
+impl EarlyStopping {
+    pub fn new(target_cost: Float, patience: usize, min_delta: Float) -> EarlyStopping {
+        EarlyStopping {
+            target_cost,
+            patience,
+            min_delta,
+            best: Float::INFINITY,
+            since_best: 0,
+        }
+    }
+
+    // Feed the cost of the current epoch, returns true when training should stop
+    pub fn update(es: &mut EarlyStopping, cost: Float) -> bool {
+        if cost <= es.target_cost {
+            return true;
+        }
+
+        if cost < es.best - es.min_delta {
+            es.best = cost;
+            es.since_best = 0;
+        } else {
+            es.since_best += 1;
+        }
+
+        es.patience > 0 && es.since_best >= es.patience
+    }
+}
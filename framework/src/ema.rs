@@ -0,0 +1,51 @@
+use super::{Float, NN};
+
+// Exponential moving average of a network's weights and biases, updated after every
+// optimizer step. The average of the last few hundred steps is often smoother and
+// a little better than wherever the last step happened to land.
+#[derive(Clone, Debug)]
+pub struct Ema {
+    // weight of the average so far at every update, e.g. 0.999
+    pub decay: Float,
+    // None before the first update
+    pub nn: Option<NN>,
+    updates: usize,
+}
+
+impl Ema {
+    pub fn new(decay: Float) -> Ema {
+        Ema {
+            decay,
+            nn: None,
+            updates: 0,
+        }
+    }
+
+    // Blends `nn` into the average. The decay starts low and grows towards `decay`
+    // so the first updates don't stay stuck near the initialization.
+    pub fn update(ema: &mut Ema, nn: &NN) {
+        ema.updates += 1;
+        let Some(average) = &mut ema.nn else {
+            ema.nn = Some(nn.clone());
+            return;
+        };
+        let t = ema.updates as Float;
+        let decay = ema.decay.min((1.0 + t) / (10.0 + t));
+        let params = average
+            .weights
+            .iter_mut()
+            .zip(&nn.weights)
+            .chain(average.biases.iter_mut().zip(&nn.biases));
+        for (avg, new) in params {
+            for (avg, new) in avg.data.iter_mut().flatten().zip(new.data.iter().flatten()) {
+                *avg = decay * *avg + (1.0 - decay) * new;
+            }
+        }
+    }
+
+    // Starts over from the next update, e.g. after the network was replaced
+    pub fn reset(ema: &mut Ema) {
+        ema.nn = None;
+        ema.updates = 0;
+    }
+}
@@ -0,0 +1,176 @@
+use std::{fmt, io};
+
+use super::{Dataset, Float, Mat, StdRng, NN};
+
+// Shape problems the fallible APIs (NN::try_forward, NN::try_backprop, Mat::try_new,
+// Dataset::validate) report instead of panicking somewhere inside the framework
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error {
+    Empty,
+    // a matrix row with a different number of values than the first one
+    RaggedRow {
+        row: usize,
+        expected: usize,
+        found: usize,
+    },
+    InputSize {
+        expected: usize,
+        found: usize,
+    },
+    OutputSize {
+        expected: usize,
+        found: usize,
+    },
+    // inputs and targets of a different number of samples
+    SampleCount {
+        inputs: usize,
+        outputs: usize,
+    },
+    // a gradient shaped after another architecture
+    Architecture {
+        expected: Vec<usize>,
+        found: Vec<usize>,
+    },
+    NotFinite {
+        row: usize,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Empty => write!(f, "no data"),
+            Error::RaggedRow {
+                row,
+                expected,
+                found,
+            } => write!(f, "row {} has {} values, expected {}", row, found, expected),
+            Error::InputSize { expected, found } => write!(
+                f,
+                "{} inputs given to a network with {} inputs",
+                found, expected
+            ),
+            Error::OutputSize { expected, found } => write!(
+                f,
+                "{} targets given to a network with {} outputs",
+                found, expected
+            ),
+            Error::SampleCount { inputs, outputs } => {
+                write!(f, "{} input rows but {} target rows", inputs, outputs)
+            }
+            Error::Architecture { expected, found } => {
+                write!(f, "architecture {:?}, expected {:?}", found, expected)
+            }
+            Error::NotFinite { row } => write!(f, "row {} has a NaN or infinite value", row),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<Error> for io::Error {
+    fn from(e: Error) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, e)
+    }
+}
+
+impl Mat {
+    pub fn try_new(data: &[&[Float]]) -> Result<Mat, Error> {
+        let cols = data.first().ok_or(Error::Empty)?.len();
+        if let Some(row) = data.iter().position(|row| row.len() != cols) {
+            return Err(Error::RaggedRow {
+                row,
+                expected: cols,
+                found: data[row].len(),
+            });
+        }
+        Ok(Mat::new(data))
+    }
+}
+
+impl NN {
+    // Whether t_input and t_output fit the network's input and output layers
+    pub fn check(nn: &NN, t_input: &Mat, t_output: &Mat) -> Result<(), Error> {
+        if t_input.rows != t_output.rows {
+            return Err(Error::SampleCount {
+                inputs: t_input.rows,
+                outputs: t_output.rows,
+            });
+        }
+        if t_input.cols != nn.activations[0].cols {
+            return Err(Error::InputSize {
+                expected: nn.activations[0].cols,
+                found: t_input.cols,
+            });
+        }
+        if t_output.cols != nn.activations[nn.count - 1].cols {
+            return Err(Error::OutputSize {
+                expected: nn.activations[nn.count - 1].cols,
+                found: t_output.cols,
+            });
+        }
+        Ok(())
+    }
+
+    // NN::forward on the one-row `input`, which is copied into the input layer first
+    pub fn try_forward(nn: &mut NN, input: &Mat) -> Result<(), Error> {
+        if input.rows != 1 || input.cols != nn.activations[0].cols {
+            return Err(Error::InputSize {
+                expected: nn.activations[0].cols,
+                found: input.cols * input.rows,
+            });
+        }
+        Mat::copy(&mut nn.activations[0], input);
+        Self::forward(nn);
+        Ok(())
+    }
+
+    pub fn try_backprop(
+        nn: &mut NN,
+        g: &mut NN,
+        t_input: &Mat,
+        t_output: &Mat,
+        rng: &mut StdRng,
+    ) -> Result<(), Error> {
+        Self::check(nn, t_input, t_output)?;
+        if Self::arch(g) != Self::arch(nn) {
+            return Err(Error::Architecture {
+                expected: Self::arch(nn),
+                found: Self::arch(g),
+            });
+        }
+        Self::backprop(nn, g, t_input, t_output, rng);
+        Ok(())
+    }
+}
+
+impl Dataset {
+    // Same number of samples on both sides, every row complete and no NaN/inf
+    pub fn validate(dataset: &Dataset) -> Result<(), Error> {
+        let (t_input, t_output) = (&dataset.t_input, &dataset.t_output);
+        if t_input.rows == 0 {
+            return Err(Error::Empty);
+        }
+        if t_input.rows != t_output.rows {
+            return Err(Error::SampleCount {
+                inputs: t_input.rows,
+                outputs: t_output.rows,
+            });
+        }
+        for mat in [t_input, t_output] {
+            for (row, values) in mat.data.iter().enumerate() {
+                if values.len() != mat.cols {
+                    return Err(Error::RaggedRow {
+                        row,
+                        expected: mat.cols,
+                        found: values.len(),
+                    });
+                }
+                if values.iter().any(|v| !v.is_finite()) {
+                    return Err(Error::NotFinite { row });
+                }
+            }
+        }
+        Ok(())
+    }
+}
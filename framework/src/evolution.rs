@@ -0,0 +1,115 @@
+use rand::Rng;
+
+use super::{init::rand_normal, Float, Init, Mat, StdRng, NN};
+
+// Parents are the best of this many networks picked at random
+const TOURNAMENT: usize = 3;
+
+// Training without gradients: a population of networks of the same shape, scored by
+// their cost on the training samples. Every generation keeps the `elite` best as they
+// are and replaces the rest with children of two tournament winners, each weight and
+// bias from either parent and some of them mutated with gaussian noise.
+#[derive(Clone, Debug)]
+pub struct Evolution {
+    // best first after every generation
+    pub population: Vec<NN>,
+    // sorted costs of the last generation scored, the fitness (lower is better)
+    pub costs: Vec<Float>,
+    pub elite: usize,
+    // probability of mutating each weight and bias of a child
+    pub mutation_rate: Float,
+    // standard deviation of a mutation
+    pub mutation_scale: Float,
+}
+
+impl Evolution {
+    // `nn` and `size - 1` others initialized from it with new random weights
+    pub fn new(nn: &NN, size: usize, rng: &mut StdRng) -> Evolution {
+        assert!(size >= 2, "a population needs two parents");
+        let mut population = vec![nn.clone()];
+        for _ in 1..size {
+            let mut other = nn.clone();
+            Init::apply(Init::Auto, &mut other, rng);
+            NN::zero_pruned(&mut other);
+            population.push(other);
+        }
+        Evolution {
+            population,
+            costs: vec![],
+            elite: 2,
+            mutation_rate: 0.1,
+            mutation_scale: 0.3,
+        }
+    }
+
+    // The network with the lowest cost of the last generation scored, the first one
+    // before any
+    pub fn best(evo: &Evolution) -> &NN {
+        &evo.population[0]
+    }
+
+    // Scores the population and breeds the next one, the best `elite` of the scored
+    // generation first
+    pub fn generation(evo: &mut Evolution, t_input: &Mat, t_output: &Mat, rng: &mut StdRng) {
+        let mut scored: Vec<(Float, NN)> = evo
+            .population
+            .drain(..)
+            .map(|nn| {
+                let cost = NN::cost(&nn, t_input, t_output);
+                // a diverged network is the least fit
+                let cost = if cost.is_nan() { Float::INFINITY } else { cost };
+                (cost, nn)
+            })
+            .collect();
+        scored.sort_by(|a, b| a.0.total_cmp(&b.0));
+        evo.costs = scored.iter().map(|(cost, _)| *cost).collect();
+
+        let size = scored.len();
+        let elite = evo.elite.clamp(1, size);
+        let mut next: Vec<NN> = scored[..elite].iter().map(|(_, nn)| nn.clone()).collect();
+        while next.len() < size {
+            let a = &scored[tournament(size, rng)].1;
+            let b = &scored[tournament(size, rng)].1;
+            next.push(child(evo, a, b, rng));
+        }
+        evo.population = next;
+    }
+
+    // (best, lower quartile, median, upper quartile, worst) of the last costs
+    pub fn spread(evo: &Evolution) -> [Float; 5] {
+        let costs = &evo.costs;
+        if costs.is_empty() {
+            return [Float::NAN; 5];
+        }
+        let at = |q: Float| costs[((costs.len() - 1) as Float * q).round() as usize];
+        [at(0.0), at(0.25), at(0.5), at(0.75), at(1.0)]
+    }
+}
+
+// Index of the best of TOURNAMENT random networks, the population being sorted
+fn tournament(size: usize, rng: &mut StdRng) -> usize {
+    (0..TOURNAMENT)
+        .map(|_| rng.gen_range(0..size))
+        .min()
+        .unwrap()
+}
+
+fn child(evo: &Evolution, a: &NN, b: &NN, rng: &mut StdRng) -> NN {
+    let mut child = a.clone();
+    let params = child.weights.iter_mut().chain(child.biases.iter_mut());
+    let others = b.weights.iter().chain(&b.biases);
+    for (mat, other) in params.zip(others) {
+        for (row, other) in mat.data.iter_mut().zip(&other.data) {
+            for (val, &other) in row.iter_mut().zip(other) {
+                if rng.gen_bool(0.5) {
+                    *val = other;
+                }
+                if rng.gen::<Float>() < evo.mutation_rate {
+                    *val += rand_normal(rng) * evo.mutation_scale;
+                }
+            }
+        }
+    }
+    NN::zero_pruned(&mut child);
+    child
+}
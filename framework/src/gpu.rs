@@ -0,0 +1,546 @@
+use std::{borrow::Cow, ops::Range, sync::mpsc, sync::Mutex};
+
+use wgpu::util::DeviceExt;
+
+use super::{Activation, Float, Loss, Mat, Normalization, NN};
+
+// NN::backprop through wgpu compute shaders, built with `--features gpu` and switched
+// on by set_backend(Backend::Gpu). A batch goes up as whole matrices: the layers'
+// forward passes, the output deltas, then every layer's weight and bias gradient and
+// the deltas of the layer below. The optimizer and the regularization stay on the
+// CPU, so the weights go up and the gradient comes back once a batch.
+
+// Samples in one pass, bigger batches are summed pass by pass so the buffers stay
+// under the adapter's binding limits (MNIST's training inputs alone are 188 MB)
+const ROWS: usize = 4096;
+// Threads in a workgroup, like @workgroup_size in the shader
+const WORKGROUP: usize = 64;
+// Workgroups in one dimension of a dispatch, more are spread over y
+const MAX_GROUPS: usize = 65535;
+
+// The adapter set_backend started, None on the CPU
+pub(crate) static GPU: Mutex<Option<Gpu>> = Mutex::new(None);
+
+// One thread per output value, the sums are plain loops. `a`, `b` and `c` are read,
+// `out` is written, what they hold depends on the entry point.
+const SHADER: &str = r#"
+struct Dims {
+    rows: u32,
+    inner: u32,
+    cols: u32,
+    act: u32,
+    loss: u32,
+    huber: f32,
+    n: f32,
+    // threads in a row of workgroups
+    stride: u32,
+}
+
+@group(0) @binding(0) var<uniform> dims: Dims;
+@group(0) @binding(1) var<storage, read> a: array<f32>;
+@group(0) @binding(2) var<storage, read> b: array<f32>;
+@group(0) @binding(3) var<storage, read> c: array<f32>;
+@group(0) @binding(4) var<storage, read_write> out: array<f32>;
+
+// Activation::apply and Activation::deriv
+fn activate(act: u32, x: f32) -> f32 {
+    switch act {
+        case 0u: { return 1.0 / (1.0 + exp(-x)); }
+        // tanh is exp(2x) inside, which overflows
+        case 1u: { return tanh(clamp(x, -15.0, 15.0)); }
+        default: { return max(x, 0.0); }
+    }
+}
+
+fn deriv(act: u32, a: f32) -> f32 {
+    switch act {
+        case 0u: { return a * (1.0 - a); }
+        case 1u: { return 1.0 - a * a; }
+        default: { return select(0.0, 1.0, a > 0.0); }
+    }
+}
+
+// Loss::deriv
+fn loss_deriv(a: f32, y: f32) -> f32 {
+    switch dims.loss {
+        case 0u: { return 2.0 * (a - y); }
+        case 1u: {
+            let p = clamp(a, 1e-7, 1.0 - 1e-7);
+            return (p - y) / (p * (1.0 - p));
+        }
+        case 2u: { return select(sign(a - y), 0.0, a == y); }
+        default: { return clamp(a - y, -dims.huber, dims.huber); }
+    }
+}
+
+// a: rows x inner activations, b: inner x cols weights, c: cols biases,
+// out: rows x cols activations of the next layer
+@compute @workgroup_size(64)
+fn forward(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x + id.y * dims.stride;
+    if i >= dims.rows * dims.cols {
+        return;
+    }
+    let r = i / dims.cols;
+    let j = i % dims.cols;
+    var sum = c[j];
+    for (var k = 0u; k < dims.inner; k += 1u) {
+        sum += a[r * dims.inner + k] * b[k * dims.cols + j];
+    }
+    out[i] = activate(dims.act, sum);
+}
+
+// a: rows x cols outputs, b: the targets, c: rows sample weights, out: the deltas
+@compute @workgroup_size(64)
+fn output(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x + id.y * dims.stride;
+    if i >= dims.rows * dims.cols {
+        return;
+    }
+    let r = i / dims.cols;
+    out[i] = c[r] * loss_deriv(a[i], b[i]) / dims.n * deriv(dims.act, a[i]);
+}
+
+// a: rows x inner activations, b: rows x cols deltas of the layer above, out: the
+// inner x cols weight gradient followed by the cols bias gradient
+@compute @workgroup_size(64)
+fn gradient(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x + id.y * dims.stride;
+    if i >= (dims.inner + 1u) * dims.cols {
+        return;
+    }
+    let k = i / dims.cols;
+    let j = i % dims.cols;
+    var sum = 0.0;
+    for (var r = 0u; r < dims.rows; r += 1u) {
+        let x = select(a[r * dims.inner + k], 1.0, k == dims.inner);
+        sum += x * b[r * dims.cols + j];
+    }
+    out[i] = sum;
+}
+
+// a: rows x cols deltas, b: inner x cols weights, c: rows x inner activations,
+// out: rows x inner deltas of the layer below
+@compute @workgroup_size(64)
+fn delta(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x + id.y * dims.stride;
+    if i >= dims.rows * dims.inner {
+        return;
+    }
+    let r = i / dims.inner;
+    let k = i % dims.inner;
+    var sum = 0.0;
+    for (var j = 0u; j < dims.cols; j += 1u) {
+        sum += a[r * dims.cols + j] * b[k * dims.cols + j];
+    }
+    out[i] = sum * deriv(dims.act, c[i]);
+}
+"#;
+
+pub(crate) struct Gpu {
+    // what the adapter calls itself
+    pub name: String,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    layout: wgpu::BindGroupLayout,
+    forward: wgpu::ComputePipeline,
+    output: wgpu::ComputePipeline,
+    gradient: wgpu::ComputePipeline,
+    delta: wgpu::ComputePipeline,
+    // bound where an entry point reads nothing
+    unused: wgpu::Buffer,
+}
+
+// The uniform of a dispatch
+#[derive(Clone, Copy, Default)]
+struct Dims {
+    rows: usize,
+    inner: usize,
+    cols: usize,
+    act: u32,
+    loss: u32,
+    huber: Float,
+    n: usize,
+}
+
+impl Gpu {
+    // The first adapter that runs compute shaders, blocks until it's ready
+    pub fn new() -> Result<Gpu, String> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            ..Default::default()
+        }))
+        .ok_or_else(|| "no adapter".to_owned())?;
+        let info = adapter.get_info();
+        if !adapter
+            .get_downlevel_capabilities()
+            .flags
+            .contains(wgpu::DownlevelFlags::COMPUTE_SHADERS)
+        {
+            return Err(format!("{} has no compute shaders", info.name));
+        }
+        let (device, queue) = pollster::block_on(adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                label: None,
+                required_features: wgpu::Features::empty(),
+                required_limits: adapter.limits(),
+            },
+            None,
+        ))
+        .map_err(|e| e.to_string())?;
+
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(SHADER)),
+        });
+        let entry = |binding: u32, ty: wgpu::BufferBindingType| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let read = wgpu::BufferBindingType::Storage { read_only: true };
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                entry(0, wgpu::BufferBindingType::Uniform),
+                entry(1, read),
+                entry(2, read),
+                entry(3, read),
+                entry(4, wgpu::BufferBindingType::Storage { read_only: false }),
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = |entry_point: &str| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(&pipeline_layout),
+                module: &module,
+                entry_point,
+            })
+        };
+        let (forward, output, gradient, delta) = (
+            pipeline("forward"),
+            pipeline("output"),
+            pipeline("gradient"),
+            pipeline("delta"),
+        );
+        let unused = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: 4,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+
+        Ok(Gpu {
+            name: format!("{} ({:?})", info.name, info.backend),
+            device,
+            queue,
+            layout,
+            forward,
+            output,
+            gradient,
+            delta,
+            unused,
+        })
+    }
+
+    // What the shaders can do: dense layers of sigmoid, tanh and relu without
+    // dropout. Anything else stays on the CPU.
+    pub fn supports(nn: &NN) -> bool {
+        nn.dropout.iter().all(|&p| p == 0.0)
+            && nn
+                .act
+                .iter()
+                .all(|&act| !matches!(act, Activation::Custom(_)))
+    }
+
+    // Adds the gradient of the cost over all of t_input to g, like NN::backprop_rows
+    // over every row
+    pub fn backprop(gpu: &Gpu, nn: &NN, g: &mut NN, t_input: &Mat, t_output: &Mat) {
+        let n = t_input.rows;
+        for start in (0..n).step_by(ROWS) {
+            Self::pass(
+                gpu,
+                nn,
+                g,
+                t_input,
+                t_output,
+                start..(start + ROWS).min(n),
+                n,
+            );
+        }
+    }
+
+    fn pass(
+        gpu: &Gpu,
+        nn: &NN,
+        g: &mut NN,
+        t_input: &Mat,
+        t_output: &Mat,
+        rows: Range<usize>,
+        n: usize,
+    ) {
+        let m = rows.len();
+        let layers = nn.count - 1;
+        let arch = NN::arch(nn);
+        let storage = wgpu::BufferUsages::STORAGE;
+
+        // the input scaling NN::forward does, on the CPU
+        let mut input = Mat {
+            rows: m,
+            cols: t_input.cols,
+            data: t_input.data[rows.clone()].to_vec(),
+        };
+        Normalization::apply(&nn.norm, &mut input);
+        let weights: Vec<Float> = rows
+            .clone()
+            .map(|i| NN::sample_weight(nn, &t_output.data[i]))
+            .collect();
+
+        let mut activations = vec![Self::upload(gpu, input.data.concat(), storage)];
+        for l in 0..layers {
+            activations.push(Self::zeroed(gpu, m * arch[l + 1], storage));
+        }
+        // deltas[l] of activations[l + 1]
+        let deltas: Vec<wgpu::Buffer> = (0..layers)
+            .map(|l| Self::zeroed(gpu, m * arch[l + 1], storage))
+            .collect();
+        let layer_weights: Vec<wgpu::Buffer> = (0..layers)
+            .map(|l| Self::upload(gpu, nn.weights[l].data.concat(), storage))
+            .collect();
+        let biases: Vec<wgpu::Buffer> = (0..layers)
+            .map(|l| Self::upload(gpu, nn.biases[l].data.concat(), storage))
+            .collect();
+        let targets = Self::upload(gpu, t_output.data[rows].concat(), storage);
+        let sample_weights = Self::upload(gpu, weights, storage);
+        let grads: Vec<wgpu::Buffer> = (0..layers)
+            .map(|l| {
+                let usage = storage | wgpu::BufferUsages::COPY_SRC;
+                Self::zeroed(gpu, (arch[l] + 1) * arch[l + 1], usage)
+            })
+            .collect();
+        let total: usize = (0..layers).map(|l| (arch[l] + 1) * arch[l + 1]).sum();
+        let staging = Self::zeroed(
+            gpu,
+            total,
+            wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        );
+
+        let (loss, huber) = match nn.loss {
+            Loss::Mse => (0, 0.0),
+            Loss::CrossEntropy => (1, 0.0),
+            Loss::Mae => (2, 0.0),
+            Loss::Huber(delta) => (3, delta),
+        };
+        let mut encoder = gpu.device.create_command_encoder(&Default::default());
+        for l in 0..layers {
+            let dims = Dims {
+                rows: m,
+                inner: arch[l],
+                cols: arch[l + 1],
+                act: Self::code(nn.act[l]),
+                ..Dims::default()
+            };
+            let buffers = [
+                &activations[l],
+                &layer_weights[l],
+                &biases[l],
+                &activations[l + 1],
+            ];
+            Self::dispatch(
+                gpu,
+                &mut encoder,
+                &gpu.forward,
+                dims,
+                buffers,
+                m * arch[l + 1],
+            );
+        }
+        let dims = Dims {
+            rows: m,
+            cols: arch[layers],
+            act: Self::code(nn.act[layers - 1]),
+            loss,
+            huber,
+            n,
+            ..Dims::default()
+        };
+        let buffers = [
+            &activations[layers],
+            &targets,
+            &sample_weights,
+            &deltas[layers - 1],
+        ];
+        Self::dispatch(
+            gpu,
+            &mut encoder,
+            &gpu.output,
+            dims,
+            buffers,
+            m * arch[layers],
+        );
+        for l in (0..layers).rev() {
+            let dims = Dims {
+                rows: m,
+                inner: arch[l],
+                cols: arch[l + 1],
+                act: if l > 0 { Self::code(nn.act[l - 1]) } else { 0 },
+                ..Dims::default()
+            };
+            let buffers = [&activations[l], &deltas[l], &gpu.unused, &grads[l]];
+            let threads = (arch[l] + 1) * arch[l + 1];
+            Self::dispatch(gpu, &mut encoder, &gpu.gradient, dims, buffers, threads);
+            // the input layer has no weights below it to take a delta
+            if l > 0 {
+                let buffers = [
+                    &deltas[l],
+                    &layer_weights[l],
+                    &activations[l],
+                    &deltas[l - 1],
+                ];
+                Self::dispatch(gpu, &mut encoder, &gpu.delta, dims, buffers, m * arch[l]);
+            }
+        }
+        let mut offset = 0;
+        for (l, grad) in grads.iter().enumerate() {
+            let size = ((arch[l] + 1) * arch[l + 1] * 4) as u64;
+            encoder.copy_buffer_to_buffer(grad, 0, &staging, offset, size);
+            offset += size;
+        }
+        gpu.queue.submit(Some(encoder.finish()));
+
+        let values = Self::download(gpu, &staging);
+        let mut values = values.iter();
+        for l in 0..layers {
+            for row in &mut g.weights[l].data {
+                for (w, v) in row.iter_mut().zip(&mut values) {
+                    *w += v;
+                }
+            }
+            for (b, v) in g.biases[l].data[0].iter_mut().zip(&mut values) {
+                *b += v;
+            }
+        }
+    }
+
+    // The number the shader's activate and deriv switch on
+    fn code(act: Activation) -> u32 {
+        match act {
+            Activation::Sigmoid => 0,
+            Activation::Tanh => 1,
+            // supports() keeps custom activations off the GPU
+            Activation::Relu | Activation::Custom(_) => 2,
+        }
+    }
+
+    // Encodes `entry` over `threads` values with a, b, c and out from `buffers`
+    fn dispatch(
+        gpu: &Gpu,
+        encoder: &mut wgpu::CommandEncoder,
+        entry: &wgpu::ComputePipeline,
+        dims: Dims,
+        buffers: [&wgpu::Buffer; 4],
+        threads: usize,
+    ) {
+        let groups = threads.div_ceil(WORKGROUP);
+        let x = groups.clamp(1, MAX_GROUPS);
+        let y = groups.div_ceil(x).max(1);
+        // the huber cast is a no-op unless the f64 feature is on
+        #[allow(clippy::unnecessary_cast)]
+        let uniform: Vec<u8> = [
+            dims.rows as u32,
+            dims.inner as u32,
+            dims.cols as u32,
+            dims.act,
+            dims.loss,
+            (dims.huber as f32).to_bits(),
+            (dims.n as f32).to_bits(),
+            (x * WORKGROUP) as u32,
+        ]
+        .iter()
+        .flat_map(|v| v.to_ne_bytes())
+        .collect();
+        let uniform = gpu
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: None,
+                contents: &uniform,
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+        let mut entries = vec![wgpu::BindGroupEntry {
+            binding: 0,
+            resource: uniform.as_entire_binding(),
+        }];
+        for (binding, buffer) in (1..).zip(buffers) {
+            entries.push(wgpu::BindGroupEntry {
+                binding,
+                resource: buffer.as_entire_binding(),
+            });
+        }
+        let bind_group = gpu.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &gpu.layout,
+            entries: &entries,
+        });
+
+        let mut pass = encoder.begin_compute_pass(&Default::default());
+        pass.set_pipeline(entry);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.dispatch_workgroups(x as u32, y as u32, 1);
+    }
+
+    fn upload(gpu: &Gpu, values: Vec<Float>, usage: wgpu::BufferUsages) -> wgpu::Buffer {
+        // a no-op unless the f64 feature is on
+        #[allow(clippy::unnecessary_cast)]
+        let bytes: Vec<u8> = values
+            .into_iter()
+            .flat_map(|v| (v as f32).to_ne_bytes())
+            .collect();
+        gpu.device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: None,
+                contents: &bytes,
+                usage,
+            })
+    }
+
+    // `len` zeros, wgpu clears new buffers
+    fn zeroed(gpu: &Gpu, len: usize, usage: wgpu::BufferUsages) -> wgpu::Buffer {
+        gpu.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: (len * 4) as u64,
+            usage,
+            mapped_at_creation: false,
+        })
+    }
+
+    // Waits for the queue and reads `staging` back as floats
+    fn download(gpu: &Gpu, staging: &wgpu::Buffer) -> Vec<Float> {
+        let slice = staging.slice(..);
+        let (tx, rx) = mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = tx.send(result);
+        });
+        gpu.device.poll(wgpu::Maintain::Wait);
+        rx.recv()
+            .expect("the GPU dropped the readback")
+            .expect("can't read the gradient back from the GPU");
+        let values = slice
+            .get_mapped_range()
+            .chunks_exact(4)
+            .map(|bytes| f32::from_ne_bytes(bytes.try_into().unwrap()) as Float)
+            .collect();
+        staging.unmap();
+        values
+    }
+}
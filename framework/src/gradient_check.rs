@@ -0,0 +1,65 @@
+use super::{seeded_rng, Float, Mat, Regularization, NN};
+
+// Compares NN::backprop against central finite differences and returns the relative
+// error |analytic - numeric| / (|analytic| + |numeric|) per layer, with the weights and
+// biases of a layer taken as one vector. Dropout is turned off for the check. A few
+// 1e-4 is normal in f32 with eps 1e-3 (1e-7 with the f64 feature), anything close to
+// 1 means a wrong gradient.
+pub fn gradient_check(nn: &NN, t_input: &Mat, t_output: &Mat, eps: Float) -> Vec<Float> {
+    let mut nn = nn.clone();
+    nn.dropout.fill(0.0);
+    let mut g = NN::new(&NN::arch(&nn));
+    NN::backprop(&mut nn, &mut g, t_input, t_output, &mut seeded_rng(0));
+
+    // backprop differentiates the mean loss per sample plus the penalty,
+    // NN::cost sums the loss
+    let n = t_input.rows as Float;
+    let cost = |nn: &NN| {
+        let penalty = Regularization::penalty(nn);
+        (NN::cost(nn, t_input, t_output) - penalty) / n + penalty
+    };
+
+    (0..nn.count - 1)
+        .map(|l| {
+            // squared norms of the difference and of both gradients
+            let (mut diff, mut a, mut b) = (0.0, 0.0, 0.0);
+            let mut compare = |analytic: Float, numeric: Float| {
+                diff += (analytic - numeric).powi(2);
+                a += analytic.powi(2);
+                b += numeric.powi(2);
+            };
+            for j in 0..nn.weights[l].rows {
+                for k in 0..nn.weights[l].cols {
+                    let n = nudge(&mut nn, &cost, eps, |nn| &mut nn.weights[l].data[j][k]);
+                    compare(g.weights[l].data[j][k], n);
+                }
+            }
+            for k in 0..nn.biases[l].cols {
+                let n = nudge(&mut nn, &cost, eps, |nn| &mut nn.biases[l].data[0][k]);
+                compare(g.biases[l].data[0][k], n);
+            }
+            let norm = a.sqrt() + b.sqrt();
+            if norm > 0.0 {
+                diff.sqrt() / norm
+            } else {
+                0.0
+            }
+        })
+        .collect()
+}
+
+// Central difference of cost by the parameter `param` points at
+fn nudge(
+    nn: &mut NN,
+    cost: &dyn Fn(&NN) -> Float,
+    eps: Float,
+    param: impl Fn(&mut NN) -> &mut Float,
+) -> Float {
+    let saved = *param(nn);
+    *param(nn) = saved + eps;
+    let plus = cost(nn);
+    *param(nn) = saved - eps;
+    let minus = cost(nn);
+    *param(nn) = saved;
+    (plus - minus) / (2.0 * eps)
+}
@@ -0,0 +1,100 @@
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::Path,
+};
+
+use super::{one_hot, Dataset, Float, Mat};
+
+// IDX files (the format MNIST is distributed in) of unsigned bytes:
+// two zero bytes, the type (0x08), the number of dimensions, the dimensions
+// as big endian u32s and then the data
+#[derive(Clone, Debug)]
+pub struct Idx {
+    pub dims: Vec<usize>,
+    pub data: Vec<u8>,
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, msg.to_owned())
+}
+
+impl Idx {
+    pub fn parse(bytes: &[u8]) -> io::Result<Idx> {
+        if bytes.len() < 4 || bytes[0] != 0 || bytes[1] != 0 {
+            return Err(invalid("not an IDX file"));
+        }
+        if bytes[2] != 0x08 {
+            return Err(invalid("only unsigned byte IDX files are supported"));
+        }
+
+        let ndims = bytes[3] as usize;
+        let header = 4 + 4 * ndims;
+        if bytes.len() < header {
+            return Err(invalid("truncated IDX header"));
+        }
+        let dims: Vec<usize> = bytes[4..header]
+            .chunks(4)
+            .map(|d| u32::from_be_bytes([d[0], d[1], d[2], d[3]]) as usize)
+            .collect();
+
+        let len: usize = dims.iter().product();
+        if bytes.len() < header + len {
+            return Err(invalid("truncated IDX data"));
+        }
+
+        Ok(Idx {
+            dims,
+            data: bytes[header..header + len].to_vec(),
+        })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Idx> {
+        Self::parse(&fs::read(path)?)
+    }
+}
+
+impl Dataset {
+    // The first `limit` (0 for all) MNIST digits, pixels scaled to 0..1 and the
+    // labels one-hot encoded into 10 outputs
+    pub fn load_mnist(
+        images: impl AsRef<Path>,
+        labels: impl AsRef<Path>,
+        limit: usize,
+    ) -> io::Result<Dataset> {
+        let images = Idx::load(images)?;
+        let labels = Idx::load(labels)?;
+        if images.dims.len() != 3 || labels.dims.len() != 1 {
+            return Err(invalid("expected 3D images and 1D labels"));
+        }
+        if images.dims[0] != labels.dims[0] {
+            return Err(invalid("image and label counts differ"));
+        }
+
+        let mut n = images.dims[0];
+        if limit > 0 {
+            n = n.min(limit);
+        }
+        let pixels = images.dims[1] * images.dims[2];
+
+        let t_input = Mat {
+            rows: n,
+            cols: pixels,
+            data: images.data[..n * pixels]
+                .chunks(pixels)
+                .map(|image| image.iter().map(|&p| p as Float / 255.0).collect())
+                .collect(),
+        };
+        let labels: Vec<usize> = labels.data[..n].iter().map(|&l| l as usize).collect();
+        if labels.iter().any(|&l| l > 9) {
+            return Err(invalid("labels must be digits 0-9"));
+        }
+
+        Ok(Dataset {
+            input_names: (0..pixels).map(|i| format!("pixel{}", i)).collect(),
+            output_names: (0..10).map(|i| i.to_string()).collect(),
+            t_input,
+            t_output: one_hot(&labels, 10),
+        })
+    }
+}
@@ -0,0 +1,57 @@
+use super::{rand_float, Activation, Float, Mat, StdRng, NN, PI};
+
+// Weight initialization strategies. Biases start at zero for all of them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Init {
+    // U(-l, l) with l = sqrt(6 / (fan_in + fan_out)), suited for sigmoid and tanh
+    XavierUniform,
+    // N(0, 2 / fan_in), suited for relu
+    HeNormal,
+    // Pick per layer from the layer's activation function
+    Auto,
+}
+
+impl Init {
+    pub fn for_activation(act: Activation) -> Init {
+        match act {
+            Activation::Sigmoid | Activation::Tanh | Activation::Custom(_) => Init::XavierUniform,
+            Activation::Relu => Init::HeNormal,
+        }
+    }
+
+    pub fn apply(init: Init, nn: &mut NN, rng: &mut StdRng) {
+        for i in 0..nn.count - 1 {
+            let init = match init {
+                Init::Auto => Init::for_activation(nn.act[i]),
+                init => init,
+            };
+            init_layer(init, &mut nn.weights[i], rng);
+            Mat::fill(&mut nn.biases[i], 0.0);
+        }
+    }
+}
+
+pub(crate) fn init_layer(init: Init, w: &mut Mat, rng: &mut StdRng) {
+    let fan_in = w.rows as Float;
+    let fan_out = w.cols as Float;
+
+    for row in &mut w.data {
+        for val in row.iter_mut() {
+            *val = match init {
+                Init::XavierUniform => {
+                    let limit = (6.0 / (fan_in + fan_out)).sqrt();
+                    rand_float(rng, -limit, limit)
+                }
+                Init::HeNormal => rand_normal(rng) * (2.0 / fan_in).sqrt(),
+                Init::Auto => unreachable!(),
+            };
+        }
+    }
+}
+
+// Standard normal sample (Box-Muller)
+pub(crate) fn rand_normal(rng: &mut StdRng) -> Float {
+    let u1 = rand_float(rng, Float::EPSILON, 1.0);
+    let u2 = rand_float(rng, 0.0, 1.0);
+    (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()
+}
@@ -0,0 +1,56 @@
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::Path,
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::NN;
+
+// Files are stored as JSON when the name ends with .json
+// and in a compact binary format (bincode) otherwise
+fn is_json(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "json")
+}
+
+fn invalid_data<E: std::error::Error + Send + Sync + 'static>(e: E) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, e)
+}
+
+pub(crate) fn write<T: Serialize>(value: &T, path: &Path) -> io::Result<()> {
+    let bytes = if is_json(path) {
+        serde_json::to_vec_pretty(value).map_err(invalid_data)?
+    } else {
+        bincode::serialize(value).map_err(invalid_data)?
+    };
+    fs::write(path, bytes)
+}
+
+pub(crate) fn read<T: DeserializeOwned>(path: &Path) -> io::Result<T> {
+    parse(&fs::read(path)?, path)
+}
+
+// The path only picks the format
+pub(crate) fn parse<T: DeserializeOwned>(bytes: &[u8], path: &Path) -> io::Result<T> {
+    if is_json(path) {
+        serde_json::from_slice(bytes).map_err(invalid_data)
+    } else {
+        bincode::deserialize(bytes).map_err(invalid_data)
+    }
+}
+
+impl NN {
+    pub fn save(nn: &NN, path: impl AsRef<Path>) -> io::Result<()> {
+        write(nn, path.as_ref())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<NN> {
+        read(path.as_ref())
+    }
+
+    // What NN::load reads from `path`, already read (the browser has no files)
+    pub fn parse(bytes: &[u8], path: impl AsRef<Path>) -> io::Result<NN> {
+        parse(bytes, path.as_ref())
+    }
+}
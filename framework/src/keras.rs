@@ -0,0 +1,305 @@
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::Path,
+};
+
+use serde::Deserialize;
+
+use super::{Activation, Float, Mat, NN};
+
+// Dense layers exported from Keras, as an .npz of the kernels and biases in order
+//
+//     np.savez("model.npz", *model.get_weights())
+//
+// or as JSON
+//
+//     json.dump({"layers": [{"kernel": l.kernel.numpy().tolist(),
+//                            "bias": l.bias.numpy().tolist(),
+//                            "activation": l.activation.__name__}
+//                           for l in model.layers]}, f)
+//
+// The .npz has no activations, its hidden layers get `act` and the output layer a
+// sigmoid. Other layers than dense ones and compressed .npz files aren't supported.
+
+#[derive(Deserialize)]
+struct KerasJson {
+    layers: Vec<KerasLayer>,
+}
+
+#[derive(Deserialize)]
+struct KerasLayer {
+    kernel: Vec<Vec<Float>>,
+    bias: Vec<Float>,
+    activation: Option<String>,
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, msg.to_owned())
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> io::Result<usize> {
+    bytes
+        .get(at..at + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]) as usize)
+        .ok_or_else(|| invalid("truncated .npz"))
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> io::Result<usize> {
+    bytes
+        .get(at..at + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize)
+        .ok_or_else(|| invalid("truncated .npz"))
+}
+
+fn u64_at(bytes: &[u8], at: usize) -> io::Result<usize> {
+    bytes
+        .get(at..at + 8)
+        .map(|b| u64::from_le_bytes(b.try_into().unwrap()) as usize)
+        .ok_or_else(|| invalid("truncated .npz"))
+}
+
+// The (name, data) of every file in a zip archive, from its central directory since
+// numpy leaves the sizes in the local headers to a zip64 extra field
+fn unzip(bytes: &[u8]) -> io::Result<Vec<(String, &[u8])>> {
+    // the end of central directory record, followed by a comment of up to 64k
+    let end = (0..bytes.len().saturating_sub(21))
+        .rev()
+        .find(|&i| bytes[i..].starts_with(&[0x50, 0x4b, 0x05, 0x06]))
+        .ok_or_else(|| invalid("not a zip (.npz) file"))?;
+    let count = u16_at(bytes, end + 10)?;
+    let mut at = u32_at(bytes, end + 16)?;
+
+    let mut files = vec![];
+    for _ in 0..count {
+        if !bytes[at.min(bytes.len())..].starts_with(&[0x50, 0x4b, 0x01, 0x02]) {
+            return Err(invalid("broken zip central directory"));
+        }
+        if u16_at(bytes, at + 10)? != 0 {
+            return Err(invalid(
+                "compressed .npz files aren't supported, save with np.savez",
+            ));
+        }
+        let mut size = u32_at(bytes, at + 24)?;
+        let mut offset = u32_at(bytes, at + 42)?;
+        let (name_len, extra_len, comment_len) = (
+            u16_at(bytes, at + 28)?,
+            u16_at(bytes, at + 30)?,
+            u16_at(bytes, at + 32)?,
+        );
+        let name = bytes
+            .get(at + 46..at + 46 + name_len)
+            .ok_or_else(|| invalid("truncated .npz"))?;
+        let name = String::from_utf8_lossy(name).into_owned();
+
+        // zip64: the 0xffffffff fields follow in order, uncompressed size first
+        let mut extra = at + 46 + name_len;
+        let extra_end = extra + extra_len;
+        while extra + 4 <= extra_end {
+            let (id, len) = (u16_at(bytes, extra)?, u16_at(bytes, extra + 2)?);
+            if id == 1 {
+                let mut field = extra + 4;
+                if u32_at(bytes, at + 24)? == 0xffff_ffff {
+                    size = u64_at(bytes, field)?;
+                    field += 8;
+                }
+                if u32_at(bytes, at + 20)? == 0xffff_ffff {
+                    field += 8;
+                }
+                if u32_at(bytes, at + 42)? == 0xffff_ffff {
+                    offset = u64_at(bytes, field)?;
+                }
+            }
+            extra += 4 + len;
+        }
+
+        let data = offset + 30 + u16_at(bytes, offset + 26)? + u16_at(bytes, offset + 28)?;
+        let data = bytes
+            .get(data..data + size)
+            .ok_or_else(|| invalid("truncated .npz"))?;
+        files.push((name, data));
+        at = extra_end + comment_len;
+    }
+    Ok(files)
+}
+
+// A little endian float32 or float64 .npy array as a matrix, 1-D ones as a single row
+fn parse_npy(bytes: &[u8]) -> io::Result<Mat> {
+    if !bytes.starts_with(b"\x93NUMPY") || bytes.len() < 10 {
+        return Err(invalid("not a .npy array"));
+    }
+    let (len, start): (usize, usize) = match bytes[6] {
+        1 => (u16_at(bytes, 8)?, 10),
+        _ => (u32_at(bytes, 8)?, 12),
+    };
+    let header = start
+        .checked_add(len)
+        .and_then(|end| bytes.get(start..end))
+        .ok_or_else(|| invalid("truncated .npy header"))?;
+    let header = String::from_utf8_lossy(header);
+    let data = &bytes[start + len..];
+
+    // {'descr': '<f4', 'fortran_order': False, 'shape': (2, 4), }
+    let value = |key: &str| {
+        let at = header.find(&format!("'{}':", key))? + key.len() + 3;
+        Some(header[at..].trim_start())
+    };
+    let descr = value("descr").ok_or_else(|| invalid("no dtype in .npy header"))?;
+    let width = if descr.starts_with("'<f4'") {
+        4
+    } else if descr.starts_with("'<f8'") {
+        8
+    } else {
+        return Err(invalid("only float32 and float64 arrays are supported"));
+    };
+    let fortran = value("fortran_order").is_some_and(|v| v.starts_with("True"));
+    let shape = value("shape").ok_or_else(|| invalid("no shape in .npy header"))?;
+    let shape = shape
+        .strip_prefix('(')
+        .and_then(|shape| Some(&shape[..shape.find(')')?]))
+        .ok_or_else(|| invalid("bad .npy shape"))?;
+    let shape: Vec<usize> = shape
+        .split(',')
+        .filter(|dim| !dim.trim().is_empty())
+        .map(|dim| dim.trim().parse().map_err(|_| invalid("bad .npy shape")))
+        .collect::<io::Result<_>>()?;
+    let (rows, cols) = match shape[..] {
+        [cols] => (1, cols),
+        [rows, cols] => (rows, cols),
+        _ => return Err(invalid("only 1-D and 2-D arrays are supported")),
+    };
+    let size = rows
+        .checked_mul(cols)
+        .and_then(|n| n.checked_mul(width))
+        .ok_or_else(|| invalid(".npy array too large"))?;
+    if data.len() < size {
+        return Err(invalid("truncated .npy data"));
+    }
+
+    let at = |i: usize| {
+        let b = &data[i * width..(i + 1) * width];
+        if width == 4 {
+            f32::from_le_bytes(b.try_into().unwrap()) as Float
+        } else {
+            f64::from_le_bytes(b.try_into().unwrap()) as Float
+        }
+    };
+    Ok(Mat {
+        rows,
+        cols,
+        data: (0..rows)
+            .map(|r| {
+                (0..cols)
+                    .map(|c| at(if fortran { c * rows + r } else { r * cols + c }))
+                    .collect()
+            })
+            .collect(),
+    })
+}
+
+fn activation(name: &str) -> io::Result<Activation> {
+    Activation::from_name(name).ok_or_else(|| {
+        invalid(&format!(
+            "activation `{}` isn't supported, only sigmoid, tanh, relu and registered ones",
+            name
+        ))
+    })
+}
+
+// A network of the (kernel, bias, activation) layers, checking they fit together
+fn network(layers: Vec<(Mat, Mat, Activation)>) -> io::Result<NN> {
+    let Some((first, _, _)) = layers.first() else {
+        return Err(invalid("no layers"));
+    };
+    let mut arch = vec![first.rows];
+    for (i, (kernel, bias, _)) in layers.iter().enumerate() {
+        if kernel.rows != arch[i] || bias.rows != 1 || bias.cols != kernel.cols {
+            return Err(invalid(&format!(
+                "layer {} is {}x{} with {} biases after {} outputs",
+                i + 1,
+                kernel.rows,
+                kernel.cols,
+                bias.cols,
+                arch[i]
+            )));
+        }
+        arch.push(kernel.cols);
+    }
+
+    let mut nn = NN::new(&arch);
+    for (i, (kernel, bias, act)) in layers.into_iter().enumerate() {
+        nn.weights[i] = kernel;
+        nn.biases[i] = bias;
+        nn.act[i] = act;
+    }
+    Ok(nn)
+}
+
+impl NN {
+    pub fn parse_npz(bytes: &[u8], act: Activation) -> io::Result<NN> {
+        let mut arrays = unzip(bytes)?;
+        // arr_0.npy, arr_1.npy, ..., arr_10.npy in number order
+        arrays.sort_by_key(|(name, _)| {
+            let digits: String = name.chars().filter(|c| c.is_ascii_digit()).collect();
+            (digits.parse::<usize>().unwrap_or(usize::MAX), name.clone())
+        });
+        let arrays: Vec<Mat> = arrays
+            .iter()
+            .map(|(_, data)| parse_npy(data))
+            .collect::<io::Result<_>>()?;
+        if !arrays.len().is_multiple_of(2) {
+            return Err(invalid("expected a kernel and a bias for every layer"));
+        }
+
+        let count = arrays.len() / 2;
+        let mut arrays = arrays.into_iter();
+        let layers = (0..count)
+            .map(|i| {
+                let act = if i + 1 == count {
+                    Activation::Sigmoid
+                } else {
+                    act
+                };
+                (arrays.next().unwrap(), arrays.next().unwrap(), act)
+            })
+            .collect();
+        network(layers)
+    }
+
+    // Layers without an activation get `act`
+    pub fn parse_keras_json(text: &str, act: Activation) -> io::Result<NN> {
+        let keras: KerasJson = serde_json::from_str(text)?;
+        let layers = keras
+            .layers
+            .into_iter()
+            .map(|layer| {
+                let kernel = Mat {
+                    rows: layer.kernel.len(),
+                    cols: layer.kernel.first().map_or(0, |row| row.len()),
+                    data: layer.kernel,
+                };
+                if kernel.data.iter().any(|row| row.len() != kernel.cols) {
+                    return Err(invalid("ragged kernel"));
+                }
+                let bias = Mat {
+                    rows: 1,
+                    cols: layer.bias.len(),
+                    data: vec![layer.bias],
+                };
+                let act = layer.activation.as_deref().map_or(Ok(act), activation)?;
+                Ok((kernel, bias, act))
+            })
+            .collect::<io::Result<_>>()?;
+        network(layers)
+    }
+
+    // .npz by the extension, JSON otherwise
+    pub fn import_keras(path: impl AsRef<Path>, act: Activation) -> io::Result<NN> {
+        let path = path.as_ref();
+        if path.extension().is_some_and(|ext| ext == "npz") {
+            Self::parse_npz(&fs::read(path)?, act)
+        } else {
+            Self::parse_keras_json(&fs::read_to_string(path)?, act)
+        }
+    }
+}
@@ -0,0 +1,360 @@
+use super::{init, rand_float, Activation, Float, Init, Loss, Mat, StdRng, NN};
+
+// A stage of a Sequential model. Layers see a whole batch at once, one sample per row.
+pub trait Layer: Send {
+    // `train` turns dropout and batch statistics on and keeps what backward needs
+    fn forward(&mut self, input: &Mat, train: bool, rng: &mut StdRng) -> Mat;
+    // Takes the gradient of the cost by the output of the last training pass, adds the
+    // parameter gradients to grads() and returns the gradient by the input
+    fn backward(&mut self, grad: &Mat) -> Mat;
+    // params() and grads() are in the same order
+    fn params(&self) -> Vec<&Mat> {
+        Vec::new()
+    }
+    fn grads(&self) -> Vec<&Mat> {
+        Vec::new()
+    }
+    // Calls f with every parameter and its gradient, for the update step
+    fn update(&mut self, _f: &mut dyn FnMut(&mut Mat, &mut Mat)) {}
+}
+
+pub(crate) fn zeros(rows: usize, cols: usize) -> Mat {
+    Mat {
+        rows,
+        cols,
+        data: vec![vec![0.0; cols]; rows],
+    }
+}
+
+// Fully connected layer, output = act(input * weights + biases)
+pub struct Dense {
+    pub weights: Mat,
+    pub biases: Mat,
+    pub act: Activation,
+    gw: Mat,
+    gb: Mat,
+    input: Mat,
+    output: Mat,
+}
+
+impl Dense {
+    // Zero weights, initialize them with Dense::init
+    pub fn new(inputs: usize, outputs: usize, act: Activation) -> Dense {
+        Dense {
+            weights: zeros(inputs, outputs),
+            biases: zeros(1, outputs),
+            act,
+            gw: zeros(inputs, outputs),
+            gb: zeros(1, outputs),
+            input: zeros(0, inputs),
+            output: zeros(0, outputs),
+        }
+    }
+
+    pub fn init(layer: &mut Dense, init: Init, rng: &mut StdRng) {
+        let init = match init {
+            Init::Auto => Init::for_activation(layer.act),
+            init => init,
+        };
+        init::init_layer(init, &mut layer.weights, rng);
+        Mat::fill(&mut layer.biases, 0.0);
+    }
+}
+
+impl Layer for Dense {
+    fn forward(&mut self, input: &Mat, train: bool, _rng: &mut StdRng) -> Mat {
+        let mut output = zeros(input.rows, self.weights.cols);
+        Mat::dot(&mut output, input, &self.weights);
+        for row in &mut output.data {
+            for (val, b) in row.iter_mut().zip(&self.biases.data[0]) {
+                *val = Activation::apply(self.act, *val + b);
+            }
+        }
+        if train {
+            self.input = input.clone();
+            self.output = output.clone();
+        }
+        output
+    }
+
+    fn backward(&mut self, grad: &Mat) -> Mat {
+        let mut grad_input = zeros(grad.rows, self.weights.rows);
+        for i in 0..grad.rows {
+            for j in 0..grad.cols {
+                let delta = grad.data[i][j] * Activation::deriv(self.act, self.output.data[i][j]);
+                self.gb.data[0][j] += delta;
+                for k in 0..self.weights.rows {
+                    self.gw.data[k][j] += delta * self.input.data[i][k];
+                    grad_input.data[i][k] += delta * self.weights.data[k][j];
+                }
+            }
+        }
+        grad_input
+    }
+
+    fn params(&self) -> Vec<&Mat> {
+        vec![&self.weights, &self.biases]
+    }
+
+    fn grads(&self) -> Vec<&Mat> {
+        vec![&self.gw, &self.gb]
+    }
+
+    fn update(&mut self, f: &mut dyn FnMut(&mut Mat, &mut Mat)) {
+        f(&mut self.weights, &mut self.gw);
+        f(&mut self.biases, &mut self.gb);
+    }
+}
+
+// Zeroes each input with probability p during training and scales the kept ones
+// by 1 / (1 - p), the same inverted dropout NN::forward_train does
+pub struct Dropout {
+    pub p: Float,
+    mask: Vec<Vec<bool>>,
+}
+
+impl Dropout {
+    pub fn new(p: Float) -> Dropout {
+        assert!((0.0..1.0).contains(&p));
+        Dropout {
+            p,
+            mask: Vec::new(),
+        }
+    }
+}
+
+impl Layer for Dropout {
+    fn forward(&mut self, input: &Mat, train: bool, rng: &mut StdRng) -> Mat {
+        let mut output = input.clone();
+        if !train {
+            return output;
+        }
+        self.mask = vec![vec![false; input.cols]; input.rows];
+        for (row, mask) in output.data.iter_mut().zip(&mut self.mask) {
+            for (val, dropped) in row.iter_mut().zip(mask.iter_mut()) {
+                *dropped = rand_float(rng, 0.0, 1.0) < self.p;
+                *val = if *dropped { 0.0 } else { *val / (1.0 - self.p) };
+            }
+        }
+        output
+    }
+
+    fn backward(&mut self, grad: &Mat) -> Mat {
+        let mut grad_input = grad.clone();
+        for (row, mask) in grad_input.data.iter_mut().zip(&self.mask) {
+            for (val, dropped) in row.iter_mut().zip(mask) {
+                *val = if *dropped { 0.0 } else { *val / (1.0 - self.p) };
+            }
+        }
+        grad_input
+    }
+}
+
+// Normalizes every input to mean 0 and variance 1 over the batch, then scales by
+// gamma and shifts by beta. Inference uses running averages of the batch statistics.
+pub struct BatchNorm {
+    pub gamma: Mat,
+    pub beta: Mat,
+    pub mean: Vec<Float>,
+    pub var: Vec<Float>,
+    // weight of the newest batch in the running averages
+    pub momentum: Float,
+    pub eps: Float,
+    g_gamma: Mat,
+    g_beta: Mat,
+    normalized: Mat,
+    std: Vec<Float>,
+}
+
+impl BatchNorm {
+    pub fn new(size: usize) -> BatchNorm {
+        let mut gamma = zeros(1, size);
+        Mat::fill(&mut gamma, 1.0);
+        BatchNorm {
+            gamma,
+            beta: zeros(1, size),
+            mean: vec![0.0; size],
+            var: vec![1.0; size],
+            momentum: 0.1,
+            eps: 1e-5,
+            g_gamma: zeros(1, size),
+            g_beta: zeros(1, size),
+            normalized: zeros(0, size),
+            std: vec![1.0; size],
+        }
+    }
+}
+
+impl Layer for BatchNorm {
+    fn forward(&mut self, input: &Mat, train: bool, _rng: &mut StdRng) -> Mat {
+        let n = input.rows as Float;
+        let mut output = input.clone();
+        for j in 0..input.cols {
+            let (mean, var) = if train {
+                let mean = input.data.iter().map(|row| row[j]).sum::<Float>() / n;
+                let var = input
+                    .data
+                    .iter()
+                    .map(|row| (row[j] - mean).powi(2))
+                    .sum::<Float>()
+                    / n;
+                self.mean[j] += self.momentum * (mean - self.mean[j]);
+                self.var[j] += self.momentum * (var - self.var[j]);
+                (mean, var)
+            } else {
+                (self.mean[j], self.var[j])
+            };
+            let std = (var + self.eps).sqrt();
+            if train {
+                self.std[j] = std;
+            }
+            for row in &mut output.data {
+                row[j] = (row[j] - mean) / std;
+            }
+        }
+        if train {
+            self.normalized = output.clone();
+        }
+        for row in &mut output.data {
+            for (j, val) in row.iter_mut().enumerate() {
+                *val = *val * self.gamma.data[0][j] + self.beta.data[0][j];
+            }
+        }
+        output
+    }
+
+    fn backward(&mut self, grad: &Mat) -> Mat {
+        let n = grad.rows as Float;
+        let mut grad_input = grad.clone();
+        for j in 0..grad.cols {
+            let mut sum = 0.0;
+            let mut dot = 0.0;
+            for i in 0..grad.rows {
+                sum += grad.data[i][j];
+                dot += grad.data[i][j] * self.normalized.data[i][j];
+            }
+            self.g_beta.data[0][j] += sum;
+            self.g_gamma.data[0][j] += dot;
+
+            let scale = self.gamma.data[0][j] / self.std[j];
+            for i in 0..grad.rows {
+                let x = self.normalized.data[i][j];
+                grad_input.data[i][j] = scale * (grad.data[i][j] - sum / n - x * dot / n);
+            }
+        }
+        grad_input
+    }
+
+    fn params(&self) -> Vec<&Mat> {
+        vec![&self.gamma, &self.beta]
+    }
+
+    fn grads(&self) -> Vec<&Mat> {
+        vec![&self.g_gamma, &self.g_beta]
+    }
+
+    fn update(&mut self, f: &mut dyn FnMut(&mut Mat, &mut Mat)) {
+        f(&mut self.gamma, &mut self.g_gamma);
+        f(&mut self.beta, &mut self.g_beta);
+    }
+}
+
+// Layers applied one after another, trained as a whole like NN.
+// The cost is the sum of the loss over every output, as in NN::cost.
+pub struct Sequential {
+    pub layers: Vec<Box<dyn Layer>>,
+    pub loss: Loss,
+}
+
+impl Sequential {
+    pub fn new(loss: Loss) -> Sequential {
+        Sequential {
+            layers: Vec::new(),
+            loss,
+        }
+    }
+
+    pub fn push(model: &mut Sequential, layer: impl Layer + 'static) {
+        model.layers.push(Box::new(layer));
+    }
+
+    // Dense and Dropout layers computing the same as nn, with its input
+    // normalization folded into the first layer
+    pub fn from_nn(nn: &NN) -> Sequential {
+        let nn = NN::fold_normalization(nn);
+        let mut model = Sequential::new(nn.loss);
+        for l in 0..nn.count - 1 {
+            if nn.dropout[l] > 0.0 {
+                Self::push(&mut model, Dropout::new(nn.dropout[l]));
+            }
+            let mut dense = Dense::new(nn.weights[l].rows, nn.weights[l].cols, nn.act[l]);
+            dense.weights = nn.weights[l].clone();
+            dense.biases = nn.biases[l].clone();
+            Self::push(&mut model, dense);
+        }
+        model
+    }
+
+    fn run(model: &mut Sequential, input: &Mat, train: bool, rng: &mut StdRng) -> Mat {
+        let mut x = input.clone();
+        for layer in &mut model.layers {
+            x = layer.forward(&x, train, rng);
+        }
+        x
+    }
+
+    // Outputs for every row of input, without dropout
+    pub fn predict(model: &mut Sequential, input: &Mat, rng: &mut StdRng) -> Mat {
+        Self::run(model, input, false, rng)
+    }
+
+    pub fn cost(model: &mut Sequential, t_input: &Mat, t_output: &Mat, rng: &mut StdRng) -> Float {
+        assert_eq!(t_input.rows, t_output.rows);
+        let output = Self::predict(model, t_input, rng);
+        let mut cost = 0.0;
+        for (a, y) in output
+            .data
+            .iter()
+            .flatten()
+            .zip(t_output.data.iter().flatten())
+        {
+            cost += Loss::apply(model.loss, *a, *y);
+        }
+        cost
+    }
+
+    // Sets the gradients to those of the mean cost over the batch
+    pub fn backprop(model: &mut Sequential, t_input: &Mat, t_output: &Mat, rng: &mut StdRng) {
+        assert_eq!(t_input.rows, t_output.rows);
+        Self::zero(model);
+        let output = Self::run(model, t_input, true, rng);
+
+        let n = t_input.rows as Float;
+        let mut grad = output.clone();
+        for (i, row) in grad.data.iter_mut().enumerate() {
+            for (j, val) in row.iter_mut().enumerate() {
+                *val = Loss::deriv(model.loss, output.data[i][j], t_output.data[i][j]) / n;
+            }
+        }
+        for layer in model.layers.iter_mut().rev() {
+            grad = layer.backward(&grad);
+        }
+    }
+
+    pub fn zero(model: &mut Sequential) {
+        for layer in &mut model.layers {
+            layer.update(&mut |_, g| Mat::fill(g, 0.0));
+        }
+    }
+
+    // Plain gradient descent step
+    pub fn learn(model: &mut Sequential, rate: Float) {
+        for layer in &mut model.layers {
+            layer.update(&mut |p, g| {
+                for (val, d) in p.data.iter_mut().flatten().zip(g.data.iter().flatten()) {
+                    *val -= rate * d;
+                }
+            });
+        }
+    }
+}
@@ -1,4 +1,139 @@
-use rand::Rng;
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+// The generator every random function takes, seeded so runs can be repeated
+pub use rand::rngs::StdRng;
+use serde::{Deserialize, Serialize};
+use std::{
+    ops::Range,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    thread,
+};
+
+// Scalar of Mat and NN: f32, or f64 with the `f64` feature for gradient checks and
+// numerically touchy experiments (the visualizer needs the default)
+#[cfg(not(feature = "f64"))]
+pub type Float = f32;
+#[cfg(feature = "f64")]
+pub type Float = f64;
+#[cfg(not(feature = "f64"))]
+pub(crate) use std::f32::consts::PI;
+#[cfg(feature = "f64")]
+pub(crate) use std::f64::consts::PI;
+
+mod activation;
+pub use activation::Activation;
+
+mod augment;
+pub use augment::Augment;
+
+mod backend;
+pub use backend::Backend;
+
+#[cfg(feature = "blas")]
+mod blas;
+
+mod builder;
+pub use builder::NNBuilder;
+
+mod checkpoint;
+pub use checkpoint::{Checkpoint, Checkpointer, TrainingState};
+
+mod codegen;
+
+mod conv;
+pub use conv::{Conv2D, MaxPool2D};
+
+mod dataset;
+pub use dataset::{argmax, argmax_rows, encode_labels, one_hot, Dataset};
+
+mod descent;
+pub use descent::Descent;
+
+mod divergence;
+pub use divergence::Divergence;
+
+mod early_stopping;
+pub use early_stopping::EarlyStopping;
+
+mod ema;
+pub use ema::Ema;
+
+mod error;
+pub use error::Error;
+
+mod evolution;
+pub use evolution::Evolution;
+
+#[cfg(feature = "gpu")]
+mod gpu;
+
+mod gradient_check;
+pub use gradient_check::gradient_check;
+
+mod idx;
+pub use idx::Idx;
+
+mod init;
+mod io;
+mod onnx;
+pub use init::Init;
+
+mod keras;
+
+mod layer;
+pub use layer::{BatchNorm, Dense, Dropout, Layer, Sequential};
+
+mod logger;
+pub use logger::{Metrics, MetricsLogger};
+
+mod loss;
+pub use loss::Loss;
+
+mod lr_finder;
+pub use lr_finder::LrFinder;
+
+mod metrics;
+pub use metrics::{balanced_weights, class_of, is_classification};
+
+mod normalize;
+pub use normalize::{Normalization, Scaling};
+
+mod optimizer;
+pub use optimizer::{Clip, Method, Optimizer, OptimizerState};
+
+mod presets;
+pub use presets::Preset;
+
+mod prune;
+
+mod quantize;
+pub use quantize::QuantizedNN;
+
+mod regularization;
+pub use regularization::Regularization;
+
+mod rnn;
+pub use rnn::{Rnn, RnnWeights, Sequence};
+
+mod schedule;
+pub use schedule::Schedule;
+
+mod simd;
+
+mod synthetic;
+
+mod tensorboard;
+pub use tensorboard::EventWriter;
+
+mod websocket;
+pub use websocket::MetricsServer;
+
+// Samples per parallel backprop task, fixed so the gradient doesn't depend on the
+// number of cores
+const PARALLEL_CHUNK: usize = 256;
+// Threads backprop spreads the chunks over, 0 for one per core
+static BACKPROP_THREADS: AtomicUsize = AtomicUsize::new(0);
+// Sigmoid and tanh through the approximations in activation.rs, see set_fast_activations
+static FAST_ACTIVATIONS: AtomicBool = AtomicBool::new(false);
 
 #[macro_export]
 macro_rules! nn_input {
@@ -20,12 +155,36 @@ macro_rules! nn_output {
 //     };
 // }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct NN {
     pub count: usize,
     pub weights: Vec<Mat>,
     pub biases: Vec<Mat>,
     pub activations: Vec<Mat>,
+    // activation function of every layer after the input one
+    pub act: Vec<Activation>,
+    pub reg: Regularization,
+    // probability of dropping each neuron of a layer during training,
+    // indexed like activations (the output layer is never dropped)
+    pub dropout: Vec<Float>,
+    // neurons dropped in the last training pass
+    pub dropped: Vec<Vec<bool>>,
+    // applied to a copy of activations[0] (input) at the start of every forward pass
+    pub norm: Normalization,
+    pub loss: Loss,
+    // how much the samples of every class (see class_of) count in the cost and the
+    // gradient, empty counts them all once. balanced_weights evens out a dataset
+    // where one class has most of the samples.
+    #[serde(default)]
+    pub class_weights: Vec<Float>,
+    // weights held at zero, [layer][row][col] like weights, empty when none are. See
+    // NN::prune.
+    #[serde(default)]
+    pub pruned: Vec<Vec<Vec<bool>>>,
+    // What the first layer sees: activations[0] scaled by norm, with the input dropout
+    // of the last training pass. Scratch, activations[0] stays as the caller wrote it.
+    #[serde(skip)]
+    pub input: Mat,
 }
 
 impl NN {
@@ -34,20 +193,60 @@ impl NN {
     }
 
     pub fn forward(nn: &mut NN) {
+        Self::scale_input(nn);
         for i in 0..nn.count - 1 {
-            let mut new_nn = nn.clone();
-            Mat::dot(
-                &mut new_nn.activations[i + 1],
-                &nn.activations[i],
-                &nn.weights[i],
-            );
-            nn.activations[i + 1] = new_nn.activations[i + 1].clone();
-            Mat::sum(&mut nn.activations[i + 1], &nn.biases[i]);
-            Mat::sig(&mut nn.activations[i + 1]);
+            Self::forward_layer(nn, i);
         }
     }
 
-    pub fn cost(nn: &NN, t_input: &Mat, t_output: &Mat) -> f32 {
+    fn scale_input(nn: &mut NN) {
+        nn.input.clone_from(&nn.activations[0]);
+        Normalization::apply(&nn.norm, &mut nn.input);
+    }
+
+    fn forward_layer(nn: &mut NN, i: usize) {
+        let (prev, next) = nn.activations.split_at_mut(i + 1);
+        let input = if i == 0 { &nn.input } else { &prev[i] };
+        Mat::dot(&mut next[0], input, &nn.weights[i]);
+        Mat::sum(&mut nn.activations[i + 1], &nn.biases[i]);
+        Mat::act(&mut nn.activations[i + 1], nn.act[i]);
+    }
+
+    // Forward pass with dropout, used by backprop. Dropped neurons are zeroed and the
+    // kept ones scaled by 1 / (1 - p), so NN::forward needs no correction afterwards.
+    pub fn forward_train(nn: &mut NN, rng: &mut StdRng) {
+        Self::scale_input(nn);
+        Self::drop_neurons(nn, 0, rng);
+        for i in 0..nn.count - 1 {
+            Self::forward_layer(nn, i);
+            if i + 1 < nn.count - 1 {
+                Self::drop_neurons(nn, i + 1, rng);
+            }
+        }
+    }
+
+    fn drop_neurons(nn: &mut NN, l: usize, rng: &mut StdRng) {
+        let p = nn.dropout[l];
+        if p == 0.0 {
+            nn.dropped[l].fill(false);
+            return;
+        }
+        let a = if l == 0 {
+            &mut nn.input
+        } else {
+            &mut nn.activations[l]
+        };
+        for j in 0..a.cols {
+            nn.dropped[l][j] = rand_float(rng, 0.0, 1.0) < p;
+            if nn.dropped[l][j] {
+                a.data[0][j] = 0.0;
+            } else {
+                a.data[0][j] /= 1.0 - p;
+            }
+        }
+    }
+
+    pub fn cost(nn: &NN, t_input: &Mat, t_output: &Mat) -> Float {
         let mut nn = nn.clone();
         assert_eq!(t_input.rows, t_output.rows);
         assert_eq!(t_output.cols, nn.activations[nn.count - 1].cols);
@@ -62,17 +261,17 @@ impl NN {
             Mat::copy(&mut nn_input!(nn), &x);
             Self::forward(&mut nn);
             let q = t_output.cols;
+            let weight = Self::sample_weight(&nn, &y.data[0]);
             for j in 0..q {
-                let diff: f32 = nn_output!(nn).data[0][j] - y.data[0][j];
                 // cost is magnified
-                cost += diff * diff;
+                cost += weight * Loss::apply(nn.loss, nn_output!(nn).data[0][j], y.data[0][j]);
             }
         }
 
-        cost
+        cost + Regularization::penalty(&nn)
     }
 
-    pub fn learn(nn: &mut NN, g: &NN, rate: f32) {
+    pub fn learn(nn: &mut NN, g: &NN, rate: Float) {
         for i in 0..nn.count - 1 {
             for j in 0..nn.weights[i].rows {
                 for k in 0..nn.weights[i].cols {
@@ -88,17 +287,17 @@ impl NN {
         }
     }
 
-    pub fn randomize(nn: &mut NN, min: f32, max: f32) {
+    pub fn randomize(nn: &mut NN, rng: &mut StdRng, min: Float, max: Float) {
         for i in 0..nn.count - 1 {
             for j in 0..nn.weights[i].rows {
                 for k in 0..nn.weights[i].cols {
-                    nn.weights[i].data[j][k] = rand_float(min, max);
+                    nn.weights[i].data[j][k] = rand_float(rng, min, max);
                 }
             }
 
             for j in 0..nn.biases[i].rows {
                 for k in 0..nn.biases[i].cols {
-                    nn.biases[i].data[j][k] = rand_float(min, max);
+                    nn.biases[i].data[j][k] = rand_float(rng, min, max);
                 }
             }
         }
@@ -120,8 +319,8 @@ impl NN {
         }
     }
 
-    pub fn finite_diff(nn: &mut NN, g: &mut NN, eps: f32, t_input: &Mat, t_output: &Mat) {
-        let mut saved: f32;
+    pub fn finite_diff(nn: &mut NN, g: &mut NN, eps: Float, t_input: &Mat, t_output: &Mat) {
+        let mut saved: Float;
         let c = Self::cost(nn, &t_input.clone(), &t_output.clone());
 
         for i in 0..nn.count - 1 {
@@ -147,42 +346,234 @@ impl NN {
         }
     }
 
-    pub fn backprop(nn: &mut NN, g: &mut NN, t_input: &Mat, t_output: &Mat) {
+    // `rng` decides the dropped neurons. Bigger sets are split into chunks of
+    // PARALLEL_CHUNK samples whose gradients are computed on all cores and summed in
+    // order, so the result only depends on the samples and the seed. After
+    // set_backend(Backend::Gpu) the networks the GPU supports are done there instead.
+    pub fn backprop(nn: &mut NN, g: &mut NN, t_input: &Mat, t_output: &Mat, rng: &mut StdRng) {
         assert_eq!(t_input.rows, t_output.rows);
         let n = t_input.rows;
         assert_eq!(nn.activations[nn.count - 1].cols, t_output.cols);
 
         NN::zero(g);
 
-        for i in 0..n {
-            Mat::copy(&mut nn_input!(nn), &Mat::row(t_input, i));
+        #[cfg(feature = "gpu")]
+        if let Some(gpu) = gpu::GPU
+            .lock()
+            .unwrap()
+            .as_ref()
+            .filter(|_| gpu::Gpu::supports(nn))
+        {
+            gpu::Gpu::backprop(gpu, nn, g, t_input, t_output);
+            // the seeds the chunks would have taken, so the batches after are
+            // shuffled like on the CPU
+            if n > PARALLEL_CHUNK {
+                for _ in (0..n).step_by(PARALLEL_CHUNK) {
+                    rng.gen::<u64>();
+                }
+            }
+            // and activations left at the last sample, like backprop_rows leaves them
+            Mat::copy(&mut nn_input!(nn), &Mat::row(t_input, n - 1));
             Self::forward(nn);
+            Regularization::gradient(nn, g);
+            return;
+        }
+
+        if n <= PARALLEL_CHUNK {
+            Self::backprop_rows(nn, g, t_input, t_output, 0..n, n, rng);
+        } else {
+            // every chunk gets its own dropout generator, seeded in chunk order
+            let chunks: Vec<(Range<usize>, u64)> = (0..n)
+                .step_by(PARALLEL_CHUNK)
+                .map(|start| (start..(start + PARALLEL_CHUNK).min(n), rng.gen()))
+                .collect();
+            let workers = match BACKPROP_THREADS.load(Ordering::Relaxed) {
+                0 => thread::available_parallelism().map_or(1, |n| n.get()),
+                threads => threads,
+            }
+            .min(chunks.len());
+
+            let work = |w: usize| {
+                let mut nn = nn.clone();
+                let mut done = vec![];
+                for (c, (rows, seed)) in chunks.iter().enumerate().skip(w).step_by(workers) {
+                    let mut g = NN::new(&NN::arch(&nn));
+                    let mut rng = seeded_rng(*seed);
+                    Self::backprop_rows(
+                        &mut nn,
+                        &mut g,
+                        t_input,
+                        t_output,
+                        rows.clone(),
+                        n,
+                        &mut rng,
+                    );
+                    done.push((c, g));
+                }
+                (done, nn)
+            };
+
+            let mut partials: Vec<Option<NN>> = vec![None; chunks.len()];
+            let results: Vec<(Vec<(usize, NN)>, NN)> = if workers == 1 {
+                // the same chunks without a thread, the browser can't start one
+                vec![work(0)]
+            } else {
+                thread::scope(|s| {
+                    let handles: Vec<_> = (0..workers)
+                        .map(|w| {
+                            let work = &work;
+                            s.spawn(move || work(w))
+                        })
+                        .collect();
+                    handles
+                        .into_iter()
+                        .map(|handle| handle.join().unwrap())
+                        .collect()
+                })
+            };
+            // the worker of the last chunk ended on the last sample, its activations
+            // and dropout masks are what the sequential pass leaves in nn
+            let last = &results[(chunks.len() - 1) % workers].1;
+            nn.activations.clone_from(&last.activations);
+            nn.dropped.clone_from(&last.dropped);
+            for (done, _) in results {
+                for (c, partial) in done {
+                    partials[c] = Some(partial);
+                }
+            }
+
+            for partial in partials.into_iter().flatten() {
+                for l in 0..nn.count - 1 {
+                    Mat::sum(&mut g.weights[l], &partial.weights[l]);
+                    Mat::sum(&mut g.biases[l], &partial.biases[l]);
+                }
+            }
+        }
+
+        Regularization::gradient(nn, g);
+    }
+
+    // From class_weights, by the class of the sample's target
+    fn sample_weight(nn: &NN, target: &[Float]) -> Float {
+        if nn.class_weights.is_empty() {
+            return 1.0;
+        }
+        nn.class_weights
+            .get(class_of(target))
+            .copied()
+            .unwrap_or(1.0)
+    }
+
+    // Adds the gradient of the cost over `n` samples from `rows` to g
+    fn backprop_rows(
+        nn: &mut NN,
+        g: &mut NN,
+        t_input: &Mat,
+        t_output: &Mat,
+        rows: Range<usize>,
+        n: usize,
+        rng: &mut StdRng,
+    ) {
+        for i in rows {
+            Mat::copy(&mut nn_input!(nn), &Mat::row(t_input, i));
+            Self::forward_train(nn, rng);
 
             for j in 0..nn.count {
                 Mat::fill(&mut g.activations[j], 0.0);
             }
 
+            let weight = Self::sample_weight(nn, &t_output.data[i]);
             for j in 0..t_output.cols {
-                g.activations[nn.count - 1].data[0][j] =
-                    (nn_output!(nn).data[0][j] - t_output.data[i][j]) * 2.0 / n as f32;
+                g.activations[nn.count - 1].data[0][j] = weight
+                    * Loss::deriv(nn.loss, nn_output!(nn).data[0][j], t_output.data[i][j])
+                    / n as Float;
             }
 
             for l in (0..nn.count - 1).rev() {
                 for j in 0..nn.activations[l + 1].cols {
+                    if nn.dropped[l + 1][j] {
+                        continue;
+                    }
+                    // undo the dropout scaling to get the derivative of the activation
+                    let keep = 1.0 - nn.dropout[l + 1];
                     let a = nn.activations[l + 1].data[0][j];
                     let da = g.activations[l + 1].data[0][j];
-                    g.biases[l].data[0][j] += da * a * (1.0 - a);
-                    for k in 0..nn.activations[l].cols {
-                        let pa = nn.activations[l].data[0][k];
+                    let qa = Activation::deriv(nn.act[l], a * keep) / keep;
+                    g.biases[l].data[0][j] += da * qa;
+                    let prev = if l == 0 {
+                        &nn.input
+                    } else {
+                        &nn.activations[l]
+                    };
+                    for k in 0..prev.cols {
+                        let pa = prev.data[0][k];
                         let w = nn.weights[l].data[k][j];
-                        g.weights[l].data[k][j] += da * a * (1.0 - a) * pa;
-                        g.activations[l].data[0][k] += da * a * (1.0 - a) * w;
+                        g.weights[l].data[k][j] += da * qa * pa;
+                        g.activations[l].data[0][k] += da * qa * w;
                     }
                 }
             }
         }
     }
 
+    // Activation of the hidden layers, the output layer stays as it is
+    pub fn set_hidden_activation(nn: &mut NN, act: Activation) {
+        let hidden = nn.count.saturating_sub(2);
+        for a in &mut nn.act[..hidden] {
+            *a = act;
+        }
+    }
+
+    pub fn set_hidden_dropout(nn: &mut NN, p: Float) {
+        assert!((0.0..1.0).contains(&p));
+        let hidden = nn.count.saturating_sub(1);
+        for d in nn.dropout.iter_mut().take(hidden).skip(1) {
+            *d = p;
+        }
+    }
+
+    // L2 norm over all weights and biases
+    pub fn param_norm(nn: &NN) -> Float {
+        nn.weights
+            .iter()
+            .chain(nn.biases.iter())
+            .flat_map(|m| m.data.iter().flatten())
+            .map(|val| val * val)
+            .sum::<Float>()
+            .sqrt()
+    }
+
+    // L2 norm of every layer's weights and biases together, used on a gradient
+    // to spot vanishing or exploding layers
+    pub fn layer_norms(nn: &NN) -> Vec<Float> {
+        nn.weights
+            .iter()
+            .zip(&nn.biases)
+            .map(|(w, b)| {
+                w.data
+                    .iter()
+                    .chain(b.data.iter())
+                    .flatten()
+                    .map(|val| val * val)
+                    .sum::<Float>()
+                    .sqrt()
+            })
+            .collect()
+    }
+
+    // False once any weight or bias became NaN or infinite
+    pub fn is_finite(nn: &NN) -> bool {
+        nn.weights
+            .iter()
+            .chain(nn.biases.iter())
+            .all(|m| m.data.iter().flatten().all(|val| val.is_finite()))
+    }
+
+    // Layer sizes, the inverse of NN::new
+    pub fn arch(nn: &NN) -> Vec<usize> {
+        nn.activations.iter().map(|a| a.cols).collect()
+    }
+
     pub fn alloc(arch: &[usize]) -> NN {
         assert!(!arch.is_empty());
 
@@ -223,20 +614,38 @@ impl NN {
             weights,
             biases,
             activations,
+            act: vec![Activation::Sigmoid; count - 1],
+            reg: Regularization::default(),
+            dropout: vec![0.0; count],
+            dropped: arch.iter().map(|&n| vec![false; n]).collect(),
+            norm: Normalization::default(),
+            loss: Loss::default(),
+            class_weights: vec![],
+            pruned: vec![],
+            input: Mat::default(),
         }
     }
 }
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct Mat {
     pub rows: usize,
     pub cols: usize,
-    pub data: Vec<Vec<f32>>,
+    pub data: Vec<Vec<Float>>,
 }
 
 impl Mat {
-    pub fn new(data: &[&[f32]]) -> Mat {
+    // Panics on ragged rows, Mat::try_new returns the error instead
+    pub fn new(data: &[&[Float]]) -> Mat {
         let rows = data.len();
-        let cols = data[0].len();
+        let cols = data.first().map_or(0, |row| row.len());
+        if let Some(row) = data.iter().position(|row| row.len() != cols) {
+            panic!(
+                "Mat::new: row {} has {} values, expected {}",
+                row,
+                data[row].len(),
+                cols
+            );
+        }
 
         let mut mat = Mat {
             rows,
@@ -271,18 +680,38 @@ impl Mat {
         assert_eq!(dst.rows, a.rows);
         assert_eq!(dst.cols, b.cols);
 
+        #[cfg(feature = "blas")]
+        if a.rows * a.cols * b.cols >= blas::MIN_WORK {
+            blas::dot(dst, a, b);
+            return;
+        }
+
         Mat::fill(dst, 0.0);
 
         for (i, row) in dst.data.iter_mut().enumerate() {
             for (k, val) in a.data[i].iter().enumerate() {
-                for (j, val2) in row.iter_mut().enumerate() {
-                    *val2 += val * b.data[k][j];
-                }
+                simd::axpy(row, *val, &b.data[k]);
+            }
+        }
+    }
+
+    // Mat::dot without SIMD (or BLAS), gives the same result (see examples/matmul.rs
+    // for the difference in speed)
+    pub fn dot_scalar(dst: &mut Mat, a: &Mat, b: &Mat) {
+        assert_eq!(a.cols, b.rows);
+        assert_eq!(dst.rows, a.rows);
+        assert_eq!(dst.cols, b.cols);
+
+        Mat::fill(dst, 0.0);
+
+        for (i, row) in dst.data.iter_mut().enumerate() {
+            for (k, val) in a.data[i].iter().enumerate() {
+                simd::axpy_scalar(row, *val, &b.data[k]);
             }
         }
     }
 
-    pub fn fill(dst: &mut Mat, val: f32) {
+    pub fn fill(dst: &mut Mat, val: Float) {
         for i in 0..dst.rows {
             for j in 0..dst.cols {
                 dst.data[i][j] = val;
@@ -298,6 +727,14 @@ impl Mat {
         }
     }
 
+    pub fn act(dst: &mut Mat, act: Activation) {
+        for row in &mut dst.data {
+            for val in row.iter_mut() {
+                *val = Activation::apply(act, *val);
+            }
+        }
+    }
+
     pub fn row(mat: &Mat, row: usize) -> Mat {
         Mat {
             rows: 1,
@@ -315,14 +752,224 @@ impl Mat {
             }
         }
     }
+
+    // New matrix made of the given rows of mat, in that order
+    pub fn select_rows(mat: &Mat, rows: &[usize]) -> Mat {
+        Mat {
+            rows: rows.len(),
+            cols: mat.cols,
+            data: rows.iter().map(|&i| mat.data[i].clone()).collect(),
+        }
+    }
+
+    pub fn transpose(mat: &Mat) -> Mat {
+        Mat {
+            rows: mat.cols,
+            cols: mat.rows,
+            data: (0..mat.cols)
+                .map(|j| mat.data.iter().map(|row| row[j]).collect())
+                .collect(),
+        }
+    }
+
+    // Copies of a range of rows or columns. Every row is its own Vec, so there's no
+    // column to borrow and a row range is just &mat.data[rows].
+    pub fn slice_rows(mat: &Mat, rows: Range<usize>) -> Mat {
+        Mat {
+            rows: rows.len(),
+            cols: mat.cols,
+            data: mat.data[rows].to_vec(),
+        }
+    }
+
+    pub fn slice_cols(mat: &Mat, cols: Range<usize>) -> Mat {
+        Mat {
+            rows: mat.rows,
+            cols: cols.len(),
+            data: mat
+                .data
+                .iter()
+                .map(|row| row[cols.clone()].to_vec())
+                .collect(),
+        }
+    }
+
+    // Shuffles the rows of all of `mats` the same way, e.g. the inputs and outputs
+    // of a training set at the start of an epoch
+    pub fn shuffle_rows(mats: &mut [&mut Mat], rng: &mut StdRng) {
+        let Some(rows) = mats.first().map(|mat| mat.rows) else {
+            return;
+        };
+        assert!(mats.iter().all(|mat| mat.rows == rows));
+        // Fisher-Yates
+        for i in (1..rows).rev() {
+            let j = rng.gen_range(0..=i);
+            for mat in mats.iter_mut() {
+                mat.data.swap(i, j);
+            }
+        }
+    }
+
+    // The columns of all of `mats` side by side, they need the same number of rows
+    pub fn hconcat(mats: &[&Mat]) -> Mat {
+        let rows = mats.first().map_or(0, |mat| mat.rows);
+        assert!(mats.iter().all(|mat| mat.rows == rows));
+        Mat {
+            rows,
+            cols: mats.iter().map(|mat| mat.cols).sum(),
+            data: (0..rows)
+                .map(|i| mats.iter().flat_map(|mat| mat.data[i].clone()).collect())
+                .collect(),
+        }
+    }
+
+    // The rows of all of `mats` one after the other, they need the same number of
+    // columns
+    pub fn vconcat(mats: &[&Mat]) -> Mat {
+        let cols = mats.first().map_or(0, |mat| mat.cols);
+        assert!(mats.iter().all(|mat| mat.cols == cols));
+        Mat {
+            rows: mats.iter().map(|mat| mat.rows).sum(),
+            cols,
+            data: mats.iter().flat_map(|mat| mat.data.clone()).collect(),
+        }
+    }
+}
+
+// Randomly holds out a `validation` fraction of the samples,
+// returns ((train input, train output), (validation input, validation output))
+pub fn split(
+    t_input: &Mat,
+    t_output: &Mat,
+    validation: Float,
+    rng: &mut StdRng,
+) -> ((Mat, Mat), (Mat, Mat)) {
+    assert_eq!(t_input.rows, t_output.rows);
+    assert!((0.0..1.0).contains(&validation));
+    let n = t_input.rows;
+
+    let mut order: Vec<usize> = (0..n).collect();
+    order.shuffle(rng);
+    let (v_rows, t_rows) = order.split_at((n as Float * validation).round() as usize);
+
+    (
+        (
+            Mat::select_rows(t_input, t_rows),
+            Mat::select_rows(t_output, t_rows),
+        ),
+        (
+            Mat::select_rows(t_input, v_rows),
+            Mat::select_rows(t_output, v_rows),
+        ),
+    )
+}
+
+// Splits the training data into shuffled mini-batches of (at most) batch_size samples.
+// A batch_size of 0 or >= the sample count gives a single full batch.
+pub fn batches(
+    t_input: &Mat,
+    t_output: &Mat,
+    batch_size: usize,
+    rng: &mut StdRng,
+) -> Vec<(Mat, Mat)> {
+    assert_eq!(t_input.rows, t_output.rows);
+    let n = t_input.rows;
+    if batch_size == 0 || batch_size >= n {
+        return vec![(t_input.clone(), t_output.clone())];
+    }
+
+    let mut order: Vec<usize> = (0..n).collect();
+    order.shuffle(rng);
+
+    order
+        .chunks(batch_size)
+        .map(|rows| {
+            (
+                Mat::select_rows(t_input, rows),
+                Mat::select_rows(t_output, rows),
+            )
+        })
+        .collect()
 }
 
-pub fn sigmoidf(x: f32) -> f32 {
+pub fn sigmoidf(x: Float) -> Float {
+    #[cfg(feature = "fast-activations")]
+    if FAST_ACTIVATIONS.load(Ordering::Relaxed) {
+        return activation::fast_sigmoid(x);
+    }
     1.0 / (1.0 + (-x).exp())
 }
 
-pub fn rand_float(min: f32, max: f32) -> f32 {
-    rand::thread_rng().gen_range(min..max)
+pub fn tanhf(x: Float) -> Float {
+    #[cfg(feature = "fast-activations")]
+    if FAST_ACTIVATIONS.load(Ordering::Relaxed) {
+        return activation::fast_tanh(x);
+    }
+    x.tanh()
+}
+
+pub fn rand_float(rng: &mut StdRng, min: Float, max: Float) -> Float {
+    rng.gen_range(min..max)
+}
+
+// The same seed gives the same initialization, split, batches and dropout
+pub fn seeded_rng(seed: u64) -> StdRng {
+    StdRng::seed_from_u64(seed)
+}
+
+// For runs without a fixed seed, shown so they can be repeated
+pub fn random_seed() -> u64 {
+    rand::thread_rng().gen()
+}
+
+// Caps the threads of NN::backprop, 0 goes back to one per core. Only the speed
+// changes, the chunks are the same and summed in the same order on any number of
+// threads, so a seeded run gives the same weights bit for bit.
+pub fn set_backprop_threads(threads: usize) {
+    BACKPROP_THREADS.store(threads, Ordering::Relaxed);
+}
+
+// Switches sigmoidf and tanhf (so every sigmoid and tanh layer) to approximations
+// without exp, within 1e-4 of the exact ones, for every network in the program. Only
+// with the `fast-activations` feature, without it they stay exact. Exported code
+// and ONNX models always use the exact functions.
+pub fn set_fast_activations(on: bool) {
+    FAST_ACTIVATIONS.store(on, Ordering::Relaxed);
+}
+
+// Whether set_fast_activations does anything in this build
+pub fn has_fast_activations() -> bool {
+    cfg!(feature = "fast-activations")
+}
+
+// Where NN::backprop of every network in the program runs from now on, returns the
+// adapter's name. The GPU needs the `gpu` feature and an adapter with compute
+// shaders, otherwise this fails and backprop stays on the CPU. Networks with dropout
+// or custom activations are always trained on the CPU, the sums come out within
+// float rounding of the CPU's either way. Every batch is a round trip to the
+// adapter, so it only pays off for big layers and batches.
+pub fn set_backend(backend: Backend) -> Result<String, String> {
+    match backend {
+        Backend::Cpu => {
+            #[cfg(feature = "gpu")]
+            gpu::GPU.lock().unwrap().take();
+            Ok("the CPU".to_owned())
+        }
+        #[cfg(feature = "gpu")]
+        Backend::Gpu => {
+            let gpu = gpu::Gpu::new()?;
+            let name = gpu.name.clone();
+            *gpu::GPU.lock().unwrap() = Some(gpu);
+            Ok(name)
+        }
+        #[cfg(not(feature = "gpu"))]
+        Backend::Gpu => Err("built without the gpu feature".to_owned()),
+    }
+}
+
+// Whether set_backend(Backend::Gpu) can work in this build
+pub fn has_gpu() -> bool {
+    cfg!(feature = "gpu")
 }
 
 #[cfg(test)]
@@ -0,0 +1,83 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use serde::Serialize;
+
+// Training progress at one epoch
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct Metrics {
+    pub epoch: usize,
+    pub cost: f32,
+    // None for regression datasets
+    pub accuracy: Option<f32>,
+    pub learning_rate: f32,
+    // wall-clock seconds since training started
+    pub time: f32,
+}
+
+// Appends the metrics every `every` epochs to a CSV file with a header, or as JSON
+// lines when the path ends with .jsonl. Existing files are continued, not replaced.
+#[derive(Debug)]
+pub struct MetricsLogger {
+    pub path: PathBuf,
+    pub every: usize,
+    // opened on the first row
+    file: Option<File>,
+}
+
+impl MetricsLogger {
+    pub fn new(path: impl Into<PathBuf>, every: usize) -> MetricsLogger {
+        MetricsLogger {
+            path: path.into(),
+            every,
+            file: None,
+        }
+    }
+
+    // Whether log writes a row for this epoch, to skip computing the metrics otherwise
+    pub fn due(logger: &MetricsLogger, epoch: usize) -> bool {
+        logger.every != 0 && epoch.is_multiple_of(logger.every)
+    }
+
+    // Returns whether a row was written
+    pub fn log(logger: &mut MetricsLogger, metrics: &Metrics) -> io::Result<bool> {
+        if !Self::due(logger, metrics.epoch) {
+            return Ok(false);
+        }
+        let jsonl = logger.path.extension().is_some_and(|ext| ext == "jsonl");
+
+        let file = match &mut logger.file {
+            Some(file) => file,
+            None => {
+                let mut file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&logger.path)?;
+                if !jsonl && file.metadata()?.len() == 0 {
+                    writeln!(file, "epoch,cost,accuracy,learning_rate,time")?;
+                }
+                logger.file.insert(file)
+            }
+        };
+
+        if jsonl {
+            serde_json::to_writer(&mut *file, metrics)?;
+            writeln!(file)?;
+        } else {
+            writeln!(
+                file,
+                "{},{},{},{},{}",
+                metrics.epoch,
+                metrics.cost,
+                // empty for regression datasets
+                metrics.accuracy.map(|a| a.to_string()).unwrap_or_default(),
+                metrics.learning_rate,
+                metrics.time
+            )?;
+        }
+        Ok(true)
+    }
+}
@@ -0,0 +1,59 @@
+use serde::{Deserialize, Serialize};
+
+use super::Float;
+
+// Keeps ln() finite when an output saturates
+const EPS: Float = 1e-7;
+
+// What NN::cost sums over every output and NN::backprop differentiates
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Loss {
+    #[default]
+    Mse,
+    // binary cross-entropy, expects outputs in (0, 1)
+    CrossEntropy,
+    // mean absolute error, outliers pull on the fit no harder than any other sample
+    Mae,
+    // squared error within `delta` of the target and absolute error beyond it
+    Huber(Float),
+}
+
+impl Loss {
+    pub fn apply(loss: Loss, a: Float, y: Float) -> Float {
+        match loss {
+            Loss::Mse => (a - y) * (a - y),
+            Loss::CrossEntropy => {
+                let a = a.clamp(EPS, 1.0 - EPS);
+                -(y * a.ln() + (1.0 - y) * (1.0 - a).ln())
+            }
+            Loss::Mae => (a - y).abs(),
+            Loss::Huber(delta) => {
+                let d = (a - y).abs();
+                if d <= delta {
+                    0.5 * d * d
+                } else {
+                    delta * (d - 0.5 * delta)
+                }
+            }
+        }
+    }
+
+    // Derivative by the output `a`
+    pub fn deriv(loss: Loss, a: Float, y: Float) -> Float {
+        match loss {
+            Loss::Mse => 2.0 * (a - y),
+            Loss::CrossEntropy => {
+                let a = a.clamp(EPS, 1.0 - EPS);
+                (a - y) / (a * (1.0 - a))
+            }
+            Loss::Mae => {
+                if a == y {
+                    0.0
+                } else {
+                    (a - y).signum()
+                }
+            }
+            Loss::Huber(delta) => (a - y).clamp(-delta, delta),
+        }
+    }
+}
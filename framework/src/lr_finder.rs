@@ -0,0 +1,79 @@
+use super::{batches, Float, Mat, Optimizer, StdRng, NN};
+
+// Smoothing of the recorded batch costs, the raw ones are too noisy to read a slope from
+const SMOOTHING: Float = 0.9;
+// The test stops once the smoothed cost is this many times its lowest
+const BLOWUP: Float = 4.0;
+
+// Learning rate range test: trains a copy of the network for `steps` batches with
+// the rate growing exponentially from `min_rate` to `max_rate`, recording the
+// cost at every rate. The cost stays flat while the rate is too low, falls
+// once it's high enough and blows up when it's too high, a good rate is where it
+// falls fastest (see LrFinder::suggest).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LrFinder {
+    pub min_rate: Float,
+    pub max_rate: Float,
+    pub steps: usize,
+}
+
+impl LrFinder {
+    // (rate, smoothed cost) at every step, fewer of them when the cost blew up
+    // before `steps`. `optimizer` is cloned, its method and state are used as they are.
+    pub fn run(
+        finder: &LrFinder,
+        nn: &NN,
+        optimizer: &Optimizer,
+        t_input: &Mat,
+        t_output: &Mat,
+        batch_size: usize,
+        rng: &mut StdRng,
+    ) -> Vec<(Float, Float)> {
+        let mut nn = nn.clone();
+        let mut optimizer = optimizer.clone();
+        let mut gradient = NN::new(&NN::arch(&nn));
+        let growth = (finder.max_rate / finder.min_rate).ln() / finder.steps.max(2) as Float;
+
+        let mut curve = vec![];
+        let mut average = 0.0;
+        let mut best = Float::INFINITY;
+        let mut epoch = vec![];
+        for step in 0..finder.steps {
+            if epoch.is_empty() {
+                epoch = batches(t_input, t_output, batch_size, rng);
+                epoch.reverse();
+            }
+            let (x, y) = epoch.pop().unwrap();
+
+            let rate = finder.min_rate * (growth * step as Float).exp();
+            let cost = NN::cost(&nn, &x, &y);
+            average = SMOOTHING * average + (1.0 - SMOOTHING) * cost;
+            // without the bias towards the 0 it starts from
+            let smoothed = average / (1.0 - SMOOTHING.powi(step as i32 + 1));
+            if !smoothed.is_finite() || smoothed > BLOWUP * best {
+                break;
+            }
+            best = best.min(smoothed);
+            curve.push((rate, smoothed));
+
+            optimizer.rate = rate;
+            NN::backprop(&mut nn, &mut gradient, &x, &y, rng);
+            Optimizer::step(&mut optimizer, &mut nn, &gradient);
+        }
+        curve
+    }
+
+    // The rate where the cost fell fastest against log(rate), None for a curve that
+    // never falls
+    pub fn suggest(curve: &[(Float, Float)]) -> Option<Float> {
+        curve
+            .windows(2)
+            .map(|pair| {
+                let ((r1, c1), (r2, c2)) = (pair[0], pair[1]);
+                ((c2 - c1) / (r2.ln() - r1.ln()), r1)
+            })
+            .filter(|(slope, _)| *slope < 0.0)
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, rate)| rate)
+    }
+}
@@ -0,0 +1,73 @@
+use super::{argmax, Float, Mat, NN};
+
+// Targets that are all 0 or 1, a single output thresholded at 0.5 or one-hot classes
+pub fn is_classification(t_output: &Mat) -> bool {
+    t_output
+        .data
+        .iter()
+        .flatten()
+        .all(|&y| y == 0.0 || y == 1.0)
+}
+
+// Class of an output row, 0/1 for a single output and the argmax otherwise
+pub fn class_of(values: &[Float]) -> usize {
+    if values.len() == 1 {
+        (values[0] >= 0.5) as usize
+    } else {
+        argmax(values)
+    }
+}
+
+// Class weights (see NN::class_weights) that give every class the same total weight,
+// n / (classes * samples of the class), so the samples weigh 1 on average. Classes
+// without samples get 1.
+pub fn balanced_weights(t_output: &Mat) -> Vec<Float> {
+    let classes = t_output.cols.max(2);
+    let mut counts = vec![0usize; classes];
+    for row in &t_output.data {
+        counts[class_of(row)] += 1;
+    }
+    let present = counts.iter().filter(|&&count| count > 0).count();
+    counts
+        .iter()
+        .map(|&count| {
+            if count == 0 {
+                1.0
+            } else {
+                t_output.rows as Float / (present * count) as Float
+            }
+        })
+        .collect()
+}
+
+impl NN {
+    // Fraction of the samples whose predicted class matches the target's
+    pub fn accuracy(nn: &NN, t_input: &Mat, t_output: &Mat) -> Float {
+        assert_eq!(t_input.rows, t_output.rows);
+        let mut nn = nn.clone();
+        let mut correct = 0;
+        for i in 0..t_input.rows {
+            Mat::copy(&mut nn.activations[0], &Mat::row(t_input, i));
+            NN::forward(&mut nn);
+            if class_of(&nn.activations[nn.count - 1].data[0]) == class_of(&t_output.data[i]) {
+                correct += 1;
+            }
+        }
+        correct as Float / t_input.rows as Float
+    }
+
+    // counts[target][predicted] over the samples, at least 2 classes for a single output
+    pub fn confusion(nn: &NN, t_input: &Mat, t_output: &Mat) -> Vec<Vec<usize>> {
+        assert_eq!(t_input.rows, t_output.rows);
+        let classes = t_output.cols.max(2);
+        let mut counts = vec![vec![0; classes]; classes];
+        let mut nn = nn.clone();
+        for i in 0..t_input.rows {
+            Mat::copy(&mut nn.activations[0], &Mat::row(t_input, i));
+            NN::forward(&mut nn);
+            let predicted = class_of(&nn.activations[nn.count - 1].data[0]);
+            counts[class_of(&t_output.data[i])][predicted] += 1;
+        }
+        counts
+    }
+}
@@ -0,0 +1,91 @@
+use serde::{Deserialize, Serialize};
+
+use super::{Float, Mat, NN};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Scaling {
+    None,
+    // every input to 0..1 over the training samples
+    MinMax,
+    // every input to mean 0 and standard deviation 1 (z-score)
+    Standard,
+}
+
+// Per-input affine transform x' = (x - offset) * scale fitted on the training inputs.
+// Stored in the network so NN::forward takes raw inputs, empty means no scaling.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Normalization {
+    pub offset: Vec<Float>,
+    pub scale: Vec<Float>,
+}
+
+// Constant inputs would divide by zero, those are only shifted
+fn inverse(range: Float) -> Float {
+    if range > Float::EPSILON {
+        1.0 / range
+    } else {
+        1.0
+    }
+}
+
+impl Normalization {
+    pub fn fit(scaling: Scaling, t_input: &Mat) -> Normalization {
+        if scaling == Scaling::None {
+            return Normalization::default();
+        }
+        let n = t_input.rows as Float;
+        let column = |j: usize| t_input.data.iter().map(move |row| row[j]);
+
+        let (offset, scale) = (0..t_input.cols)
+            .map(|j| match scaling {
+                Scaling::None => unreachable!(),
+                Scaling::MinMax => {
+                    let min = column(j).fold(Float::INFINITY, Float::min);
+                    let max = column(j).fold(Float::NEG_INFINITY, Float::max);
+                    (min, inverse(max - min))
+                }
+                Scaling::Standard => {
+                    let mean = column(j).sum::<Float>() / n;
+                    let var = column(j).map(|x| (x - mean).powi(2)).sum::<Float>() / n;
+                    (mean, inverse(var.sqrt()))
+                }
+            })
+            .unzip();
+        Normalization { offset, scale }
+    }
+
+    // Scales every row of `input` in place
+    pub fn apply(norm: &Normalization, input: &mut Mat) {
+        if norm.offset.is_empty() {
+            return;
+        }
+        assert_eq!(input.cols, norm.offset.len());
+        for row in &mut input.data {
+            for (j, val) in row.iter_mut().enumerate() {
+                *val = (*val - norm.offset[j]) * norm.scale[j];
+            }
+        }
+    }
+}
+
+impl NN {
+    // The same network with the normalization folded into the first layer's weights
+    // and biases, for exports that only know about plain layers
+    pub fn fold_normalization(nn: &NN) -> NN {
+        let mut folded = nn.clone();
+        let norm = std::mem::take(&mut folded.norm);
+        if norm.offset.is_empty() {
+            return folded;
+        }
+
+        let w = &mut folded.weights[0];
+        let b = &mut folded.biases[0];
+        for k in 0..w.rows {
+            for j in 0..w.cols {
+                w.data[k][j] *= norm.scale[k];
+                b.data[0][j] -= norm.offset[k] * w.data[k][j];
+            }
+        }
+        folded
+    }
+}
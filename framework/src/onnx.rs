@@ -0,0 +1,159 @@
+use std::{fs, io, path::Path};
+
+use super::{Activation, Float, NN};
+
+// Minimal ONNX export: every layer becomes MatMul -> Add -> activation.
+// The protobuf messages are encoded by hand, only the fields used here are
+// written (field numbers from onnx.proto). The encoders are shared with the
+// TensorBoard event writer.
+
+const IR_VERSION: u64 = 7;
+const OPSET_VERSION: u64 = 13;
+// TensorProto.DataType.FLOAT
+const FLOAT: u64 = 1;
+
+pub(crate) fn varint(buf: &mut Vec<u8>, mut v: u64) {
+    while v >= 0x80 {
+        buf.push((v as u8) | 0x80);
+        v >>= 7;
+    }
+    buf.push(v as u8);
+}
+
+pub(crate) fn key(buf: &mut Vec<u8>, field: u64, wire_type: u64) {
+    varint(buf, (field << 3) | wire_type);
+}
+
+pub(crate) fn uint(buf: &mut Vec<u8>, field: u64, v: u64) {
+    key(buf, field, 0);
+    varint(buf, v);
+}
+
+pub(crate) fn bytes(buf: &mut Vec<u8>, field: u64, data: &[u8]) {
+    key(buf, field, 2);
+    varint(buf, data.len() as u64);
+    buf.extend_from_slice(data);
+}
+
+pub(crate) fn string(buf: &mut Vec<u8>, field: u64, s: &str) {
+    bytes(buf, field, s.as_bytes());
+}
+
+// TensorProto with the values stored as little endian raw_data, always 32 bit
+fn tensor(name: &str, dims: &[usize], values: &[Float]) -> Vec<u8> {
+    let mut buf = vec![];
+    for &d in dims {
+        uint(&mut buf, 1, d as u64);
+    }
+    uint(&mut buf, 2, FLOAT);
+    string(&mut buf, 8, name);
+    // a no-op unless the f64 feature is on
+    #[allow(clippy::unnecessary_cast)]
+    let raw: Vec<u8> = values
+        .iter()
+        .flat_map(|&v| (v as f32).to_le_bytes())
+        .collect();
+    bytes(&mut buf, 9, &raw);
+    buf
+}
+
+// ValueInfoProto of a float tensor with a symbolic batch dimension
+fn value_info(name: &str, size: usize) -> Vec<u8> {
+    let mut batch = vec![];
+    string(&mut batch, 2, "N");
+    let mut features = vec![];
+    uint(&mut features, 1, size as u64);
+
+    let mut shape = vec![];
+    bytes(&mut shape, 1, &batch);
+    bytes(&mut shape, 1, &features);
+
+    let mut tensor_type = vec![];
+    uint(&mut tensor_type, 1, FLOAT);
+    bytes(&mut tensor_type, 2, &shape);
+
+    let mut type_proto = vec![];
+    bytes(&mut type_proto, 1, &tensor_type);
+
+    let mut buf = vec![];
+    string(&mut buf, 1, name);
+    bytes(&mut buf, 2, &type_proto);
+    buf
+}
+
+fn node(op_type: &str, name: &str, inputs: &[&str], output: &str) -> Vec<u8> {
+    let mut buf = vec![];
+    for input in inputs {
+        string(&mut buf, 1, input);
+    }
+    string(&mut buf, 2, output);
+    string(&mut buf, 3, name);
+    string(&mut buf, 4, op_type);
+    buf
+}
+
+// Custom activations keep their name, which runtimes only know as a custom operator
+fn op_type(act: Activation) -> String {
+    match act {
+        Activation::Sigmoid => "Sigmoid".to_owned(),
+        Activation::Tanh => "Tanh".to_owned(),
+        Activation::Relu => "Relu".to_owned(),
+        Activation::Custom(_) => Activation::name(act),
+    }
+}
+
+impl NN {
+    // Serialized ONNX ModelProto taking "input" [N, arch[0]] to "output" [N, arch[last]]
+    pub fn to_onnx(nn: &NN) -> Vec<u8> {
+        let nn = &NN::fold_normalization(nn);
+        let arch = NN::arch(nn);
+        let mut graph = vec![];
+
+        let mut prev = "input".to_owned();
+        for l in 0..nn.count - 1 {
+            let w = format!("W{}", l);
+            let b = format!("B{}", l);
+            let mm = format!("matmul{}", l);
+            let z = format!("add{}", l);
+            let a = if l == nn.count - 2 {
+                "output".to_owned()
+            } else {
+                format!("act{}", l)
+            };
+
+            bytes(&mut graph, 1, &node("MatMul", &mm, &[&prev, &w], &mm));
+            bytes(&mut graph, 1, &node("Add", &z, &[&mm, &b], &z));
+            bytes(&mut graph, 1, &node(&op_type(nn.act[l]), &a, &[&z], &a));
+
+            let weights: Vec<Float> = nn.weights[l].data.iter().flatten().copied().collect();
+            let dims = [nn.weights[l].rows, nn.weights[l].cols];
+            bytes(&mut graph, 5, &tensor(&w, &dims, &weights));
+            bytes(
+                &mut graph,
+                5,
+                &tensor(&b, &[arch[l + 1]], &nn.biases[l].data[0]),
+            );
+
+            prev = a;
+        }
+
+        string(&mut graph, 2, "nn-rust");
+        bytes(&mut graph, 11, &value_info("input", arch[0]));
+        bytes(&mut graph, 12, &value_info("output", arch[nn.count - 1]));
+
+        let mut opset = vec![];
+        string(&mut opset, 1, "");
+        uint(&mut opset, 2, OPSET_VERSION);
+
+        let mut model = vec![];
+        uint(&mut model, 1, IR_VERSION);
+        string(&mut model, 2, "nn-rust");
+        bytes(&mut model, 7, &graph);
+        bytes(&mut model, 8, &opset);
+        model
+    }
+
+    pub fn export_onnx(nn: &NN, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, Self::to_onnx(nn))
+    }
+}
@@ -0,0 +1,206 @@
+use serde::{Deserialize, Serialize};
+
+use super::{Float, NN};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Method {
+    Sgd,
+    Momentum {
+        momentum: Float,
+        nesterov: bool,
+    },
+    Adam {
+        beta1: Float,
+        beta2: Float,
+        eps: Float,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Clip {
+    None,
+    // clamp every gradient component to [-max, max]
+    Value(Float),
+    // rescale the whole gradient so its L2 norm is at most max
+    Norm(Float),
+}
+
+impl Clip {
+    pub fn apply(clip: Clip, g: &mut NN) {
+        match clip {
+            Clip::None => {}
+            Clip::Value(max) => {
+                for m in g.weights.iter_mut().chain(g.biases.iter_mut()) {
+                    for val in m.data.iter_mut().flatten() {
+                        *val = val.clamp(-max, max);
+                    }
+                }
+            }
+            Clip::Norm(max) => {
+                let norm = NN::param_norm(g);
+                if norm > max {
+                    let scale = max / norm;
+                    for m in g.weights.iter_mut().chain(g.biases.iter_mut()) {
+                        for val in m.data.iter_mut().flatten() {
+                            *val *= scale;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+// What an Optimizer accumulates over the steps, saved with TrainingState
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct OptimizerState {
+    // velocity (Momentum) or first and second moment estimates (Adam)
+    m: Option<NN>,
+    v: Option<NN>,
+    t: i32,
+}
+
+// Applies a gradient (as computed by NN::backprop) to a network.
+// Per-parameter state is kept in NNs shaped like the network itself,
+// the same way the gradient is.
+#[derive(Clone, Debug)]
+pub struct Optimizer {
+    pub rate: Float,
+    pub method: Method,
+    pub clip: Clip,
+    state: OptimizerState,
+}
+
+impl Optimizer {
+    pub fn new(rate: Float, method: Method) -> Optimizer {
+        Optimizer {
+            rate,
+            method,
+            clip: Clip::None,
+            state: OptimizerState::default(),
+        }
+    }
+
+    pub fn sgd(rate: Float) -> Optimizer {
+        Self::new(rate, Method::Sgd)
+    }
+
+    pub fn momentum(rate: Float, momentum: Float, nesterov: bool) -> Optimizer {
+        Self::new(rate, Method::Momentum { momentum, nesterov })
+    }
+
+    pub fn adam(rate: Float) -> Optimizer {
+        Self::new(
+            rate,
+            Method::Adam {
+                beta1: 0.9,
+                beta2: 0.999,
+                eps: 1e-8,
+            },
+        )
+    }
+
+    // Forget the accumulated state, e.g. after the network was re-randomized
+    pub fn reset(opt: &mut Optimizer) {
+        opt.state = OptimizerState::default();
+    }
+
+    pub fn state(opt: &Optimizer) -> &OptimizerState {
+        &opt.state
+    }
+
+    // Continues from a saved state, which has to come from a network of the same
+    // architecture as the one being trained
+    pub fn restore(opt: &mut Optimizer, state: OptimizerState) {
+        opt.state = state;
+    }
+
+    pub fn step(opt: &mut Optimizer, nn: &mut NN, g: &NN) {
+        let clipped;
+        let g = if opt.clip == Clip::None {
+            g
+        } else {
+            let mut g = g.clone();
+            Clip::apply(opt.clip, &mut g);
+            clipped = g;
+            &clipped
+        };
+
+        if opt.method == Method::Sgd {
+            NN::learn(nn, g, opt.rate);
+            NN::zero_pruned(nn);
+            return;
+        }
+
+        let rate = opt.rate;
+        let method = opt.method;
+        let state = &mut opt.state;
+        state.t += 1;
+        let t = state.t;
+
+        let m = state.m.get_or_insert_with(|| NN::new(&NN::arch(nn)));
+        let v = state.v.get_or_insert_with(|| NN::new(&NN::arch(nn)));
+
+        for i in 0..nn.count - 1 {
+            for j in 0..nn.weights[i].rows {
+                for k in 0..nn.weights[i].cols {
+                    update(
+                        method,
+                        rate,
+                        t,
+                        &mut nn.weights[i].data[j][k],
+                        g.weights[i].data[j][k],
+                        &mut m.weights[i].data[j][k],
+                        &mut v.weights[i].data[j][k],
+                    );
+                }
+            }
+
+            for j in 0..nn.biases[i].rows {
+                for k in 0..nn.biases[i].cols {
+                    update(
+                        method,
+                        rate,
+                        t,
+                        &mut nn.biases[i].data[j][k],
+                        g.biases[i].data[j][k],
+                        &mut m.biases[i].data[j][k],
+                        &mut v.biases[i].data[j][k],
+                    );
+                }
+            }
+        }
+        NN::zero_pruned(nn);
+    }
+}
+
+// Update of a single parameter `p` with gradient `grad`, `m` and `v` being its state
+fn update(
+    method: Method,
+    rate: Float,
+    t: i32,
+    p: &mut Float,
+    grad: Float,
+    m: &mut Float,
+    v: &mut Float,
+) {
+    match method {
+        Method::Sgd => *p -= rate * grad,
+        Method::Momentum { momentum, nesterov } => {
+            // m is the velocity
+            *m = momentum * *m + grad;
+            if nesterov {
+                *p -= rate * (grad + momentum * *m);
+            } else {
+                *p -= rate * *m;
+            }
+        }
+        Method::Adam { beta1, beta2, eps } => {
+            *m = beta1 * *m + (1.0 - beta1) * grad;
+            *v = beta2 * *v + (1.0 - beta2) * grad * grad;
+            let m_hat = *m / (1.0 - beta1.powi(t));
+            let v_hat = *v / (1.0 - beta2.powi(t));
+            *p -= rate * m_hat / (v_hat.sqrt() + eps);
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use super::{Dataset, Float, Mat, StdRng};
+
+// Small built-in datasets
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Preset {
+    Xor,
+    And,
+    Or,
+    // 2 bit + 2 bit -> 3 bit sum
+    Adder,
+    // y = 1 - x
+    Linear,
+    // two interleaved spirals, one per class
+    Spiral,
+    Moons,
+    Circles,
+}
+
+// Size and jitter of the generated 2D presets
+const SAMPLES: usize = 100;
+const NOISE: Float = 0.03;
+
+fn mat(data: Vec<Vec<Float>>) -> Mat {
+    Mat {
+        rows: data.len(),
+        cols: data[0].len(),
+        data,
+    }
+}
+
+fn names(prefix: &str, n: usize) -> Vec<String> {
+    (0..n).map(|i| format!("{}{}", prefix, i)).collect()
+}
+
+// All combinations of two binary inputs mapped through `f`
+fn gate(f: fn(bool, bool) -> bool) -> Dataset {
+    let mut t_input = vec![];
+    let mut t_output = vec![];
+    for a in [false, true] {
+        for b in [false, true] {
+            t_input.push(vec![a as u8 as Float, b as u8 as Float]);
+            t_output.push(vec![f(a, b) as u8 as Float]);
+        }
+    }
+    Dataset {
+        input_names: vec!["a".to_owned(), "b".to_owned()],
+        output_names: vec!["y".to_owned()],
+        t_input: mat(t_input),
+        t_output: mat(t_output),
+    }
+}
+
+// Little endian bits of n
+fn bits(n: usize, count: usize) -> Vec<Float> {
+    (0..count).map(|i| ((n >> i) & 1) as Float).collect()
+}
+
+impl Preset {
+    pub const ALL: [Preset; 8] = [
+        Preset::Xor,
+        Preset::And,
+        Preset::Or,
+        Preset::Adder,
+        Preset::Linear,
+        Preset::Spiral,
+        Preset::Moons,
+        Preset::Circles,
+    ];
+
+    pub fn name(preset: Preset) -> &'static str {
+        match preset {
+            Preset::Xor => "XOR",
+            Preset::And => "AND",
+            Preset::Or => "OR",
+            Preset::Adder => "2-bit adder",
+            Preset::Linear => "Linear (1 - x)",
+            Preset::Spiral => "Spiral",
+            Preset::Moons => "Moons",
+            Preset::Circles => "Circles",
+        }
+    }
+
+    // The variant name in any case ("xor", "Moons"), for picking one on the command line
+    pub fn from_name(name: &str) -> Option<Preset> {
+        Preset::ALL
+            .into_iter()
+            .find(|preset| format!("{:?}", preset).eq_ignore_ascii_case(name))
+    }
+
+    // `rng` adds the noise of the generated datasets
+    pub fn build(preset: Preset, rng: &mut StdRng) -> Dataset {
+        match preset {
+            Preset::Xor => gate(|a, b| a ^ b),
+            Preset::And => gate(|a, b| a & b),
+            Preset::Or => gate(|a, b| a | b),
+            Preset::Adder => {
+                let mut t_input = vec![];
+                let mut t_output = vec![];
+                for a in 0..4 {
+                    for b in 0..4 {
+                        let mut input = bits(a, 2);
+                        input.extend(bits(b, 2));
+                        t_input.push(input);
+                        t_output.push(bits(a + b, 3));
+                    }
+                }
+                let mut input_names = names("a", 2);
+                input_names.extend(names("b", 2));
+                Dataset {
+                    input_names,
+                    output_names: names("sum", 3),
+                    t_input: mat(t_input),
+                    t_output: mat(t_output),
+                }
+            }
+            Preset::Linear => {
+                let xs: Vec<Float> = (0..=10).map(|i| i as Float / 10.0).collect();
+                Dataset {
+                    input_names: vec!["x".to_owned()],
+                    output_names: vec!["y".to_owned()],
+                    t_input: mat(xs.iter().map(|&x| vec![x]).collect()),
+                    t_output: mat(xs.iter().map(|&x| vec![1.0 - x]).collect()),
+                }
+            }
+            Preset::Spiral => Dataset::spirals(SAMPLES, 0.0, rng),
+            Preset::Moons => Dataset::moons(SAMPLES, NOISE, rng),
+            Preset::Circles => Dataset::circles(SAMPLES, NOISE, rng),
+        }
+    }
+}
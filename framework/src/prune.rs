@@ -0,0 +1,69 @@
+use super::{Float, NN};
+
+impl NN {
+    // Magnitude pruning: zeroes the smallest weights of every layer until `fraction`
+    // of its weights are, the biases stay. Optimizer::step keeps them at zero, so
+    // training on fine-tunes the rest around them. Every layer loses the same share,
+    // pruning by the magnitudes over the whole network would empty the layers with the
+    // smallest weights first.
+    pub fn prune(nn: &mut NN, fraction: Float) {
+        let fraction = fraction.clamp(0.0, 1.0);
+        if nn.pruned.len() != nn.weights.len() {
+            nn.pruned = nn
+                .weights
+                .iter()
+                .map(|w| vec![vec![false; w.cols]; w.rows])
+                .collect();
+        }
+        for (w, pruned) in nn.weights.iter_mut().zip(&mut nn.pruned) {
+            let mut order: Vec<(usize, usize)> = (0..w.rows)
+                .flat_map(|j| (0..w.cols).map(move |k| (j, k)))
+                .collect();
+            // the ones pruned before first, they're zero anyway
+            order.sort_by(|&(a, b), &(c, d)| {
+                pruned[c][d]
+                    .cmp(&pruned[a][b])
+                    .then(w.data[a][b].abs().total_cmp(&w.data[c][d].abs()))
+            });
+            let count = (fraction * order.len() as Float).round() as usize;
+            for &(j, k) in &order[..count] {
+                pruned[j][k] = true;
+                w.data[j][k] = 0.0;
+            }
+        }
+    }
+
+    // Lets every weight train again, the pruned ones from zero
+    pub fn unprune(nn: &mut NN) {
+        nn.pruned.clear();
+    }
+
+    pub fn is_pruned(nn: &NN, layer: usize, row: usize, col: usize) -> bool {
+        nn.pruned
+            .get(layer)
+            .and_then(|rows| rows.get(row))
+            .and_then(|cols| cols.get(col))
+            .copied()
+            .unwrap_or(false)
+    }
+
+    // Fraction of all the weights that are pruned
+    pub fn sparsity(nn: &NN) -> Float {
+        let total: usize = nn.weights.iter().map(|w| w.rows * w.cols).sum();
+        let pruned = nn.pruned.iter().flatten().flatten().filter(|&&p| p).count();
+        pruned as Float / total.max(1) as Float
+    }
+
+    // After every step, a gradient moves the pruned weights too
+    pub(crate) fn zero_pruned(nn: &mut NN) {
+        for (w, pruned) in nn.weights.iter_mut().zip(&nn.pruned) {
+            for (row, pruned) in w.data.iter_mut().zip(pruned) {
+                for (val, &pruned) in row.iter_mut().zip(pruned) {
+                    if pruned {
+                        *val = 0.0;
+                    }
+                }
+            }
+        }
+    }
+}
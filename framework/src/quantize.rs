@@ -0,0 +1,214 @@
+use std::{fmt::Write, fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    class_of,
+    codegen::{floats, fn_body},
+    Activation, Float, Mat, NN,
+};
+
+// A trained network with its weights rounded to int8, one scale per layer
+// (weight = scale * q), for microcontrollers without much flash or an FPU to spare.
+// The biases stay floats, there are few of them. Every layer's input is rounded to
+// int8 too, with a scale from its largest value, so the sums are integer multiply
+// adds.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct QuantizedNN {
+    pub arch: Vec<usize>,
+    // input by input, every input's weights to all the outputs of the layer
+    pub weights: Vec<Vec<i8>>,
+    pub scales: Vec<Float>,
+    pub biases: Vec<Vec<Float>>,
+    pub act: Vec<Activation>,
+}
+
+// Rounds to -127..=127 with the largest magnitude at 127, and the scale back
+fn quantize(values: &[Float]) -> (Vec<i8>, Float) {
+    let max = values.iter().fold(0.0 as Float, |m, v| m.max(v.abs()));
+    let scale = if max > 0.0 { max / 127.0 } else { 1.0 };
+    let q = values
+        .iter()
+        .map(|v| (v / scale).round().clamp(-127.0, 127.0) as i8)
+        .collect();
+    (q, scale)
+}
+
+impl QuantizedNN {
+    // The input normalization is folded into the first layer first
+    pub fn new(nn: &NN) -> QuantizedNN {
+        let nn = NN::fold_normalization(nn);
+        let (mut weights, mut scales) = (vec![], vec![]);
+        for w in &nn.weights {
+            let (q, scale) = quantize(&w.data.concat());
+            weights.push(q);
+            scales.push(scale);
+        }
+        QuantizedNN {
+            arch: NN::arch(&nn),
+            weights,
+            scales,
+            biases: nn.biases.iter().map(|b| b.data[0].clone()).collect(),
+            act: nn.act.clone(),
+        }
+    }
+
+    pub fn forward(q: &QuantizedNN, input: &[Float]) -> Vec<Float> {
+        assert_eq!(input.len(), q.arch[0]);
+        let mut a = input.to_vec();
+        for l in 0..q.arch.len() - 1 {
+            let cols = q.arch[l + 1];
+            let (x, input_scale) = quantize(&a);
+            let mut sums = vec![0i32; cols];
+            for (i, &x) in x.iter().enumerate() {
+                let row = &q.weights[l][i * cols..(i + 1) * cols];
+                for (sum, &w) in sums.iter_mut().zip(row) {
+                    *sum += x as i32 * w as i32;
+                }
+            }
+            a = sums
+                .iter()
+                .zip(&q.biases[l])
+                .map(|(&sum, &b)| {
+                    Activation::apply(q.act[l], b + sum as Float * input_scale * q.scales[l])
+                })
+                .collect();
+        }
+        a
+    }
+
+    // Like NN::accuracy, to compare with the network it came from
+    pub fn accuracy(q: &QuantizedNN, t_input: &Mat, t_output: &Mat) -> Float {
+        assert_eq!(t_input.rows, t_output.rows);
+        let correct = (0..t_input.rows)
+            .filter(|&i| {
+                class_of(&Self::forward(q, &t_input.data[i])) == class_of(&t_output.data[i])
+            })
+            .count();
+        correct as Float / t_input.rows as Float
+    }
+
+    // Largest difference between an output and the network's, over the samples
+    pub fn max_error(q: &QuantizedNN, nn: &NN, t_input: &Mat) -> Float {
+        let mut nn = nn.clone();
+        let mut error: Float = 0.0;
+        for i in 0..t_input.rows {
+            Mat::copy(&mut nn.activations[0], &Mat::row(t_input, i));
+            NN::forward(&mut nn);
+            let output = Self::forward(q, &t_input.data[i]);
+            for (a, b) in output.iter().zip(&nn.activations[nn.count - 1].data[0]) {
+                error = error.max((a - b).abs());
+            }
+        }
+        error
+    }
+
+    // Bytes of the weights, scales and biases
+    pub fn size(q: &QuantizedNN) -> usize {
+        let floats = q.scales.len() + q.biases.iter().map(Vec::len).sum::<usize>();
+        q.weights.iter().map(Vec::len).sum::<usize>() + floats * std::mem::size_of::<f32>()
+    }
+
+    // Standalone Rust source like NN::to_rust, with i8 weight consts and the same
+    // forward pass as QuantizedNN::forward
+    pub fn to_rust(q: &QuantizedNN) -> io::Result<String> {
+        let mut src = String::new();
+        writeln!(
+            src,
+            "// Generated by nn-rust, int8 weights with a scale per layer, architecture {:?}",
+            q.arch
+        )
+        .unwrap();
+        writeln!(src).unwrap();
+
+        for l in 0..q.arch.len() - 1 {
+            let (rows, cols) = (q.arch[l], q.arch[l + 1]);
+            writeln!(src, "pub const W{}: [[i8; {}]; {}] = [", l, cols, rows).unwrap();
+            for row in q.weights[l].chunks(cols) {
+                writeln!(src, "    {:?},", row).unwrap();
+            }
+            writeln!(src, "];").unwrap();
+            writeln!(src, "pub const S{}: f32 = {:?};", l, q.scales[l]).unwrap();
+            writeln!(
+                src,
+                "pub const B{}: [f32; {}] = {};",
+                l,
+                cols,
+                floats(&q.biases[l])
+            )
+            .unwrap();
+            writeln!(src).unwrap();
+        }
+
+        let last = q.arch.len() - 1;
+        writeln!(
+            src,
+            "pub fn forward(input: [f32; {}]) -> [f32; {}] {{",
+            q.arch[0], q.arch[last]
+        )
+        .unwrap();
+        writeln!(src, "    let a0 = input;").unwrap();
+        for l in 0..last {
+            let call = format!(
+                "layer(&a{}, &W{}, S{}, &B{}, {})",
+                l,
+                l,
+                l,
+                l,
+                Activation::name(q.act[l])
+            );
+            if l + 1 < last {
+                writeln!(src, "    let a{} = {};", l + 1, call).unwrap();
+            } else {
+                writeln!(src, "    {}", call).unwrap();
+            }
+        }
+        writeln!(src, "}}").unwrap();
+        writeln!(src).unwrap();
+
+        src.push_str(
+            "fn layer<const I: usize, const O: usize>(
+    input: &[f32; I],
+    w: &[[i8; O]; I],
+    scale: f32,
+    b: &[f32; O],
+    act: fn(f32) -> f32,
+) -> [f32; O] {
+    // the input to int8 as well, so the sums are integer
+    let max = input.iter().fold(0.0f32, |m, x| m.max(x.abs()));
+    let input_scale = if max > 0.0 { max / 127.0 } else { 1.0 };
+    let mut sums = [0i32; O];
+    for (i, x) in input.iter().enumerate() {
+        let x = (x / input_scale).round().clamp(-127.0, 127.0) as i32;
+        for (o, sum) in sums.iter_mut().enumerate() {
+            *sum += x * w[i][o] as i32;
+        }
+    }
+    let mut out = *b;
+    for (val, sum) in out.iter_mut().zip(sums) {
+        *val += sum as f32 * input_scale * scale;
+    }
+    out.map(act)
+}
+",
+        );
+
+        let mut emitted = vec![];
+        for &act in &q.act {
+            if emitted.contains(&act) {
+                continue;
+            }
+            emitted.push(act);
+            writeln!(src).unwrap();
+            writeln!(src, "fn {}(x: f32) -> f32 {{", Activation::name(act)).unwrap();
+            writeln!(src, "    {}", fn_body(act)?).unwrap();
+            writeln!(src, "}}").unwrap();
+        }
+
+        Ok(src)
+    }
+
+    pub fn export_rust(q: &QuantizedNN, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, Self::to_rust(q)?)
+    }
+}
@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+
+use super::{Float, NN};
+
+// Weight decay, applied to the weights only (not the biases).
+// The penalty is added once to NN::cost, not per sample.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Regularization {
+    pub l1: Float,
+    pub l2: Float,
+}
+
+impl Regularization {
+    pub fn penalty(nn: &NN) -> Float {
+        let reg = nn.reg;
+        if reg == Regularization::default() {
+            return 0.0;
+        }
+
+        let mut penalty = 0.0;
+        for w in &nn.weights {
+            for val in w.data.iter().flatten() {
+                penalty += reg.l1 * val.abs() + reg.l2 * val * val;
+            }
+        }
+        penalty
+    }
+
+    // Adds the gradient of the penalty to g
+    pub fn gradient(nn: &NN, g: &mut NN) {
+        let reg = nn.reg;
+        if reg == Regularization::default() {
+            return;
+        }
+
+        for i in 0..nn.count - 1 {
+            for j in 0..nn.weights[i].rows {
+                for k in 0..nn.weights[i].cols {
+                    let w = nn.weights[i].data[j][k];
+                    // 0 for w == 0, like the subgradient usually picked for |w|
+                    let sign = if w > 0.0 {
+                        1.0
+                    } else if w < 0.0 {
+                        -1.0
+                    } else {
+                        0.0
+                    };
+                    g.weights[i].data[j][k] += reg.l1 * sign + 2.0 * reg.l2 * w;
+                }
+            }
+        }
+    }
+}
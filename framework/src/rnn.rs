@@ -0,0 +1,209 @@
+use super::{init, layer::zeros, Activation, Float, Init, Loss, Mat, StdRng};
+
+// A sequence of samples in time order, row t of t_input is the input at step t and
+// row t of t_output what the network should answer right after seeing it
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sequence {
+    pub t_input: Mat,
+    pub t_output: Mat,
+}
+
+impl Sequence {
+    // `len` steps of next-bit prediction on `pattern` repeated forever
+    pub fn repeating(pattern: &[bool], len: usize) -> Sequence {
+        assert!(!pattern.is_empty());
+        let bit = |t: usize| if pattern[t % pattern.len()] { 1.0 } else { 0.0 };
+        let column = |offset: usize| Mat {
+            rows: len,
+            cols: 1,
+            data: (0..len).map(|t| vec![bit(t + offset)]).collect(),
+        };
+        Sequence {
+            t_input: column(0),
+            t_output: column(1),
+        }
+    }
+}
+
+// Weights of an Elman network, also used for their gradients
+#[derive(Clone, Debug, PartialEq)]
+pub struct RnnWeights {
+    // input -> hidden, hidden -> hidden (previous step), hidden bias
+    pub wx: Mat,
+    pub wh: Mat,
+    pub bh: Mat,
+    // hidden -> output, output bias
+    pub wy: Mat,
+    pub by: Mat,
+}
+
+impl RnnWeights {
+    fn zeros(inputs: usize, hidden: usize, outputs: usize) -> RnnWeights {
+        RnnWeights {
+            wx: zeros(inputs, hidden),
+            wh: zeros(hidden, hidden),
+            bh: zeros(1, hidden),
+            wy: zeros(hidden, outputs),
+            by: zeros(1, outputs),
+        }
+    }
+
+    fn mats(w: &mut RnnWeights) -> [&mut Mat; 5] {
+        [&mut w.wx, &mut w.wh, &mut w.bh, &mut w.wy, &mut w.by]
+    }
+}
+
+// Elman recurrent network: h_t = act(x_t wx + h_t-1 wh + bh), y_t = out_act(h_t wy + by).
+// The hidden state carries over between calls to Rnn::step until Rnn::reset.
+#[derive(Clone, Debug)]
+pub struct Rnn {
+    pub w: RnnWeights,
+    pub act: Activation,
+    pub out_act: Activation,
+    pub loss: Loss,
+    pub state: Mat,
+    g: RnnWeights,
+}
+
+impl Rnn {
+    // Zero weights, initialize them with Rnn::init
+    pub fn new(inputs: usize, hidden: usize, outputs: usize) -> Rnn {
+        Rnn {
+            w: RnnWeights::zeros(inputs, hidden, outputs),
+            act: Activation::Tanh,
+            out_act: Activation::Sigmoid,
+            loss: Loss::default(),
+            state: zeros(1, hidden),
+            g: RnnWeights::zeros(inputs, hidden, outputs),
+        }
+    }
+
+    pub fn init(rnn: &mut Rnn, init: Init, rng: &mut StdRng) {
+        let pick = |act| match init {
+            Init::Auto => Init::for_activation(act),
+            init => init,
+        };
+        init::init_layer(pick(rnn.act), &mut rnn.w.wx, rng);
+        init::init_layer(pick(rnn.act), &mut rnn.w.wh, rng);
+        init::init_layer(pick(rnn.out_act), &mut rnn.w.wy, rng);
+        Mat::fill(&mut rnn.w.bh, 0.0);
+        Mat::fill(&mut rnn.w.by, 0.0);
+        Self::reset(rnn);
+    }
+
+    pub fn reset(rnn: &mut Rnn) {
+        Mat::fill(&mut rnn.state, 0.0);
+    }
+
+    // Gradient of the last Rnn::backprop, in the same layout as the weights
+    pub fn gradient(rnn: &Rnn) -> &RnnWeights {
+        &rnn.g
+    }
+
+    // One time step, returns the output and advances the hidden state
+    pub fn step(rnn: &mut Rnn, x: &Mat) -> Mat {
+        let mut h = zeros(1, rnn.state.cols);
+        let mut recurrent = zeros(1, rnn.state.cols);
+        Mat::dot(&mut h, x, &rnn.w.wx);
+        Mat::dot(&mut recurrent, &rnn.state, &rnn.w.wh);
+        Mat::sum(&mut h, &recurrent);
+        Mat::sum(&mut h, &rnn.w.bh);
+        Mat::act(&mut h, rnn.act);
+        rnn.state = h;
+
+        let mut y = zeros(1, rnn.w.wy.cols);
+        Mat::dot(&mut y, &rnn.state, &rnn.w.wy);
+        Mat::sum(&mut y, &rnn.w.by);
+        Mat::act(&mut y, rnn.out_act);
+        y
+    }
+
+    // Summed loss over the whole sequence, starting from a zero state
+    pub fn cost(rnn: &Rnn, seq: &Sequence) -> Float {
+        let mut rnn = rnn.clone();
+        Self::reset(&mut rnn);
+        let mut cost = 0.0;
+        for t in 0..seq.t_input.rows {
+            let y = Self::step(&mut rnn, &Mat::row(&seq.t_input, t));
+            for (a, target) in y.data[0].iter().zip(&seq.t_output.data[t]) {
+                cost += Loss::apply(rnn.loss, *a, *target);
+            }
+        }
+        cost
+    }
+
+    // Truncated backprop through time: the sequence is cut into windows of `window`
+    // steps, the hidden state carries over from one window to the next but the
+    // gradient doesn't flow back past the start of a window. Sets the gradient to
+    // that of the mean cost per step. Starts from a zero state.
+    pub fn backprop(rnn: &mut Rnn, seq: &Sequence, window: usize) {
+        assert!(window > 0);
+        assert_eq!(seq.t_input.rows, seq.t_output.rows);
+        for g in RnnWeights::mats(&mut rnn.g) {
+            Mat::fill(g, 0.0);
+        }
+        Self::reset(rnn);
+        let n = seq.t_input.rows as Float;
+
+        let mut start = 0;
+        while start < seq.t_input.rows {
+            let end = (start + window).min(seq.t_input.rows);
+            // states[0] is the one carried in, states[t - start + 1] the one after step t
+            let mut states = vec![rnn.state.clone()];
+            let mut outputs = Vec::new();
+            for t in start..end {
+                outputs.push(Self::step(rnn, &Mat::row(&seq.t_input, t)));
+                states.push(rnn.state.clone());
+            }
+
+            let hidden = rnn.state.cols;
+            let mut dh_next = vec![0.0; hidden];
+            for t in (start..end).rev() {
+                let s = t - start;
+                let x = &seq.t_input.data[t];
+                let (prev, h) = (&states[s].data[0], &states[s + 1].data[0]);
+                let y = &outputs[s].data[0];
+
+                let dy: Vec<Float> = y
+                    .iter()
+                    .zip(&seq.t_output.data[t])
+                    .map(|(a, target)| {
+                        Loss::deriv(rnn.loss, *a, *target) / n * Activation::deriv(rnn.out_act, *a)
+                    })
+                    .collect();
+                let mut dh = dh_next.clone();
+                for (k, d) in dy.iter().enumerate() {
+                    rnn.g.by.data[0][k] += d;
+                    for j in 0..hidden {
+                        rnn.g.wy.data[j][k] += h[j] * d;
+                        dh[j] += rnn.w.wy.data[j][k] * d;
+                    }
+                }
+
+                dh_next = vec![0.0; hidden];
+                for j in 0..hidden {
+                    let dz = dh[j] * Activation::deriv(rnn.act, h[j]);
+                    rnn.g.bh.data[0][j] += dz;
+                    for (i, xi) in x.iter().enumerate() {
+                        rnn.g.wx.data[i][j] += xi * dz;
+                    }
+                    for i in 0..hidden {
+                        rnn.g.wh.data[i][j] += prev[i] * dz;
+                        dh_next[i] += rnn.w.wh.data[i][j] * dz;
+                    }
+                }
+            }
+            start = end;
+        }
+    }
+
+    // Plain gradient descent step with the last Rnn::backprop gradient
+    pub fn learn(rnn: &mut Rnn, rate: Float) {
+        let Rnn { w, g, .. } = rnn;
+        for (w, g) in RnnWeights::mats(w).into_iter().zip(RnnWeights::mats(g)) {
+            for (val, d) in w.data.iter_mut().flatten().zip(g.data.iter().flatten()) {
+                *val -= rate * d;
+            }
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use super::{Float, PI};
+
+// Learning rate as a function of the epoch, relative to a base rate
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Schedule {
+    Constant,
+    // base * gamma^(epoch / step)
+    Step { step: usize, gamma: Float },
+    // base * gamma^epoch
+    Exponential { gamma: Float },
+    // half a cosine from base down to min_rate over `epochs`, then stays at min_rate
+    Cosine { epochs: usize, min_rate: Float },
+    // triangular cycles of `cycle` epochs, from min_rate up to base and back down
+    Cyclical { cycle: usize, min_rate: Float },
+}
+
+impl Schedule {
+    pub fn rate(schedule: &Schedule, base: Float, epoch: usize) -> Float {
+        match *schedule {
+            Schedule::Constant => base,
+            Schedule::Step { step, gamma } => base * gamma.powi((epoch / step.max(1)) as i32),
+            Schedule::Exponential { gamma } => base * gamma.powf(epoch as Float),
+            Schedule::Cosine { epochs, min_rate } => {
+                if epoch >= epochs {
+                    return min_rate;
+                }
+                let t = epoch as Float / epochs as Float;
+                min_rate + (base - min_rate) * 0.5 * (1.0 + (PI * t).cos())
+            }
+            Schedule::Cyclical { cycle, min_rate } => {
+                let t = (epoch % cycle.max(1)) as Float / cycle.max(1) as Float;
+                min_rate + (base - min_rate) * (1.0 - (2.0 * t - 1.0).abs())
+            }
+        }
+    }
+}
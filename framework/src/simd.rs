@@ -0,0 +1,44 @@
+use super::Float;
+
+// a += x * b element-wise, the inner loop of Mat::dot. Uses AVX when the CPU has
+// it (for f32 only), the result is the same as the plain loop's since every element
+// still gets one multiply and one add (no FMA).
+pub(crate) fn axpy(a: &mut [Float], x: Float, b: &[Float]) {
+    assert_eq!(a.len(), b.len());
+    #[cfg(all(target_arch = "x86_64", not(feature = "f64")))]
+    if is_x86_feature_detected!("avx") {
+        // SAFETY: the CPU supports AVX
+        unsafe { axpy_avx(a, x, b) };
+        return;
+    }
+    axpy_scalar(a, x, b);
+}
+
+pub(crate) fn axpy_scalar(a: &mut [Float], x: Float, b: &[Float]) {
+    for (val, other) in a.iter_mut().zip(b) {
+        *val += x * other;
+    }
+}
+
+#[cfg(all(target_arch = "x86_64", not(feature = "f64")))]
+#[target_feature(enable = "avx")]
+unsafe fn axpy_avx(a: &mut [f32], x: f32, b: &[f32]) {
+    use std::arch::x86_64::{
+        _mm256_add_ps, _mm256_loadu_ps, _mm256_mul_ps, _mm256_set1_ps, _mm256_storeu_ps,
+    };
+
+    let n = a.len();
+    let xs = _mm256_set1_ps(x);
+    let mut i = 0;
+    // 8 lanes at a time, the rest one by one
+    while i + 8 <= n {
+        let va = _mm256_loadu_ps(a.as_ptr().add(i));
+        let vb = _mm256_loadu_ps(b.as_ptr().add(i));
+        _mm256_storeu_ps(
+            a.as_mut_ptr().add(i),
+            _mm256_add_ps(va, _mm256_mul_ps(xs, vb)),
+        );
+        i += 8;
+    }
+    axpy_scalar(&mut a[i..], x, &b[i..]);
+}
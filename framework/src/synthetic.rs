@@ -0,0 +1,106 @@
+use super::{init::rand_normal, Dataset, Float, Mat, StdRng, PI};
+
+// Classic 2D two-class problems. Points lie roughly in the unit square, class 0
+// and 1 alternate sample by sample and `noise` is the standard deviation of the
+// gaussian jitter added to every coordinate.
+
+fn two_class(
+    samples: usize,
+    noise: Float,
+    rng: &mut StdRng,
+    point: impl Fn(usize, Float) -> (Float, Float),
+) -> Dataset {
+    // the same number of points for both classes, evenly spaced along the curve
+    let per_class = samples.div_ceil(2).max(1);
+    let points: Vec<_> = (0..samples)
+        .map(|i| {
+            let class = i % 2;
+            let (x, y) = point(class, (i / 2) as Float / per_class as Float);
+            (
+                x + rand_normal(rng) * noise,
+                y + rand_normal(rng) * noise,
+                class,
+            )
+        })
+        .collect();
+    Dataset::from_points(&points)
+}
+
+impl Dataset {
+    // (x, y, class) points, e.g. placed by hand, with the class as the single output
+    pub fn from_points(points: &[(Float, Float, usize)]) -> Dataset {
+        Dataset {
+            input_names: vec!["x".to_owned(), "y".to_owned()],
+            output_names: vec!["class".to_owned()],
+            t_input: Mat {
+                rows: points.len(),
+                cols: 2,
+                data: points.iter().map(|&(x, y, _)| vec![x, y]).collect(),
+            },
+            t_output: Mat {
+                rows: points.len(),
+                cols: 1,
+                data: points
+                    .iter()
+                    .map(|&(_, _, class)| vec![class as Float])
+                    .collect(),
+            },
+        }
+    }
+
+    // A grayscale picture, `pixels` row by row from the top, as one sample per pixel
+    // mapping its center (x, y) in the unit square to its brightness. y grows
+    // downwards like in the picture.
+    pub fn from_picture(width: usize, height: usize, pixels: &[Float]) -> Dataset {
+        assert_eq!(pixels.len(), width * height, "picture size mismatch");
+        let center = |i: usize, size: usize| (i as Float + 0.5) / size as Float;
+        Dataset {
+            input_names: vec!["x".to_owned(), "y".to_owned()],
+            output_names: vec!["brightness".to_owned()],
+            t_input: Mat {
+                rows: pixels.len(),
+                cols: 2,
+                data: (0..pixels.len())
+                    .map(|i| vec![center(i % width, width), center(i / width, height)])
+                    .collect(),
+            },
+            t_output: Mat {
+                rows: pixels.len(),
+                cols: 1,
+                data: pixels.iter().map(|&p| vec![p]).collect(),
+            },
+        }
+    }
+
+    // Two interleaving half circles
+    pub fn moons(samples: usize, noise: Float, rng: &mut StdRng) -> Dataset {
+        two_class(samples, noise, rng, |class, t| {
+            let angle = t * PI;
+            let (x, y) = if class == 0 {
+                (angle.cos(), angle.sin())
+            } else {
+                (1.0 - angle.cos(), 0.5 - angle.sin())
+            };
+            // from [-1, 2] x [-0.5, 1] to the middle of the unit square
+            ((x + 1.0) / 3.0, (y + 0.5) / 3.0 + 0.25)
+        })
+    }
+
+    // A small circle (class 1) inside a large one (class 0)
+    pub fn circles(samples: usize, noise: Float, rng: &mut StdRng) -> Dataset {
+        two_class(samples, noise, rng, |class, t| {
+            let angle = t * 2.0 * PI;
+            let r = if class == 0 { 0.4 } else { 0.2 };
+            (0.5 + r * angle.cos(), 0.5 + r * angle.sin())
+        })
+    }
+
+    // Two spirals winding out from the center, half a turn apart
+    pub fn spirals(samples: usize, noise: Float, rng: &mut StdRng) -> Dataset {
+        two_class(samples, noise, rng, |class, t| {
+            let angle = t * 3.0 * PI + class as Float * PI;
+            let r = 0.1 + 0.4 * t;
+            (0.5 + r * angle.cos(), 0.5 + r * angle.sin())
+        })
+    }
+}
@@ -0,0 +1,106 @@
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::onnx::{bytes, key, string, uint};
+
+// Writes scalars as TensorBoard event files: TFRecords (length, CRC of the length,
+// data, CRC of the data) holding Event protobufs, encoded by hand like the ONNX
+// export (field numbers from tensorflow/core/util/event.proto and summary.proto).
+#[derive(Debug)]
+pub struct EventWriter {
+    pub path: PathBuf,
+    file: File,
+}
+
+// CRC-32C (Castagnoli), bit by bit, the records are small
+pub(crate) fn crc32c(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0x82f6_3b78
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+// TFRecord stores the CRCs rotated and offset
+fn masked_crc(data: &[u8]) -> u32 {
+    crc32c(data).rotate_right(15).wrapping_add(0xa282_ead8)
+}
+
+fn wall_time() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0.0, |d| d.as_secs_f64())
+}
+
+// Event with wall_time (a double) and step set, `what` is the rest of the message
+fn event(step: usize, what: &[u8]) -> Vec<u8> {
+    let mut buf = vec![];
+    key(&mut buf, 1, 1);
+    buf.extend_from_slice(&wall_time().to_le_bytes());
+    uint(&mut buf, 2, step as u64);
+    buf.extend_from_slice(what);
+    buf
+}
+
+impl EventWriter {
+    // Starts dir/events.out.tfevents.<unix time>.nn-rust, point `tensorboard --logdir`
+    // at dir (or its parent, to compare runs)
+    pub fn create(dir: impl AsRef<Path>) -> io::Result<EventWriter> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        let path = dir.join(format!(
+            "events.out.tfevents.{}.nn-rust",
+            wall_time() as u64
+        ));
+        let mut writer = EventWriter {
+            file: File::create(&path)?,
+            path,
+        };
+
+        let mut version = vec![];
+        string(&mut version, 3, "brain.Event:2");
+        Self::record(&mut writer, &event(0, &version))?;
+        Ok(writer)
+    }
+
+    fn record(writer: &mut EventWriter, data: &[u8]) -> io::Result<()> {
+        let len = (data.len() as u64).to_le_bytes();
+        let mut buf = len.to_vec();
+        buf.extend_from_slice(&masked_crc(&len).to_le_bytes());
+        buf.extend_from_slice(data);
+        buf.extend_from_slice(&masked_crc(data).to_le_bytes());
+        writer.file.write_all(&buf)?;
+        // TensorBoard reads the file while training runs
+        writer.file.flush()
+    }
+
+    // One Summary with a simple_value per (tag, value)
+    pub fn scalars(
+        writer: &mut EventWriter,
+        step: usize,
+        scalars: &[(&str, f32)],
+    ) -> io::Result<()> {
+        let mut summary = vec![];
+        for (tag, value) in scalars {
+            let mut val = vec![];
+            string(&mut val, 1, tag);
+            key(&mut val, 2, 5);
+            val.extend_from_slice(&value.to_le_bytes());
+            bytes(&mut summary, 1, &val);
+        }
+        let mut what = vec![];
+        bytes(&mut what, 5, &summary);
+        Self::record(writer, &event(step, &what))
+    }
+}
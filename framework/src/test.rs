@@ -82,6 +82,81 @@ mod tests {
         assert_eq!(row.data, vec![vec![3.0, 4.0]]);
     }
 
+    #[test]
+    fn test_mat_transpose() {
+        let mat = Mat::new(&[&[1.0, 2.0, 3.0], &[4.0, 5.0, 6.0]]);
+
+        let t = Mat::transpose(&mat);
+
+        assert_eq!((t.rows, t.cols), (3, 2));
+        assert_eq!(t.data, vec![vec![1.0, 4.0], vec![2.0, 5.0], vec![3.0, 6.0]]);
+        assert_eq!(Mat::transpose(&t), mat);
+    }
+
+    #[test]
+    fn test_mat_slice() {
+        let mat = Mat::new(&[&[1.0, 2.0, 3.0], &[4.0, 5.0, 6.0], &[7.0, 8.0, 9.0]]);
+
+        let rows = Mat::slice_rows(&mat, 1..3);
+        assert_eq!((rows.rows, rows.cols), (2, 3));
+        assert_eq!(rows.data, vec![vec![4.0, 5.0, 6.0], vec![7.0, 8.0, 9.0]]);
+
+        let cols = Mat::slice_cols(&mat, 0..2);
+        assert_eq!((cols.rows, cols.cols), (3, 2));
+        assert_eq!(
+            cols.data,
+            vec![vec![1.0, 2.0], vec![4.0, 5.0], vec![7.0, 8.0]]
+        );
+
+        assert_eq!(Mat::slice_rows(&mat, 1..1).rows, 0);
+    }
+
+    #[test]
+    fn test_mat_shuffle_rows() {
+        let rows = |n: usize, f: fn(usize) -> Float| Mat {
+            rows: n,
+            cols: 1,
+            data: (0..n).map(|i| vec![f(i)]).collect(),
+        };
+        let mut input = rows(20, |i| i as Float);
+        let mut output = rows(20, |i| i as Float * 10.0);
+
+        Mat::shuffle_rows(&mut [&mut input, &mut output], &mut seeded_rng(4));
+
+        // the pairs stay together
+        for (x, y) in input.data.iter().zip(&output.data) {
+            assert_eq!(x[0] * 10.0, y[0]);
+        }
+        let mut seen: Vec<Float> = input.data.iter().map(|row| row[0]).collect();
+        assert_ne!(seen, (0..20).map(|i| i as Float).collect::<Vec<_>>());
+        seen.sort_by(Float::total_cmp);
+        assert_eq!(seen, (0..20).map(|i| i as Float).collect::<Vec<_>>());
+
+        // the same seed shuffles the same way
+        let mut again = rows(20, |i| i as Float);
+        Mat::shuffle_rows(&mut [&mut again], &mut seeded_rng(4));
+        assert_eq!(again, input);
+    }
+
+    #[test]
+    fn test_mat_concat() {
+        let a = Mat::new(&[&[1.0, 2.0], &[3.0, 4.0]]);
+        let b = Mat::new(&[&[5.0], &[6.0]]);
+
+        let h = Mat::hconcat(&[&a, &b]);
+        assert_eq!((h.rows, h.cols), (2, 3));
+        assert_eq!(h.data, vec![vec![1.0, 2.0, 5.0], vec![3.0, 4.0, 6.0]]);
+        assert_eq!(Mat::slice_cols(&h, 0..2), a);
+
+        let v = Mat::vconcat(&[&a, &a]);
+        assert_eq!((v.rows, v.cols), (4, 2));
+        assert_eq!(Mat::slice_rows(&v, 2..4), a);
+        assert_eq!(
+            Mat::vconcat(&[&Mat::transpose(&b), &Mat::transpose(&b)]).rows,
+            2
+        );
+    }
+
     #[test]
     fn test_mat_copy() {
         let src = Mat {
@@ -119,7 +194,1647 @@ mod tests {
 
         NN::forward(&mut nn);
 
-        assert_eq!(nn.activations[1].data[0][0], 0.631_812_45);
-        assert_eq!(nn.activations[1].data[0][1], 0.659_260_4);
+        // within f32 precision, also holds with the f64 feature
+        assert!((nn.activations[1].data[0][0] - 0.631_812_45).abs() < 1e-6);
+        assert!((nn.activations[1].data[0][1] - 0.659_260_4).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_adam_step() {
+        let mut nn = NN::new(&[1, 1]);
+        let mut g = NN::new(&[1, 1]);
+        g.weights[0].data[0][0] = 0.5;
+        g.biases[0].data[0][0] = -2.0;
+
+        let mut opt = Optimizer::adam(0.1);
+        Optimizer::step(&mut opt, &mut nn, &g);
+
+        // The first bias-corrected Adam step moves each parameter by ~rate
+        assert!((nn.weights[0].data[0][0] + 0.1).abs() < 1e-5);
+        assert!((nn.biases[0].data[0][0] - 0.1).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_momentum_step() {
+        let mut nn = NN::new(&[1, 1]);
+        let mut g = NN::new(&[1, 1]);
+        g.weights[0].data[0][0] = 1.0;
+
+        let mut opt = Optimizer::momentum(0.1, 0.5, false);
+        Optimizer::step(&mut opt, &mut nn, &g);
+        Optimizer::step(&mut opt, &mut nn, &g);
+        // velocity 1.0 then 1.5
+        assert!((nn.weights[0].data[0][0] + 0.25).abs() < 1e-6);
+
+        let mut nn = NN::new(&[1, 1]);
+        let mut opt = Optimizer::momentum(0.1, 0.5, true);
+        Optimizer::step(&mut opt, &mut nn, &g);
+        // look-ahead: grad + momentum * velocity = 1.5
+        assert!((nn.weights[0].data[0][0] + 0.15).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_batches() {
+        let t_input = Mat::new(&[&[0.0], &[1.0], &[2.0], &[3.0], &[4.0]]);
+        let t_output = Mat::new(&[&[0.0], &[10.0], &[20.0], &[30.0], &[40.0]]);
+
+        let b = batches(&t_input, &t_output, 2, &mut seeded_rng(0));
+        assert_eq!(b.len(), 3);
+        assert_eq!(b[2].0.rows, 1);
+
+        // every sample shows up exactly once and stays paired with its target
+        let mut seen = vec![];
+        for (x, y) in &b {
+            for i in 0..x.rows {
+                assert_eq!(x.data[i][0] * 10.0, y.data[i][0]);
+                seen.push(x.data[i][0]);
+            }
+        }
+        seen.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(seen, vec![0.0, 1.0, 2.0, 3.0, 4.0]);
+
+        assert_eq!(batches(&t_input, &t_output, 0, &mut seeded_rng(0)).len(), 1);
+    }
+
+    #[test]
+    fn test_schedule() {
+        assert_eq!(Schedule::rate(&Schedule::Constant, 0.5, 1000), 0.5);
+
+        let step = Schedule::Step {
+            step: 10,
+            gamma: 0.5,
+        };
+        assert_eq!(Schedule::rate(&step, 1.0, 9), 1.0);
+        assert_eq!(Schedule::rate(&step, 1.0, 25), 0.25);
+
+        let exp = Schedule::Exponential { gamma: 0.5 };
+        assert_eq!(Schedule::rate(&exp, 1.0, 3), 0.125);
+
+        let cos = Schedule::Cosine {
+            epochs: 100,
+            min_rate: 0.1,
+        };
+        assert_eq!(Schedule::rate(&cos, 1.0, 0), 1.0);
+        assert!((Schedule::rate(&cos, 1.0, 50) - 0.55).abs() < 1e-6);
+        assert_eq!(Schedule::rate(&cos, 1.0, 150), 0.1);
+
+        let cyclical = Schedule::Cyclical {
+            cycle: 100,
+            min_rate: 0.2,
+        };
+        assert_eq!(Schedule::rate(&cyclical, 1.0, 0), 0.2);
+        assert_eq!(Schedule::rate(&cyclical, 1.0, 50), 1.0);
+        assert!((Schedule::rate(&cyclical, 1.0, 75) - 0.6).abs() < 1e-6);
+        assert_eq!(Schedule::rate(&cyclical, 1.0, 150), 1.0);
+    }
+
+    #[test]
+    fn test_init() {
+        let mut nn = NN::new(&[2, 8, 8, 1]);
+        NN::set_hidden_activation(&mut nn, Activation::Relu);
+        assert_eq!(nn.act[2], Activation::Sigmoid);
+
+        NN::randomize(&mut nn, &mut seeded_rng(0), 5.0, 6.0);
+        Init::apply(Init::Auto, &mut nn, &mut seeded_rng(0));
+
+        // Xavier limit for the 8x1 output layer
+        let limit = (6.0 as Float / 9.0).sqrt();
+        for row in &nn.weights[2].data {
+            assert!(row[0].abs() <= limit);
+        }
+        for l in 0..3 {
+            assert!(nn.biases[l].data[0].iter().all(|b| *b == 0.0));
+        }
+        // He normal for the relu layers, anything left from randomize would be >= 5
+        assert!(nn.weights[1].data.iter().flatten().all(|w| w.abs() < 5.0));
+    }
+
+    #[test]
+    fn test_regularization() {
+        let mut nn = NN::new(&[2, 1]);
+        nn.weights[0].data = vec![vec![0.5], vec![-2.0]];
+        let t_input = Mat::new(&[&[0.0, 0.0]]);
+        let t_output = Mat::new(&[&[0.5]]);
+
+        // zero input and bias, so the only cost is the penalty
+        assert_eq!(NN::cost(&nn, &t_input, &t_output), 0.0);
+
+        nn.reg = Regularization { l1: 0.1, l2: 0.01 };
+        let expected = 0.1 * 2.5 + 0.01 * (0.25 + 4.0);
+        assert!((NN::cost(&nn, &t_input, &t_output) - expected).abs() < 1e-6);
+
+        let mut g = NN::new(&[2, 1]);
+        NN::backprop(&mut nn, &mut g, &t_input, &t_output, &mut seeded_rng(0));
+        assert!((g.weights[0].data[0][0] - (0.1 + 0.01)).abs() < 1e-6);
+        assert!((g.weights[0].data[1][0] - (-0.1 - 0.04)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_dropout() {
+        let mut nn = NN::new(&[1, 1000, 1]);
+        NN::set_hidden_dropout(&mut nn, 0.5);
+        assert_eq!(nn.dropout, vec![0.0, 0.5, 0.0]);
+        nn.biases[0].data = vec![vec![1.0; 1000]];
+        NN::set_hidden_activation(&mut nn, Activation::Relu);
+
+        NN::forward_train(&mut nn, &mut seeded_rng(0));
+        let dropped = nn.dropped[1].iter().filter(|d| **d).count();
+        assert!(dropped > 400 && dropped < 600);
+        for j in 0..1000 {
+            let expected = if nn.dropped[1][j] { 0.0 } else { 2.0 };
+            assert_eq!(nn.activations[1].data[0][j], expected);
+        }
+
+        // inference is unaffected
+        NN::forward(&mut nn);
+        assert!(nn.activations[1].data[0].iter().all(|a| *a == 1.0));
+    }
+
+    #[test]
+    fn test_clip() {
+        let mut g = NN::new(&[2, 1]);
+        g.weights[0].data = vec![vec![3.0], vec![-10.0]];
+        Clip::apply(Clip::Value(1.0), &mut g);
+        assert_eq!(g.weights[0].data, vec![vec![1.0], vec![-1.0]]);
+
+        g.weights[0].data = vec![vec![3.0], vec![4.0]];
+        Clip::apply(Clip::Norm(1.0), &mut g);
+        assert!((NN::param_norm(&g) - 1.0).abs() < 1e-6);
+        assert!((g.weights[0].data[0][0] - 0.6).abs() < 1e-6);
+
+        assert!(NN::is_finite(&g));
+        g.biases[0].data[0][0] = Float::NAN;
+        assert!(!NN::is_finite(&g));
+    }
+
+    #[test]
+    fn test_early_stopping() {
+        let mut es = EarlyStopping::new(0.01, 3, 0.001);
+        assert!(!EarlyStopping::update(&mut es, 1.0));
+        assert!(!EarlyStopping::update(&mut es, 0.5));
+        // not enough improvement, three times in a row
+        assert!(!EarlyStopping::update(&mut es, 0.4999));
+        assert!(!EarlyStopping::update(&mut es, 0.6));
+        assert!(EarlyStopping::update(&mut es, 0.5));
+
+        let mut es = EarlyStopping::new(0.01, 0, 0.0);
+        assert!(!EarlyStopping::update(&mut es, 1.0));
+        assert!(EarlyStopping::update(&mut es, 0.01));
+    }
+
+    #[test]
+    fn test_split() {
+        let t_input = Mat {
+            rows: 8,
+            cols: 1,
+            data: (0..8).map(|i| vec![i as Float]).collect(),
+        };
+        let t_output = t_input.clone();
+
+        let ((ti, to), (vi, vo)) = split(&t_input, &t_output, 0.25, &mut seeded_rng(0));
+        assert_eq!((ti.rows, vi.rows), (6, 2));
+        assert_eq!(ti.data, to.data);
+        assert_eq!(vi.data, vo.data);
+
+        let mut all: Vec<Float> = ti.data.iter().chain(vi.data.iter()).map(|r| r[0]).collect();
+        all.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(all, vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0]);
+
+        let (_, (vi, _)) = split(&t_input, &t_output, 0.0, &mut seeded_rng(0));
+        assert_eq!(vi.rows, 0);
+    }
+
+    #[test]
+    fn test_save_load() {
+        let mut nn = NN::new(&[2, 3, 1]);
+        NN::randomize(&mut nn, &mut seeded_rng(0), -1.0, 1.0);
+        NN::set_hidden_activation(&mut nn, Activation::Tanh);
+
+        for file in ["nn_test_model.json", "nn_test_model.bin"] {
+            let path = std::env::temp_dir().join(file);
+            NN::save(&nn, &path).unwrap();
+            let loaded = NN::load(&path).unwrap();
+            // the same bytes dropped on the browser window
+            let parsed = NN::parse(&std::fs::read(&path).unwrap(), file).unwrap();
+            std::fs::remove_file(&path).unwrap();
+
+            assert_eq!(loaded.weights, nn.weights);
+            assert_eq!(loaded.biases, nn.biases);
+            assert_eq!(loaded.act, nn.act);
+            assert_eq!(parsed.weights, nn.weights);
+        }
+    }
+
+    #[test]
+    fn test_checkpointer() {
+        let dir = std::env::temp_dir().join("nn_test_checkpoints");
+        let _ = std::fs::remove_dir_all(&dir);
+        let nn = NN::new(&[2, 1]);
+        let mut cp = Checkpointer::new(&dir, 10, true);
+
+        assert!(Checkpointer::update(&mut cp, &nn, 5, 1.0)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            Checkpointer::update(&mut cp, &nn, 10, 1.0).unwrap().len(),
+            2
+        );
+        // worse than the best so far, only the periodic snapshot
+        assert_eq!(
+            Checkpointer::update(&mut cp, &nn, 20, 2.0).unwrap().len(),
+            1
+        );
+        assert_eq!(
+            Checkpointer::update(&mut cp, &nn, 30, 0.5).unwrap().len(),
+            2
+        );
+
+        let best = Checkpoint::load(dir.join("best.bin")).unwrap();
+        assert_eq!((best.epoch, best.cost), (30, 0.5));
+        assert!(dir.join("epoch_000020.bin").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_onnx() {
+        let mut nn = NN::new(&[2, 3, 1]);
+        NN::randomize(&mut nn, &mut seeded_rng(0), -1.0, 1.0);
+        let bytes = NN::to_onnx(&nn);
+
+        // ir_version = 7 comes first
+        assert_eq!(&bytes[..2], &[0x08, 7]);
+        let contains = |needle: &[u8]| bytes.windows(needle.len()).any(|w| w == needle);
+        for op in ["MatMul", "Add", "Sigmoid", "W0", "B1", "input", "output"] {
+            assert!(contains(op.as_bytes()), "missing {}", op);
+        }
+        // the weights are stored as raw little endian 32 bit floats
+        #[allow(clippy::unnecessary_cast)]
+        let weight = nn.weights[1].data[2][0] as f32;
+        assert!(contains(&weight.to_le_bytes()));
+    }
+
+    #[test]
+    fn test_to_rust() {
+        let mut nn = NN::new(&[2, 3, 1]);
+        NN::set_hidden_activation(&mut nn, Activation::Relu);
+        nn.weights[1].data = vec![vec![0.5], vec![-1.0], vec![2.0]];
+        let src = NN::to_rust(&nn).unwrap();
+
+        assert!(src.contains("pub fn forward(input: [f32; 2]) -> [f32; 1] {"));
+        assert!(src
+            .contains("pub const W1: [[f32; 1]; 3] = [\n    [0.5],\n    [-1.0],\n    [2.0],\n];"));
+        assert!(src.contains("let a1 = layer(&a0, &W0, &B0, relu);"));
+        assert!(src.contains("    layer(&a1, &W1, &B1, sigmoid)\n}"));
+        assert!(src.contains("fn relu(x: f32) -> f32 {"));
+        assert!(!src.contains("fn tanh("));
+    }
+
+    #[test]
+    fn test_dataset_csv() {
+        let csv = "# xor\nin:a, in:b, out:xor\n0,0,0\n0,1,1\n\n1,0,1\n1,1,0\n";
+        let dataset = Dataset::from_csv(csv).unwrap();
+        assert_eq!(dataset.input_names, vec!["a", "b"]);
+        assert_eq!(dataset.output_names, vec!["xor"]);
+        assert_eq!((dataset.t_input.rows, dataset.t_input.cols), (4, 2));
+        assert_eq!(dataset.t_input.data[2], vec![1.0, 0.0]);
+        assert_eq!(dataset.t_output.data[2], vec![1.0]);
+
+        // targets don't have to be the last columns
+        let dataset = Dataset::from_csv("out:y,in:x\n1,2\n").unwrap();
+        assert_eq!(dataset.t_input.data, vec![vec![2.0]]);
+
+        assert!(Dataset::from_csv("a,out:y\n1,2\n").is_err());
+        assert!(Dataset::from_csv("in:x,out:y\n1\n").is_err());
+        assert!(Dataset::from_csv("in:x,out:y\n1,two\n").is_err());
+        let dataset = Dataset::parse_csv(b"in:x,out:y\n1,2\n").unwrap();
+        assert_eq!(dataset.t_output.data, vec![vec![2.0]]);
+        assert!(Dataset::parse_csv(b"in:x,out:y\n\xff,2\n").is_err());
+
+        // labels become one-hot outputs, integers in numeric order
+        let dataset = Dataset::from_csv("in:x,class:digit\n1,10\n2,2\n3,10\n").unwrap();
+        assert_eq!(dataset.output_names, vec!["digit=2", "digit=10"]);
+        assert_eq!(
+            dataset.t_output.data,
+            vec![vec![0.0, 1.0], vec![1.0, 0.0], vec![0.0, 1.0]]
+        );
+        assert_eq!(argmax_rows(&dataset.t_output), vec![1, 0, 1]);
+        let (indices, classes) = encode_labels(&["b", "a", "b"]);
+        assert_eq!(
+            (indices, classes),
+            (vec![1, 0, 1], vec!["a".to_owned(), "b".to_owned()])
+        );
+        assert!(Dataset::from_csv("in:x,class:a,class:b\n1,2,3\n").is_err());
+    }
+
+    #[test]
+    fn test_idx() {
+        // 2 images of 2x3 pixels
+        let mut bytes = vec![0, 0, 0x08, 3, 0, 0, 0, 2, 0, 0, 0, 2, 0, 0, 0, 3];
+        bytes.extend((0..12).map(|i| i * 20));
+        let idx = Idx::parse(&bytes).unwrap();
+        assert_eq!(idx.dims, vec![2, 2, 3]);
+        assert_eq!(idx.data[11], 220);
+
+        assert!(Idx::parse(&bytes[..20]).is_err());
+        assert!(Idx::parse(&[0, 0, 0x0D, 1, 0, 0, 0, 0]).is_err());
+
+        let labels = one_hot(&[2, 0], 3);
+        assert_eq!(labels.data, vec![vec![0.0, 0.0, 1.0], vec![1.0, 0.0, 0.0]]);
+    }
+
+    #[test]
+    fn test_presets() {
+        for preset in Preset::ALL {
+            let dataset = Preset::build(preset, &mut seeded_rng(0));
+            assert_eq!(dataset.t_input.rows, dataset.t_output.rows);
+            assert_eq!(dataset.t_input.cols, dataset.input_names.len());
+            assert_eq!(dataset.t_output.cols, dataset.output_names.len());
+        }
+
+        let xor = Preset::build(Preset::Xor, &mut seeded_rng(0));
+        assert_eq!(
+            xor.t_output.data,
+            vec![vec![0.0], vec![1.0], vec![1.0], vec![0.0]]
+        );
+
+        // 3 + 2 = 5 -> bits 1, 0, 1
+        let adder = Preset::build(Preset::Adder, &mut seeded_rng(0));
+        assert_eq!(adder.t_input.data[14], vec![1.0, 1.0, 0.0, 1.0]);
+        assert_eq!(adder.t_output.data[14], vec![1.0, 0.0, 1.0]);
+
+        assert_eq!(Preset::from_name("xor"), Some(Preset::Xor));
+        assert_eq!(Preset::from_name("MOONS"), Some(Preset::Moons));
+        assert_eq!(Preset::from_name("datasets/xor.csv"), None);
+    }
+
+    #[test]
+    fn test_synthetic() {
+        for dataset in [
+            Dataset::moons(51, 0.1, &mut seeded_rng(0)),
+            Dataset::circles(51, 0.1, &mut seeded_rng(0)),
+            Dataset::spirals(51, 0.1, &mut seeded_rng(0)),
+        ] {
+            assert_eq!(dataset.t_input.rows, 51);
+            assert_eq!(dataset.t_input.cols, 2);
+            assert_eq!(dataset.t_output.rows, 51);
+            let ones = dataset.t_output.data.iter().filter(|y| y[0] == 1.0).count();
+            assert_eq!(ones, 25);
+        }
+
+        // without noise every circle point is at its class radius
+        let circles = Dataset::circles(20, 0.0, &mut seeded_rng(0));
+        for (x, y) in circles.t_input.data.iter().zip(&circles.t_output.data) {
+            let r = ((x[0] - 0.5).powi(2) + (x[1] - 0.5).powi(2)).sqrt();
+            let expected = if y[0] == 1.0 { 0.2 } else { 0.4 };
+            assert!((r - expected).abs() < 1e-5);
+        }
+
+        let drawn = Dataset::from_points(&[(0.1, 0.2, 0), (0.8, 0.9, 1)]);
+        assert_eq!(drawn.t_input, Mat::new(&[&[0.1, 0.2], &[0.8, 0.9]]));
+        assert_eq!(drawn.t_output, Mat::new(&[&[0.0], &[1.0]]));
+
+        // a 2x1 picture, pixel centers left to right
+        let picture = Dataset::from_picture(2, 1, &[0.25, 1.0]);
+        assert_eq!(picture.t_input, Mat::new(&[&[0.25, 0.5], &[0.75, 0.5]]));
+        assert_eq!(picture.t_output, Mat::new(&[&[0.25], &[1.0]]));
+    }
+
+    #[test]
+    fn test_normalization() {
+        let t_input = Mat::new(&[&[0.0, 100.0], &[5.0, 300.0], &[10.0, 200.0]]);
+
+        let mut scaled = t_input.clone();
+        Normalization::apply(&Normalization::fit(Scaling::MinMax, &t_input), &mut scaled);
+        assert_eq!(scaled, Mat::new(&[&[0.0, 0.0], &[0.5, 1.0], &[1.0, 0.5]]));
+
+        let mut scaled = t_input.clone();
+        Normalization::apply(
+            &Normalization::fit(Scaling::Standard, &t_input),
+            &mut scaled,
+        );
+        for j in 0..2 {
+            let mean: Float = scaled.data.iter().map(|row| row[j]).sum::<Float>() / 3.0;
+            let var: Float = scaled.data.iter().map(|row| row[j].powi(2)).sum::<Float>() / 3.0;
+            assert!(mean.abs() < 1e-5);
+            assert!((var - 1.0).abs() < 1e-5);
+        }
+
+        // folding into the first layer gives the same outputs without the normalization
+        let mut nn = NN::new(&[2, 3, 1]);
+        NN::randomize(&mut nn, &mut seeded_rng(0), -1.0, 1.0);
+        nn.norm = Normalization::fit(Scaling::Standard, &t_input);
+        let mut folded = NN::fold_normalization(&nn);
+        assert!(folded.norm.offset.is_empty());
+        for i in 0..t_input.rows {
+            Mat::copy(&mut nn.activations[0], &Mat::row(&t_input, i));
+            Mat::copy(&mut folded.activations[0], &Mat::row(&t_input, i));
+            NN::forward(&mut nn);
+            NN::forward(&mut folded);
+            // scaled on the way into the first layer, the input itself stays raw
+            assert_eq!(nn.activations[0], Mat::row(&t_input, i));
+            let (a, b) = (
+                nn.activations[2].data[0][0],
+                folded.activations[2].data[0][0],
+            );
+            assert!((a - b).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_accuracy() {
+        assert!(is_classification(&Mat::new(&[&[0.0], &[1.0]])));
+        assert!(!is_classification(&Mat::new(&[&[0.0], &[0.5]])));
+        assert_eq!(class_of(&[0.7]), 1);
+        assert_eq!(class_of(&[0.2, 0.1, 0.7]), 2);
+
+        // always outputs sigmoid(2) > 0.5, so only the class 1 samples are correct
+        let mut nn = NN::new(&[1, 1]);
+        nn.biases[0].data[0][0] = 2.0;
+        let t_input = Mat::new(&[&[0.0], &[1.0], &[0.0], &[1.0]]);
+        let t_output = Mat::new(&[&[1.0], &[0.0], &[1.0], &[1.0]]);
+        assert_eq!(NN::accuracy(&nn, &t_input, &t_output), 0.75);
+        assert_eq!(
+            NN::confusion(&nn, &t_input, &t_output),
+            vec![vec![0, 1], vec![0, 3]]
+        );
+    }
+
+    #[test]
+    fn test_layer_norms() {
+        let mut g = NN::new(&[2, 1, 1]);
+        g.weights[0].data = vec![vec![3.0], vec![0.0]];
+        g.biases[0].data = vec![vec![4.0]];
+        g.weights[1].data = vec![vec![1.0]];
+        assert_eq!(NN::layer_norms(&g), vec![5.0, 1.0]);
+    }
+
+    #[test]
+    fn test_metrics_logger() {
+        let metrics = |epoch| Metrics {
+            epoch,
+            cost: 0.5,
+            accuracy: Some(0.75),
+            learning_rate: 0.1,
+            time: 2.0,
+        };
+        for file in ["nn_test_metrics.csv", "nn_test_metrics.jsonl"] {
+            let path = std::env::temp_dir().join(file);
+            let _ = std::fs::remove_file(&path);
+            let mut logger = MetricsLogger::new(&path, 10);
+            assert!(MetricsLogger::log(&mut logger, &metrics(0)).unwrap());
+            assert!(!MetricsLogger::log(&mut logger, &metrics(5)).unwrap());
+            assert!(MetricsLogger::log(&mut logger, &metrics(10)).unwrap());
+            drop(logger);
+
+            let text = std::fs::read_to_string(&path).unwrap();
+            std::fs::remove_file(&path).unwrap();
+            let lines: Vec<_> = text.lines().collect();
+            if file.ends_with(".csv") {
+                assert_eq!(
+                    lines,
+                    [
+                        "epoch,cost,accuracy,learning_rate,time",
+                        "0,0.5,0.75,0.1,2",
+                        "10,0.5,0.75,0.1,2"
+                    ]
+                );
+            } else {
+                assert_eq!(lines.len(), 2);
+                assert_eq!(
+                    lines[1],
+                    r#"{"epoch":10,"cost":0.5,"accuracy":0.75,"learning_rate":0.1,"time":2.0}"#
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_event_writer() {
+        assert_eq!(crate::tensorboard::crc32c(b"123456789"), 0xe306_9283);
+
+        let dir = std::env::temp_dir().join("nn_test_events");
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut writer = EventWriter::create(&dir).unwrap();
+        EventWriter::scalars(&mut writer, 10, &[("cost", 0.5), ("accuracy", 1.0)]).unwrap();
+        let data = std::fs::read(&writer.path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // walk the records: u64 length, length CRC, data, data CRC
+        let mut records = vec![];
+        let mut rest = &data[..];
+        while !rest.is_empty() {
+            let len = u64::from_le_bytes(rest[..8].try_into().unwrap()) as usize;
+            records.push(&rest[12..12 + len]);
+            rest = &rest[12 + len + 4..];
+        }
+        assert_eq!(records.len(), 2);
+        let contains = |record: &[u8], s: &[u8]| record.windows(s.len()).any(|w| w == s);
+        assert!(contains(records[0], b"brain.Event:2"));
+        assert!(contains(records[1], b"cost"));
+        assert!(contains(records[1], &0.5f32.to_le_bytes()));
+    }
+
+    #[test]
+    fn test_seeded_rng() {
+        let build = |seed| {
+            let mut rng = seeded_rng(seed);
+            let dataset = Preset::build(Preset::Moons, &mut rng);
+            let mut nn = NN::new(&[2, 4, 1]);
+            Init::apply(Init::Auto, &mut nn, &mut rng);
+            NN::set_hidden_dropout(&mut nn, 0.5);
+            let (x, y) = batches(&dataset.t_input, &dataset.t_output, 8, &mut rng).remove(0);
+            let mut g = NN::new(&[2, 4, 1]);
+            NN::backprop(&mut nn, &mut g, &x, &y, &mut rng);
+            (dataset.t_input, nn.weights, nn.dropped, g.weights)
+        };
+        assert_eq!(build(7), build(7));
+        assert_ne!(build(7), build(8));
+    }
+
+    #[test]
+    fn test_parallel_backprop() {
+        // more samples than PARALLEL_CHUNK, not a multiple of it
+        let mut rng = seeded_rng(1);
+        let n = PARALLEL_CHUNK * 2 + 37;
+        let rows = |cols| Mat {
+            rows: n,
+            cols,
+            data: (0..n)
+                .map(|i| {
+                    (0..cols)
+                        .map(|j| ((i * 7 + j * 3) % 11) as Float / 11.0)
+                        .collect()
+                })
+                .collect(),
+        };
+        let (t_input, t_output) = (rows(3), rows(2));
+        let mut nn = NN::new(&[3, 5, 2]);
+        Init::apply(Init::Auto, &mut nn, &mut rng);
+
+        let mut g = NN::new(&[3, 5, 2]);
+        NN::backprop(&mut nn, &mut g, &t_input, &t_output, &mut seeded_rng(2));
+        let mut sequential = NN::new(&[3, 5, 2]);
+        NN::backprop_rows(
+            &mut nn,
+            &mut sequential,
+            &t_input,
+            &t_output,
+            0..n,
+            n,
+            &mut rng,
+        );
+        for l in 0..2 {
+            for (a, b) in g.weights[l]
+                .data
+                .iter()
+                .flatten()
+                .zip(sequential.weights[l].data.iter().flatten())
+            {
+                assert!((a - b).abs() < 1e-5);
+            }
+        }
+
+        // the same with dropout for the same seed
+        NN::set_hidden_dropout(&mut nn, 0.5);
+        let mut a = NN::new(&[3, 5, 2]);
+        let mut b = NN::new(&[3, 5, 2]);
+        NN::backprop(&mut nn, &mut a, &t_input, &t_output, &mut seeded_rng(3));
+        NN::backprop(&mut nn, &mut b, &t_input, &t_output, &mut seeded_rng(3));
+        assert_eq!(a.weights, b.weights);
+
+        // nn is left with the masks and activations of the last sample, like after
+        // the last chunk on its own
+        let mut rng = seeded_rng(3);
+        let seeds: Vec<u64> = (0..n).step_by(PARALLEL_CHUNK).map(|_| rng.gen()).collect();
+        let mut last = nn.clone();
+        NN::backprop_rows(
+            &mut last,
+            &mut NN::new(&[3, 5, 2]),
+            &t_input,
+            &t_output,
+            PARALLEL_CHUNK * 2..n,
+            n,
+            &mut seeded_rng(seeds[2]),
+        );
+        assert_eq!(nn.dropped, last.dropped);
+        assert_eq!(nn.activations, last.activations);
+        assert_eq!(nn.activations[0].data[0], t_input.data[n - 1]);
+    }
+
+    #[cfg(feature = "gpu")]
+    #[test]
+    fn test_gpu_backprop() {
+        // a software adapter like llvmpipe does, without any the test has nothing to run on
+        let gpu = match gpu::Gpu::new() {
+            Ok(gpu) => gpu,
+            Err(e) => {
+                println!("skipped, {}", e);
+                return;
+            }
+        };
+        // more samples than a pass takes, with every activation, input scaling and
+        // class weights
+        let n = 4096 + 300;
+        let rows = |cols, salt| Mat {
+            rows: n,
+            cols,
+            data: (0..n)
+                .map(|i| {
+                    (0..cols)
+                        .map(|j| ((i * 7 + j * 3 + salt) % 11) as Float / 11.0)
+                        .collect()
+                })
+                .collect(),
+        };
+        let (t_input, t_output) = (rows(3, 0), rows(2, 5));
+        let mut nn = NN::new(&[3, 6, 5, 2]);
+        Init::apply(Init::Auto, &mut nn, &mut seeded_rng(4));
+        nn.act = vec![Activation::Tanh, Activation::Relu, Activation::Sigmoid];
+        nn.norm = Normalization::fit(Scaling::Standard, &t_input);
+        nn.class_weights = vec![0.5, 2.0];
+        assert!(gpu::Gpu::supports(&nn));
+
+        for loss in [Loss::Mse, Loss::CrossEntropy, Loss::Mae, Loss::Huber(0.1)] {
+            nn.loss = loss;
+            let mut cpu = NN::new(&[3, 6, 5, 2]);
+            NN::backprop_rows(
+                &mut nn.clone(),
+                &mut cpu,
+                &t_input,
+                &t_output,
+                0..n,
+                n,
+                &mut seeded_rng(5),
+            );
+            let mut g = NN::new(&[3, 6, 5, 2]);
+            gpu::Gpu::backprop(&gpu, &nn, &mut g, &t_input, &t_output);
+            for l in 0..3 {
+                let cpu = cpu.weights[l].data.iter().chain(&cpu.biases[l].data);
+                let on_gpu = g.weights[l].data.iter().chain(&g.biases[l].data);
+                for (a, b) in on_gpu.flatten().zip(cpu.flatten()) {
+                    assert!(
+                        (a - b).abs() < 1e-4,
+                        "{:?} layer {}: {} vs {}",
+                        loss,
+                        l,
+                        a,
+                        b
+                    );
+                }
+            }
+        }
+
+        NN::set_hidden_dropout(&mut nn, 0.5);
+        assert!(!gpu::Gpu::supports(&nn));
+    }
+
+    #[test]
+    fn test_dot_simd() {
+        // sizes that aren't multiples of the 8 SIMD lanes
+        let mut rng = seeded_rng(4);
+        let mut random = |rows, cols| Mat {
+            rows,
+            cols,
+            data: (0..rows)
+                .map(|_| (0..cols).map(|_| rand_float(&mut rng, -1.0, 1.0)).collect())
+                .collect(),
+        };
+        let (a, b) = (random(3, 19), random(19, 21));
+        let mut simd = random(3, 21);
+        let mut scalar = simd.clone();
+        Mat::dot(&mut simd, &a, &b);
+        Mat::dot_scalar(&mut scalar, &a, &b);
+        assert_eq!(simd, scalar);
+    }
+
+    #[test]
+    fn test_builder() {
+        let (nn, optimizer) = NNBuilder::new()
+            .input(2)
+            .dense(4, Activation::Tanh)
+            .dropout(0.25)
+            .dense(1, Activation::Sigmoid)
+            .loss(Loss::CrossEntropy)
+            .optimizer(Optimizer::adam(0.01))
+            .build();
+        assert_eq!(nn.count, 3);
+        assert_eq!(nn.weights[0].rows, 2);
+        assert_eq!(nn.weights[1].cols, 1);
+        assert_eq!(nn.act, vec![Activation::Tanh, Activation::Sigmoid]);
+        assert_eq!(nn.dropout, vec![0.0, 0.25, 0.0]);
+        assert_eq!(nn.loss, Loss::CrossEntropy);
+        assert_eq!(optimizer.rate, 0.01);
+        assert!(matches!(optimizer.method, Method::Adam { .. }));
+    }
+
+    #[test]
+    fn test_cross_entropy() {
+        let (mut nn, _) = NNBuilder::new()
+            .input(1)
+            .dense(1, Activation::Sigmoid)
+            .loss(Loss::CrossEntropy)
+            .build();
+        let t_input = Mat::new(&[&[0.0]]);
+        let t_output = Mat::new(&[&[1.0]]);
+
+        // zero weights output 0.5
+        let expected = (2.0 as Float).ln();
+        assert!((NN::cost(&nn, &t_input, &t_output) - expected).abs() < 1e-6);
+
+        // through a sigmoid the gradient reduces to a - y
+        let mut g = NN::new(&[1, 1]);
+        NN::backprop(&mut nn, &mut g, &t_input, &t_output, &mut seeded_rng(0));
+        assert!((g.biases[0].data[0][0] - -0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_robust_losses() {
+        assert_eq!(Loss::apply(Loss::Mae, 0.5, 2.0), 1.5);
+        assert_eq!(Loss::deriv(Loss::Mae, 0.5, 2.0), -1.0);
+        assert_eq!(Loss::deriv(Loss::Mae, 2.0, 2.0), 0.0);
+
+        // quadratic near the target, linear with slope delta far from it
+        let huber = Loss::Huber(1.0);
+        assert_eq!(Loss::apply(huber, 0.5, 0.0), 0.125);
+        assert_eq!(Loss::deriv(huber, 0.5, 0.0), 0.5);
+        assert_eq!(Loss::apply(huber, 3.0, 0.0), 2.5);
+        assert_eq!(Loss::deriv(huber, -3.0, 0.0), -1.0);
+
+        let t_input = Mat::new(&[&[0.0, 0.0], &[0.0, 1.0], &[1.0, 0.0], &[1.0, 1.0]]);
+        let t_output = Mat::new(&[&[0.0], &[1.0], &[1.0], &[0.0]]);
+        for loss in [Loss::Mae, Loss::Huber(0.1)] {
+            let (mut nn, _) = NNBuilder::new()
+                .input(2)
+                .dense(3, Activation::Tanh)
+                .dense(1, Activation::Sigmoid)
+                .loss(loss)
+                .build();
+            Init::apply(Init::Auto, &mut nn, &mut seeded_rng(2));
+            let errors = gradient_check(&nn, &t_input, &t_output, 1e-3);
+            assert!(errors.iter().all(|e| *e < 1e-2), "{:?} {:?}", loss, errors);
+        }
+    }
+
+    #[test]
+    fn test_sequential() {
+        let mut rng = seeded_rng(5);
+        let t_input = Mat::new(&[&[0.0, 0.0], &[0.0, 1.0], &[1.0, 0.0], &[1.0, 1.0]]);
+        let t_output = Mat::new(&[&[0.0], &[1.0], &[1.0], &[0.0]]);
+        let mut nn = NN::new(&[2, 3, 1]);
+        NN::set_hidden_activation(&mut nn, Activation::Tanh);
+        Init::apply(Init::Auto, &mut nn, &mut rng);
+
+        // the same network layer by layer gives the same cost and gradient
+        let mut model = Sequential::from_nn(&nn);
+        let cost = Sequential::cost(&mut model, &t_input, &t_output, &mut rng);
+        assert!((cost - NN::cost(&nn, &t_input, &t_output)).abs() < 1e-6);
+
+        let mut g = NN::new(&[2, 3, 1]);
+        NN::backprop(&mut nn, &mut g, &t_input, &t_output, &mut rng);
+        Sequential::backprop(&mut model, &t_input, &t_output, &mut rng);
+        let grads: Vec<&Mat> = model.layers.iter().flat_map(|l| l.grads()).collect();
+        for l in 0..2 {
+            for (a, b) in grads[l * 2]
+                .data
+                .iter()
+                .flatten()
+                .zip(g.weights[l].data.iter().flatten())
+            {
+                assert!((a - b).abs() < 1e-6);
+            }
+            for (a, b) in grads[l * 2 + 1].data[0].iter().zip(&g.biases[l].data[0]) {
+                assert!((a - b).abs() < 1e-6);
+            }
+        }
+
+        for _ in 0..2000 {
+            Sequential::backprop(&mut model, &t_input, &t_output, &mut rng);
+            Sequential::learn(&mut model, 0.5);
+        }
+        assert!(Sequential::cost(&mut model, &t_input, &t_output, &mut rng) < cost);
+    }
+
+    #[test]
+    fn test_batch_norm() {
+        let mut rng = seeded_rng(6);
+        let t_input = Mat::new(&[&[0.5, -1.0], &[2.0, 0.0], &[-1.5, 1.0], &[0.0, 3.0]]);
+        let t_output = Mat::new(&[&[0.2], &[0.9], &[0.4], &[0.6]]);
+        let mut model = Sequential::new(Loss::Mse);
+        Sequential::push(&mut model, BatchNorm::new(2));
+        let mut dense = Dense::new(2, 1, Activation::Sigmoid);
+        Dense::init(&mut dense, Init::Auto, &mut rng);
+        Sequential::push(&mut model, dense);
+
+        let mut norm = BatchNorm::new(2);
+        let out = norm.forward(&t_input, true, &mut rng);
+        for j in 0..2 {
+            let mean = out.data.iter().map(|row| row[j]).sum::<Float>() / 4.0;
+            assert!(mean.abs() < 1e-5);
+        }
+
+        // the analytic gradient of gamma against a finite difference of the mean cost
+        Sequential::backprop(&mut model, &t_input, &t_output, &mut rng);
+        let analytic = model.layers[0].grads()[0].data[0][0];
+        let eps = 1e-2;
+        let cost_at = |delta: Float| {
+            let mut model = Sequential::new(Loss::Mse);
+            let mut norm = BatchNorm::new(2);
+            norm.gamma.data[0][0] += delta;
+            Sequential::push(&mut model, norm);
+            let mut dense = Dense::new(2, 1, Activation::Sigmoid);
+            Dense::init(&mut dense, Init::Auto, &mut seeded_rng(6));
+            Sequential::push(&mut model, dense);
+            // batch statistics, as in training
+            let mut cost = 0.0;
+            let output = model.layers.iter_mut().fold(t_input.clone(), |x, l| {
+                l.forward(&x, true, &mut seeded_rng(0))
+            });
+            for (a, y) in output
+                .data
+                .iter()
+                .flatten()
+                .zip(t_output.data.iter().flatten())
+            {
+                cost += (a - y) * (a - y);
+            }
+            cost / 4.0
+        };
+        let numeric = (cost_at(eps) - cost_at(-eps)) / (2.0 * eps);
+        assert!((analytic - numeric).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_conv() {
+        let mut rng = seeded_rng(7);
+        // two 3x3 single channel images
+        let t_input = Mat::new(&[
+            &[0.1, 0.9, 0.3, 0.5, 0.2, 0.8, 0.7, 0.4, 0.6],
+            &[0.9, 0.1, 0.0, 0.3, 1.0, 0.2, 0.5, 0.6, 0.4],
+        ]);
+        let t_output = Mat::new(&[&[1.0], &[0.0]]);
+        let mut conv = Conv2D::new(1, 2, 2, 1, (3, 3), Activation::Tanh);
+        Conv2D::init(&mut conv, Init::Auto, &mut rng);
+        assert_eq!(Conv2D::output_size(&conv), (2, 2));
+        assert_eq!(
+            Conv2D::filter(&conv, 1, 0).data[1][0],
+            conv.weights.data[2][1]
+        );
+        let pool = MaxPool2D::new(2, 2, (2, 2));
+        assert_eq!(MaxPool2D::outputs(&pool), 2);
+        let mut dense = Dense::new(2, 1, Activation::Sigmoid);
+        Dense::init(&mut dense, Init::Auto, &mut rng);
+
+        let mut model = Sequential::new(Loss::Mse);
+        Sequential::push(&mut model, conv);
+        Sequential::push(&mut model, pool);
+        Sequential::push(&mut model, dense);
+        Sequential::backprop(&mut model, &t_input, &t_output, &mut rng);
+        let analytic = model.layers[0].grads()[0].clone();
+
+        // every filter weight against a finite difference of the mean cost
+        let eps = 1e-3;
+        for r in 0..4 {
+            for c in 0..2 {
+                let nudge = |model: &mut Sequential, delta: Float| {
+                    model.layers[0].update(&mut |p, _| {
+                        if p.rows == 4 {
+                            p.data[r][c] += delta;
+                        }
+                    });
+                };
+                nudge(&mut model, eps);
+                let plus = Sequential::cost(&mut model, &t_input, &t_output, &mut rng);
+                nudge(&mut model, -2.0 * eps);
+                let minus = Sequential::cost(&mut model, &t_input, &t_output, &mut rng);
+                nudge(&mut model, eps);
+                let numeric = (plus - minus) / (2.0 * eps) / 2.0;
+                assert!((analytic.data[r][c] - numeric).abs() < 1e-2);
+            }
+        }
+    }
+
+    #[test]
+    fn test_rnn() {
+        let pattern = [true, false, false, true, true, false];
+        let seq = Sequence::repeating(&pattern, 48);
+        assert_eq!(seq.t_input.data[3][0], 1.0);
+        assert_eq!(seq.t_output.data[3][0], 1.0);
+        assert_eq!(seq.t_output.data[5][0], 1.0);
+
+        let mut rng = seeded_rng(8);
+        let mut rnn = Rnn::new(1, 8, 1);
+        Rnn::init(&mut rnn, Init::Auto, &mut rng);
+
+        // without truncation the gradient is the exact one of the mean cost
+        let short = Sequence::repeating(&pattern, 6);
+        Rnn::backprop(&mut rnn, &short, 6);
+        let eps = 1e-2;
+        for (i, j) in [(0, 0), (3, 5)] {
+            let mut nudged = rnn.clone();
+            nudged.w.wh.data[i][j] += eps;
+            let plus = Rnn::cost(&nudged, &short);
+            nudged.w.wh.data[i][j] -= 2.0 * eps;
+            let minus = Rnn::cost(&nudged, &short);
+            let numeric = (plus - minus) / (2.0 * eps) / 6.0;
+            assert!((Rnn::gradient(&rnn).wh.data[i][j] - numeric).abs() < 1e-3);
+        }
+
+        // a window of 8 steps spans more than the period of 6
+        let before = Rnn::cost(&rnn, &seq);
+        for _ in 0..2000 {
+            Rnn::backprop(&mut rnn, &seq, 8);
+            Rnn::learn(&mut rnn, 2.0);
+        }
+        assert!(Rnn::cost(&rnn, &seq) < before * 0.1);
+    }
+
+    #[test]
+    fn test_gradient_check() {
+        let t_input = Mat::new(&[&[0.0, 0.0], &[0.0, 1.0], &[1.0, 0.0], &[1.0, 1.0]]);
+        let t_output = Mat::new(&[&[0.0], &[1.0], &[1.0], &[0.0]]);
+        let (mut nn, _) = NNBuilder::new()
+            .input(2)
+            .dense(3, Activation::Tanh)
+            .dropout(0.5)
+            .dense(1, Activation::Sigmoid)
+            .loss(Loss::CrossEntropy)
+            .regularization(Regularization { l1: 0.0, l2: 0.01 })
+            .build();
+        Init::apply(Init::Auto, &mut nn, &mut seeded_rng(9));
+
+        let errors = gradient_check(&nn, &t_input, &t_output, 1e-3);
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().all(|e| *e < 1e-2), "{:?}", errors);
+
+        nn.loss = Loss::Mse;
+        let errors = gradient_check(&nn, &t_input, &t_output, 1e-3);
+        assert!(errors.iter().all(|e| *e < 1e-2), "{:?}", errors);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            Mat::try_new(&[&[1.0, 2.0], &[3.0]]),
+            Err(Error::RaggedRow {
+                row: 1,
+                expected: 2,
+                found: 1
+            })
+        );
+        assert_eq!(Mat::try_new(&[]), Err(Error::Empty));
+
+        let mut nn = NN::new(&[2, 3, 1]);
+        let t_input = Mat::new(&[&[0.0, 1.0], &[1.0, 0.0]]);
+        let t_output = Mat::new(&[&[1.0], &[1.0]]);
+        assert_eq!(NN::check(&nn, &t_input, &t_output), Ok(()));
+        assert_eq!(
+            NN::check(&nn, &t_output, &t_output),
+            Err(Error::InputSize {
+                expected: 2,
+                found: 1
+            })
+        );
+        assert!(NN::try_forward(&mut nn, &Mat::new(&[&[0.0, 1.0, 2.0]])).is_err());
+        assert!(NN::try_forward(&mut nn, &Mat::row(&t_input, 0)).is_ok());
+
+        let mut g = NN::new(&[2, 4, 1]);
+        let result = NN::try_backprop(&mut nn, &mut g, &t_input, &t_output, &mut seeded_rng(0));
+        assert!(matches!(result, Err(Error::Architecture { .. })));
+        let too_few = Mat::new(&[&[1.0]]);
+        let result = NN::try_backprop(&mut nn, &mut g, &t_input, &too_few, &mut seeded_rng(0));
+        assert!(matches!(result, Err(Error::SampleCount { .. })));
+
+        let mut dataset = Dataset::from_points(&[(0.0, 0.0, 0), (1.0, 1.0, 1)]);
+        assert_eq!(Dataset::validate(&dataset), Ok(()));
+        dataset.t_input.data[1][0] = Float::NAN;
+        assert_eq!(
+            Dataset::validate(&dataset),
+            Err(Error::NotFinite { row: 1 })
+        );
+        assert_eq!(
+            Error::NotFinite { row: 1 }.to_string(),
+            "row 1 has a NaN or infinite value"
+        );
+    }
+
+    #[test]
+    fn test_training_state() {
+        assert_eq!(
+            TrainingState::path("model.json"),
+            std::path::Path::new("model.state.json")
+        );
+        assert_eq!(
+            TrainingState::path("dir/model"),
+            std::path::Path::new("dir/model.state.bin")
+        );
+
+        let xor = Preset::build(Preset::Xor, &mut seeded_rng(0));
+        let (t_input, t_output) = (xor.t_input, xor.t_output);
+        let mut nn = NN::new(&[2, 3, 1]);
+        NN::randomize(&mut nn, &mut seeded_rng(0), -1.0, 1.0);
+        let mut g = NN::new(&[2, 3, 1]);
+        let mut opt = Optimizer::adam(0.1);
+        let mut rng = seeded_rng(0);
+        for _ in 0..5 {
+            NN::backprop(&mut nn, &mut g, &t_input, &t_output, &mut rng);
+            Optimizer::step(&mut opt, &mut nn, &g);
+        }
+
+        let path = std::env::temp_dir().join("nn_test_state.json");
+        let state = TrainingState {
+            epoch: 5,
+            optimizer: Optimizer::state(&opt).clone(),
+            history: vec![(0, 1.0), (4, 0.5)],
+            validation: vec![],
+        };
+        TrainingState::save(&state, &path).unwrap();
+        let loaded = TrainingState::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            (loaded.epoch, loaded.history),
+            (5, vec![(0, 1.0), (4, 0.5)])
+        );
+
+        // the restored optimizer takes the same next step as the original one
+        let mut restored = Optimizer::adam(0.1);
+        Optimizer::restore(&mut restored, loaded.optimizer);
+        let mut a = nn.clone();
+        let mut b = nn.clone();
+        NN::backprop(&mut nn, &mut g, &t_input, &t_output, &mut rng);
+        Optimizer::step(&mut opt, &mut a, &g);
+        Optimizer::step(&mut restored, &mut b, &g);
+        assert_eq!(a.weights, b.weights);
+        // a fresh one doesn't
+        let mut c = nn.clone();
+        Optimizer::step(&mut Optimizer::adam(0.1), &mut c, &g);
+        assert_ne!(a.weights, c.weights);
+    }
+
+    #[test]
+    fn test_metrics_server() {
+        use std::io::{BufRead, BufReader, Read, Write};
+
+        assert_eq!(
+            crate::websocket::base64(&crate::websocket::sha1(b"abc")),
+            "qZk+NkcGgWq6PiVxeFDCbJzQ2J0="
+        );
+
+        let server = MetricsServer::bind("127.0.0.1:0").unwrap();
+        let mut client = std::net::TcpStream::connect(server.addr).unwrap();
+        // the example key from RFC 6455
+        write!(
+            client,
+            "GET / HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n"
+        )
+        .unwrap();
+        let mut reader = BufReader::new(client);
+        let mut response = vec![];
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if line == "\r\n" {
+                break;
+            }
+            response.push(line);
+        }
+        assert!(response[0].starts_with("HTTP/1.1 101"));
+        assert!(
+            response.contains(&"Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n".to_owned())
+        );
+
+        let metrics = Metrics {
+            epoch: 10,
+            cost: 0.5,
+            accuracy: None,
+            learning_rate: 0.1,
+            time: 2.0,
+        };
+        assert_eq!(MetricsServer::send(&server, &metrics), 1);
+        let mut header = [0; 2];
+        reader.read_exact(&mut header).unwrap();
+        assert_eq!(header[0], 0x81);
+        let mut json = vec![0; header[1] as usize];
+        reader.read_exact(&mut json).unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(value["epoch"], 10);
+        assert_eq!(value["accuracy"], serde_json::Value::Null);
+
+        drop(reader);
+        // the write after the close notices the client is gone
+        while MetricsServer::send(&server, &metrics) > 0 {}
+    }
+
+    #[test]
+    fn test_import_keras() {
+        // a float32 .npy array, version 1 header
+        let npy = |shape: &str, values: &[f32]| {
+            let mut header = format!(
+                "{{'descr': '<f4', 'fortran_order': False, 'shape': {}, }}",
+                shape
+            );
+            while (10 + header.len() + 1) % 64 != 0 {
+                header.push(' ');
+            }
+            header.push('\n');
+            let mut bytes = b"\x93NUMPY\x01\x00".to_vec();
+            bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+            bytes.extend_from_slice(header.as_bytes());
+            for v in values {
+                bytes.extend_from_slice(&v.to_le_bytes());
+            }
+            bytes
+        };
+        // an uncompressed zip with the files in reverse order, the CRCs left at 0
+        let zip = |files: &[(&str, Vec<u8>)]| {
+            let (mut bytes, mut central) = (vec![], vec![]);
+            for (name, data) in files.iter().rev() {
+                let offset = bytes.len() as u32;
+                let mut header = vec![0; 22];
+                header[14..18].copy_from_slice(&(data.len() as u32).to_le_bytes());
+                header[18..22].copy_from_slice(&(data.len() as u32).to_le_bytes());
+                bytes.extend_from_slice(&[0x50, 0x4b, 0x03, 0x04]);
+                bytes.extend_from_slice(&header);
+                bytes.extend_from_slice(&(name.len() as u16).to_le_bytes());
+                bytes.extend_from_slice(&[0, 0]);
+                bytes.extend_from_slice(name.as_bytes());
+                bytes.extend_from_slice(data);
+
+                let mut entry = vec![0; 42];
+                entry[16..20].copy_from_slice(&(data.len() as u32).to_le_bytes());
+                entry[20..24].copy_from_slice(&(data.len() as u32).to_le_bytes());
+                entry[24..26].copy_from_slice(&(name.len() as u16).to_le_bytes());
+                entry[38..42].copy_from_slice(&offset.to_le_bytes());
+                central.extend_from_slice(&[0x50, 0x4b, 0x01, 0x02]);
+                central.extend_from_slice(&entry);
+                central.extend_from_slice(name.as_bytes());
+            }
+            let start = bytes.len() as u32;
+            bytes.extend_from_slice(&central);
+            let mut end = vec![0x50, 0x4b, 0x05, 0x06, 0, 0, 0, 0];
+            end.extend_from_slice(&(files.len() as u16).to_le_bytes());
+            end.extend_from_slice(&(files.len() as u16).to_le_bytes());
+            end.extend_from_slice(&(central.len() as u32).to_le_bytes());
+            end.extend_from_slice(&start.to_le_bytes());
+            end.extend_from_slice(&[0, 0]);
+            bytes.extend_from_slice(&end);
+            bytes
+        };
+
+        let npz = zip(&[
+            ("arr_0.npy", npy("(2, 3)", &[1., 2., 3., 4., 5., 6.])),
+            ("arr_1.npy", npy("(3,)", &[0.1, 0.2, 0.3])),
+            ("arr_2.npy", npy("(3, 1)", &[1., -1., 0.5])),
+            ("arr_3.npy", npy("(1,)", &[0.25])),
+        ]);
+        let nn = NN::parse_npz(&npz, Activation::Relu).unwrap();
+        assert_eq!(NN::arch(&nn), [2, 3, 1]);
+        assert_eq!(nn.weights[0].data, [[1., 2., 3.], [4., 5., 6.]]);
+        // stored as float32, not exactly the f64 literals
+        for (b, expected) in nn.biases[0].data[0].iter().zip([0.1, 0.2, 0.3]) {
+            assert!((b - expected).abs() < 1e-6);
+        }
+        assert_eq!(nn.weights[1].data, [[1.], [-1.], [0.5]]);
+        assert_eq!(nn.act, [Activation::Relu, Activation::Sigmoid]);
+
+        // the kernel has to take the previous layer's outputs
+        let npz = zip(&[
+            ("arr_0.npy", npy("(2, 3)", &[0.; 6])),
+            ("arr_1.npy", npy("(3,)", &[0.; 3])),
+            ("arr_2.npy", npy("(2, 1)", &[0.; 2])),
+            ("arr_3.npy", npy("(1,)", &[0.])),
+        ]);
+        assert!(NN::parse_npz(&npz, Activation::Tanh).is_err());
+
+        // malformed headers are errors, not panics
+        for shape in ["", ")", "()", "(2, x)", "(4294967296, 4294967296)"] {
+            let npz = zip(&[("arr_0.npy", npy(shape, &[0.; 2]))]);
+            assert!(NN::parse_npz(&npz, Activation::Tanh).is_err());
+        }
+
+        let json = r#"{"layers": [
+            {"kernel": [[1, 2], [3, 4]], "bias": [0, 1], "activation": "tanh"},
+            {"kernel": [[1], [1]], "bias": [0]}
+        ]}"#;
+        let nn = NN::parse_keras_json(json, Activation::Sigmoid).unwrap();
+        assert_eq!(NN::arch(&nn), [2, 2, 1]);
+        assert_eq!(nn.weights[0].data, [[1., 2.], [3., 4.]]);
+        assert_eq!(nn.act, [Activation::Tanh, Activation::Sigmoid]);
+        let linear = json.replace("\"tanh\"", "\"linear\"");
+        assert!(NN::parse_keras_json(&linear, Activation::Sigmoid).is_err());
+    }
+
+    #[test]
+    fn test_custom_activation() {
+        let softplus = Activation::register("softplus", |x| x.exp().ln_1p(), |a| 1.0 - (-a).exp());
+        assert_eq!(Activation::from_name("softplus"), Some(softplus));
+        assert_eq!(Activation::name(softplus), "softplus");
+        assert!((Activation::apply(softplus, 0.0) - (2.0 as Float).ln()).abs() < 1e-6);
+        assert!((Activation::deriv(softplus, (2.0 as Float).ln()) - 0.5).abs() < 1e-6);
+
+        let (mut nn, _) = NNBuilder::new()
+            .input(2)
+            .dense(3, softplus)
+            .dense(1, Activation::Sigmoid)
+            .build();
+        Init::apply(Init::Auto, &mut nn, &mut seeded_rng(4));
+        let t_input = Mat::new(&[&[0.0, 0.0], &[0.0, 1.0], &[1.0, 0.0], &[1.0, 1.0]]);
+        let t_output = Mat::new(&[&[0.0], &[1.0], &[1.0], &[0.0]]);
+        let errors = gradient_check(&nn, &t_input, &t_output, 1e-3);
+        assert!(errors.iter().all(|e| *e < 1e-2), "{:?}", errors);
+
+        // saved by name, unknown names don't load
+        let json = serde_json::to_string(&nn.act).unwrap();
+        assert_eq!(json, r#"[{"Custom":"softplus"},"Sigmoid"]"#);
+        let act: Vec<Activation> = serde_json::from_str(&json).unwrap();
+        assert_eq!(act, nn.act);
+        assert!(serde_json::from_str::<Activation>(r#"{"Custom":"nope"}"#).is_err());
+
+        // closures have no source to export
+        for e in [
+            NN::to_rust(&nn).unwrap_err(),
+            QuantizedNN::to_rust(&QuantizedNN::new(&nn)).unwrap_err(),
+        ] {
+            assert_eq!(e.kind(), std::io::ErrorKind::InvalidInput);
+            assert!(e.to_string().contains("`softplus`"), "{}", e);
+        }
+    }
+
+    #[test]
+    fn test_lr_finder() {
+        let t_input = Mat::new(&[&[0.0, 0.0], &[0.0, 1.0], &[1.0, 0.0], &[1.0, 1.0]]);
+        let t_output = Mat::new(&[&[0.0], &[1.0], &[1.0], &[1.0]]);
+        let (mut nn, optimizer) = NNBuilder::new()
+            .input(2)
+            .dense(4, Activation::Tanh)
+            .dense(1, Activation::Sigmoid)
+            .optimizer(Optimizer::sgd(0.0))
+            .build();
+        Init::apply(Init::Auto, &mut nn, &mut seeded_rng(1));
+        let finder = LrFinder {
+            min_rate: 1e-4,
+            max_rate: 1e3,
+            steps: 200,
+        };
+        let curve = LrFinder::run(
+            &finder,
+            &nn,
+            &optimizer,
+            &t_input,
+            &t_output,
+            0,
+            &mut seeded_rng(1),
+        );
+
+        // rates grow exponentially, the run stops when the cost blows up
+        assert!((curve[0].0 - 1e-4).abs() < 1e-9);
+        assert!(curve.windows(2).all(|pair| pair[1].0 > pair[0].0));
+        let best = curve.iter().map(|p| p.1).fold(Float::INFINITY, Float::min);
+        assert!(best < curve[0].1);
+
+        let rate = LrFinder::suggest(&curve).unwrap();
+        assert!(rate > 1e-4 && rate < 1e3);
+        assert_eq!(LrFinder::suggest(&[(0.1, 1.0), (1.0, 2.0)]), None);
+    }
+
+    #[test]
+    fn test_ema() {
+        let mut ema = Ema::new(0.5);
+        let mut nn = NN::new(&[1, 1]);
+        nn.weights[0].data[0][0] = 1.0;
+        Ema::update(&mut ema, &nn);
+        assert_eq!(ema.nn.as_ref().unwrap().weights[0].data[0][0], 1.0);
+
+        // the second update is already past the warmup, (1 + 2) / (10 + 2) < 0.5
+        nn.weights[0].data[0][0] = 5.0;
+        nn.biases[0].data[0][0] = 4.0;
+        Ema::update(&mut ema, &nn);
+        let average = ema.nn.as_ref().unwrap();
+        assert_eq!(average.weights[0].data[0][0], 0.25 * 1.0 + 0.75 * 5.0);
+        assert_eq!(average.biases[0].data[0][0], 0.75 * 4.0);
+
+        ema.decay = 0.0;
+        Ema::update(&mut ema, &nn);
+        assert_eq!(ema.nn.as_ref().unwrap().weights, nn.weights);
+
+        Ema::reset(&mut ema);
+        assert!(ema.nn.is_none());
+    }
+
+    #[test]
+    fn test_deterministic_training() {
+        // batches of several PARALLEL_CHUNKs, with dropout, on 1 and 4 threads
+        let train = |threads: usize| {
+            set_backprop_threads(threads);
+            let mut rng = seeded_rng(11);
+            let dataset = Dataset::moons(PARALLEL_CHUNK * 3 + 5, 0.1, &mut rng);
+            let mut nn = NN::new(&[2, 8, 8, 1]);
+            Init::apply(Init::Auto, &mut nn, &mut rng);
+            NN::set_hidden_dropout(&mut nn, 0.2);
+            let mut g = NN::new(&[2, 8, 8, 1]);
+            let mut optimizer = Optimizer::new(0.5, Method::Sgd);
+            for _ in 0..3 {
+                for (x, y) in batches(&dataset.t_input, &dataset.t_output, 600, &mut rng) {
+                    NN::backprop(&mut nn, &mut g, &x, &y, &mut rng);
+                    Optimizer::step(&mut optimizer, &mut nn, &g);
+                }
+            }
+            (nn.weights, nn.biases)
+        };
+        let single = train(1);
+        let parallel = train(4);
+        let again = train(4);
+        set_backprop_threads(0);
+        assert_eq!(parallel, again);
+        assert_eq!(single, parallel);
+    }
+
+    #[test]
+    fn test_augment() {
+        let mut rng = seeded_rng(5);
+        let input = Mat::new(&[&[0.0, 0.0, 0.0, 0.0], &[0.0, 1.0, 0.0, 0.0]]);
+        let off = Augment {
+            noise: 0.0,
+            image: None,
+            shift: 2,
+        };
+        assert!(Augment::is_off(&off));
+        assert_eq!(Augment::apply(&off, &input, &mut rng), input);
+
+        // noise of about the right size, different every time
+        let noisy = Augment { noise: 0.1, ..off };
+        let rows = Mat {
+            rows: 1000,
+            cols: 4,
+            data: vec![vec![0.0; 4]; 1000],
+        };
+        let a = Augment::apply(&noisy, &rows, &mut rng);
+        let values: Vec<Float> = a.data.iter().flatten().copied().collect();
+        let std = (values.iter().map(|v| v * v).sum::<Float>() / values.len() as Float).sqrt();
+        assert!((std - 0.1).abs() < 0.01, "{}", std);
+        assert_ne!(a, Augment::apply(&noisy, &rows, &mut rng));
+
+        // a 2x2 image with one lit pixel moves it around, never adding or losing it
+        // unless it goes over the edge
+        let shifting = Augment {
+            image: Some((2, 2)),
+            shift: 1,
+            ..off
+        };
+        assert!(!Augment::is_off(&shifting));
+        let mut moved = false;
+        for _ in 0..20 {
+            let out = Augment::apply(&shifting, &input, &mut rng);
+            assert_eq!(out.data[0], vec![0.0; 4]);
+            let lit: Vec<Float> = out.data[1].iter().copied().filter(|&v| v != 0.0).collect();
+            assert!(lit.is_empty() || lit == vec![1.0]);
+            moved |= out.data[1] != input.data[1];
+        }
+        assert!(moved);
+    }
+
+    #[test]
+    fn test_class_weights() {
+        // 9 samples of class 0, 1 of class 1
+        let t_input = Mat {
+            rows: 10,
+            cols: 2,
+            data: (0..10).map(|i| vec![i as Float / 10.0, 0.5]).collect(),
+        };
+        let t_output = Mat {
+            rows: 10,
+            cols: 1,
+            data: (0..10).map(|i| vec![(i == 9) as u8 as Float]).collect(),
+        };
+        let weights = balanced_weights(&t_output);
+        assert_eq!(weights.len(), 2);
+        assert!((weights[0] - 10.0 / 18.0).abs() < 1e-6);
+        assert!((weights[1] - 5.0).abs() < 1e-6);
+        let one_hot = one_hot(&[0, 0, 0, 2], 3);
+        assert_eq!(balanced_weights(&one_hot), vec![4.0 / 6.0, 1.0, 2.0]);
+
+        let mut nn = NN::new(&[2, 3, 1]);
+        Init::apply(Init::Auto, &mut nn, &mut seeded_rng(6));
+        nn.reg = Regularization::default();
+        let cost = |nn: &NN, rows: Range<usize>| {
+            let input = Mat::slice_rows(&t_input, rows.clone());
+            NN::cost(nn, &input, &Mat::slice_rows(&t_output, rows))
+        };
+        let unweighted = (cost(&nn, 0..9), cost(&nn, 9..10));
+        nn.class_weights = weights.clone();
+        let weighted = NN::cost(&nn, &t_input, &t_output);
+        let expected = weights[0] * unweighted.0 + weights[1] * unweighted.1;
+        assert!((weighted - expected).abs() < 1e-5);
+
+        // the gradient follows the weighted cost
+        let errors = gradient_check(&nn, &t_input, &t_output, 1e-3);
+        assert!(errors.iter().all(|&e| e < 1e-2), "{:?}", errors);
+    }
+
+    #[test]
+    #[cfg(feature = "fast-activations")]
+    fn test_fast_activations() {
+        // the bounds documented on fast_tanh and fast_sigmoid, out to where exp overflows
+        for i in -20000..=20000 {
+            let x = i as Float / 1000.0;
+            assert!(
+                (activation::fast_tanh(x) - x.tanh()).abs() < 1e-4,
+                "tanh({})",
+                x
+            );
+            let sigmoid = 1.0 / (1.0 + (-x).exp());
+            assert!(
+                (activation::fast_sigmoid(x) - sigmoid).abs() < 5e-5,
+                "sigmoid({})",
+                x
+            );
+        }
+        for x in [-1e30, 1e30, Float::INFINITY] {
+            assert_eq!(activation::fast_tanh(x), x.signum());
+        }
+        assert_eq!(activation::fast_tanh(0.0), 0.0);
+        assert_eq!(activation::fast_sigmoid(0.0), 0.5);
+    }
+
+    #[test]
+    fn test_quantize() {
+        let mut rng = seeded_rng(5);
+        let dataset = Dataset::moons(200, 0.1, &mut rng);
+        let mut nn = NN::new(&[2, 16, 1]);
+        nn.norm = Normalization::fit(Scaling::Standard, &dataset.t_input);
+        Init::apply(Init::Auto, &mut nn, &mut rng);
+        let mut g = NN::new(&[2, 16, 1]);
+        let mut optimizer = Optimizer::new(1.0, Method::Sgd);
+        for _ in 0..500 {
+            for (x, y) in batches(&dataset.t_input, &dataset.t_output, 32, &mut rng) {
+                NN::backprop(&mut nn, &mut g, &x, &y, &mut rng);
+                Optimizer::step(&mut optimizer, &mut nn, &g);
+            }
+        }
+
+        let q = QuantizedNN::new(&nn);
+        assert_eq!(q.arch, vec![2, 16, 1]);
+        // the largest weight of every layer is at full scale
+        for weights in &q.weights {
+            assert_eq!(weights.iter().map(|w| w.unsigned_abs()).max(), Some(127));
+        }
+        // i8 weights, and the 2 scales and 17 biases as f32
+        assert_eq!(QuantizedNN::size(&q), 2 * 16 + 16 + (2 + 17) * 4);
+        assert!(QuantizedNN::max_error(&q, &nn, &dataset.t_input) < 0.05);
+        let exact = NN::accuracy(&nn, &dataset.t_input, &dataset.t_output);
+        let int8 = QuantizedNN::accuracy(&q, &dataset.t_input, &dataset.t_output);
+        assert!(exact > 0.9, "{}", exact);
+        assert!((exact - int8).abs() <= 0.02, "{} vs {}", exact, int8);
+
+        let src = QuantizedNN::to_rust(&q).unwrap();
+        assert!(src.contains("pub const W1: [[i8; 1]; 16] = ["));
+        assert!(src.contains("let a1 = layer(&a0, &W0, S0, &B0, sigmoid);"));
+        assert!(src.contains("let mut sums = [0i32; O];"));
+    }
+
+    #[test]
+    fn test_prune() {
+        let mut rng = seeded_rng(2);
+        let dataset = Dataset::moons(100, 0.1, &mut rng);
+        let mut nn = NN::new(&[2, 10, 1]);
+        Init::apply(Init::Auto, &mut nn, &mut rng);
+        let smallest = nn.weights[0]
+            .data
+            .iter()
+            .flatten()
+            .map(|w| w.abs())
+            .fold(Float::INFINITY, Float::min);
+
+        NN::prune(&mut nn, 0.5);
+        // half of every layer, the smallest ones
+        assert_eq!(NN::sparsity(&nn), 0.5);
+        for (l, w) in nn.weights.iter().enumerate() {
+            let zeros = w.data.iter().flatten().filter(|&&v| v == 0.0).count();
+            assert_eq!(zeros, w.rows * w.cols / 2);
+            for (j, row) in w.data.iter().enumerate() {
+                for (k, &v) in row.iter().enumerate() {
+                    assert_eq!(NN::is_pruned(&nn, l, j, k), v == 0.0);
+                }
+            }
+        }
+        let first = nn.weights[0].data.concat();
+        assert!(!first.contains(&smallest) && !first.contains(&-smallest));
+
+        // fine-tuning leaves them at zero
+        let mut g = NN::new(&[2, 10, 1]);
+        for method in [
+            Method::Sgd,
+            Method::Adam {
+                beta1: 0.9,
+                beta2: 0.999,
+                eps: 1e-8,
+            },
+        ] {
+            let mut optimizer = Optimizer::new(0.5, method);
+            for (x, y) in batches(&dataset.t_input, &dataset.t_output, 10, &mut rng) {
+                NN::backprop(&mut nn, &mut g, &x, &y, &mut rng);
+                Optimizer::step(&mut optimizer, &mut nn, &g);
+            }
+        }
+        assert_eq!(NN::sparsity(&nn), 0.5);
+        for (l, w) in nn.weights.iter().enumerate() {
+            for (j, row) in w.data.iter().enumerate() {
+                for (k, &v) in row.iter().enumerate() {
+                    assert!(!NN::is_pruned(&nn, l, j, k) || v == 0.0);
+                }
+            }
+        }
+
+        // pruning more keeps the ones pruned before
+        let before = nn.pruned.clone();
+        NN::prune(&mut nn, 0.8);
+        assert_eq!(NN::sparsity(&nn), 0.8);
+        for (before, after) in before
+            .iter()
+            .flatten()
+            .flatten()
+            .zip(nn.pruned.iter().flatten().flatten())
+        {
+            assert!(!before || *after);
+        }
+        NN::unprune(&mut nn);
+        assert_eq!(NN::sparsity(&nn), 0.0);
+    }
+
+    #[test]
+    fn test_evolution() {
+        let mut rng = seeded_rng(8);
+        let (t_input, t_output) = (
+            Mat::new(&[&[0.0, 0.0], &[0.0, 1.0], &[1.0, 0.0], &[1.0, 1.0]]),
+            Mat::new(&[&[0.0], &[1.0], &[1.0], &[0.0]]),
+        );
+        let mut nn = NN::new(&[2, 4, 1]);
+        Init::apply(Init::Auto, &mut nn, &mut rng);
+        let start = NN::cost(&nn, &t_input, &t_output);
+        let mut evolution = Evolution::new(&nn, 30, &mut rng);
+        assert_eq!(Evolution::best(&evolution).weights, nn.weights);
+        assert!(Evolution::spread(&evolution)[0].is_nan());
+
+        let mut best = Float::INFINITY;
+        for _ in 0..100 {
+            Evolution::generation(&mut evolution, &t_input, &t_output, &mut rng);
+            assert_eq!(evolution.population.len(), 30);
+            let spread = Evolution::spread(&evolution);
+            assert!(spread.windows(2).all(|pair| pair[0] <= pair[1]));
+            // the elite survives, so the best never gets worse
+            assert!(spread[0] <= best);
+            best = spread[0];
+            assert_eq!(
+                NN::cost(Evolution::best(&evolution), &t_input, &t_output),
+                best
+            );
+        }
+        assert!(best < start * 0.5, "{} from {}", best, start);
+    }
+
+    #[test]
+    fn test_divergence() {
+        let mut d = Divergence::new(10.0);
+        assert_eq!(Divergence::update(&mut d, 1.0), None);
+        assert_eq!(Divergence::update(&mut d, 5.0), None);
+        assert_eq!(Divergence::update(&mut d, 100.0), Some(20.0));
+        assert_eq!(
+            Divergence::update(&mut d, Float::NAN),
+            Some(Float::INFINITY)
+        );
+        // compared with the last finite cost
+        assert_eq!(Divergence::update(&mut d, 200.0), None);
+
+        Divergence::reset(&mut d);
+        assert_eq!(Divergence::update(&mut d, 1e6), None);
+        let mut never = Divergence::new(0.0);
+        Divergence::update(&mut never, 1.0);
+        assert_eq!(Divergence::update(&mut never, 1e6), None);
+        assert_eq!(
+            Divergence::update(&mut never, Float::INFINITY),
+            Some(Float::INFINITY)
+        );
+
+        assert_eq!(Divergence::worst_layer(&[0.1, 3.0, 0.5]), Some(1));
+        assert_eq!(Divergence::worst_layer(&[0.1, 3.0, Float::NAN]), Some(2));
+        assert_eq!(Divergence::worst_layer(&[]), None);
+    }
+
+    #[test]
+    fn test_descent() {
+        let t_input = Mat::new(&[&[0.0, 0.0], &[0.0, 1.0], &[1.0, 0.0], &[1.0, 1.0]]);
+        let t_output = Mat::new(&[&[0.0], &[1.0], &[1.0], &[0.0]]);
+        let mut rng = seeded_rng(0);
+        let mut d = Descent::Full;
+        // one step an epoch, then two batches of two, then one per sample
+        for steps in [1, 2, 4, 1] {
+            let batch_size = Descent::batch_size(d);
+            assert_eq!(
+                batches(&t_input, &t_output, batch_size, &mut rng).len(),
+                steps
+            );
+            assert_eq!(Descent::of(batch_size, t_input.rows), d);
+            d = Descent::next(d, 2);
+        }
+        assert_eq!(Descent::of(8, 4), Descent::Full);
     }
 }
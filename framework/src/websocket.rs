@@ -0,0 +1,188 @@
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use super::Metrics;
+
+// Streams the metrics as JSON text messages to every WebSocket client connected to
+// `addr`, for dashboards watching a long run. The handshake and the framing are done
+// by hand like the TensorBoard events (RFC 6455), clients only listen.
+#[derive(Debug)]
+pub struct MetricsServer {
+    pub addr: SocketAddr,
+    clients: Arc<Mutex<Vec<TcpStream>>>,
+}
+
+// SHA-1, only for the handshake's Sec-WebSocket-Accept
+pub(crate) fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [
+        0x6745_2301,
+        0xefcd_ab89,
+        0x98ba_dcfe,
+        0x1032_5476,
+        0xc3d2_e1f0,
+    ];
+    let mut msg = data.to_vec();
+    msg.push(0x80);
+    while msg.len() % 64 != 56 {
+        msg.push(0);
+    }
+    msg.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+
+    for block in msg.chunks(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes(word.try_into().unwrap());
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, &word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5a82_7999),
+                20..=39 => (b ^ c ^ d, 0x6ed9_eba1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1b_bcdc),
+                _ => (b ^ c ^ d, 0xca62_c1d6),
+            };
+            let t = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = t;
+        }
+        for (h, v) in h.iter_mut().zip([a, b, c, d, e]) {
+            *h = h.wrapping_add(v);
+        }
+    }
+
+    let mut out = [0; 20];
+    for (i, word) in h.iter().enumerate() {
+        out[i * 4..i * 4 + 4].copy_from_slice(&word.to_be_bytes());
+    }
+    out
+}
+
+pub(crate) fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::new();
+    for chunk in data.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+// Answers the client's upgrade request, or fails when it isn't one
+fn handshake(stream: &TcpStream) -> io::Result<String> {
+    let mut key = None;
+    let mut reader = BufReader::new(stream);
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("sec-websocket-key") {
+                key = Some(value.trim().to_owned());
+            }
+        }
+    }
+    let Some(key) = key else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a WebSocket request",
+        ));
+    };
+
+    let accept = base64(&sha1(
+        format!("{}258EAFA5-E914-47DA-95CA-C5AB0DC85B11", key).as_bytes(),
+    ));
+    Ok(format!(
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+        accept
+    ))
+}
+
+// One unmasked, final text frame
+fn frame(text: &str) -> Vec<u8> {
+    let mut buf = vec![0x81];
+    match text.len() {
+        len @ 0..=125 => buf.push(len as u8),
+        len @ 126..=0xffff => {
+            buf.push(126);
+            buf.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            buf.push(127);
+            buf.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    buf.extend_from_slice(text.as_bytes());
+    buf
+}
+
+impl MetricsServer {
+    // Listens on addr (port 0 picks a free one, see MetricsServer::addr), accepting
+    // clients on a thread of its own
+    pub fn bind(addr: impl ToSocketAddrs) -> io::Result<MetricsServer> {
+        let listener = TcpListener::bind(addr)?;
+        let server = MetricsServer {
+            addr: listener.local_addr()?,
+            clients: Arc::new(Mutex::new(vec![])),
+        };
+        let clients = Arc::clone(&server.clients);
+        thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                // a client that never finishes its request or stops reading is dropped
+                let _ = stream.set_read_timeout(Some(Duration::from_secs(5)));
+                let _ = stream.set_write_timeout(Some(Duration::from_secs(1)));
+                let Ok(response) = handshake(&stream) else {
+                    let _ = stream.write_all(b"HTTP/1.1 400 Bad Request\r\n\r\n");
+                    continue;
+                };
+                // locked so no message gets in before the response
+                let mut clients = clients.lock().unwrap();
+                if stream.write_all(response.as_bytes()).is_ok() {
+                    clients.push(stream);
+                }
+            }
+        });
+        Ok(server)
+    }
+
+    // Sends the metrics to every client, returns how many got them. Disconnected
+    // clients are forgotten.
+    pub fn send(server: &MetricsServer, metrics: &Metrics) -> usize {
+        let Ok(json) = serde_json::to_string(metrics) else {
+            return 0;
+        };
+        let message = frame(&json);
+        let mut clients = server.clients.lock().unwrap();
+        clients.retain_mut(|client| client.write_all(&message).is_ok());
+        clients.len()
+    }
+}
@@ -0,0 +1,274 @@
+// what pyo3 0.20's #[pymethods] expand to, newer compilers warn about it
+#![allow(non_local_definitions)]
+
+use framework::{
+    batches, random_seed, seeded_rng, Activation, Error, Float, Init, Loss, Mat, Method, NNBuilder,
+    Optimizer, StdRng, NN,
+};
+use pyo3::{
+    exceptions::{PyIOError, PyValueError},
+    prelude::*,
+};
+
+// The framework for Python, e.g. in a notebook:
+//
+//     import nn_rust
+//     nn = nn_rust.NN([2, 4, 4, 1], seed=1)
+//     x, y = [[0, 0], [0, 1], [1, 0], [1, 1]], [[0], [1], [1], [0]]
+//     nn.train(x, y, epochs=5000, rate=0.5)
+//     nn.predict(x).tolist()
+//     nn.save("model.json")  # opens in the visualizer
+//     nn_rust.register_activation("softsign", lambda x: x / (1 + abs(x)),
+//                                 lambda a: (1 - abs(a)) ** 2)
+//
+// Matrices can be passed as a Mat or as lists of rows.
+
+fn value_error(e: Error) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
+
+fn mat_of(obj: &PyAny) -> PyResult<Mat> {
+    if let Ok(mat) = obj.extract::<PyRef<PyMat>>() {
+        return Ok(mat.mat.clone());
+    }
+    let rows: Vec<Vec<Float>> = obj.extract()?;
+    let rows: Vec<&[Float]> = rows.iter().map(|row| row.as_slice()).collect();
+    Mat::try_new(&rows).map_err(value_error)
+}
+
+#[pyclass(name = "Mat")]
+#[derive(Clone)]
+struct PyMat {
+    mat: Mat,
+}
+
+#[pymethods]
+impl PyMat {
+    #[new]
+    fn new(rows: &PyAny) -> PyResult<PyMat> {
+        Ok(PyMat { mat: mat_of(rows)? })
+    }
+
+    #[getter]
+    fn rows(&self) -> usize {
+        self.mat.rows
+    }
+
+    #[getter]
+    fn cols(&self) -> usize {
+        self.mat.cols
+    }
+
+    fn tolist(&self) -> Vec<Vec<Float>> {
+        self.mat.data.clone()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Mat({:?})", self.mat.data)
+    }
+}
+
+// A network with its optimizer and random generator, what the visualizer's training
+// thread holds
+#[pyclass(name = "NN")]
+struct PyNN {
+    nn: NN,
+    optimizer: Optimizer,
+    rng: StdRng,
+}
+
+// sigmoid, tanh, relu or one registered with register_activation
+fn activation(name: &str) -> PyResult<Activation> {
+    Activation::from_name(name).ok_or_else(|| {
+        PyValueError::new_err(format!(
+            "unknown activation `{}`, expected sigmoid, tanh, relu or a registered one",
+            name
+        ))
+    })
+}
+
+// Python functions of a float as an activation, NN(activation=name) uses it. `deriv`
+// gets the activation's output like Activation::register's. An exception in either
+// makes the output NaN, so training fails as diverged.
+#[pyfunction]
+fn register_activation(name: &str, f: PyObject, deriv: PyObject) {
+    let call = |function: PyObject| {
+        move |x: Float| {
+            Python::with_gil(|py| {
+                function
+                    .call1(py, (x,))
+                    .and_then(|y| y.extract::<Float>(py))
+                    .unwrap_or(Float::NAN)
+            })
+        }
+    };
+    Activation::register(name, call(f), call(deriv));
+}
+
+fn method(name: &str) -> PyResult<Method> {
+    match name {
+        "sgd" => Ok(Optimizer::sgd(0.0).method),
+        "momentum" => Ok(Optimizer::momentum(0.0, 0.9, true).method),
+        "adam" => Ok(Optimizer::adam(0.0).method),
+        _ => Err(PyValueError::new_err(format!(
+            "unknown optimizer `{}`, expected sgd, momentum or adam",
+            name
+        ))),
+    }
+}
+
+#[pymethods]
+impl PyNN {
+    // Hidden layers with `activation`, a sigmoid output layer like the visualizer's,
+    // initialized with Init::Auto. The huber loss has a delta of 1.
+    #[new]
+    #[pyo3(signature = (arch, activation = "tanh", loss = "mse", optimizer = "momentum", seed = None))]
+    fn new(
+        arch: Vec<usize>,
+        activation: &str,
+        loss: &str,
+        optimizer: &str,
+        seed: Option<u64>,
+    ) -> PyResult<PyNN> {
+        if arch.len() < 2 || arch.contains(&0) {
+            return Err(PyValueError::new_err(
+                "arch needs an input and an output size, none of them 0",
+            ));
+        }
+        let act = self::activation(activation)?;
+        let loss = match loss {
+            "mse" => Loss::Mse,
+            "cross_entropy" => Loss::CrossEntropy,
+            "mae" => Loss::Mae,
+            "huber" => Loss::Huber(1.0),
+            _ => {
+                return Err(PyValueError::new_err(format!(
+                    "unknown loss `{}`, expected mse, cross_entropy, mae or huber",
+                    loss
+                )))
+            }
+        };
+
+        let mut builder = NNBuilder::new().input(arch[0]);
+        for &size in &arch[1..arch.len() - 1] {
+            builder = builder.dense(size, act);
+        }
+        let (mut nn, optimizer) = builder
+            .dense(arch[arch.len() - 1], Activation::Sigmoid)
+            .loss(loss)
+            .optimizer(Optimizer::new(0.1, method(optimizer)?))
+            .build();
+        let mut rng = seeded_rng(seed.unwrap_or_else(random_seed));
+        Init::apply(Init::Auto, &mut nn, &mut rng);
+        Ok(PyNN { nn, optimizer, rng })
+    }
+
+    // A network saved by the visualizer or NN.save, trained on with plain momentum
+    #[staticmethod]
+    fn load(path: &str) -> PyResult<PyNN> {
+        let nn = NN::load(path).map_err(|e| PyIOError::new_err(e.to_string()))?;
+        Ok(PyNN {
+            nn,
+            optimizer: Optimizer::momentum(0.1, 0.9, true),
+            rng: seeded_rng(random_seed()),
+        })
+    }
+
+    // JSON, or the binary format for any other extension
+    fn save(&self, path: &str) -> PyResult<()> {
+        NN::save(&self.nn, path).map_err(|e| PyIOError::new_err(e.to_string()))
+    }
+
+    #[getter]
+    fn arch(&self) -> Vec<usize> {
+        NN::arch(&self.nn)
+    }
+
+    // Row i of the result is the output for row i of `inputs`
+    fn predict(&mut self, inputs: &PyAny) -> PyResult<PyMat> {
+        let inputs = mat_of(inputs)?;
+        let outputs = self.nn.activations[self.nn.count - 1].cols;
+        let mut data = Vec::with_capacity(inputs.rows);
+        for i in 0..inputs.rows {
+            NN::try_forward(&mut self.nn, &Mat::row(&inputs, i)).map_err(value_error)?;
+            data.push(self.nn.activations[self.nn.count - 1].data[0].clone());
+        }
+        Ok(PyMat {
+            mat: Mat {
+                rows: inputs.rows,
+                cols: outputs,
+                data,
+            },
+        })
+    }
+
+    fn cost(&self, inputs: &PyAny, outputs: &PyAny) -> PyResult<Float> {
+        let (inputs, outputs) = (mat_of(inputs)?, mat_of(outputs)?);
+        NN::check(&self.nn, &inputs, &outputs).map_err(value_error)?;
+        Ok(NN::cost(&self.nn, &inputs, &outputs))
+    }
+
+    // `epochs` passes over the samples in batches of `batch_size` (0 for all of them at
+    // once), returns the cost after the last one. Fails when the weights diverge.
+    #[pyo3(signature = (inputs, outputs, epochs = 1, rate = 0.1, batch_size = 0))]
+    fn train(
+        &mut self,
+        py: Python<'_>,
+        inputs: &PyAny,
+        outputs: &PyAny,
+        epochs: usize,
+        rate: Float,
+        batch_size: usize,
+    ) -> PyResult<Float> {
+        let (inputs, outputs) = (mat_of(inputs)?, mat_of(outputs)?);
+        NN::check(&self.nn, &inputs, &outputs).map_err(value_error)?;
+        let PyNN { nn, optimizer, rng } = self;
+        optimizer.rate = rate;
+        // other Python threads run meanwhile
+        py.allow_threads(|| {
+            let mut gradient = NN::new(&NN::arch(nn));
+            for _ in 0..epochs {
+                for (x, y) in batches(&inputs, &outputs, batch_size, rng) {
+                    NN::backprop(nn, &mut gradient, &x, &y, rng);
+                    Optimizer::step(optimizer, nn, &gradient);
+                }
+                if !NN::is_finite(nn) {
+                    return Err(PyValueError::new_err(
+                        "the weights diverged, try a lower rate",
+                    ));
+                }
+            }
+            Ok(NN::cost(nn, &inputs, &outputs))
+        })
+    }
+
+    // Layer `layer`'s weights, inputs by outputs, and biases
+    fn weights(&self, layer: usize) -> PyResult<(PyMat, PyMat)> {
+        if layer >= self.nn.count - 1 {
+            return Err(PyValueError::new_err(format!(
+                "the network has {} weight layers",
+                self.nn.count - 1
+            )));
+        }
+        Ok((
+            PyMat {
+                mat: self.nn.weights[layer].clone(),
+            },
+            PyMat {
+                mat: self.nn.biases[layer].clone(),
+            },
+        ))
+    }
+
+    fn __repr__(&self) -> String {
+        format!("NN({:?})", NN::arch(&self.nn))
+    }
+}
+
+#[pymodule]
+fn nn_rust(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
+    m.add_class::<PyMat>()?;
+    m.add_class::<PyNN>()?;
+    m.add_function(wrap_pyfunction!(register_activation, m)?)?;
+    Ok(())
+}
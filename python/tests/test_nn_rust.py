@@ -0,0 +1,53 @@
+# Smoke tests of the bindings, after `maturin develop`: python -m pytest tests
+import pytest
+
+import nn_rust
+
+X = [[0, 0], [0, 1], [1, 0], [1, 1]]
+Y = [[0], [1], [1], [0]]
+
+
+def test_mat():
+    mat = nn_rust.Mat([[1, 2], [3, 4]])
+    assert (mat.rows, mat.cols) == (2, 2)
+    assert mat.tolist() == [[1, 2], [3, 4]]
+    with pytest.raises(ValueError):
+        nn_rust.Mat([[1, 2], [3]])
+
+
+def test_train_xor():
+    nn = nn_rust.NN([2, 4, 4, 1], seed=1)
+    assert nn.arch == [2, 4, 4, 1]
+    before = nn.cost(X, Y)
+    after = nn.train(X, Y, epochs=5000, rate=0.5)
+    assert after < before
+    assert [round(row[0]) for row in nn.predict(X).tolist()] == [0, 1, 1, 0]
+
+
+def test_save_load(tmp_path):
+    nn = nn_rust.NN([2, 3, 1], seed=2)
+    path = str(tmp_path / "model.json")
+    nn.save(path)
+    loaded = nn_rust.NN.load(path)
+    assert loaded.predict(X).tolist() == nn.predict(X).tolist()
+
+
+def test_errors():
+    with pytest.raises(ValueError):
+        nn_rust.NN([2, 0, 1])
+    with pytest.raises(ValueError):
+        nn_rust.NN([2, 1], activation="nope")
+    nn = nn_rust.NN([2, 1], seed=3)
+    with pytest.raises(ValueError):
+        nn.cost([[0, 0, 0]], [[0]])
+    with pytest.raises(ValueError):
+        nn.weights(1)
+
+
+def test_register_activation():
+    nn_rust.register_activation(
+        "softsign", lambda x: x / (1 + abs(x)), lambda a: (1 - abs(a)) ** 2
+    )
+    nn = nn_rust.NN([2, 4, 1], activation="softsign", seed=4)
+    before = nn.cost(X, Y)
+    assert nn.train(X, Y, epochs=200, rate=0.5) < before
@@ -0,0 +1,90 @@
+use std::time::{Duration, Instant};
+
+use super::{
+    batch_size, batches, build_nn, fit_data, has_fast_activations, load_dataset, network_size,
+    random_seed, seeded_rng, set_fast_activations, Config, Init, Optimizer, Schedule, INIT, NN,
+};
+
+// `bench`: trains the configured network and dataset for a fixed time, without a
+// window, checkpoints or logging, and reports how fast it went. Every epoch is
+// the backprop and optimizer step of each batch plus the cost (forward passes over
+// the whole training set) the training loops compute after it.
+pub fn run(config: &Config, seconds: f32) {
+    let seed = config.seed.unwrap_or_else(random_seed);
+    let mut rng = seeded_rng(seed);
+    let (dataset_name, dataset, pixels) =
+        load_dataset(Config::source(config).as_ref(), &[], &mut rng, &mut None);
+    let (t_input, t_output) = (dataset.t_input, dataset.t_output);
+    let (hidden_layers, dataset_batch) = network_size(pixels.as_ref());
+    let batch_size = batch_size(config, dataset_batch, t_input.rows);
+
+    let mut nn_structure = vec![t_input.cols];
+    nn_structure.extend_from_slice(Config::hidden(config).as_deref().unwrap_or(hidden_layers));
+    nn_structure.push(t_output.cols);
+    Config::check_layers(config, &nn_structure);
+    let (mut nn, mut optimizer) = build_nn(&nn_structure, config);
+    fit_data(&mut nn, config, &t_input, &t_output);
+    Init::apply(INIT, &mut nn, &mut rng);
+    println!(
+        "Benchmarking {:?} on {} ({} samples) for {}s",
+        nn_structure, dataset_name, t_input.rows, seconds
+    );
+    if config.fast_activations && !has_fast_activations() {
+        println!("Built without the fast-activations feature, sigmoid and tanh stay exact");
+    }
+    let fast = config.fast_activations && has_fast_activations();
+    set_fast_activations(fast);
+    println!(
+        "Activations: {}",
+        if fast { "fast (within 1e-4)" } else { "exact" }
+    );
+
+    let mut gradient = NN::new(&nn_structure);
+
+    // no validation split, every sample is trained on
+    let (mut forward, mut backprop, mut learn) = (Duration::ZERO, Duration::ZERO, Duration::ZERO);
+    let mut epochs = 0;
+    let mut cost = 0.0;
+    let start = Instant::now();
+    let end = Duration::from_secs_f32(seconds);
+    while start.elapsed() < end {
+        optimizer.rate = Schedule::rate(&config.schedule, config.learning_rate, epochs);
+        for (x, y) in batches(&t_input, &t_output, batch_size, &mut rng) {
+            let t = Instant::now();
+            NN::backprop(&mut nn, &mut gradient, &x, &y, &mut rng);
+            backprop += t.elapsed();
+
+            let t = Instant::now();
+            Optimizer::step(&mut optimizer, &mut nn, &gradient);
+            learn += t.elapsed();
+        }
+
+        let t = Instant::now();
+        cost = NN::cost(&nn, &t_input, &t_output);
+        forward += t.elapsed();
+        epochs += 1;
+    }
+    let total = start.elapsed();
+
+    println!(
+        "{} epochs in {:.2}s: {:.1} epochs/s, final cost {}",
+        epochs,
+        total.as_secs_f32(),
+        epochs as f32 / total.as_secs_f32(),
+        cost
+    );
+    // the rest is batching and the loop itself
+    for (name, time) in [
+        ("forward", forward),
+        ("backprop", backprop),
+        ("learn", learn),
+    ] {
+        println!(
+            "{:>8}: {:>8.3}s {:>5.1}% {:>10.1}us/epoch",
+            name,
+            time.as_secs_f32(),
+            time.as_secs_f32() / total.as_secs_f32() * 100.0,
+            time.as_secs_f64() * 1e6 / epochs.max(1) as f64
+        );
+    }
+}
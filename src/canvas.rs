@@ -0,0 +1,155 @@
+use super::{
+    argmax, draw_rectangle, draw_rectangle_lines, draw_text, is_mouse_button_down,
+    is_mouse_button_pressed, mouse_position, Color, Mat, MouseButton, Rect, Theme, NN,
+};
+
+// Radius of the brush in pixels of the image, MNIST strokes are 2-3 pixels wide
+const BRUSH_RADIUS: f32 = 1.4;
+// Room for the labels under the canvas and the bars
+const LABEL_HEIGHT: f32 = 20.;
+
+// A digit drawn with the mouse in place of the probe sliders for image datasets
+// (I toggles), fed to the network for its guess every frame
+#[derive(Clone, Debug)]
+pub struct Canvas {
+    pub width: usize,
+    pub height: usize,
+    // 1 x width * height, brightness row by row like the MNIST samples
+    pub pixels: Mat,
+    // mouse position in pixels of the image on the last frame of a stroke, the
+    // brush is dragged along the line from it so fast strokes don't break up
+    last: Option<(f32, f32)>,
+}
+
+fn blank(size: usize) -> Mat {
+    Mat {
+        rows: 1,
+        cols: size,
+        data: vec![vec![0.; size]],
+    }
+}
+
+impl Canvas {
+    pub fn new(width: usize, height: usize) -> Canvas {
+        Canvas {
+            width,
+            height,
+            pixels: blank(width * height),
+            last: None,
+        }
+    }
+
+    // Paints a stroke from `from` to `to` (in pixels of the image), every pixel as
+    // bright as its center is close to the line, full within half the brush radius
+    pub fn stroke(canvas: &mut Canvas, from: (f32, f32), to: (f32, f32)) {
+        let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+        let length2 = dx * dx + dy * dy;
+        for row in 0..canvas.height {
+            for col in 0..canvas.width {
+                let (px, py) = (col as f32 + 0.5, row as f32 + 0.5);
+                // closest point of the line to the pixel center
+                let t = if length2 > 0. {
+                    (((px - from.0) * dx + (py - from.1) * dy) / length2).clamp(0., 1.)
+                } else {
+                    0.
+                };
+                let (cx, cy) = (from.0 + t * dx, from.1 + t * dy);
+                let distance = ((px - cx).powi(2) + (py - cy).powi(2)).sqrt();
+                let value = (2. * (1. - distance / BRUSH_RADIUS)).clamp(0., 1.);
+                let pixel = &mut canvas.pixels.data[0][row * canvas.width + col];
+                *pixel = pixel.max(value);
+            }
+        }
+    }
+
+    pub fn clear(canvas: &mut Canvas) {
+        canvas.pixels = blank(canvas.width * canvas.height);
+        canvas.last = None;
+    }
+
+    // The canvas on the left, drawn on with the left mouse button and cleared with the
+    // right one, and a bar per class on the right with the network's outputs for it,
+    // scaled to add up to 1
+    pub fn draw(canvas: &mut Canvas, nn: &NN, theme: Theme, rect: Rect) {
+        draw_rectangle(rect.x, rect.y, rect.w, rect.h, theme.panel);
+        let (width, height) = (canvas.width as f32, canvas.height as f32);
+        let cell = (rect.w / 2. / width).min((rect.h - LABEL_HEIGHT) / height);
+        let area = Rect::new(rect.x, rect.y, width * cell, height * cell);
+
+        let (mx, my) = mouse_position();
+        let at = ((mx - area.x) / cell, (my - area.y) / cell);
+        let inside = at.0 >= 0. && at.0 < width && at.1 >= 0. && at.1 < height;
+        if inside && is_mouse_button_pressed(MouseButton::Right) {
+            Canvas::clear(canvas);
+        }
+        if inside && is_mouse_button_down(MouseButton::Left) {
+            let from = canvas.last.unwrap_or(at);
+            Canvas::stroke(canvas, from, at);
+            canvas.last = Some(at);
+        } else {
+            canvas.last = None;
+        }
+
+        for row in 0..canvas.height {
+            for col in 0..canvas.width {
+                let value = canvas.pixels.data[0][row * canvas.width + col];
+                draw_rectangle(
+                    area.x + col as f32 * cell,
+                    area.y + row as f32 * cell,
+                    cell,
+                    cell,
+                    Color::new(value, value, value, 1.),
+                );
+            }
+        }
+        draw_rectangle_lines(area.x, area.y, area.w, area.h, 1., theme.inactive);
+        draw_text(
+            "draw | right click clears",
+            area.x,
+            area.y + area.h + 15.,
+            16.,
+            theme.text,
+        );
+
+        let mut nn = nn.clone();
+        Mat::copy(&mut nn.activations[0], &canvas.pixels);
+        NN::forward(&mut nn);
+        let output = &nn.activations[nn.count - 1].data[0];
+        let total: f32 = output.iter().sum();
+        let guess = argmax(output);
+
+        let chart = Rect::new(
+            area.x + area.w + 10.,
+            rect.y + 5.,
+            rect.w - area.w - 15.,
+            rect.h - LABEL_HEIGHT - 5.,
+        );
+        let bar = chart.w / output.len() as f32;
+        for (i, &value) in output.iter().enumerate() {
+            let share = if total > 0. { value / total } else { 0. };
+            let h = chart.h * share;
+            let x = chart.x + i as f32 * bar;
+            let color = if i == guess { theme.good } else { theme.cost };
+            draw_rectangle(x + 1., chart.y + chart.h - h, bar - 2., h, color);
+            draw_text(
+                format!("{}", i).as_str(),
+                x + bar / 2. - 4.,
+                chart.y + chart.h + 15.,
+                16.,
+                theme.text,
+            );
+        }
+        draw_text(
+            format!(
+                "guess {} ({:.0}%)",
+                guess,
+                100. * output[guess] / total.max(1e-9)
+            )
+            .as_str(),
+            chart.x,
+            chart.y + 12.,
+            16.,
+            theme.text,
+        );
+    }
+}
@@ -0,0 +1,858 @@
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    fs,
+    io::{self, ErrorKind},
+    path::Path,
+};
+
+use clap::{
+    builder::RangedU64ValueParser, error::ErrorKind as ClapError, value_parser, Arg, ArgAction,
+    ArgMatches, Command,
+};
+
+use super::{
+    is_picture,
+    sweep::Sweep,
+    toml::{self, Value},
+    Backend, Descent, Method, Preset, Schedule, Source, BALANCE_CLASSES, CHECKPOINT_DIR,
+    DETERMINISTIC_SEED, DIVERGENCE_FACTOR, EPOCHS_PER_SECOND, EPOCH_MAX, LEARNING_RATE,
+    MINI_BATCH_SIZE, MODEL_PATH, MOMENTUM, NESTEROV, ONNX_PATH, QUANTIZED_PATH, RUST_PATH,
+    SCHEDULE, VALIDATION_SPLIT,
+};
+
+// What can be changed without recompiling, e.g.
+//
+//     nn-rust --layers 2,8,8,1 --lr 0.5 --epochs 50000 --dataset xor
+//
+// or all at once with `--config experiment.toml` (see datasets/experiment.toml).
+// `nn-rust bench --seconds 10` takes the same options to time the training and
+// `nn-rust sweep --rates 0.1,0.5 --hidden 4,4x4` to compare hyperparameters,
+// `nn-rust curve` whether more data would help
+// (`--compare-lr 0.1` watches two of them train side by side).
+// Arguments win over the file and anything in neither falls back to the consts in
+// main.rs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    // last epoch trained
+    pub epochs: i32,
+    // base rate of the schedule
+    pub learning_rate: f32,
+    // cap on the epochs trained per second, 0.0 is as fast as possible
+    pub epochs_per_second: f32,
+    // pause when the cost jumps by more than this factor in an epoch, see Divergence
+    pub divergence_factor: f32,
+    // fraction of the samples held out for validation, 0.0 trains on everything
+    pub validation_split: f32,
+    // the whole architecture, the first and last sizes have to fit the dataset
+    pub layers: Option<Vec<usize>>,
+    // preset name (see Preset::ALL) or CSV path
+    pub dataset: Option<String>,
+    pub method: Method,
+    pub schedule: Schedule,
+    pub model_path: String,
+    pub onnx_path: String,
+    pub rust_path: String,
+    pub quantized_path: String,
+    pub checkpoint_dir: String,
+    // CSV or .jsonl file the metrics are appended to, see MetricsLogger
+    pub metrics_path: Option<String>,
+    // directory for TensorBoard event files, see EventWriter
+    pub tensorboard_dir: Option<String>,
+    // Keras export (.npz or JSON) the first network starts from, see NN::import_keras
+    pub import: Option<String>,
+    // address the metrics are streamed on over WebSocket, see MetricsServer
+    pub stream: Option<String>,
+    // for everything random, a new one every run when None
+    pub seed: Option<u64>,
+    // weigh the classes evenly, see balanced_weights
+    pub balance_classes: bool,
+    // train by neuroevolution instead of backprop, see POPULATION
+    pub evolve: bool,
+    // full batch, mini-batch or online gradient descent, None for the dataset's
+    // batch size
+    pub descent: Option<Descent>,
+    // where backprop runs, see set_backend
+    pub backend: Backend,
+    pub headless: bool,
+    // train in the terminal instead of the window, see tui.rs
+    pub tui: bool,
+    // continue --headless training from the model path and its TrainingState
+    pub resume: bool,
+    // seconds to train for with `nn-rust bench`, see bench.rs
+    pub bench: Option<f32>,
+    // bench with the approximate sigmoid and tanh, see set_fast_activations
+    pub fast_activations: bool,
+    // what `nn-rust sweep` tries, see sweep.rs
+    pub sweep: Option<Sweep>,
+    // fractions of the training samples `nn-rust curve` trains on, see curve.rs
+    pub curve: Option<Vec<f32>>,
+    // address `serve` listens on
+    pub serve: Option<String>,
+    // a second run trained and drawn next to the first one
+    pub compare: Option<Compare>,
+}
+
+// The hyperparameters of the compared run (--compare-lr, --compare-hidden), the rest
+// is the same as the first run's
+#[derive(Clone, Debug, PartialEq)]
+pub struct Compare {
+    pub learning_rate: f32,
+    // None keeps the first run's hidden layers
+    pub hidden: Option<Vec<usize>>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            epochs: EPOCH_MAX,
+            learning_rate: LEARNING_RATE,
+            epochs_per_second: EPOCHS_PER_SECOND,
+            divergence_factor: DIVERGENCE_FACTOR,
+            validation_split: VALIDATION_SPLIT,
+            layers: None,
+            dataset: None,
+            method: Method::Momentum {
+                momentum: MOMENTUM,
+                nesterov: NESTEROV,
+            },
+            schedule: SCHEDULE,
+            model_path: MODEL_PATH.to_owned(),
+            onnx_path: ONNX_PATH.to_owned(),
+            rust_path: RUST_PATH.to_owned(),
+            quantized_path: QUANTIZED_PATH.to_owned(),
+            checkpoint_dir: CHECKPOINT_DIR.to_owned(),
+            metrics_path: None,
+            tensorboard_dir: None,
+            stream: None,
+            import: None,
+            seed: None,
+            balance_classes: BALANCE_CLASSES,
+            evolve: false,
+            descent: None,
+            backend: Backend::Cpu,
+            headless: false,
+            tui: false,
+            resume: false,
+            bench: None,
+            fast_activations: false,
+            sweep: None,
+            curve: None,
+            serve: None,
+            compare: None,
+        }
+    }
+}
+
+impl Config {
+    pub fn parse() -> Config {
+        Self::parse_from(std::env::args_os())
+    }
+
+    // Prints the error (or --help) and exits on bad arguments or config files
+    pub fn parse_from<I, T>(args: I) -> Config
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let matches = command().get_matches_from(args);
+        let mut config = Config::default();
+        // a cosine schedule runs over all epochs unless the file says otherwise
+        let mut cosine_epochs = None;
+        if let Some(path) = matches.get_one::<String>("config") {
+            if let Err(e) = Self::apply_file(&mut config, &mut cosine_epochs, path) {
+                command()
+                    .error(ClapError::Io, format!("{}: {}", path, e))
+                    .exit();
+            }
+        }
+        Self::apply_args(&mut config, &matches);
+
+        if let Some(layers) = &config.layers {
+            if layers.len() < 2 || layers.contains(&0) {
+                command()
+                    .error(
+                        ClapError::InvalidValue,
+                        "layers need at least an input and an output size, none of them 0",
+                    )
+                    .exit();
+            }
+        }
+        if let Some(sweep) = &config.sweep {
+            let lists = [
+                sweep.rates.len(),
+                sweep.hidden.len(),
+                sweep.batch_sizes.len(),
+            ];
+            if sweep.random.is_some() && lists.contains(&0) {
+                command()
+                    .error(
+                        ClapError::InvalidValue,
+                        "--random picks from --rates, --hidden and --batch, none of them can be empty",
+                    )
+                    .exit();
+            }
+        }
+        if let Schedule::Cosine { epochs, .. } = &mut config.schedule {
+            *epochs = cosine_epochs.unwrap_or(config.epochs as usize);
+        }
+        config
+    }
+
+    fn apply_args(config: &mut Config, matches: &ArgMatches) {
+        if let Some(&epochs) = matches.get_one("epochs") {
+            config.epochs = epochs;
+        }
+        if let Some(&rate) = matches.get_one("epochs-per-second") {
+            config.epochs_per_second = rate;
+        }
+        if let Some(&factor) = matches.get_one("divergence-factor") {
+            config.divergence_factor = factor;
+        }
+        if let Some(&split) = matches.get_one("validation-split") {
+            config.validation_split = split;
+        }
+        if let Some(&rate) = matches.get_one("lr") {
+            config.learning_rate = rate;
+        }
+        if let Some(sizes) = matches.get_many::<usize>("layers") {
+            config.layers = Some(sizes.copied().collect());
+        }
+        if let Some(dataset) = matches.get_one::<String>("dataset") {
+            config.dataset = Some(dataset.clone());
+        }
+        if let Some(path) = matches.get_one::<String>("metrics") {
+            config.metrics_path = Some(path.clone());
+        }
+        if let Some(&seed) = matches.get_one("seed") {
+            config.seed = Some(seed);
+        }
+        // backprop already sums its parallel chunks in a fixed order, the seed is all
+        // that changes between runs
+        if matches.get_flag("deterministic") {
+            config.seed.get_or_insert(DETERMINISTIC_SEED);
+        }
+        if let Some(dir) = matches.get_one::<String>("tensorboard") {
+            config.tensorboard_dir = Some(dir.clone());
+        }
+        if let Some(&backend) = matches.get_one("backend") {
+            config.backend = backend;
+        }
+        if let Some(path) = matches.get_one::<String>("import") {
+            config.import = Some(path.clone());
+        }
+        if let Some(addr) = matches.get_one::<String>("stream") {
+            config.stream = Some(addr.clone());
+        }
+        let compare_lr = matches.get_one::<f32>("compare-lr");
+        let compare_hidden = matches.get_one::<Vec<usize>>("compare-hidden");
+        if compare_lr.is_some() || compare_hidden.is_some() {
+            config.compare = Some(Compare {
+                learning_rate: compare_lr.copied().unwrap_or(config.learning_rate),
+                hidden: compare_hidden.cloned(),
+            });
+        }
+        if matches.get_flag("balance-classes") {
+            config.balance_classes = true;
+        }
+        if matches.get_flag("evolve") {
+            config.evolve = true;
+        }
+        if let Some(&descent) = matches.get_one("descent") {
+            config.descent = Some(descent);
+        }
+        config.headless = matches.get_flag("headless");
+        config.tui = matches.get_flag("tui");
+        config.resume = matches.get_flag("resume");
+        if let Some(bench) = matches.subcommand_matches("bench") {
+            config.bench = bench.get_one("seconds").copied();
+            config.fast_activations = bench.get_flag("fast-activations");
+        }
+        if let Some(serve) = matches.subcommand_matches("serve") {
+            config.serve = serve.get_one::<String>("addr").cloned();
+            if let Some(path) = serve.get_one::<String>("model") {
+                config.model_path = path.clone();
+            }
+        }
+        if let Some(curve) = matches.subcommand_matches("curve") {
+            config.curve = Some(curve.get_many("fractions").unwrap().copied().collect());
+        }
+        if let Some(sweep) = matches.subcommand_matches("sweep") {
+            config.sweep = Some(Sweep {
+                rates: sweep.get_many("rates").unwrap().copied().collect(),
+                hidden: sweep.get_many("hidden").unwrap().cloned().collect(),
+                batch_sizes: sweep.get_many("batch").unwrap().copied().collect(),
+                random: sweep.get_one("random").copied(),
+                threads: sweep
+                    .get_one("threads")
+                    .copied()
+                    .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get())),
+            });
+        }
+    }
+
+    // An experiment file like
+    //
+    //     epochs = 50_000
+    //     learning_rate = 0.5
+    //     epochs_per_second = 500  # the same pace on every machine
+    //     divergence_factor = 10   # pause when the cost jumps 10x, 0 never does
+    //     validation_split = 0.2   # held out for the validation cost
+    //     layers = [2, 8, 8, 1]
+    //     dataset = "datasets/xor.csv"
+    //     seed = 42
+    //     balance_classes = true   # for a dataset with far more of one class
+    //     evolve = true        # a population evolved instead of backprop
+    //     descent = "online"   # full, mini or online gradient descent
+    //     backend = "gpu"      # backprop in compute shaders, needs the gpu feature
+    //
+    //     [optimizer]
+    //     method = "momentum"  # sgd, momentum (momentum, nesterov) or adam
+    //     momentum = 0.9
+    //     nesterov = true
+    //
+    //     [schedule]
+    //     kind = "cosine"      # constant, step (step, gamma), exponential (gamma)
+    //     min_rate = 0.01      # cosine (min_rate, epochs) or cyclical (min_rate, cycle),
+    //                          # both between min_rate and learning_rate
+    //
+    //     [output]
+    //     model = "model.json"
+    //     onnx = "model.onnx"
+    //     rust = "model.rs"
+    //     quantized = "model_int8.rs"
+    //     checkpoints = "checkpoints"
+    //     metrics = "metrics.csv"  # or .jsonl, not logged when left out
+    //     tensorboard = "runs/xor" # event files for `tensorboard --logdir runs`
+    //     stream = "127.0.0.1:9001" # WebSocket clients get every METRICS_EVERY epochs
+    //
+    // where every key is optional.
+    fn apply_file(
+        config: &mut Config,
+        cosine_epochs: &mut Option<usize>,
+        path: impl AsRef<Path>,
+    ) -> io::Result<()> {
+        let mut values = toml::parse(&fs::read_to_string(path)?)?;
+        let mut take = |key: &str| values.remove(key);
+
+        if let Some(v) = take("epochs") {
+            config.epochs = usize_of("epochs", &v)? as i32;
+        }
+        if let Some(v) = take("learning_rate") {
+            config.learning_rate = f32_of("learning_rate", &v)?;
+        }
+        if let Some(v) = take("epochs_per_second") {
+            config.epochs_per_second = f32_of("epochs_per_second", &v)?;
+        }
+        if let Some(v) = take("divergence_factor") {
+            config.divergence_factor = f32_of("divergence_factor", &v)?;
+        }
+        if let Some(v) = take("validation_split") {
+            let split = f32_of("validation_split", &v)?;
+            config.validation_split = parse_split(&split.to_string()).map_err(|e| invalid(&e))?;
+        }
+        if let Some(v) = take("layers") {
+            let Value::Array(sizes) = v else {
+                return Err(invalid("`layers` has to be an array of sizes"));
+            };
+            config.layers = Some(
+                sizes
+                    .iter()
+                    .map(|size| usize_of("layers", size))
+                    .collect::<io::Result<_>>()?,
+            );
+        }
+        if let Some(v) = take("dataset") {
+            config.dataset = Some(str_of("dataset", &v)?);
+        }
+        if let Some(v) = take("balance_classes") {
+            config.balance_classes = bool_of("balance_classes", &v)?;
+        }
+        if let Some(v) = take("evolve") {
+            config.evolve = bool_of("evolve", &v)?;
+        }
+        if let Some(v) = take("descent") {
+            config.descent = Some(parse_descent(&str_of("descent", &v)?).map_err(|e| invalid(&e))?);
+        }
+        if let Some(v) = take("seed") {
+            config.seed = Some(usize_of("seed", &v)? as u64);
+        }
+        if let Some(v) = take("backend") {
+            config.backend = parse_backend(&str_of("backend", &v)?).map_err(|e| invalid(&e))?;
+        }
+
+        if let Some(v) = take("optimizer.method") {
+            config.method = match str_of("optimizer.method", &v)?.as_str() {
+                "sgd" => Method::Sgd,
+                "momentum" => Method::Momentum {
+                    momentum: MOMENTUM,
+                    nesterov: NESTEROV,
+                },
+                "adam" => Method::Adam {
+                    beta1: 0.9,
+                    beta2: 0.999,
+                    eps: 1e-8,
+                },
+                other => return Err(invalid(&format!("unknown optimizer `{}`", other))),
+            };
+        }
+        if let Method::Momentum { momentum, nesterov } = &mut config.method {
+            if let Some(v) = take("optimizer.momentum") {
+                *momentum = f32_of("optimizer.momentum", &v)?;
+            }
+            if let Some(v) = take("optimizer.nesterov") {
+                *nesterov = bool_of("optimizer.nesterov", &v)?;
+            }
+        }
+
+        if let Some(v) = take("schedule.kind") {
+            config.schedule = match str_of("schedule.kind", &v)?.as_str() {
+                "constant" => Schedule::Constant,
+                "step" => Schedule::Step {
+                    step: 1000,
+                    gamma: 0.5,
+                },
+                "exponential" => Schedule::Exponential { gamma: 0.9999 },
+                "cosine" => Schedule::Cosine {
+                    epochs: 0,
+                    min_rate: 0.01,
+                },
+                "cyclical" => Schedule::Cyclical {
+                    cycle: 2000,
+                    min_rate: 0.01,
+                },
+                other => return Err(invalid(&format!("unknown schedule `{}`", other))),
+            };
+        }
+        match &mut config.schedule {
+            Schedule::Constant => {}
+            Schedule::Step { step, gamma } => {
+                if let Some(v) = take("schedule.step") {
+                    *step = usize_of("schedule.step", &v)?;
+                }
+                if let Some(v) = take("schedule.gamma") {
+                    *gamma = f32_of("schedule.gamma", &v)?;
+                }
+            }
+            Schedule::Exponential { gamma } => {
+                if let Some(v) = take("schedule.gamma") {
+                    *gamma = f32_of("schedule.gamma", &v)?;
+                }
+            }
+            Schedule::Cosine { min_rate, .. } => {
+                if let Some(v) = take("schedule.min_rate") {
+                    *min_rate = f32_of("schedule.min_rate", &v)?;
+                }
+                if let Some(v) = take("schedule.epochs") {
+                    *cosine_epochs = Some(usize_of("schedule.epochs", &v)?);
+                }
+            }
+            Schedule::Cyclical { cycle, min_rate } => {
+                if let Some(v) = take("schedule.cycle") {
+                    *cycle = usize_of("schedule.cycle", &v)?;
+                }
+                if let Some(v) = take("schedule.min_rate") {
+                    *min_rate = f32_of("schedule.min_rate", &v)?;
+                }
+            }
+        }
+
+        let outputs = [
+            ("output.model", &mut config.model_path),
+            ("output.onnx", &mut config.onnx_path),
+            ("output.rust", &mut config.rust_path),
+            ("output.quantized", &mut config.quantized_path),
+            ("output.checkpoints", &mut config.checkpoint_dir),
+        ];
+        for (key, path) in outputs {
+            if let Some(v) = take(key) {
+                *path = str_of(key, &v)?;
+            }
+        }
+        if let Some(v) = take("output.metrics") {
+            config.metrics_path = Some(str_of("output.metrics", &v)?);
+        }
+        if let Some(v) = take("output.tensorboard") {
+            config.tensorboard_dir = Some(str_of("output.tensorboard", &v)?);
+        }
+        if let Some(v) = take("output.stream") {
+            config.stream = Some(str_of("output.stream", &v)?);
+        }
+
+        // typos, or settings of another optimizer or schedule
+        if let Some(key) = sorted_keys(&values).first() {
+            return Err(invalid(&format!("unused setting `{}`", key)));
+        }
+        Ok(())
+    }
+
+    // Hidden layers of the configured layers
+    pub fn hidden(config: &Config) -> Option<Vec<usize>> {
+        let layers = config.layers.as_ref()?;
+        Some(layers[1..layers.len() - 1].to_vec())
+    }
+
+    // Warns when the configured input or output size doesn't fit the dataset, which wins
+    pub fn check_layers(config: &Config, arch: &[usize]) {
+        let Some(layers) = &config.layers else {
+            return;
+        };
+        if layers.first() != arch.first() || layers.last() != arch.last() {
+            println!(
+                "Layers {:?} don't fit the dataset, training {:?} instead",
+                layers, arch
+            );
+        }
+    }
+
+    pub fn source(config: &Config) -> Option<Source> {
+        let dataset = config.dataset.as_ref()?;
+        Some(match Preset::from_name(dataset) {
+            Some(preset) => Source::Preset(preset),
+            None if is_picture(Path::new(dataset)) => Source::Picture(dataset.into()),
+            None => Source::Csv(dataset.into()),
+        })
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, msg)
+}
+
+fn sorted_keys(values: &HashMap<String, Value>) -> Vec<&String> {
+    let mut keys: Vec<_> = values.keys().collect();
+    keys.sort();
+    keys
+}
+
+fn f32_of(key: &str, value: &Value) -> io::Result<f32> {
+    Value::as_f64(value)
+        .map(|v| v as f32)
+        .ok_or_else(|| invalid(&format!("`{}` has to be a number", key)))
+}
+
+fn usize_of(key: &str, value: &Value) -> io::Result<usize> {
+    Value::as_usize(value).ok_or_else(|| invalid(&format!("`{}` has to be a whole number", key)))
+}
+
+fn bool_of(key: &str, value: &Value) -> io::Result<bool> {
+    match *value {
+        Value::Bool(b) => Ok(b),
+        _ => Err(invalid(&format!("`{}` has to be true or false", key))),
+    }
+}
+
+fn str_of(key: &str, value: &Value) -> io::Result<String> {
+    match value {
+        Value::Str(s) => Ok(s.clone()),
+        _ => Err(invalid(&format!("`{}` has to be a string", key))),
+    }
+}
+
+fn parse_backend(s: &str) -> Result<Backend, String> {
+    Backend::from_name(s).ok_or_else(|| format!("`{}` is not cpu or gpu", s))
+}
+
+fn command() -> Command {
+    Command::new("nn-rust")
+        .about("Trains a small neural network and shows it learning")
+        .arg(
+            Arg::new("config")
+                .global(true)
+                .long("config")
+                .value_name("TOML")
+                .help("Experiment file with the architecture, dataset, optimizer, schedule and output paths"),
+        )
+        .arg(
+            Arg::new("layers")
+                .global(true)
+                .long("layers")
+                .value_name("SIZES")
+                .help("Layer sizes including input and output, e.g. 2,8,8,1")
+                .value_delimiter(',')
+                .value_parser(value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("lr")
+                .global(true)
+                .long("lr")
+                .value_name("RATE")
+                .help("Learning rate")
+                .value_parser(value_parser!(f32)),
+        )
+        .arg(
+            Arg::new("epochs-per-second")
+                .long("epochs-per-second")
+                .value_name("N")
+                .help("Train at most this many epochs a second, the same pace on every machine (0 is as fast as possible)")
+                .value_parser(value_parser!(f32)),
+        )
+        .arg(
+            Arg::new("divergence-factor")
+                .long("divergence-factor")
+                .value_name("FACTOR")
+                .help("Pause training when the cost jumps by more than this factor in an epoch or isn't finite (0 only for NaN/inf)")
+                .value_parser(value_parser!(f32)),
+        )
+        .arg(
+            Arg::new("validation-split")
+                .global(true)
+                .long("validation-split")
+                .value_name("FRACTION")
+                .help("Fraction of the samples held out for the validation cost, 0 trains on all of them")
+                .value_parser(parse_split),
+        )
+        .arg(
+            Arg::new("epochs")
+                .global(true)
+                .long("epochs")
+                .value_name("N")
+                .help("Epochs to train for")
+                .value_parser(value_parser!(i32).range(0..)),
+        )
+        .arg(
+            Arg::new("dataset")
+                .global(true)
+                .long("dataset")
+                .value_name("NAME|CSV")
+                .help("Built-in dataset (xor, and, or, adder, linear, spiral, moons, circles), a CSV file or a .png picture to redraw"),
+        )
+        .arg(
+            Arg::new("seed")
+                .global(true)
+                .long("seed")
+                .value_name("N")
+                .help("Seed for the initialization, validation split, batches and dropout, to repeat a run")
+                .value_parser(value_parser!(u64)),
+        )
+        .arg(
+            Arg::new("backend")
+                .global(true)
+                .long("backend")
+                .value_name("BACKEND")
+                .help("Backpropagate on the cpu or in compute shaders on the gpu (needs the gpu feature, falls back to the CPU without an adapter)")
+                .value_parser(parse_backend),
+        )
+        .arg(
+            Arg::new("deterministic")
+                .global(true)
+                .long("deterministic")
+                .help("Make every run give the same weights bit for bit on any number of cores, with seed 0 unless --seed is given")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("metrics")
+                .long("metrics")
+                .value_name("PATH")
+                .help("Append epoch, cost, accuracy, learning rate and time to a CSV (or .jsonl) file"),
+        )
+        .arg(
+            Arg::new("tensorboard")
+                .long("tensorboard")
+                .value_name("DIR")
+                .help("Write cost, accuracy, learning rate and gradient norms as TensorBoard events to DIR"),
+        )
+        .arg(
+            Arg::new("import")
+                .long("import")
+                .value_name("PATH")
+                .help("Start from dense layer weights exported from Keras, an .npz of model.get_weights() or JSON"),
+        )
+        .arg(
+            Arg::new("stream")
+                .long("stream")
+                .value_name("ADDR")
+                .help("Stream epoch, cost, accuracy, learning rate and time as JSON to WebSocket clients on ADDR, e.g. 127.0.0.1:9001"),
+        )
+        .arg(
+            Arg::new("compare-lr")
+                .long("compare-lr")
+                .value_name("RATE")
+                .help("Train a second network with this learning rate next to the first and draw both")
+                .value_parser(value_parser!(f32)),
+        )
+        .arg(
+            Arg::new("compare-hidden")
+                .long("compare-hidden")
+                .value_name("LAYERS")
+                .help("Hidden layers of the second network, sizes joined by x, e.g. 8x8")
+                .value_parser(parse_hidden),
+        )
+        .arg(
+            Arg::new("balance-classes")
+                .global(true)
+                .long("balance-classes")
+                .help("Weigh the samples of every class so the classes count the same, for imbalanced datasets")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("descent")
+                .global(true)
+                .long("descent")
+                .value_name("KIND")
+                .help("Gradient steps on the whole training set (full), on mini-batches (mini) or on one sample at a time (online)")
+                .value_parser(parse_descent),
+        )
+        .arg(
+            Arg::new("evolve")
+                .long("evolve")
+                .help("Evolve a population of networks (crossover and mutation of the weights) instead of training one by backprop")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("headless")
+                .long("headless")
+                .help("Train without opening a window and save the model")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("tui")
+                .long("tui")
+                .conflicts_with("headless")
+                .help("Train in the terminal with the cost, metrics and keys shown, no window needed")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("resume")
+                .long("resume")
+                .requires("headless")
+                .help("Continue from the saved model and the training state saved next to it")
+                .action(ArgAction::SetTrue),
+        )
+        .subcommand(
+            Command::new("bench")
+                .about("Trains without a window for a fixed time and reports epochs/s and where it went")
+                .arg(
+                    Arg::new("seconds")
+                        .long("seconds")
+                        .value_name("S")
+                        .help("How long to train for")
+                        .default_value("10")
+                        .value_parser(value_parser!(f32)),
+                )
+                .arg(
+                    Arg::new("fast-activations")
+                        .long("fast-activations")
+                        .help("Approximate sigmoid and tanh without exp, to compare the speed (needs the fast-activations feature)")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("sweep")
+                .about("Trains every combination of the rates, hidden layers and batch sizes without a window (--epochs each) and prints them best first")
+                .arg(
+                    Arg::new("rates")
+                        .long("rates")
+                        .value_name("RATES")
+                        .help("Learning rates to try")
+                        .value_delimiter(',')
+                        .default_value("0.05,0.1,0.5,1")
+                        .value_parser(parse_rate),
+                )
+                .arg(
+                    Arg::new("hidden")
+                        .long("hidden")
+                        .value_name("LAYERS")
+                        .help("Hidden layers to try, sizes of one architecture joined by x, e.g. 4,8,4x4")
+                        .value_delimiter(',')
+                        .default_value("4,8,4x4")
+                        .value_parser(parse_hidden),
+                )
+                .arg(
+                    Arg::new("batch")
+                        .long("batch")
+                        .value_name("SIZES")
+                        .help("Batch sizes to try, 0 for the whole dataset")
+                        .value_delimiter(',')
+                        .default_value("0,16")
+                        .value_parser(value_parser!(usize)),
+                )
+                .arg(
+                    Arg::new("random")
+                        .long("random")
+                        .value_name("N")
+                        .help("N random picks (log-uniform rate within the given ones) instead of the full grid")
+                        .value_parser(RangedU64ValueParser::<usize>::new().range(1..)),
+                )
+                .arg(
+                    Arg::new("threads")
+                        .long("threads")
+                        .value_name("N")
+                        .help("Runs trained at once, all cores by default")
+                        .value_parser(RangedU64ValueParser::<usize>::new().range(1..)),
+                ),
+        )
+        .subcommand(
+            Command::new("curve")
+                .about("Trains on growing fractions of the dataset without a window (--epochs each) and plots the training and validation costs against the number of samples")
+                .arg(
+                    Arg::new("fractions")
+                        .long("fractions")
+                        .value_name("FRACTIONS")
+                        .help("Fractions of the training samples to train on")
+                        .value_delimiter(',')
+                        .default_value("0.1,0.25,0.5,1")
+                        .value_parser(parse_fraction),
+                ),
+        )
+        .subcommand(
+            Command::new("serve")
+                .about("Answers HTTP requests with the saved model: POST a JSON array of inputs, get the outputs back")
+                .arg(
+                    Arg::new("addr")
+                        .long("addr")
+                        .value_name("ADDR")
+                        .help("Address to listen on")
+                        .default_value("127.0.0.1:8080"),
+                )
+                .arg(
+                    Arg::new("model")
+                        .long("model")
+                        .value_name("PATH")
+                        .help("Model to serve instead of the configured model path"),
+                ),
+        )
+}
+
+fn parse_fraction(s: &str) -> Result<f32, String> {
+    match s.parse() {
+        Ok(fraction) if fraction > 0.0 && fraction <= 1.0 => Ok(fraction),
+        _ => Err(format!("`{}` is not a fraction in (0, 1]", s)),
+    }
+}
+
+// The random picks of a sweep are log-uniform, so above 0
+fn parse_rate(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(rate) if rate > 0.0 && rate.is_finite() => Ok(rate),
+        _ => Err(format!("`{}` is not a learning rate above 0", s)),
+    }
+}
+
+// Something has to be left to train on
+fn parse_split(s: &str) -> Result<f32, String> {
+    match s.parse() {
+        Ok(split) if (0.0..1.0).contains(&split) => Ok(split),
+        _ => Err(format!("`{}` is not a fraction in [0, 1)", s)),
+    }
+}
+
+// The mini-batches get the dataset's batch size when it has one, see batch_size
+fn parse_descent(s: &str) -> Result<Descent, String> {
+    match s {
+        "full" => Ok(Descent::Full),
+        "mini" => Ok(Descent::MiniBatch(MINI_BATCH_SIZE)),
+        "online" => Ok(Descent::Online),
+        _ => Err(format!("`{}` is not full, mini or online", s)),
+    }
+}
+
+// "4x4" -> [4, 4]
+fn parse_hidden(s: &str) -> Result<Vec<usize>, String> {
+    s.split('x')
+        .map(|size| match size.parse() {
+            Ok(0) | Err(_) => Err(format!("`{}` is not a layer size", size)),
+            Ok(size) => Ok(size),
+        })
+        .collect()
+}
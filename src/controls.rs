@@ -0,0 +1,149 @@
+use super::{
+    draw_rectangle, draw_rectangle_lines, draw_text, is_mouse_button_down, is_mouse_button_pressed,
+    mouse_position, vec2, MouseButton, Rect, Theme,
+};
+
+// The training settings the sliders change, sent to the training thread with
+// Signal::Set. Start out as the consts in main.rs on every reset.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hyperparams {
+    // before the schedule is applied
+    pub learning_rate: f32,
+    // only used by the momentum optimizer
+    pub momentum: f32,
+    // samples per gradient step, 0 means the whole training set
+    pub batch_size: usize,
+    // epochs trained per drawn frame, 0 trains as fast as possible
+    pub epochs_per_frame: usize,
+    // 0.0 is as fast as possible
+    pub epochs_per_second: f32,
+    // standard deviation of the noise added to the training inputs every epoch
+    pub noise: f32,
+}
+
+// Name, range and whether the slider moves logarithmically, in Hyperparams order.
+// The epochs per second slider turns the cap off (0.0) all the way to the right.
+const SLIDERS: [(&str, f32, f32, bool); 6] = [
+    ("Learning rate", 0.001, 2.0, true),
+    ("Momentum", 0.0, 0.99, false),
+    ("Batch size", 0.0, 256.0, false),
+    ("Epochs/frame", 0.0, 200.0, false),
+    ("Epochs/second", 1.0, 10_000.0, true),
+    ("Input noise", 0.0, 0.3, false),
+];
+const UNCAPPED: usize = 4;
+// Space for the name and value above every slider
+const SLIDER_HEIGHT: f32 = 40.;
+pub const CONTROLS_HEIGHT: f32 = SLIDER_HEIGHT * SLIDERS.len() as f32 + 10.;
+
+fn get(params: &Hyperparams, i: usize) -> f32 {
+    match i {
+        0 => params.learning_rate,
+        1 => params.momentum,
+        2 => params.batch_size as f32,
+        3 => params.epochs_per_frame as f32,
+        4 => params.epochs_per_second,
+        _ => params.noise,
+    }
+}
+
+fn set(params: &mut Hyperparams, i: usize, value: f32) {
+    match i {
+        0 => params.learning_rate = value,
+        1 => params.momentum = value,
+        2 => params.batch_size = value.round() as usize,
+        3 => params.epochs_per_frame = value.round() as usize,
+        4 => params.epochs_per_second = value.round(),
+        _ => params.noise = value,
+    }
+}
+
+fn label(params: &Hyperparams, i: usize) -> String {
+    match i {
+        0 => format!("{:.4}", params.learning_rate),
+        1 => format!("{:.2}", params.momentum),
+        2 if params.batch_size == 0 => "all".to_owned(),
+        3 if params.epochs_per_frame == 0 => "max".to_owned(),
+        4 if params.epochs_per_second == 0. => "max".to_owned(),
+        5 if params.noise == 0. => "off".to_owned(),
+        5 => format!("{:.3}", params.noise),
+        _ => format!("{}", get(params, i)),
+    }
+}
+
+// Slider position (0..1) of a value and back
+fn to_t(value: f32, (min, max, log): (f32, f32, bool)) -> f32 {
+    let t = if log {
+        (value / min).ln() / (max / min).ln()
+    } else {
+        (value - min) / (max - min)
+    };
+    t.clamp(0., 1.)
+}
+
+fn from_t(t: f32, (min, max, log): (f32, f32, bool)) -> f32 {
+    if log {
+        min * (max / min).powf(t)
+    } else {
+        min + (max - min) * t
+    }
+}
+
+// Draws the sliders and moves the one dragged with the left mouse button,
+// `drag` keeps which one across frames. Returns whether a value changed.
+pub fn draw_controls(
+    params: &mut Hyperparams,
+    drag: &mut Option<usize>,
+    theme: Theme,
+    rect: Rect,
+) -> bool {
+    let (mx, my) = mouse_position();
+    if !is_mouse_button_down(MouseButton::Left) {
+        *drag = None;
+    }
+
+    let mut changed = false;
+    for (i, &(name, min, max, log)) in SLIDERS.iter().enumerate() {
+        let y = rect.y + i as f32 * SLIDER_HEIGHT;
+        let track = Rect::new(rect.x, y + 25., rect.w - 10., 8.);
+
+        // a bit of slack around the thin track
+        let grab = Rect::new(track.x - 5., track.y - 8., track.w + 10., track.h + 16.);
+        if is_mouse_button_pressed(MouseButton::Left) && grab.contains(vec2(mx, my)) {
+            *drag = Some(i);
+        }
+        if *drag == Some(i) {
+            let t = ((mx - track.x) / track.w).clamp(0., 1.);
+            let before = *params;
+            if i == UNCAPPED && t == 1. {
+                set(params, i, 0.);
+            } else {
+                set(params, i, from_t(t, (min, max, log)));
+            }
+            changed |= *params != before;
+        }
+
+        draw_text(
+            format!("{}: {}", name, label(params, i)).as_str(),
+            rect.x,
+            y + 18.,
+            18.,
+            theme.text,
+        );
+        draw_rectangle(track.x, track.y, track.w, track.h, theme.panel);
+        draw_rectangle_lines(track.x, track.y, track.w, track.h, 1., theme.inactive);
+        let t = if i == UNCAPPED && get(params, i) == 0. {
+            1.
+        } else {
+            to_t(get(params, i), (min, max, log))
+        };
+        draw_rectangle(
+            track.x + t * track.w - 3.,
+            track.y - 4.,
+            6.,
+            track.h + 8.,
+            theme.positive,
+        );
+    }
+    changed
+}
@@ -0,0 +1,154 @@
+use std::{thread, time::Instant};
+
+use super::{
+    batch_size, load_dataset, network_size, random_seed, seeded_rng, split,
+    sweep::{self, Trained},
+    Config, Mat,
+};
+
+// Held out when --validation-split holds out nothing, a learning curve needs samples the
+// networks never saw
+const HELD_OUT: f32 = 0.2;
+// Width of the longest cost bar
+const BAR_WIDTH: usize = 40;
+// The validation cost still falling by this much between the two largest sizes means
+// more data would help
+const STILL_FALLING: f32 = 0.05;
+// and a validation cost within this much of the training cost meets it
+const CLOSE: f32 = 0.15;
+
+struct Point {
+    fraction: f32,
+    samples: usize,
+    trained: Trained,
+}
+
+// `curve`: trains the configured network on growing fractions of the training samples
+// (each one a prefix of the next, all at once on their own threads) and compares the
+// final training cost with the cost on the same held out samples, the learning curve.
+// The validation cost still falling at the full set means more data would help, meeting
+// the training cost means the network is too small to do better with more.
+pub fn run(config: &Config, fractions: &[f32]) {
+    let seed = config.seed.unwrap_or_else(random_seed);
+    let mut rng = seeded_rng(seed);
+    let (dataset_name, dataset, pixels) =
+        load_dataset(Config::source(config).as_ref(), &[], &mut rng, &mut None);
+    let held_out = if config.validation_split > 0. {
+        config.validation_split
+    } else {
+        HELD_OUT
+    };
+    let ((t_input, t_output), (v_input, v_output)) =
+        split(&dataset.t_input, &dataset.t_output, held_out, &mut rng);
+    let (hidden_layers, dataset_batch) = network_size(pixels.as_ref());
+    let mut arch = vec![t_input.cols];
+    arch.extend_from_slice(Config::hidden(config).as_deref().unwrap_or(hidden_layers));
+    arch.push(t_output.cols);
+    Config::check_layers(config, &arch);
+    println!(
+        "Learning curve of {:?} on {} ({} samples, {} held out) with seed {}, up to {} epochs each",
+        arch, dataset_name, t_input.rows, v_input.rows, seed, config.epochs
+    );
+
+    let start = Instant::now();
+    let mut points: Vec<Point> = thread::scope(|s| {
+        let threads: Vec<_> = fractions
+            .iter()
+            .map(|&fraction| {
+                let (arch, t_input, t_output) = (&arch, &t_input, &t_output);
+                let (v_input, v_output) = (&v_input, &v_output);
+                s.spawn(move || {
+                    let samples = ((t_input.rows as f32 * fraction).round() as usize).max(1);
+                    let rows: Vec<usize> = (0..samples.min(t_input.rows)).collect();
+                    let subset = (
+                        Mat::select_rows(t_input, &rows),
+                        Mat::select_rows(t_output, &rows),
+                    );
+                    // mini-batches of the same size however many samples there are
+                    let batch_size = batch_size(config, dataset_batch, t_input.rows);
+                    let trained = sweep::train(
+                        config,
+                        arch,
+                        (&subset.0, &subset.1),
+                        (v_input, v_output),
+                        batch_size,
+                        seed,
+                    );
+                    Point {
+                        fraction,
+                        samples: subset.0.rows,
+                        trained,
+                    }
+                })
+            })
+            .collect();
+        threads.into_iter().map(|t| t.join().unwrap()).collect()
+    });
+    points.sort_by(|a, b| a.fraction.total_cmp(&b.fraction));
+
+    // the costs against the number of samples, one bar each
+    let max = points
+        .iter()
+        .flat_map(|p| [p.trained.cost, p.trained.validation])
+        .flatten()
+        .fold(0.0f32, f32::max)
+        .max(f32::MIN_POSITIVE);
+    let bar = |cost: Option<f32>| match cost {
+        Some(cost) => format!(
+            "{:<width$} {:.6}",
+            "#".repeat((cost / max * BAR_WIDTH as f32).round() as usize),
+            cost,
+            width = BAR_WIDTH
+        ),
+        None => "diverged".to_owned(),
+    };
+    println!();
+    for p in &points {
+        let accuracy = p.trained.accuracy.map_or(String::new(), |a| {
+            format!(", validation accuracy {:.1}%", a * 100.)
+        });
+        println!(
+            "{:>4.0}% {:>7} samples, {} epochs{}",
+            p.fraction * 100.,
+            p.samples,
+            p.trained.epochs,
+            accuracy
+        );
+        println!("      train      {}", bar(p.trained.cost));
+        println!("      validation {}", bar(p.trained.validation));
+    }
+    println!();
+    println!("{}", verdict(&points));
+    println!("Took {:.2}s", start.elapsed().as_secs_f32());
+}
+
+// What the two largest sizes say about more data
+fn verdict(points: &[Point]) -> String {
+    let [.., before, last] = points else {
+        return "Needs two dataset sizes to compare".to_owned();
+    };
+    let (Some(v_before), Some(v_last), Some(cost)) = (
+        before.trained.validation,
+        last.trained.validation,
+        last.trained.cost,
+    ) else {
+        return "No validation costs to compare, a run diverged or nothing was held out".to_owned();
+    };
+    let falling = (v_before - v_last) / v_before.max(f32::MIN_POSITIVE);
+    if falling > STILL_FALLING {
+        format!(
+            "The validation cost was still falling ({:.0}% from {} to {} samples), more data would help",
+            falling * 100.,
+            before.samples,
+            last.samples
+        )
+    } else if v_last - cost <= CLOSE * v_last.max(cost) {
+        "The validation cost meets the training cost and stopped falling, the network is \
+         the bottleneck: try more or bigger hidden layers"
+            .to_owned()
+    } else {
+        "The validation cost stopped falling above the training cost, the network overfits: \
+         more data or regularization would help"
+            .to_owned()
+    }
+}
@@ -1,61 +1,644 @@
 use macroquad::window::screen_width;
 
 use super::{
-    color_lerp, draw_circle, draw_line, draw_rectangle, draw_text, f32, screen_height, sigmoidf,
-    Color, Mat, EPOCH_MAX, GRAY, LEARNING_RATE, LINE_COLOR, NN, TEXT_COLOR,
+    argmax, class_of, clear_background, color_lerp, draw_circle, draw_circle_lines, draw_controls,
+    draw_line, draw_rectangle, draw_rectangle_lines, draw_text, f32, get_time, is_classification,
+    is_key_pressed, is_mouse_button_down, is_mouse_button_pressed, measure_text, mouse_position,
+    mouse_wheel, render_target, screen_height, set_camera, set_default_camera, sigmoidf,
+    Activation, Camera2D, Canvas, Color, Descent, Hyperparams, KeyCode, Loss, LrFinder, Mat,
+    Method, MouseButton, Probe, Rect, Theme, Timeline, CONTROLS_HEIGHT, GOLD, LIME, NN, ORANGE,
+    PINK, SHOW_DROPOUT, SKYBLUE, VIOLET,
 };
 
-const LOW_COLOR: Color = Color {
-    r: 0.,
-    g: 1.,
-    b: 0.,
-    a: 1.,
-};
-
-const HIGH_COLOR: Color = Color {
-    r: 1.,
-    g: 0.,
-    b: 1.,
-    a: 1.,
-};
+// Height of the status lines at the top and width of the key list on the right
+const STATUS_HEIGHT: f32 = 70.;
+const HELP_WIDTH: f32 = 150.;
+// The timeline bar between the status lines and the diagram
+const TIMELINE_HEIGHT: f32 = 30.;
+// The hidden layer sizes under the sliders
+const ARCH_HEIGHT: f32 = 25.;
+// Samples with more inputs (like MNIST pixels) aren't listed
+const MAX_LISTED_INPUTS: usize = 8;
+// Bigger layers (like the 784 MNIST inputs) only get their first neurons drawn
+const MAX_DRAWN_NEURONS: usize = 32;
+// Rows of the input -> output listing at the bottom left
+const MAX_SHOWN_SAMPLES: usize = 10;
+// Labeled ticks on each axis of the cost graph, plus one at the origin
+const GRAPH_TICKS: usize = 4;
+// Size of the exported cost plot PNG
+const PLOT_WIDTH: u32 = 1200;
+const PLOT_HEIGHT: u32 = 600;
+// Smallest epoch range the cost graph zooms in to
+const MIN_GRAPH_EPOCHS: f32 = 10.;
+// Cells per side of the decision boundary panel, each one is a forward pass
+const BOUNDARY_RESOLUTION: usize = 40;
+// Forward passes along the fitted curve of 1-input, 1-output networks
+const CURVE_RESOLUTION: usize = 100;
+// Colors of the earlier runs on the cost graph, by run number
+const RUN_COLORS: [Color; 6] = [ORANGE, SKYBLUE, PINK, LIME, VIOLET, GOLD];
+// Bars of the weight and bias histograms, spread evenly over [-max |value|, max |value|]
+const HISTOGRAM_BINS: usize = 21;
+// Dash and gap length of the pruned connections and the pruning marks
+const DASH: f32 = 6.;
 
 #[derive(Clone, Debug)]
 pub struct Renderinfo {
     pub epoch: i32,
     pub cost: f32,
+    // None unless the targets are classes
+    pub accuracy: Option<f32>,
+    // the training data only changes with a reset, which builds a new Renderinfo
     pub t_input: Mat,
     pub t_output: Mat,
+    // held out samples, empty without a validation split
+    pub v_input: Mat,
+    pub v_output: Mat,
     pub training_time: f32,
-    pub cost_history: Vec<f32>,
+    pub cost_history: CostHistory,
+    pub graph_view: GraphView,
     pub paused: bool,
+    pub learning_rate: f32,
+    // last epoch, from --epochs
+    pub epochs: i32,
+    // the momentum itself is in params, the slider changes it
+    pub method: Method,
+    // weights became NaN/inf, training was stopped
+    pub diverged: bool,
+    // epoch at which early stopping ended the training
+    pub converged: Option<i32>,
+    // why the last dataset or model couldn't be loaded, shown until it's replaced
+    pub error: Option<String>,
+    // (width, height) when the inputs are the pixels of an image (MNIST)
+    pub image: Option<(usize, usize)>,
+    // (width, height, brightness row by row) of the picture the network redraws from
+    // the pixel coordinates
+    pub picture: Option<(usize, usize, Vec<f32>)>,
+    // numbered from 1, every reset starts a new run
+    pub run_id: usize,
+    // the runs before this one, oldest first
+    pub past_runs: Vec<PastRun>,
+    // Draw their cost curves under this run's (U toggles)
+    pub show_runs: bool,
+    // Name of the preset or file the samples came from
+    pub dataset: String,
+    // everything random in this run came from it, --seed repeats the run
+    pub seed: u64,
+    // Draw the weight heatmaps in place of the network diagram
+    pub show_weights: bool,
+    // Histograms of the weights and biases in place of the diagram, over the heatmaps
+    pub show_histograms: bool,
+    // Plot the layers' parameter norms in place of the costs (K toggles)
+    pub show_norms: bool,
+    // The network drawn and evaluated is the moving average of the weights (A toggles)
+    pub show_ema: bool,
+    // (rate, cost) of the last learning rate range test (F), plotted over the cost graph
+    pub lr_curve: Option<Vec<(f32, f32)>>,
+    // the last int8 export (J), until the next one or a reset
+    pub quantized: Option<Quantized>,
+    // (epoch, fraction) of every X and Z, marked on the cost plot
+    pub prunes: Vec<(i32, f32)>,
+    // (epoch, descent) of every Tab, marked on the cost plot too
+    pub descents: Vec<(i32, Descent)>,
+    // the cost jump training paused at, until P resumes
+    pub jump: Option<Jump>,
+    // size of the population with --evolve, None for backprop
+    pub population: Option<usize>,
+    // L2 norm of the last gradient of every layer's weights and biases
+    pub grad_norms: Vec<f32>,
+    // Confusion matrices in place of the decision boundary (M toggles)
+    pub show_confusion: bool,
+    // Sliders for inputs made up by hand in the side panel (I toggles)
+    pub show_probe: bool,
+    pub probe: Probe,
+    // what the probe panel is for image datasets
+    pub canvas: Option<Canvas>,
+    // Plot log10 of the cost (O toggles)
+    pub log_scale: bool,
+    pub theme: Theme,
+    pub collapsed: Collapsed,
+    // What the sliders show, the training thread gets changes through Signal::Set
+    pub params: Hyperparams,
+    // slider being dragged
+    pub slider_drag: Option<usize>,
+    // frames drawn so far, for Hyperparams::epochs_per_frame
+    pub frame: usize,
+    // hidden layer the architecture keys change, underlined in the controls panel
+    pub edit_layer: usize,
+    // Training sample whose activations fill the neurons (arrow keys)
+    pub sample: usize,
+    // earlier networks to look at instead of the live one
+    pub timeline: Timeline,
 }
 
-pub fn draw_frame(nn: &NN, info: &mut Renderinfo) {
-    let nn = nn.clone();
-    let (width, height) = (screen_width(), screen_height());
+// Panels hidden with F1-F5, the network diagram takes over their space
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Collapsed {
+    pub help: bool,
+    pub graph: bool,
+    // decision boundary, fitted curve, confusion matrices, the MNIST sample, picture or probe
+    pub side: bool,
+    pub samples: bool,
+    pub controls: bool,
+}
+
+// Where every panel goes this frame, None for collapsed or unused ones
+struct Layout {
+    timeline: Rect,
+    diagram: Rect,
+    controls: Option<Rect>,
+    help: Option<Rect>,
+    graph: Option<Rect>,
+    side: Option<Rect>,
+    samples: Option<Rect>,
+}
+
+// What the side panel shows, in order of preference
+enum Side {
+    Canvas,
+    Probe,
+    Confusion,
+    Image(usize, usize),
+    Picture,
+    Boundary,
+    Curve,
+}
+
+fn side_panel(info: &Renderinfo) -> Option<Side> {
+    if info.show_probe && info.canvas.is_some() {
+        Some(Side::Canvas)
+    } else if info.show_probe && info.t_input.cols <= MAX_LISTED_INPUTS {
+        Some(Side::Probe)
+    } else if info.show_confusion && is_classification(&info.t_output) {
+        Some(Side::Confusion)
+    } else if let Some((image_width, image_height)) = info.image {
+        Some(Side::Image(image_width, image_height))
+    } else if info.picture.is_some() {
+        Some(Side::Picture)
+    } else if info.t_input.cols == 2 && info.t_output.cols == 1 {
+        Some(Side::Boundary)
+    } else if info.t_input.cols == 1 && info.t_output.cols == 1 {
+        Some(Side::Curve)
+    } else {
+        None
+    }
+}
+
+// Splits the window into the status lines at the top, the sliders and help on the right,
+// a strip at the bottom for the sample listing, side panel and cost graph (left to
+// right) and the network diagram in what's left. Recomputed every frame, so it
+// follows window resizes.
+fn layout(info: &Renderinfo, width: f32, height: f32) -> Layout {
+    let collapsed = info.collapsed;
+    let help_width = if collapsed.help && collapsed.controls {
+        0.
+    } else {
+        HELP_WIDTH
+    };
+    let samples = !collapsed.samples && info.t_input.cols <= MAX_LISTED_INPUTS;
+    let side = if collapsed.side {
+        None
+    } else {
+        side_panel(info)
+    };
+    let graph = !collapsed.graph;
+
+    let strip_height = if samples || side.is_some() || graph {
+        (height * 0.35).clamp(150., height * 0.5)
+    } else {
+        0.
+    };
+    let strip_y = height - strip_height;
+
+    let in_strip = |x: f32, w: f32| Rect::new(x, strip_y, w, strip_height);
+    let samples = samples.then(|| in_strip(0., width * 0.3));
+    let side_x = samples.map_or(0., |rect| rect.w);
+    let side = side.map(|side| {
+        // the confusion matrices of train and validation go side by side, and so do
+        // the canvas and its bar chart
+        let count = match side {
+            Side::Confusion if info.v_input.rows > 0 => 2.,
+            Side::Canvas => 2.,
+            _ => 1.,
+        };
+        in_strip(side_x, (strip_height - 25. + 10.) * count)
+    });
+    let graph_x = side.map_or(side_x, |rect| rect.x + rect.w);
+    let graph = graph.then(|| in_strip(graph_x, (width - graph_x).max(0.)));
+
+    let diagram_y = STATUS_HEIGHT + TIMELINE_HEIGHT;
+    Layout {
+        timeline: Rect::new(0., STATUS_HEIGHT, width - help_width, TIMELINE_HEIGHT),
+        diagram: Rect::new(0., diagram_y, width - help_width, strip_y - diagram_y),
+        controls: (!collapsed.controls).then(|| {
+            Rect::new(
+                width - help_width,
+                0.,
+                help_width,
+                CONTROLS_HEIGHT + ARCH_HEIGHT,
+            )
+        }),
+        help: (!collapsed.help).then(|| {
+            let help_y = if collapsed.controls {
+                0.
+            } else {
+                CONTROLS_HEIGHT + ARCH_HEIGHT
+            };
+            Rect::new(width - help_width, help_y, help_width, strip_y - help_y)
+        }),
+        graph,
+        side,
+        samples,
+    }
+}
+
+// Visible epoch range of the cost graph, changed with the mouse wheel (zoom),
+// dragging (pan) and right click (back to the whole history)
+#[derive(Clone, Debug, Default)]
+pub struct GraphView {
+    // None follows the whole history
+    pub range: Option<(f32, f32)>,
+    // mouse x of the last frame while dragging
+    pub drag: Option<f32>,
+}
+
+// (epoch, cost) points of the cost graph, per sample so the curves of the training
+// and validation sets compare
+#[derive(Clone, Debug, Default)]
+pub struct CostHistory {
+    pub train: Vec<(i32, f32)>,
+    pub validation: Vec<(i32, f32)>,
+    // (epoch, fraction correct) for classification datasets
+    pub accuracy: Vec<(i32, f32)>,
+    // train costs of the compared run, empty without one
+    pub compare: Vec<(i32, f32)>,
+    // the scheduled learning rate, plotted under the costs when it changes
+    pub rate: Vec<(i32, f32)>,
+    // (epoch, L2 norm of the weights and biases) of every layer, see NN::layer_norms
+    pub norms: Vec<Vec<(i32, f32)>>,
+    // (generation, spread of the costs) of an evolved population, see Evolution::spread
+    pub population: Vec<(i32, [f32; 5])>,
+}
 
+// The training costs of a run before the last reset, kept for comparing the next
+// ones against
+#[derive(Clone, Debug)]
+pub struct PastRun {
+    pub id: usize,
+    // dataset, learning rate and hidden layers
+    pub label: String,
+    pub train: Vec<(i32, f32)>,
+}
+
+// How the int8 weights of the last J export did on the training samples
+#[derive(Clone, Copy, Debug)]
+pub enum Quantized {
+    // classes, the accuracy of the network and of its int8 version
+    Accuracy { epoch: i32, exact: f32, int8: f32 },
+    // anything else, the largest difference between their outputs
+    Error { epoch: i32, max: f32 },
+}
+
+// A jump in the cost training paused at (see Divergence), shown until it resumes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Jump {
+    pub epoch: i32,
+    // times the epoch before's cost, infinite for a NaN/inf cost
+    pub factor: f32,
+    // index into grad_norms of the largest gradient, None without backprop
+    pub layer: Option<usize>,
+}
+
+// Adds the cost (and accuracy) of the network's latest epoch to the history
+fn record_cost(nn: &NN, info: &mut Renderinfo) {
     // Skip epoch 0 because the value is already in the cost history (from creating the struct)
-    if info.epoch < EPOCH_MAX && !info.paused && info.epoch != 0 {
-        let cost = NN::cost(&nn, &info.t_input, &info.t_output);
+    if info.epoch < info.epochs && !info.paused && info.epoch != 0 && info.converged.is_none() {
+        let cost = NN::cost(nn, &info.t_input, &info.t_output);
 
         info.cost = cost;
-        info.cost_history.push(cost);
+        // NaN would wreck the graph scaling
+        if cost.is_finite() {
+            let per_sample = cost / info.t_input.rows as f32;
+            info.cost_history.train.push((info.epoch, per_sample));
+        }
+        info.cost_history
+            .rate
+            .push((info.epoch, info.learning_rate));
+        let norms = NN::layer_norms(nn);
+        info.cost_history.norms.resize(norms.len(), vec![]);
+        for (series, norm) in info.cost_history.norms.iter_mut().zip(norms) {
+            series.push((info.epoch, norm));
+        }
+
+        if is_classification(&info.t_output) {
+            let accuracy = NN::accuracy(nn, &info.t_input, &info.t_output);
+            info.accuracy = Some(accuracy);
+            info.cost_history.accuracy.push((info.epoch, accuracy));
+        }
+    }
+}
+
+// Returns the new hyperparameters when a slider was moved. A compared run gets the
+// right half of the diagram and its cost curve on the graph.
+pub fn draw_frame(
+    nn: &NN,
+    info: &mut Renderinfo,
+    mut compared: Option<(&NN, &mut Renderinfo)>,
+) -> Option<Hyperparams> {
+    let theme = info.theme;
+    let (width, height) = (screen_width(), screen_height());
+
+    record_cost(nn, info);
+    Timeline::record(&mut info.timeline, info.epoch, nn);
+    // everything below shows the network picked on the timeline
+    let nn = match Timeline::selected(&info.timeline) {
+        Some((_, earlier)) => earlier.clone(),
+        None => nn.clone(),
+    };
+    if let Some((compared_nn, compared)) = &mut compared {
+        record_cost(compared_nn, compared);
+        if let Some(&last) = compared.cost_history.train.last() {
+            if info.cost_history.compare.last() != Some(&last) {
+                info.cost_history.compare.push(last);
+            }
+        }
     }
 
-    draw_nn(&nn, width, height * 0.8);
-    draw_graph(width, height, info);
-    draw_data(info, nn);
+    info.frame += 1;
 
-    draw_text("r - reset", width - 100., 20., 20., TEXT_COLOR);
-    draw_text("p - pause", width - 100., 40., 20., TEXT_COLOR);
-    draw_text("q - quit", width - 100., 60., 20., TEXT_COLOR);
+    let layout = layout(info, width, height);
+    Timeline::draw(&mut info.timeline, theme, layout.timeline);
+    let mut hits = vec![];
+    let diagram = layout.diagram;
+    let halves: Vec<(&Renderinfo, NN, Rect)> = match &compared {
+        Some((compared_nn, compared)) => {
+            let half = diagram.w / 2.;
+            vec![
+                (&*info, nn.clone(), Rect { w: half, ..diagram }),
+                (
+                    &**compared,
+                    (*compared_nn).clone(),
+                    Rect {
+                        x: diagram.x + half,
+                        w: half,
+                        ..diagram
+                    },
+                ),
+            ]
+        }
+        None => vec![(&*info, nn.clone(), diagram)],
+    };
+    let split = halves.len() > 1;
+    for (i, (run, nn, rect)) in halves.into_iter().enumerate() {
+        if split {
+            draw_run_label(run, &nn, i, rect);
+        }
+        if info.show_histograms {
+            draw_histograms(&nn, theme, rect);
+        } else if info.show_weights {
+            draw_weights(&nn, info.image, theme, rect);
+        } else {
+            // the compared run's diagram uses the first one's sample and panels
+            hits.extend(draw_nn(info, nn, rect));
+        }
+    }
+    if let (Some(rect), Some(curve)) = (layout.graph, &info.lr_curve) {
+        draw_lr_curve(curve, theme, rect);
+    } else if let Some(rect) = layout.graph {
+        draw_graph(
+            rect,
+            info,
+            compared.as_ref().map(|(_, compared)| compared.cost),
+        );
+    }
+    if let Some(rect) = layout.side {
+        match side_panel(info) {
+            Some(Side::Canvas) => {
+                if let Some(canvas) = &mut info.canvas {
+                    Canvas::draw(canvas, &nn, theme, rect)
+                }
+            }
+            Some(Side::Probe) => {
+                let sample = info
+                    .t_input
+                    .data
+                    .get(info.sample)
+                    .map_or(&[][..], Vec::as_slice);
+                Probe::draw(&mut info.probe, &nn, sample, theme, rect)
+            }
+            Some(Side::Confusion) => draw_confusion(info, &nn, rect),
+            Some(Side::Image(image_width, image_height)) => {
+                draw_sample(info, nn.clone(), image_width, image_height, rect)
+            }
+            Some(Side::Picture) => draw_picture(info, nn.clone(), rect),
+            Some(Side::Boundary) => draw_boundary(info, nn.clone(), rect),
+            Some(Side::Curve) => draw_curve(info, nn.clone(), rect),
+            None => {}
+        }
+    }
+    draw_data(info, nn.clone(), layout.samples);
+
+    if info.diverged {
+        draw_text(
+            "Training diverged (NaN/inf weights) - lower the learning rate and reset",
+            10.,
+            layout.diagram.y + layout.diagram.h * 0.5,
+            24.,
+            theme.bad,
+        );
+    } else if let Some(jump) = info.jump {
+        let cost = if jump.factor.is_finite() {
+            format!("Cost jumped {:.1}x", jump.factor)
+        } else {
+            "Cost became NaN/inf".to_owned()
+        };
+        let layer = jump.layer.map_or(String::new(), |i| {
+            format!(", largest gradient into layer {}", i + 1)
+        });
+        let y = layout.diagram.y + layout.diagram.h * 0.5;
+        draw_text(
+            format!("{} at epoch {}{}", cost, jump.epoch, layer).as_str(),
+            10.,
+            y,
+            24.,
+            theme.bad,
+        );
+        draw_text(
+            "Paused - lower the learning rate, P resumes",
+            10.,
+            y + 24.,
+            24.,
+            theme.bad,
+        );
+    }
+
+    if let Some(error) = &info.error {
+        draw_text(
+            error.as_str(),
+            10.,
+            layout.diagram.y + layout.diagram.h - 10.,
+            24.,
+            theme.bad,
+        );
+    }
+
+    draw_tooltip(&hits, theme);
+
+    if let Some(rect) = layout.help {
+        let keys = [
+            "r - reset",
+            "p - pause",
+            "n/b - step 1/100",
+            "enter - rewind",
+            "s - save",
+            "l - load",
+            "e - export onnx",
+            "g - export rust",
+            "j - export int8",
+            "x/z - prune/unprune",
+            "tab - full/mini/online",
+            "w - weights",
+            "y - histograms",
+            "o - log cost",
+            "m - confusion",
+            "i - probe/draw digit",
+            "f - find rate",
+            "k - param norms",
+            "a - averaged weights",
+            "c - screenshot",
+            "h - cost plot",
+            "u - earlier runs",
+            "v - record gif",
+            "t - theme",
+            "1-8 - dataset",
+            "d - draw dataset",
+            "[ ] - pick layer",
+            "= - - add/remove",
+            "up/down - neurons",
+            "f1-f5 - panels",
+            "q - quit",
+        ];
+        for (i, key) in keys.iter().enumerate() {
+            let y = rect.y + 20. + i as f32 * 20.;
+            if y > rect.y + rect.h {
+                break;
+            }
+            draw_text(key, rect.x, y, 20., theme.text);
+        }
+    }
+
+    let changed = layout.controls.is_some_and(|rect| {
+        draw_arch(info, &nn, rect);
+        let sliders = Rect::new(rect.x, rect.y, rect.w, CONTROLS_HEIGHT);
+        draw_controls(&mut info.params, &mut info.slider_drag, theme, sliders)
+    });
+    changed.then_some(info.params)
+}
+
+// "A: rate 0.5, hidden [4, 4], cost 0.01" above each half of a split diagram, in the
+// color of the run's cost curve
+fn draw_run_label(run: &Renderinfo, nn: &NN, index: usize, rect: Rect) {
+    let theme = run.theme;
+    let arch = NN::arch(nn);
+    let state = if run.diverged {
+        " (diverged)".to_owned()
+    } else if let Some(epoch) = run.converged {
+        format!(" (converged at {})", epoch)
+    } else {
+        String::new()
+    };
+    draw_text(
+        format!(
+            "{}: rate {:.4}, hidden {:?}, cost {:.6}{}",
+            if index == 0 { "A" } else { "B" },
+            run.learning_rate,
+            &arch[1..arch.len() - 1],
+            run.cost,
+            state
+        )
+        .as_str(),
+        rect.x + 10.,
+        rect.y + 15.,
+        18.,
+        if run.diverged {
+            theme.bad
+        } else if index == 0 {
+            theme.cost
+        } else {
+            theme.compare
+        },
+    );
 }
 
-fn draw_nn(nn: &NN, width: f32, height: f32) {
-    let x = 0.;
-    let y = 0.;
+// "Hidden: 4 4" under the sliders with the layer being edited underlined
+fn draw_arch(info: &Renderinfo, nn: &NN, rect: Rect) {
+    let theme = info.theme;
+    let arch = NN::arch(nn);
+    let y = rect.y + CONTROLS_HEIGHT + 15.;
+    let mut x = rect.x;
+    let label = "Hidden:";
+    draw_text(label, x, y, 18., theme.text);
+    x += measure_text(label, None, 18, 1.).width + 6.;
+
+    if arch.len() <= 2 {
+        draw_text("none", x, y, 18., theme.inactive);
+        return;
+    }
+    for (i, neurons) in arch[1..arch.len() - 1].iter().enumerate() {
+        let text = neurons.to_string();
+        let text_width = measure_text(&text, None, 18, 1.).width;
+        draw_text(&text, x, y, 18., theme.text);
+        if i == info.edit_layer {
+            draw_line(x, y + 3., x + text_width, y + 3., 2., theme.positive);
+        }
+        x += text_width + 8.;
+    }
+}
+
+// Activation mapped to 0..1 for coloring a neuron
+fn activation_shade(act: Activation, a: f32) -> f32 {
+    match act {
+        Activation::Tanh => (a + 1.) / 2.,
+        Activation::Sigmoid | Activation::Relu | Activation::Custom(_) => a.clamp(0., 1.),
+    }
+}
+
+// Element of the network diagram under the mouse, with the values its tooltip shows
+enum Hit {
+    Neuron {
+        center: (f32, f32),
+        radius: f32,
+        layer: usize,
+        index: usize,
+        bias: Option<f32>,
+        activation: f32,
+    },
+    Connection {
+        from: (f32, f32),
+        to: (f32, f32),
+        layer: usize,
+        // neuron in `layer` and in the next one
+        indices: (usize, usize),
+        weight: f32,
+    },
+}
+
+// Connections are colored by weight, neurons filled by their activation for the
+// selected sample and ringed by their bias. Returns where everything was drawn
+// for the hover tooltips.
+fn draw_nn(info: &Renderinfo, mut nn: NN, rect: Rect) -> Vec<Hit> {
+    let theme = info.theme;
+    let Rect {
+        x,
+        y,
+        w: width,
+        h: height,
+    } = rect;
+
+    let sample = info.sample % info.t_input.rows;
+    Mat::copy(&mut nn.activations[0], &Mat::row(&info.t_input, sample));
+    NN::forward(&mut nn);
+    let nn = &nn;
 
-    let neuron_radius = height * 0.03;
     let layer_border_vpad = height * 0.08;
     let layer_border_hpad = width * 0.06;
     let nn_width = width - 2.0 * layer_border_hpad;
@@ -64,140 +647,1412 @@ fn draw_nn(nn: &NN, width: f32, height: f32) {
     let nn_y = y + height / 2.0 - nn_height / 2.0;
     let arch_count = nn.count;
     let layer_hpad = nn_width / arch_count as f32;
+    let shown = |l: usize| nn.activations[l].cols.min(MAX_DRAWN_NEURONS);
+    let neuron_radius = (height * 0.03).min(nn_height / MAX_DRAWN_NEURONS as f32 * 0.4);
+    let mut hits = vec![];
 
     for l in 0..arch_count {
-        let layer_vpad1 = nn_height / nn.activations[l].cols as f32;
-        for i in 0..nn.activations[l].cols {
+        let layer_vpad1 = nn_height / shown(l) as f32;
+        for i in 0..shown(l) {
             let cx1 = nn_x + l as f32 * layer_hpad + layer_hpad / 2.0;
             let cy1 = nn_y + i as f32 * layer_vpad1 + layer_vpad1 / 2.0;
             if l + 1 < arch_count {
-                let layer_vpad2 = nn_height / nn.activations[l + 1].cols as f32;
-                for j in 0..nn.activations[l + 1].cols {
+                let layer_vpad2 = nn_height / shown(l + 1) as f32;
+                for j in 0..shown(l + 1) {
                     let cx2 = nn_x + (l + 1) as f32 * layer_hpad + layer_hpad / 2.0;
                     let cy2 = nn_y + j as f32 * layer_vpad2 + layer_vpad2 / 2.0;
                     let value = sigmoidf(nn.weights[l].data[i][j]);
                     let thick = height * 0.004;
-                    draw_line(
-                        cx1,
-                        cy1,
-                        cx2,
-                        cy2,
-                        thick,
-                        color_lerp(LOW_COLOR, HIGH_COLOR, value),
-                    );
+                    if NN::is_pruned(nn, l, i, j) {
+                        draw_dashed_line(cx1, cy1, cx2, cy2, thick * 0.5, theme.inactive);
+                    } else {
+                        draw_line(
+                            cx1,
+                            cy1,
+                            cx2,
+                            cy2,
+                            thick,
+                            color_lerp(theme.negative, theme.positive, value),
+                        );
+                    }
+                    hits.push(Hit::Connection {
+                        from: (cx1, cy1),
+                        to: (cx2, cy2),
+                        layer: l,
+                        indices: (i, j),
+                        weight: nn.weights[l].data[i][j],
+                    });
                 }
             }
-            if l > 0 {
-                let value = sigmoidf(nn.biases[l - 1].data[0][i]);
-                draw_circle(
-                    cx1,
-                    cy1,
-                    neuron_radius,
-                    color_lerp(LOW_COLOR, HIGH_COLOR, value),
-                );
+            hits.push(Hit::Neuron {
+                center: (cx1, cy1),
+                radius: neuron_radius,
+                layer: l,
+                index: i,
+                bias: l.checked_sub(1).map(|b| nn.biases[b].data[0][i]),
+                activation: nn.activations[l].data[0][i],
+            });
+            if SHOW_DROPOUT && nn.dropped[l][i] {
+                draw_circle(cx1, cy1, neuron_radius, theme.inactive);
+                continue;
+            }
+            let a = nn.activations[l].data[0][i];
+            let shade = if l > 0 {
+                activation_shade(nn.act[l - 1], a)
             } else {
-                draw_circle(cx1, cy1, neuron_radius, GRAY);
+                a.clamp(0., 1.)
+            };
+            draw_circle(
+                cx1,
+                cy1,
+                neuron_radius,
+                color_lerp(theme.background, theme.text, shade),
+            );
+            let ring = if l > 0 {
+                color_lerp(
+                    theme.negative,
+                    theme.positive,
+                    sigmoidf(nn.biases[l - 1].data[0][i]),
+                )
+            } else {
+                theme.inactive
+            };
+            draw_circle_lines(cx1, cy1, neuron_radius, 2., ring);
+        }
+
+        // Gradient of the weights into this layer on a log scale, 1e-6 (empty) to 1e2 (full)
+        if let Some(&norm) = l.checked_sub(1).and_then(|i| info.grad_norms.get(i)) {
+            let bar_width = layer_hpad * 0.5;
+            let bar_x = nn_x + l as f32 * layer_hpad + layer_hpad / 2.0 - bar_width / 2.0;
+            let bar_y = nn_y + nn_height + 25.;
+            let t = ((norm.max(1e-12).log10() + 6.) / 8.).clamp(0., 1.);
+            // the layer a jump in the cost most likely came from
+            let culprit = info.jump.and_then(|jump| jump.layer) == Some(l - 1);
+            if culprit {
+                draw_rectangle_lines(bar_x - 3., bar_y - 3., bar_width + 6., 12., 2., theme.bad);
             }
+            draw_rectangle(bar_x, bar_y, bar_width, 6., theme.panel);
+            draw_rectangle(
+                bar_x,
+                bar_y,
+                bar_width * t,
+                6.,
+                color_lerp(theme.negative, theme.positive, t),
+            );
+            draw_text(
+                format!("|g| {:.1e}", norm).as_str(),
+                bar_x,
+                bar_y + 20.,
+                16.,
+                if culprit { theme.bad } else { theme.text },
+            );
+        }
+
+        let hidden = nn.activations[l].cols - shown(l);
+        if hidden > 0 {
+            draw_text(
+                format!("+{} more", hidden).as_str(),
+                nn_x + l as f32 * layer_hpad + layer_hpad / 2.0 - 30.,
+                nn_y + nn_height + 15.,
+                20.,
+                theme.text,
+            );
         }
     }
+
+    hits
 }
 
-fn draw_graph(width: f32, height: f32, info: &Renderinfo) {
-    let x = 0.;
-    let y = 0.;
+// Distance from p to the segment a-b
+fn draw_dashed_line(x1: f32, y1: f32, x2: f32, y2: f32, thickness: f32, color: Color) {
+    let length = ((x2 - x1).powi(2) + (y2 - y1).powi(2)).sqrt();
+    let dashes = (length / (2. * DASH)).ceil() as usize;
+    for i in 0..dashes {
+        let t1 = i as f32 * 2. * DASH / length;
+        let t2 = ((i as f32 * 2. + 1.) * DASH / length).min(1.);
+        draw_line(
+            x1 + (x2 - x1) * t1,
+            y1 + (y2 - y1) * t1,
+            x1 + (x2 - x1) * t2,
+            y1 + (y2 - y1) * t2,
+            thickness,
+            color,
+        );
+    }
+}
 
-    // Draw a cost history graph in the bottom right
-    let graph_width = width * 0.3;
-    let graph_height = height * 0.3;
-    let graph_x = x + width - graph_width;
-    let graph_y = y + height - graph_height;
+fn segment_distance(p: (f32, f32), a: (f32, f32), b: (f32, f32)) -> f32 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let len2 = dx * dx + dy * dy;
+    let t = if len2 > 0. {
+        (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / len2).clamp(0., 1.)
+    } else {
+        0.
+    };
+    let (cx, cy) = (a.0 + t * dx, a.1 + t * dy);
+    ((p.0 - cx).powi(2) + (p.1 - cy).powi(2)).sqrt()
+}
 
-    draw_rectangle(
-        graph_x,
-        graph_y,
-        graph_width,
-        graph_height,
-        Color {
-            r: 0.2,
-            g: 0.2,
-            b: 0.2,
-            a: 0.5,
+// Values of the neuron or connection under the mouse, neurons win over the
+// connections they sit on
+fn draw_tooltip(hits: &[Hit], theme: Theme) {
+    let mouse = mouse_position();
+    let neuron = hits.iter().find(|hit| match hit {
+        Hit::Neuron { center, radius, .. } => segment_distance(mouse, *center, *center) <= *radius,
+        Hit::Connection { .. } => false,
+    });
+    let hit = neuron.or_else(|| {
+        hits.iter().find(|hit| match hit {
+            Hit::Connection { from, to, .. } => segment_distance(mouse, *from, *to) <= 3.,
+            Hit::Neuron { .. } => false,
+        })
+    });
+
+    let text = match hit {
+        Some(Hit::Neuron {
+            layer,
+            index,
+            bias,
+            activation,
+            ..
+        }) => match bias {
+            Some(bias) => format!(
+                "layer {} neuron {} | bias {:.4} | activation {:.4}",
+                layer, index, bias, activation
+            ),
+            None => format!("input {} | value {:.4}", index, activation),
         },
+        Some(Hit::Connection {
+            layer,
+            indices: (i, j),
+            weight,
+            ..
+        }) => format!(
+            "weight {}:{} -> {}:{} | {:.4}",
+            layer,
+            i,
+            layer + 1,
+            j,
+            weight
+        ),
+        None => return,
+    };
+
+    let (x, y) = (mouse.0 + 12., mouse.1 + 12.);
+    draw_rectangle(
+        x - 4.,
+        y - 16.,
+        text.len() as f32 * 8. + 8.,
+        22.,
+        theme.panel,
     );
+    draw_text(text.as_str(), x, y, 20., theme.text);
+}
+
+// Every weight matrix as a grid (rows are the inputs of the layer, columns its
+// neurons), red for positive and blue for negative weights, brighter the larger
+// the weight is compared to the largest one of the layer. With image inputs the
+// first layer is drawn as the filter every neuron learned instead. The visualizer
+// only trains the fully connected NN, Conv2D filters are printed by
+// framework/examples/mnist_cnn.rs.
+fn draw_weights(nn: &NN, image: Option<(usize, usize)>, theme: Theme, rect: Rect) {
+    let layers = nn.count - 1;
+    let slot_width = rect.w / layers as f32;
+    // room for the labels
+    let panel_height = rect.h - 30.;
+    let panel_y = rect.y + 25.;
+
+    for (l, w) in nn.weights.iter().enumerate() {
+        let max = w
+            .data
+            .iter()
+            .flatten()
+            .fold(0.0f32, |max, val| max.max(val.abs()));
+        let panel_width = slot_width * 0.8;
+        let panel_x = rect.x + l as f32 * slot_width + slot_width * 0.1;
+        let cell_width = panel_width / w.cols as f32;
+        let cell_height = panel_height / w.rows as f32;
+
+        let panel = Rect::new(panel_x, panel_y, panel_width, panel_height);
+        match image {
+            Some(size) if l == 0 && size.0 * size.1 == w.rows => {
+                draw_filters(w, size, max, theme, panel)
+            }
+            _ => {
+                for (i, row) in w.data.iter().enumerate() {
+                    for (j, val) in row.iter().enumerate() {
+                        draw_rectangle(
+                            panel_x + j as f32 * cell_width,
+                            panel_y + i as f32 * cell_height,
+                            cell_width,
+                            cell_height,
+                            weight_color(*val, max, theme),
+                        );
+                    }
+                }
+            }
+        }
+
+        draw_text(
+            format!("W{} {}x{} |max| {:.2}", l, w.rows, w.cols, max).as_str(),
+            panel_x,
+            panel_y - 5.,
+            20.,
+            theme.text,
+        );
+    }
+}
+
+// A column per layer with the distribution of its weights above its biases', redrawn
+// every frame so the drift away from the initialization shows while training
+fn draw_histograms(nn: &NN, theme: Theme, rect: Rect) {
+    let layers = nn.count - 1;
+    let slot_width = rect.w / layers as f32;
+    let panel_width = slot_width * 0.8;
+    // room for the labels
+    let weights_height = (rect.h - 60.) * 0.6;
+    let biases_height = (rect.h - 60.) * 0.4;
+
+    for l in 0..layers {
+        let panel_x = rect.x + l as f32 * slot_width + slot_width * 0.1;
+        let weights: Vec<f32> = nn.weights[l].data.iter().flatten().copied().collect();
+        let biases: Vec<f32> = nn.biases[l].data.iter().flatten().copied().collect();
+        draw_histogram(
+            &weights,
+            &format!("W{}", l),
+            theme,
+            Rect::new(panel_x, rect.y + 25., panel_width, weights_height),
+        );
+        draw_histogram(
+            &biases,
+            &format!("b{}", l),
+            theme,
+            Rect::new(
+                panel_x,
+                rect.y + 55. + weights_height,
+                panel_width,
+                biases_height,
+            ),
+        );
+    }
+}
+
+fn draw_histogram(values: &[f32], label: &str, theme: Theme, rect: Rect) {
+    draw_rectangle(rect.x, rect.y, rect.w, rect.h, theme.panel);
+    let max = values.iter().fold(0.0f32, |max, val| max.max(val.abs()));
+    let mean = values.iter().sum::<f32>() / values.len().max(1) as f32;
+    let std = (values.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / values.len().max(1) as f32)
+        .sqrt();
+    draw_text(
+        format!("{} mean {:.2} std {:.2} |max| {:.2}", label, mean, std, max).as_str(),
+        rect.x,
+        rect.y - 5.,
+        20.,
+        theme.text,
+    );
+
+    // all zero, e.g. freshly initialized biases, goes in the middle bar
+    let range = if max > 0. { max } else { 1. };
+    let mut counts = [0usize; HISTOGRAM_BINS];
+    for val in values {
+        let bin = ((val / range + 1.) / 2. * HISTOGRAM_BINS as f32) as usize;
+        counts[bin.min(HISTOGRAM_BINS - 1)] += 1;
+    }
+    let highest = counts.iter().copied().max().unwrap_or(0).max(1);
+
+    let bar_width = rect.w / HISTOGRAM_BINS as f32;
+    for (i, count) in counts.iter().enumerate() {
+        let height = *count as f32 / highest as f32 * rect.h;
+        let center = (i as f32 + 0.5) / HISTOGRAM_BINS as f32 * 2. - 1.;
+        draw_rectangle(
+            rect.x + i as f32 * bar_width + 1.,
+            rect.y + rect.h - height,
+            bar_width - 2.,
+            height,
+            if center < 0. {
+                theme.negative
+            } else {
+                theme.positive
+            },
+        );
+    }
+    // zero
+    let zero = rect.x + rect.w / 2.;
+    draw_line(zero, rect.y, zero, rect.y + rect.h, 1., theme.text);
+}
+
+fn weight_color(val: f32, max: f32, theme: Theme) -> Color {
+    let t = if max > 0. { val.abs() / max } else { 0. };
+    let sign = if val >= 0. {
+        theme.positive
+    } else {
+        theme.negative
+    };
+    color_lerp(theme.panel, sign, t)
+}
+
+// The incoming weights of every neuron laid out like the input image,
+// tiled in a square-ish grid
+fn draw_filters(
+    w: &Mat,
+    (image_width, image_height): (usize, usize),
+    max: f32,
+    theme: Theme,
+    rect: Rect,
+) {
+    let columns = (w.cols as f32).sqrt().ceil() as usize;
+    let rows = w.cols.div_ceil(columns);
+    let tile = (rect.w / columns as f32).min(rect.h / rows as f32);
+    // a gap between the tiles
+    let cell = tile * 0.9 / image_width.max(image_height) as f32;
+
+    for j in 0..w.cols {
+        let x = rect.x + (j % columns) as f32 * tile;
+        let y = rect.y + (j / columns) as f32 * tile;
+        for row in 0..image_height {
+            for col in 0..image_width {
+                draw_rectangle(
+                    x + col as f32 * cell,
+                    y + row as f32 * cell,
+                    cell,
+                    cell,
+                    weight_color(w.data[row * image_width + col][j], max, theme),
+                );
+            }
+        }
+    }
+}
+
+// The current training sample as a (2x downsampled) grayscale image with the
+// network's guess, a new sample every second
+fn draw_sample(info: &Renderinfo, mut nn: NN, image_width: usize, image_height: usize, rect: Rect) {
+    let theme = info.theme;
+    let i = get_time() as usize % info.t_input.rows;
+    let pixels = &info.t_input.data[i];
+
+    // room for the label below
+    let cell = (rect.w / (image_width / 2) as f32).min((rect.h - 30.) / (image_height / 2) as f32);
+    let (x, y) = (rect.x, rect.y);
+    for row in 0..image_height / 2 {
+        for col in 0..image_width / 2 {
+            let at = |r: usize, c: usize| pixels[r * image_width + c];
+            let value = (at(2 * row, 2 * col)
+                + at(2 * row, 2 * col + 1)
+                + at(2 * row + 1, 2 * col)
+                + at(2 * row + 1, 2 * col + 1))
+                / 4.;
+            draw_rectangle(
+                x + col as f32 * cell,
+                y + row as f32 * cell,
+                cell,
+                cell,
+                Color::new(value, value, value, 1.),
+            );
+        }
+    }
+
+    Mat::copy(&mut nn.activations[0], &Mat::row(&info.t_input, i));
+    NN::forward(&mut nn);
+    draw_text(
+        format!(
+            "label {} | guess {}",
+            argmax(&info.t_output.data[i]),
+            argmax(&nn.activations[nn.count - 1].data[0])
+        )
+        .as_str(),
+        x,
+        y + (image_height / 2) as f32 * cell + 20.,
+        20.,
+        theme.text,
+    );
+}
+
+// The picture next to the network's reconstruction of it, pixel by pixel
+fn draw_picture(info: &Renderinfo, mut nn: NN, rect: Rect) {
+    let Some((width, height, pixels)) = &info.picture else {
+        return;
+    };
+    let theme = info.theme;
+    draw_rectangle(rect.x, rect.y, rect.w, rect.h, theme.panel);
+
+    // two pictures side by side with a gap, room for the labels above
+    let cell = ((rect.w - 10.) / (2 * width) as f32).min((rect.h - 25.) / *height as f32);
+    let left = rect.x;
+    let right = rect.x + *width as f32 * cell + 10.;
+    let top = rect.y + 20.;
+    for row in 0..*height {
+        for col in 0..*width {
+            let value = pixels[row * width + col];
+            let y = top + row as f32 * cell;
+            draw_rectangle(
+                left + col as f32 * cell,
+                y,
+                cell,
+                cell,
+                Color::new(value, value, value, 1.),
+            );
+
+            // the same pixel center Dataset::from_picture trained on
+            nn.activations[0].data[0][0] = (col as f32 + 0.5) / *width as f32;
+            nn.activations[0].data[0][1] = (row as f32 + 0.5) / *height as f32;
+            NN::forward(&mut nn);
+            let value = nn.activations[nn.count - 1].data[0][0].clamp(0., 1.);
+            draw_rectangle(
+                right + col as f32 * cell,
+                y,
+                cell,
+                cell,
+                Color::new(value, value, value, 1.),
+            );
+        }
+    }
+    draw_text("Picture", left + 5., rect.y + 15., 16., theme.text);
+    draw_text("Network", right + 5., rect.y + 15., 16., theme.text);
+}
+
+// The network's output over the input plane, with the
+// training points on top colored by their target
+fn draw_boundary(info: &Renderinfo, mut nn: NN, rect: Rect) {
+    let theme = info.theme;
+    let size = rect.w.min(rect.h);
+    let panel_x = rect.x;
+    let panel_y = rect.y + rect.h - size;
+
+    // Input range of the samples with a margin, so points don't sit on the border
+    let mut min = [f32::INFINITY; 2];
+    let mut max = [f32::NEG_INFINITY; 2];
+    for row in &info.t_input.data {
+        for j in 0..2 {
+            min[j] = min[j].min(row[j]);
+            max[j] = max[j].max(row[j]);
+        }
+    }
+    for j in 0..2 {
+        let margin = ((max[j] - min[j]) * 0.1).max(0.1);
+        min[j] -= margin;
+        max[j] += margin;
+    }
+
+    let cell = size / BOUNDARY_RESOLUTION as f32;
+    for row in 0..BOUNDARY_RESOLUTION {
+        for col in 0..BOUNDARY_RESOLUTION {
+            let tx = (col as f32 + 0.5) / BOUNDARY_RESOLUTION as f32;
+            // y grows upwards like in a plot
+            let ty = 1. - (row as f32 + 0.5) / BOUNDARY_RESOLUTION as f32;
+            nn.activations[0].data[0][0] = min[0] + tx * (max[0] - min[0]);
+            nn.activations[0].data[0][1] = min[1] + ty * (max[1] - min[1]);
+            NN::forward(&mut nn);
+
+            let value = nn.activations[nn.count - 1].data[0][0];
+            let mut color = color_lerp(theme.negative, theme.positive, value.clamp(0., 1.));
+            color.a = 0.5;
+            draw_rectangle(
+                panel_x + col as f32 * cell,
+                panel_y + row as f32 * cell,
+                cell,
+                cell,
+                color,
+            );
+        }
+    }
+
+    for (input, output) in info.t_input.data.iter().zip(&info.t_output.data) {
+        let px = panel_x + (input[0] - min[0]) / (max[0] - min[0]) * size;
+        let py = panel_y + size - (input[1] - min[1]) / (max[1] - min[1]) * size;
+        let color = color_lerp(theme.negative, theme.positive, output[0].clamp(0., 1.));
+        draw_circle(px, py, 3., color);
+        draw_circle_lines(px, py, 3., 1., theme.text);
+    }
+}
+
+// Regression: the training (and validation) samples and the network's output over
+// their input range, the y range following the curve so a bad early fit stays visible
+fn draw_curve(info: &Renderinfo, mut nn: NN, rect: Rect) {
+    let theme = info.theme;
+    draw_rectangle(rect.x, rect.y, rect.w, rect.h, theme.panel);
+
+    let samples = || {
+        let train = info.t_input.data.iter().zip(&info.t_output.data);
+        let validation = info.v_input.data.iter().zip(&info.v_output.data);
+        train
+            .map(|s| (s, false))
+            .chain(validation.map(|s| (s, true)))
+    };
+    let (mut min_x, mut max_x) = (f32::INFINITY, f32::NEG_INFINITY);
+    let (mut min_y, mut max_y) = (f32::INFINITY, f32::NEG_INFINITY);
+    for ((input, output), _) in samples() {
+        (min_x, max_x) = (min_x.min(input[0]), max_x.max(input[0]));
+        (min_y, max_y) = (min_y.min(output[0]), max_y.max(output[0]));
+    }
+    let margin = ((max_x - min_x) * 0.05).max(0.05);
+    (min_x, max_x) = (min_x - margin, max_x + margin);
+
+    let curve: Vec<(f32, f32)> = (0..=CURVE_RESOLUTION)
+        .map(|i| {
+            let x = min_x + i as f32 / CURVE_RESOLUTION as f32 * (max_x - min_x);
+            nn.activations[0].data[0][0] = x;
+            NN::forward(&mut nn);
+            (x, nn.activations[nn.count - 1].data[0][0])
+        })
+        .collect();
+    for &(_, y) in &curve {
+        (min_y, max_y) = (min_y.min(y), max_y.max(y));
+    }
+    let margin = ((max_y - min_y) * 0.1).max(0.05);
+    (min_y, max_y) = (min_y - margin, max_y + margin);
+
+    let to_screen = |x: f32, y: f32| {
+        (
+            rect.x + (x - min_x) / (max_x - min_x) * rect.w,
+            rect.y + rect.h - (y - min_y) / (max_y - min_y) * rect.h,
+        )
+    };
+    for ((input, output), validation) in samples() {
+        let (px, py) = to_screen(input[0], output[0]);
+        let color = if validation {
+            theme.validation
+        } else {
+            theme.text
+        };
+        draw_circle(px, py, 3., color);
+    }
+    for pair in curve.windows(2) {
+        let (x1, y1) = to_screen(pair[0].0, pair[0].1);
+        let (x2, y2) = to_screen(pair[1].0, pair[1].1);
+        draw_line(x1, y1, x2, y2, 2., theme.cost);
+    }
+    draw_text("Fit", rect.x + 5., rect.y + 15., 16., theme.text);
+}
+
+// Target (rows) against predicted class (columns) for the training set and, right
+// of it, the validation set. Diagonal cells are green, mistakes red, brighter the
+// larger their share of the row.
+fn draw_confusion(info: &Renderinfo, nn: &NN, rect: Rect) {
+    let theme = info.theme;
+    // below the name
+    let size = rect.h - 25.;
+    let mut panel_x = rect.x;
+    let panel_y = rect.y + 25.;
+
+    let sets = [
+        ("train", &info.t_input, &info.t_output),
+        ("validation", &info.v_input, &info.v_output),
+    ];
+    for (name, input, output) in sets {
+        if input.rows == 0 {
+            continue;
+        }
+        let counts = NN::confusion(nn, input, output);
+        let cell = size / counts.len() as f32;
+        for (target, row) in counts.iter().enumerate() {
+            let total = row.iter().sum::<usize>().max(1) as f32;
+            for (predicted, &count) in row.iter().enumerate() {
+                let color = if target == predicted {
+                    theme.good
+                } else {
+                    theme.bad
+                };
+                let (x, y) = (
+                    panel_x + predicted as f32 * cell,
+                    panel_y + target as f32 * cell,
+                );
+                draw_rectangle(
+                    x,
+                    y,
+                    cell - 1.,
+                    cell - 1.,
+                    color_lerp(theme.panel, color, count as f32 / total),
+                );
+                if cell >= 16. {
+                    draw_text(count.to_string().as_str(), x + 2., y + 14., 14., theme.text);
+                }
+            }
+        }
+        draw_text(name, panel_x, panel_y - 5., 20., theme.text);
+        panel_x += size + 10.;
+    }
+}
+
+// `compared` is the current cost of the compared run
+fn draw_graph(rect: Rect, info: &mut Renderinfo, compared: Option<f32>) {
+    let theme = info.theme;
+
+    // The readouts above the plot
+    let graph_width = rect.w;
+    let graph_height = rect.h - 60.;
+    let graph_x = rect.x;
+    let graph_y = rect.y + 60.;
+
+    draw_rectangle(graph_x, graph_y, graph_width, graph_height, theme.panel);
     draw_text(
         format!("Cost: {}", info.cost).as_str(),
         graph_x,
         graph_y - 5.,
         20.,
-        Color {
-            r: 1.,
-            g: 1.,
-            b: 1.,
-            a: 1.,
-        },
+        theme.text,
+    );
+
+    if let Some(&(_, cost)) = info.cost_history.validation.last() {
+        draw_text(
+            format!("Validation: {}", cost).as_str(),
+            graph_x,
+            graph_y - 25.,
+            20.,
+            theme.validation,
+        );
+    }
+
+    if let Some(cost) = compared {
+        draw_text(
+            format!("B: {}", cost).as_str(),
+            graph_x,
+            graph_y - 45.,
+            20.,
+            theme.compare,
+        );
+    }
+
+    let rect = (graph_x, graph_y, graph_width, graph_height);
+    let max_epoch = max_epoch(info);
+    let range = update_view(&mut info.graph_view, max_epoch, rect);
+    if info.show_norms {
+        draw_norms(info, rect, range);
+    } else {
+        draw_plot(info, rect, range, true);
+    }
+}
+
+// Every layer's parameter norm over the epochs `start..end`, from the first layer in
+// the negative color to the last in the positive one. A layer whose norm keeps
+// growing is exploding, one that stays flat isn't learning.
+fn draw_norms(
+    info: &Renderinfo,
+    (graph_x, graph_y, graph_width, graph_height): (f32, f32, f32, f32),
+    (start, end): (f32, f32),
+) {
+    let theme = info.theme;
+    let norms = &info.cost_history.norms;
+    let visible = |epoch: i32| (start..=end).contains(&(epoch as f32));
+    let high = norms
+        .iter()
+        .flatten()
+        .filter(|(epoch, _)| visible(*epoch))
+        .fold(0.0f32, |high, &(_, norm)| high.max(norm));
+    if high <= 0. {
+        return;
+    }
+    let to_x = |epoch: f32| graph_x + (epoch - start) / (end - start) * graph_width;
+    let to_y = |norm: f32| graph_y + graph_height - norm / (high * 1.05) * graph_height;
+
+    for k in 0..=GRAPH_TICKS {
+        let t = k as f32 / GRAPH_TICKS as f32;
+        let tick_y = graph_y + graph_height - t * graph_height;
+        draw_line(graph_x, tick_y, graph_x + 5., tick_y, 1., theme.text);
+        draw_text(
+            format!("{:.2}", t * high * 1.05).as_str(),
+            graph_x + 7.,
+            tick_y + 4.,
+            14.,
+            theme.text,
+        );
+    }
+
+    let mut legend_x = graph_x + 60.;
+    for (l, series) in norms.iter().enumerate() {
+        let t = l as f32 / (norms.len() - 1).max(1) as f32;
+        let color = color_lerp(theme.negative, theme.positive, t);
+        for pair in series.windows(2) {
+            let ((e1, n1), (e2, n2)) = (pair[0], pair[1]);
+            if visible(e1) && visible(e2) {
+                draw_line(
+                    to_x(e1 as f32),
+                    to_y(n1),
+                    to_x(e2 as f32),
+                    to_y(n2),
+                    1.,
+                    color,
+                );
+            }
+        }
+        if let Some(&(_, norm)) = series.last() {
+            let text = format!("W{} {:.2}", l, norm);
+            draw_text(&text, legend_x, graph_y + 15., 16., color);
+            legend_x += measure_text(&text, None, 16, 1.).width + 10.;
+        }
+    }
+}
+
+fn max_epoch(info: &Renderinfo) -> f32 {
+    let mut max_epoch = 1;
+    for &(epoch, _) in info
+        .cost_history
+        .train
+        .iter()
+        .chain(&info.cost_history.validation)
+        .chain(&info.cost_history.compare)
+    {
+        max_epoch = max_epoch.max(epoch);
+    }
+    max_epoch as f32
+}
+
+// The cost (and accuracy) curves over the epochs `start..end` inside `rect`, with
+// a readout of the point under the cursor when `hover` is set
+// The learning rate range test's cost against a log scale rate, the suggested rate
+// marked where the cost falls fastest
+fn draw_lr_curve(curve: &[(f32, f32)], theme: Theme, rect: Rect) {
+    let graph = Rect::new(rect.x, rect.y + 60., rect.w, rect.h - 60.);
+    draw_rectangle(graph.x, graph.y, graph.w, graph.h, theme.panel);
+    let suggested = LrFinder::suggest(curve);
+    draw_text(
+        match suggested {
+            Some(rate) => format!("Suggested rate: {:.4} (F closes)", rate),
+            None => "The cost didn't fall, try a higher rate (F closes)".to_owned(),
+        }
+        .as_str(),
+        graph.x,
+        graph.y - 5.,
+        20.,
+        theme.text,
+    );
+    if curve.len() < 2 {
+        return;
+    }
+
+    let (first, last) = (curve[0].0.log10(), curve[curve.len() - 1].0.log10());
+    let (low, high) = curve
+        .iter()
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(low, high), p| {
+            (low.min(p.1), high.max(p.1))
+        });
+    let pad = ((high - low) * 0.05).max(1e-6);
+    let (low, high) = (low - pad, high + pad);
+    let to_x = |rate: f32| graph.x + (rate.log10() - first) / (last - first) * graph.w;
+    let to_y = |cost: f32| graph.y + graph.h - (cost - low) / (high - low) * graph.h;
+
+    for k in 0..=GRAPH_TICKS {
+        let t = k as f32 / GRAPH_TICKS as f32;
+        let tick_x = graph.x + t * graph.w;
+        let bottom = graph.y + graph.h;
+        draw_line(tick_x, bottom - 5., tick_x, bottom, 1., theme.text);
+        if k > 0 {
+            draw_text(
+                format!("{:.0e}", 10f32.powf(first + t * (last - first))).as_str(),
+                tick_x - 40.,
+                bottom - 7.,
+                14.,
+                theme.text,
+            );
+        }
+    }
+    for pair in curve.windows(2) {
+        draw_line(
+            to_x(pair[0].0),
+            to_y(pair[0].1),
+            to_x(pair[1].0),
+            to_y(pair[1].1),
+            1.,
+            theme.cost,
+        );
+    }
+    if let Some(rate) = suggested {
+        let x = to_x(rate);
+        draw_line(x, graph.y, x, graph.y + graph.h, 1., theme.good);
+    }
+}
+
+fn draw_plot(
+    info: &Renderinfo,
+    (graph_x, graph_y, graph_width, graph_height): (f32, f32, f32, f32),
+    (start, end): (f32, f32),
+    hover: bool,
+) {
+    let theme = info.theme;
+    let to_x = |epoch: f32| graph_x + (epoch - start) / (end - start) * graph_width;
+    let visible = |epoch: i32| (start..=end).contains(&(epoch as f32));
+
+    let series = [
+        &info.cost_history.train,
+        &info.cost_history.validation,
+        &info.cost_history.compare,
+    ];
+    // Plotted value of a cost, log10 keeps the small costs late in training visible
+    let scale = |cost: f32| {
+        if info.log_scale {
+            cost.max(f32::MIN_POSITIVE).log10()
+        } else {
+            cost
+        }
+    };
+
+    let past_runs: &[PastRun] = if info.show_runs { &info.past_runs } else { &[] };
+    let run_color = |run: &PastRun| RUN_COLORS[run.id % RUN_COLORS.len()];
+
+    // Fit the cost axis to the visible points
+    let mut low = f32::INFINITY;
+    let mut high = f32::NEG_INFINITY;
+    let past = past_runs.iter().map(|run| &run.train);
+    for &(epoch, cost) in series.into_iter().chain(past).flatten() {
+        if visible(epoch) {
+            low = low.min(scale(cost));
+            high = high.max(scale(cost));
+        }
+    }
+    // the middle half of an evolved population, the worst would squash the rest
+    let population = &info.cost_history.population;
+    for &(epoch, spread) in population {
+        if visible(epoch) && spread[3].is_finite() {
+            low = low.min(scale(spread[1]));
+            high = high.max(scale(spread[3]));
+        }
+    }
+    if !low.is_finite() {
+        return;
+    }
+    let pad = ((high - low) * 0.05).max(1e-6);
+    low -= pad;
+    high += pad;
+    let to_y =
+        |cost: f32| graph_y + graph_height - (scale(cost) - low) / (high - low) * graph_height;
+
+    let label = |value: f32| {
+        if info.log_scale {
+            format!("{:.1e}", 10f32.powf(value))
+        } else if value.abs() >= 0.01 {
+            format!("{:.3}", value)
+        } else {
+            format!("{:.1e}", value)
+        }
+    };
+
+    for k in 0..=GRAPH_TICKS {
+        let t = k as f32 / GRAPH_TICKS as f32;
+        let tick_y = graph_y + graph_height - t * graph_height;
+        draw_line(graph_x, tick_y, graph_x + 5., tick_y, 1., theme.text);
+        draw_text(
+            label(low + t * (high - low)).as_str(),
+            graph_x + 7.,
+            tick_y + 4.,
+            14.,
+            theme.text,
+        );
+
+        let tick_x = graph_x + t * graph_width;
+        let epoch = (start + t * (end - start)) as i32;
+        let bottom = graph_y + graph_height;
+        draw_line(tick_x, bottom - 5., tick_x, bottom, 1., theme.text);
+        if k > 0 {
+            draw_text(
+                epoch.to_string().as_str(),
+                tick_x - 40.,
+                bottom - 7.,
+                14.,
+                theme.text,
+            );
+        }
+    }
+
+    // Quartiles of the population's costs as a bar per generation and their median as
+    // a line, under the best network's cost
+    if !population.is_empty() {
+        let bar = (graph_width / (end - start)).max(1.);
+        let band = Color {
+            a: 0.25,
+            ..theme.cost
+        };
+        for &(epoch, spread) in population {
+            if visible(epoch) && spread[3].is_finite() {
+                let x = to_x(epoch as f32);
+                draw_line(x, to_y(spread[1]), x, to_y(spread[3]), bar, band);
+            }
+        }
+        for pair in population.windows(2) {
+            let ((e1, s1), (e2, s2)) = (pair[0], pair[1]);
+            if visible(e1) && visible(e2) && s1[2].is_finite() && s2[2].is_finite() {
+                draw_line(
+                    to_x(e1 as f32),
+                    to_y(s1[2]),
+                    to_x(e2 as f32),
+                    to_y(s2[2]),
+                    1.,
+                    Color { a: 0.6, ..band },
+                );
+            }
+        }
+        draw_text(
+            "population: quartiles, median and best",
+            graph_x + 60.,
+            graph_y + graph_height - 20.,
+            14.,
+            theme.text,
+        );
+    }
+
+    let past = past_runs.iter().map(|run| (&run.train, run_color(run)));
+    let current = series
+        .into_iter()
+        .zip([theme.cost, theme.validation, theme.compare]);
+    // the current run's curves on top
+    for (points, color) in past.chain(current) {
+        for pair in points.windows(2) {
+            let ((e1, c1), (e2, c2)) = (pair[0], pair[1]);
+            if visible(e1) && visible(e2) {
+                draw_line(
+                    to_x(e1 as f32),
+                    to_y(c1),
+                    to_x(e2 as f32),
+                    to_y(c2),
+                    1.,
+                    color,
+                );
+            }
+        }
+    }
+
+    // Accuracy on its own fixed 0..1 axis
+    for pair in info.cost_history.accuracy.windows(2) {
+        let ((e1, a1), (e2, a2)) = (pair[0], pair[1]);
+        if visible(e1) && visible(e2) {
+            let to_y = |a: f32| graph_y + graph_height - a * graph_height;
+            draw_line(
+                to_x(e1 as f32),
+                to_y(a1),
+                to_x(e2 as f32),
+                to_y(a2),
+                1.,
+                theme.accuracy,
+            );
+        }
+    }
+
+    // The learning rate in the bottom quarter, scaled to its own range
+    let rates = &info.cost_history.rate;
+    let (low, high) = rates
+        .iter()
+        .fold((f32::INFINITY, 0.0f32), |(low, high), &(_, r)| {
+            (low.min(r), high.max(r))
+        });
+    if high > low {
+        let band = graph_height * 0.25;
+        let to_y = |r: f32| graph_y + graph_height - (r - low) / (high - low) * band;
+        for pair in rates.windows(2) {
+            let ((e1, r1), (e2, r2)) = (pair[0], pair[1]);
+            if visible(e1) && visible(e2) {
+                draw_line(
+                    to_x(e1 as f32),
+                    to_y(r1),
+                    to_x(e2 as f32),
+                    to_y(r2),
+                    1.,
+                    theme.text,
+                );
+            }
+        }
+        draw_text(
+            format!("lr {:.4}..{:.4}", low, high).as_str(),
+            graph_x + graph_width - 140.,
+            graph_y + graph_height - band - 5.,
+            14.,
+            theme.text,
+        );
+    }
+
+    // Legend of the runs in the top left, this one last
+    if !past_runs.is_empty() {
+        let mut y = graph_y + 15.;
+        for run in past_runs {
+            let cost = run.train.last().map_or(String::new(), |&(_, cost)| {
+                format!(": {}", label(scale(cost)))
+            });
+            draw_text(
+                format!("#{} {}{}", run.id, run.label, cost).as_str(),
+                graph_x + 60.,
+                y,
+                14.,
+                run_color(run),
+            );
+            y += 14.;
+        }
+        draw_text(
+            format!("#{} (this run)", info.run_id).as_str(),
+            graph_x + 60.,
+            y,
+            14.,
+            theme.cost,
+        );
+    }
+
+    // Where X and Z pruned, so the cost after them shows how much the network lost
+    for &(epoch, fraction) in &info.prunes {
+        if !visible(epoch) {
+            continue;
+        }
+        let x = to_x(epoch as f32);
+        draw_dashed_line(x, graph_y, x, graph_y + graph_height, 1., theme.inactive);
+        draw_text(
+            format!("{:.0}%", fraction * 100.).as_str(),
+            x + 3.,
+            graph_y + graph_height - 5.,
+            14.,
+            theme.text,
+        );
+    }
+
+    // Where Tab switched, the cost's noise changes with it
+    for &(epoch, descent) in &info.descents {
+        if !visible(epoch) {
+            continue;
+        }
+        let x = to_x(epoch as f32);
+        draw_dashed_line(x, graph_y, x, graph_y + graph_height, 1., theme.inactive);
+        draw_text(
+            match descent {
+                Descent::Full => "full".to_owned(),
+                Descent::MiniBatch(size) => format!("mini {}", size),
+                Descent::Online => "online".to_owned(),
+            }
+            .as_str(),
+            x + 3.,
+            graph_y + 12.,
+            14.,
+            theme.text,
+        );
+    }
+
+    // Readout of the training point closest to the cursor
+    let (mx, my) = mouse_position();
+    let train = &info.cost_history.train;
+    if hover
+        && in_rect((mx, my), (graph_x, graph_y, graph_width, graph_height))
+        && !train.is_empty()
+    {
+        let at = start + (mx - graph_x) / graph_width * (end - start);
+        let i = train.partition_point(|&(epoch, _)| (epoch as f32) < at);
+        let (epoch, cost) = if i == 0 {
+            train[0]
+        } else if i == train.len() || at - train[i - 1].0 as f32 <= train[i].0 as f32 - at {
+            train[i - 1]
+        } else {
+            train[i]
+        };
+        if visible(epoch) {
+            let (px, py) = (to_x(epoch as f32), to_y(cost));
+            draw_line(px, graph_y, px, graph_y + graph_height, 1., theme.inactive);
+            draw_circle(px, py, 3., theme.cost);
+            draw_text(
+                format!("({}, {})", epoch, label(scale(cost))).as_str(),
+                graph_x + graph_width - 150.,
+                graph_y + 15.,
+                16.,
+                theme.text,
+            );
+        }
+    }
+}
+
+// The whole cost history as a standalone PNG chart
+pub fn export_plot(info: &Renderinfo, path: &str) {
+    let theme = info.theme;
+    let (width, height) = (PLOT_WIDTH as f32, PLOT_HEIGHT as f32);
+    let target = render_target(PLOT_WIDTH, PLOT_HEIGHT);
+    set_camera(&Camera2D {
+        render_target: Some(target),
+        ..Camera2D::from_display_rect(Rect::new(0., 0., width, height))
+    });
+
+    clear_background(theme.background);
+    draw_text(
+        format!(
+            "{} | epoch {} | cost {}",
+            info.dataset, info.epoch, info.cost
+        )
+        .as_str(),
+        10.,
+        25.,
+        24.,
+        theme.text,
+    );
+    draw_plot(
+        info,
+        (0., 40., width, height - 40.),
+        (0., max_epoch(info)),
+        false,
     );
 
-    let mut max_cost = 0.;
-    // Check for max cost
-    for i in 0..info.cost_history.len() {
-        if info.cost_history[i] > max_cost {
-            max_cost = info.cost_history[i];
+    set_default_camera();
+    target.texture.get_texture_data().export_png(path);
+}
+
+// Dataset editing mode (D): left click places a point of the current class in the
+// unit square, right click switches the class and backspace removes the last point
+pub fn draw_editor(points: &mut Vec<(f32, f32, usize)>, class: &mut usize, theme: Theme) {
+    let (width, height) = (screen_width(), screen_height());
+    let size = (width.min(height) - 100.).max(50.);
+    let panel_x = (width - size) / 2.;
+    let panel_y = (height - size) / 2.;
+    let panel = (panel_x, panel_y, size, size);
+
+    let (mx, my) = mouse_position();
+    if is_mouse_button_pressed(MouseButton::Left) && in_rect((mx, my), panel) {
+        // y grows upwards like in the boundary panel
+        points.push(((mx - panel_x) / size, 1. - (my - panel_y) / size, *class));
+    }
+    if is_mouse_button_pressed(MouseButton::Right) {
+        *class = 1 - *class;
+    }
+    if is_key_pressed(KeyCode::Backspace) {
+        points.pop();
+    }
+
+    let class_color = |class: usize| {
+        if class == 0 {
+            theme.negative
+        } else {
+            theme.positive
         }
+    };
+    draw_rectangle(panel_x, panel_y, size, size, theme.panel);
+    for &(x, y, point_class) in points.iter() {
+        let px = panel_x + x * size;
+        let py = panel_y + (1. - y) * size;
+        draw_circle(px, py, 5., class_color(point_class));
+        draw_circle_lines(px, py, 5., 1., theme.text);
+    }
+
+    draw_text(
+        format!(
+            "Drawing a dataset: {} points, placing class {} (right click switches)",
+            points.len(),
+            class
+        )
+        .as_str(),
+        10.,
+        20.,
+        20.,
+        class_color(*class),
+    );
+    draw_text(
+        "left click - add point | backspace - undo | d - train on it",
+        10.,
+        40.,
+        20.,
+        theme.text,
+    );
+}
+
+fn in_rect((x, y): (f32, f32), (rx, ry, rw, rh): (f32, f32, f32, f32)) -> bool {
+    x >= rx && x <= rx + rw && y >= ry && y <= ry + rh
+}
+
+// Applies this frame's zoom/pan to the view and returns the visible epoch range
+fn update_view(view: &mut GraphView, max_epoch: f32, rect: (f32, f32, f32, f32)) -> (f32, f32) {
+    let (graph_x, _, graph_width, _) = rect;
+    let (mx, my) = mouse_position();
+    let inside = in_rect((mx, my), rect);
+    let (mut start, mut end) = view.range.unwrap_or((0., max_epoch));
+
+    if inside && is_mouse_button_pressed(MouseButton::Right) {
+        *view = GraphView::default();
+        return (0., max_epoch);
+    }
+
+    let (_, wheel) = mouse_wheel();
+    if inside && wheel != 0. {
+        // keep the epoch under the cursor in place
+        let at = start + (mx - graph_x) / graph_width * (end - start);
+        let factor = if wheel > 0. { 0.8 } else { 1.25 };
+        start = at - (at - start) * factor;
+        end = at + (end - at) * factor;
+        view.range = Some((start, end));
+    }
+
+    if !is_mouse_button_down(MouseButton::Left) {
+        view.drag = None;
+    } else if inside && is_mouse_button_pressed(MouseButton::Left) {
+        view.drag = Some(mx);
+    } else if let Some(last) = view.drag {
+        let shift = (last - mx) / graph_width * (end - start);
+        start += shift;
+        end += shift;
+        view.drag = Some(mx);
+        view.range = Some((start, end));
     }
-    let mut last_x = graph_x;
-    let mut last_y;
-    last_y = graph_y + graph_height - info.cost_history[0] / max_cost * graph_height;
 
-    for i in 0..info.cost_history.len() {
-        let x = graph_x + i as f32 * graph_width / info.cost_history.len() as f32;
-        let y = graph_y + graph_height - info.cost_history[i] / max_cost * graph_height;
-        draw_line(last_x, last_y, x, y, 1., LINE_COLOR);
-        last_x = x;
-        last_y = y;
+    if view.range.is_none() {
+        return (start, end);
     }
+    // Zoomed all the way out follows the history again
+    let span = (end - start).max(MIN_GRAPH_EPOCHS);
+    if span >= max_epoch {
+        view.range = None;
+        return (0., max_epoch);
+    }
+    let start = start.clamp(0., max_epoch - span);
+    view.range = Some((start, start + span));
+    (start, start + span)
 }
 
-fn draw_data(info: &Renderinfo, mut nn: NN) {
+fn draw_data(info: &Renderinfo, mut nn: NN, samples: Option<Rect>) {
+    let theme = info.theme;
     // Top right parameters
     draw_text(
         format!(
-            "Epoch: {}/{} | Learning Rate: {:.4}",
-            info.epoch, EPOCH_MAX, LEARNING_RATE
+            "Epoch: {}/{} ({:.0}/s{}) | Learning Rate: {:.4} | {}{}",
+            info.epoch,
+            info.epochs,
+            info.epoch as f32 / info.training_time.max(1e-6),
+            if info.params.epochs_per_second > 0. {
+                format!(" of {}", info.params.epochs_per_second)
+            } else {
+                String::new()
+            },
+            info.learning_rate,
+            match (info.population, info.method) {
+                (Some(size), _) => format!("Neuroevolution: {} networks", size),
+                (None, Method::Sgd) => "SGD".to_owned(),
+                (None, Method::Momentum { nesterov, .. }) => format!(
+                    "Momentum: {:.2}{}",
+                    info.params.momentum,
+                    if nesterov { " (Nesterov)" } else { "" }
+                ),
+                (None, Method::Adam { .. }) => "Adam".to_owned(),
+            },
+            match (
+                info.population,
+                Descent::of(info.params.batch_size, info.t_input.rows)
+            ) {
+                (Some(_), _) => String::new(),
+                (None, Descent::Full) => ", full batch".to_owned(),
+                (None, Descent::MiniBatch(size)) => format!(", batch {}", size),
+                (None, Descent::Online) => ", online".to_owned(),
+            }
         )
         .as_str(),
         0.,
         15.,
         20.,
-        TEXT_COLOR,
+        theme.text,
     );
 
     draw_text(
-        format!("Training time: {:.2}s", info.training_time).as_str(),
+        format!(
+            "Run #{} | Dataset: {} | Seed: {} | Training time: {:.2}s{}{}{}",
+            info.run_id,
+            info.dataset,
+            info.seed,
+            info.training_time,
+            if info.show_ema { " | EMA weights" } else { "" },
+            match NN::sparsity(&nn) {
+                0. => String::new(),
+                sparsity => format!(" | {:.0}% pruned", sparsity * 100.),
+            },
+            match info.quantized {
+                Some(Quantized::Accuracy { epoch, exact, int8 }) => format!(
+                    " | int8 at epoch {}: {:.1}% ({:+.1})",
+                    epoch,
+                    int8 * 100.,
+                    (int8 - exact) * 100.
+                ),
+                Some(Quantized::Error { epoch, max }) => {
+                    format!(" | int8 at epoch {}: outputs within {:.4}", epoch, max)
+                }
+                None => String::new(),
+            }
+        )
+        .as_str(),
         0.,
         30.,
         20.,
-        TEXT_COLOR,
+        theme.text,
     );
 
-    // Write the testing results at the bottom left
-    for i in 0..info.t_input.rows {
-        for j in 0..nn.activations[0].data[0].len() {
-            nn.activations[0].data[0][j] = info.t_input.data[i][j];
-        }
+    draw_text(
+        format!(
+            "Sample {}/{} (left/right): {:?}",
+            info.sample % info.t_input.rows + 1,
+            info.t_input.rows,
+            // MNIST pixels don't fit
+            &info.t_input.data[info.sample % info.t_input.rows]
+                [..info.t_input.cols.min(MAX_LISTED_INPUTS)]
+        )
+        .as_str(),
+        0.,
+        60.,
+        20.,
+        theme.text,
+    );
 
-        NN::forward(&mut nn);
+    if let Some(epoch) = info.converged {
         draw_text(
-            format!(
-                // Input | Output
-                "{:?} -> {:?}",
-                info.t_input.data[i],
-                nn.activations[nn.count - 1].data[0] // -1 because the last activation is the output
-            )
-            .as_str(),
+            format!("Converged at epoch {}", epoch).as_str(),
             0.,
-            screen_height() - 10. - i as f32 * 20.,
+            45.,
             20.,
-            TEXT_COLOR,
+            theme.good,
         );
     }
+
+    let Some(rect) = samples else {
+        return;
+    };
+
+    // Table of the first samples with the network's prediction and per-sample loss,
+    // misclassified samples in red and the one in the diagram underlined
+    let classification = is_classification(&info.t_output);
+    let values = |row: &[f32]| {
+        row.iter()
+            .map(|v| format!("{:.2}", v))
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    let fits = ((rect.h - 10.) / 20.) as usize;
+    let shown = info
+        .t_input
+        .rows
+        .min(MAX_SHOWN_SAMPLES)
+        .min(fits.saturating_sub(1));
+    let mut rows = vec![(
+        ["input", "target", "prediction", "loss"].map(str::to_owned),
+        theme.text,
+    )];
+    for i in 0..shown {
+        Mat::copy(&mut nn.activations[0], &Mat::row(&info.t_input, i));
+        NN::forward(&mut nn);
+        let prediction = &nn.activations[nn.count - 1].data[0];
+        let target = &info.t_output.data[i];
+        let loss: f32 = prediction
+            .iter()
+            .zip(target)
+            .map(|(&a, &y)| Loss::apply(nn.loss, a, y))
+            .sum();
+        let wrong = classification && class_of(prediction) != class_of(target);
+        rows.push((
+            [
+                values(&info.t_input.data[i]),
+                values(target),
+                values(prediction),
+                format!("{:.4}", loss),
+            ],
+            if wrong { theme.bad } else { theme.text },
+        ));
+    }
+
+    // every column as wide as its widest cell
+    let mut widths = [0.; 4];
+    for (cells, _) in &rows {
+        for (width, cell) in widths.iter_mut().zip(cells) {
+            *width = f32::max(*width, measure_text(cell, None, 18, 1.).width);
+        }
+    }
+    for (r, (cells, color)) in rows.iter().enumerate() {
+        let y = rect.y + 20. + r as f32 * 20.;
+        let mut x = rect.x + 5.;
+        for (cell, width) in cells.iter().zip(widths) {
+            draw_text(cell, x, y, 18., *color);
+            x += width + 12.;
+        }
+        if r == info.sample % info.t_input.rows + 1 {
+            draw_line(rect.x + 5., y + 3., x - 12., y + 3., 1., theme.positive);
+        }
+    }
 }
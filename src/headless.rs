@@ -0,0 +1,192 @@
+use super::{
+    batch_size, batches, build_nn, date, evolution, fit_data, is_classification, load_dataset,
+    network_size, progress, random_seed, seeded_rng, split, Augment, Checkpointer, Config,
+    Divergence, EarlyStopping, Error, Evolution, Init, Metrics, Optimizer, Pixels, Progress,
+    Schedule, TrainingState, AUGMENT_NOISE, CHECKPOINT_EVERY, IMAGE_SHIFT, INIT, MIN_DELTA, NN,
+    PATIENCE, TARGET_COST,
+};
+
+// Print the cost every this many epochs
+const PRINT_EVERY: i32 = 1000;
+
+// `--headless`: the same training as the window does with the configured dataset,
+// without opening one. Prints the progress and saves the result to the model path,
+// with the TrainingState that --resume continues from.
+pub fn train(config: &Config) {
+    let seed = config.seed.unwrap_or_else(random_seed);
+    let mut rng = seeded_rng(seed);
+    let (dataset_name, dataset, pixels) =
+        load_dataset(Config::source(config).as_ref(), &[], &mut rng, &mut None);
+    let (t_input, t_output) = (dataset.t_input, dataset.t_output);
+    let (hidden_layers, dataset_batch) = network_size(pixels.as_ref());
+
+    let mut nn_structure = vec![t_input.cols];
+    nn_structure.extend_from_slice(Config::hidden(config).as_deref().unwrap_or(hidden_layers));
+    nn_structure.push(t_output.cols);
+    Config::check_layers(config, &nn_structure);
+    let (mut nn, mut optimizer) = build_nn(&nn_structure, config);
+    println!(
+        "Training {:?} on {} with seed {}",
+        nn_structure, dataset_name, seed
+    );
+
+    let ((t_input, t_output), (v_input, v_output)) =
+        split(&t_input, &t_output, config.validation_split, &mut rng);
+    let batch_size = batch_size(config, dataset_batch, t_input.rows);
+
+    let augment = Augment {
+        noise: AUGMENT_NOISE,
+        image: match pixels {
+            Some(Pixels::Inputs(width, height)) => Some((width, height)),
+            _ => None,
+        },
+        shift: IMAGE_SHIFT,
+    };
+    let mut gradient = NN::new(&nn_structure);
+    let mut early_stopping = EarlyStopping::new(TARGET_COST, PATIENCE, MIN_DELTA);
+    let mut divergence = Divergence::new(config.divergence_factor);
+    let mut checkpointer = Checkpointer::new(&config.checkpoint_dir, CHECKPOINT_EVERY, true);
+    let mut progress = Progress::new(config, progress::stream(config));
+
+    fit_data(&mut nn, config, &t_input, &t_output);
+    Init::apply(INIT, &mut nn, &mut rng);
+    let mut history = vec![];
+    let mut validation = vec![];
+    let mut first = 0;
+    if config.resume {
+        match resume(config, &nn_structure) {
+            Ok((loaded, state)) => {
+                nn = loaded;
+                Optimizer::restore(&mut optimizer, state.optimizer);
+                (first, history, validation) =
+                    (state.epoch as i32, state.history, state.validation);
+                println!("Resuming {} at epoch {}", config.model_path, first);
+            }
+            Err(e) => {
+                println!("Can't resume: {}", e);
+                return;
+            }
+        }
+    }
+    println!("Initial cost: {}", NN::cost(&nn, &t_input, &t_output));
+    let mut evolution = evolution(config, &nn, &mut rng);
+
+    let start = date::now();
+    let mut next = first;
+    for i in first..=config.epochs {
+        next = i + 1;
+        optimizer.rate = Schedule::rate(&config.schedule, config.learning_rate, i as usize);
+        if let Some(evolution) = &mut evolution {
+            Evolution::generation(evolution, &t_input, &t_output, &mut rng);
+            nn.clone_from(Evolution::best(evolution));
+        } else {
+            for (x, y) in batches(&t_input, &t_output, batch_size, &mut rng) {
+                let x = if Augment::is_off(&augment) {
+                    x
+                } else {
+                    Augment::apply(&augment, &x, &mut rng)
+                };
+                NN::backprop(&mut nn, &mut gradient, &x, &y, &mut rng);
+                Optimizer::step(&mut optimizer, &mut nn, &gradient);
+            }
+        }
+
+        if !NN::is_finite(&nn) {
+            println!("Diverged at epoch {}, not saving", i);
+            return;
+        }
+        let cost = NN::cost(&nn, &t_input, &t_output);
+        history.push((i as usize, cost / t_input.rows as f32));
+        // nobody to pause for here, the warning is all
+        if let Some(factor) = Divergence::update(&mut divergence, cost) {
+            let layer = evolution
+                .is_none()
+                .then(|| Divergence::worst_layer(&NN::layer_norms(&gradient)))
+                .flatten()
+                .map_or(String::new(), |l| {
+                    format!(" (largest gradient into layer {})", l + 1)
+                });
+            println!(
+                "Cost jumped {:.1}x to {} at epoch {}{}, the learning rate may be too high",
+                factor, cost, i, layer
+            );
+        }
+
+        if let Err(e) = Checkpointer::update(&mut checkpointer, &nn, i as usize, cost) {
+            println!("Checkpoint at epoch {} failed: {}", i, e);
+        }
+
+        if Progress::due(&progress, i as usize) {
+            let metrics = Metrics {
+                epoch: i as usize,
+                cost,
+                accuracy: is_classification(&t_output)
+                    .then(|| NN::accuracy(&nn, &t_input, &t_output)),
+                learning_rate: optimizer.rate,
+                time: (date::now() - start) as f32,
+            };
+            Progress::log(&mut progress, &metrics, &NN::layer_norms(&gradient));
+        }
+
+        if i % PRINT_EVERY == 0 {
+            let mut line = format!("Epoch {}: cost {}", i, cost);
+            if v_input.rows > 0 {
+                let cost = NN::cost(&nn, &v_input, &v_output) / v_input.rows as f32;
+                validation.push((i as usize, cost));
+                line += &format!(
+                    ", per sample {} training and {} validation",
+                    history[history.len() - 1].1,
+                    cost
+                );
+            }
+            if is_classification(&t_output) {
+                line += &format!(
+                    ", accuracy {:.1}%",
+                    NN::accuracy(&nn, &t_input, &t_output) * 100.
+                );
+            }
+            if let Some(evolution) = &evolution {
+                line += &format!(", population median {}", Evolution::spread(evolution)[2]);
+            }
+            println!("{}", line);
+        }
+
+        if EarlyStopping::update(&mut early_stopping, cost) {
+            println!("Converged at epoch {} with cost {}", i, cost);
+            break;
+        }
+    }
+    println!("Training time: {}", (date::now() - start) as f32);
+
+    match NN::save(&nn, &config.model_path) {
+        Ok(()) => println!("Saved to {}", config.model_path),
+        Err(e) => println!("Saving to {} failed: {}", config.model_path, e),
+    }
+    let state = TrainingState {
+        epoch: next as usize,
+        optimizer: Optimizer::state(&optimizer).clone(),
+        history,
+        validation,
+    };
+    let state_path = TrainingState::path(&config.model_path);
+    if let Err(e) = TrainingState::save(&state, &state_path) {
+        println!("Saving to {} failed: {}", state_path.display(), e);
+    }
+}
+
+// The saved model, if it fits the architecture, and the state saved next to it
+fn resume(config: &Config, arch: &[usize]) -> Result<(NN, TrainingState), String> {
+    let nn = NN::load(&config.model_path)
+        .map_err(|e| format!("loading {} failed: {}", config.model_path, e))?;
+    if NN::arch(&nn) != arch {
+        let e = Error::Architecture {
+            expected: arch.to_vec(),
+            found: NN::arch(&nn),
+        };
+        return Err(format!("{} has {}", config.model_path, e));
+    }
+    let path = TrainingState::path(&config.model_path);
+    let state = TrainingState::load(&path)
+        .map_err(|e| format!("loading {} failed: {}", path.display(), e))?;
+    Ok((nn, state))
+}
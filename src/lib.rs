@@ -0,0 +1,7 @@
+// The training code without the window: the network, matrices, datasets, optimizers
+// and exporters the visualizer is built on. Depend on it with
+//
+//     nn-rust = { git = "...", default-features = false }
+//
+// to leave out macroquad and the binary.
+pub use framework::*;
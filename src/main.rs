@@ -1,159 +1,634 @@
 use std::{
-    sync::{
-        mpsc::{channel, Receiver, Sender},
-        Arc, Mutex,
-    },
-    thread,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    sync::mpsc::Sender,
 };
 
-use framework::{sigmoidf, Mat, NN};
-use macroquad::prelude::*;
+use framework::{
+    argmax, balanced_weights, batches, class_of, has_fast_activations, is_classification,
+    rand_float, random_seed, seeded_rng, set_backend, set_fast_activations, sigmoidf, split,
+    Activation, Augment, Backend, Checkpointer, Clip, Dataset, Descent, Divergence, EarlyStopping,
+    Ema, Error, EventWriter, Evolution, Init, Loss, LrFinder, Mat, Method, Metrics, MetricsLogger,
+    MetricsServer, NNBuilder, Normalization, Optimizer, OptimizerState, Preset, QuantizedNN,
+    Regularization, Scaling, Schedule, StdRng, TrainingState, NN,
+};
+use macroquad::{miniquad::date, prelude::*};
 
+mod canvas;
+mod controls;
+use canvas::Canvas;
+use controls::{draw_controls, Hyperparams, CONTROLS_HEIGHT};
+mod config;
 mod draw;
-use draw::{draw_frame, Renderinfo};
+mod toml;
+use config::{Compare, Config};
+mod bench;
+mod curve;
+mod headless;
+mod probe;
+mod progress;
+mod serve;
+mod sweep;
+use draw::{
+    draw_editor, draw_frame, export_plot, Collapsed, CostHistory, GraphView, Jump, PastRun,
+    Quantized, Renderinfo,
+};
+use probe::Probe;
+use progress::Progress;
+mod record;
+mod snapshot;
+use record::Recorder;
+use snapshot::Snapshot;
+mod theme;
+use theme::Theme;
+mod timeline;
+mod training;
+#[cfg(not(target_arch = "wasm32"))]
+mod tui;
+use timeline::Timeline;
+use training::{Run, Trainer};
 
+// Defaults of --epochs and --lr (see config.rs for the other arguments)
 const EPOCH_MAX: i32 = 100_000;
-const LEARNING_RATE: f32 = 1.;
+// The input and output layer sizes come from the dataset
+const HIDDEN_LAYERS: &[usize] = &[4, 4];
+// Layers added with = start with this many neurons when there's no other hidden one,
+// up/down change the count up to MAX_HIDDEN_NEURONS
+const HIDDEN_NEURONS: usize = 4;
+const MAX_HIDDEN_NEURONS: usize = 256;
+// Training data CSV (see datasets/xor.csv for the format), reloaded on every reset.
+// None uses the built-in XOR example.
+const DATASET_PATH: Option<&str> = None;
+// Keys 1-8 switch to the built-in presets, in Preset::ALL order
+const PRESET_KEYS: [KeyCode; 8] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+];
+// MNIST demo: point MNIST_IMAGES at train-images-idx3-ubyte to train a
+// 784-MNIST_HIDDEN-10 network on the first MNIST_SAMPLES digits
+const MNIST_IMAGES: Option<&str> = None;
+const MNIST_LABELS: &str = "datasets/train-labels-idx1-ubyte";
+const MNIST_SAMPLES: usize = 1000;
+const MNIST_HIDDEN: &[usize] = &[32];
+const MNIST_BATCH_SIZE: usize = 32;
+// Picture demo: `--dataset photo.png` (or dropping a .png/.tga) trains the network to
+// redraw the picture from (x, y), shrunk to at most PICTURE_SIZE pixels a side
+const PICTURE_SIZE: u32 = 32;
+const PICTURE_HIDDEN: &[usize] = &[32, 32, 32];
+const PICTURE_BATCH_SIZE: usize = 64;
+// Input scaling fitted on the training samples and saved with the network
+const SCALING: Scaling = Scaling::MinMax;
+// Hidden layers only, the output layer is always a sigmoid
+const ACTIVATION: Activation = Activation::Tanh;
+// Init::Auto picks Xavier for sigmoid/tanh and He for relu layers
+const INIT: Init = Init::Auto;
+// Weight decay lambdas, 0.0 turns them off
+const L1_LAMBDA: f32 = 0.0;
+const L2_LAMBDA: f32 = 0.0001;
+// Gaussian noise added to the training inputs every epoch (the input noise slider),
+// in the inputs' units, and how many pixels image samples are shifted around by at
+// most. Smooths the decision boundary, 0.0 and 0 turn them off (see Augment).
+const AUGMENT_NOISE: f32 = 0.0;
+const IMAGE_SHIFT: usize = 0;
+// --evolve trains this many networks by neuroevolution (see Evolution) instead of
+// one by backprop
+const POPULATION: usize = 50;
+// X prunes this much more of every layer's weights, the smallest ones, up to
+// MAX_PRUNED. Z lets them all train again.
+const PRUNE_STEP: f32 = 0.1;
+const MAX_PRUNED: f32 = 0.95;
+// Probability of dropping a hidden neuron during training
+const DROPOUT: f32 = 0.0;
+// Weigh every class's samples so the classes count the same in the cost, for datasets
+// where one class has most of the samples (--balance-classes)
+const BALANCE_CLASSES: bool = false;
+// Loss::CrossEntropy suits the classification datasets, Loss::Mae or Loss::Huber noisy
+// regression ones
+const LOSS: Loss = Loss::Mse;
+// Grey out the neurons dropped in the last training pass
+const SHOW_DROPOUT: bool = true;
+// Start with the weight heatmaps instead of the network diagram (W toggles)
+const SHOW_WEIGHTS: bool = false;
+// Start with histograms of every layer's weights and biases instead (Y toggles)
+const SHOW_HISTOGRAMS: bool = false;
+// Start with the layers' parameter norms in the graph instead of the costs (K toggles)
+const SHOW_NORMS: bool = false;
+// Decay of the moving average of the weights kept while training (see Ema), None
+// doesn't keep one. A shows and evaluates the average instead of the trained weights.
+const EMA_DECAY: Option<f32> = Some(0.99);
+const SHOW_EMA: bool = false;
+// Start with a log scale cost graph (O toggles)
+const LOG_SCALE: bool = false;
+// Cost curves of the last MAX_PAST_RUNS runs are kept across resets and drawn under
+// the current one (U toggles)
+const MAX_PAST_RUNS: usize = 6;
+const SHOW_RUNS: bool = true;
+// Start with the confusion matrices shown for classification datasets (M toggles)
+const SHOW_CONFUSION: bool = false;
+// Start with the probe panel, sliders for one made-up sample (or a canvas to draw a digit
+// on for MNIST), in the side panel (I toggles)
+const SHOW_PROBE: bool = false;
+const LEARNING_RATE: f32 = 0.5;
+// Rates the learning rate range test (F) goes through, one batch each
+const LR_FINDER: LrFinder = LrFinder {
+    min_rate: 1e-4,
+    max_rate: 10.,
+    steps: 100,
+};
+// How the learning rate changes over the epochs, starting from the --lr rate.
+// A cosine schedule is stretched over --epochs instead.
+// These and the paths and directories below are defaults, an experiment file
+// (--config) can replace them.
+const SCHEDULE: Schedule = Schedule::Cosine {
+    epochs: EPOCH_MAX as usize,
+    min_rate: 0.01,
+};
+const MOMENTUM: f32 = 0.9;
+const NESTEROV: bool = true;
+// Clip::Value(max) clamps every gradient component, Clip::None disables clipping
+const CLIP: Clip = Clip::Norm(5.0);
+// Stop early once the cost reaches TARGET_COST or hasn't improved by MIN_DELTA
+// for PATIENCE epochs (0 disables the patience check)
+const TARGET_COST: f32 = 0.0001;
+const PATIENCE: usize = 2000;
+const MIN_DELTA: f32 = 1e-6;
+// Pause training when the cost grows more than DIVERGENCE_FACTOR times in an epoch
+// (or isn't finite), before the weights turn NaN. 0.0 only pauses for NaN/inf.
+const DIVERGENCE_FACTOR: f32 = 10.0;
+// The timeline above the diagram keeps the network every TIMELINE_EVERY epochs, the
+// last TIMELINE_CAPACITY of them
+const TIMELINE_EVERY: i32 = 100;
+const TIMELINE_CAPACITY: usize = 500;
+// Fraction of the samples held out for validation, 0.0 trains on everything
+// (XOR has no samples to spare). The costs are drawn per sample so the two curves
+// compare (--validation-split).
+const VALIDATION_SPLIT: f32 = 0.0;
+// Compute the validation cost every this many epochs
+const VALIDATION_EVERY: i32 = 100;
+// Samples per gradient step, 0 means the whole training set
+const BATCH_SIZE: usize = 0;
+// Samples in a mini-batch (Tab, --descent mini) of the datasets above that train on
+// full batches, at most half of them
+const MINI_BATCH_SIZE: usize = 32;
+// Epochs trained per drawn frame, 0 trains as fast as possible. Slows training
+// down enough to watch it. This, the cap below and the three above can be changed
+// with the sliders.
+const EPOCHS_PER_FRAME: usize = 0;
+// Cap on the epochs trained per second, independent of the frame rate, 0.0 trains as
+// fast as possible. Unlike the epochs per frame it paces training the same on every
+// machine fast enough for it (--epochs-per-second).
+const EPOCHS_PER_SECOND: f32 = 0.0;
+// In the browser training runs in the frame loop, for at most this many seconds a frame
+const TRAIN_SLICE: f64 = 0.012;
+
+// Seed of --deterministic runs without --seed
+const DETERMINISTIC_SEED: u64 = 0;
 
 const WINDOW_WIDTH: i32 = 800;
 const WINDOW_HEIGHT: i32 = 600;
 
-const BACKGROUND_COLOR: Color = BLACK;
-const TEXT_COLOR: Color = WHITE;
-const LINE_COLOR: Color = RED;
+// S saves the network here, L loads it back (.json or binary, by extension)
+const MODEL_PATH: &str = "model.json";
+// E exports the network as an ONNX model
+const ONNX_PATH: &str = "model.onnx";
+// G generates standalone Rust source with the weights and a forward() function
+const RUST_PATH: &str = "model.rs";
+// J generates the same with int8 weights (see QuantizedNN) for microcontrollers
+const QUANTIZED_PATH: &str = "model_int8.rs";
+
+// C saves a screenshot and H the whole cost history as a chart, as timestamped PNGs here.
+// V starts/stops recording a GIF into the same directory.
+const SCREENSHOT_DIR: &str = "screenshots";
+// Seconds between recorded frames, and every RECORD_SCALE-th pixel is kept
+const RECORD_INTERVAL: f64 = 0.1;
+const RECORD_SCALE: usize = 2;
+// The recording stops by itself after this many frames
+const RECORD_MAX_FRAMES: usize = 600;
+
+// Snapshot the network into CHECKPOINT_DIR every CHECKPOINT_EVERY epochs (0 disables),
+// also keeping the lowest-cost one as best.bin
+const CHECKPOINT_DIR: &str = "checkpoints";
+const CHECKPOINT_EVERY: usize = 10_000;
+// Append a row to the --metrics file (and an event to --tensorboard) every this many
+// epochs
+const METRICS_EVERY: usize = 10;
+
+// Colors on top of the dark theme (see theme.rs for the format), T cycles through
+// it and the built-in dark and light themes
+const THEME_PATH: &str = "theme.json";
+
+// Datasets chosen while running, replacing the configured one
+enum Source {
+    // number keys
+    Preset(Preset),
+    // the points placed in the dataset editor
+    Drawn,
+    // a CSV dropped on the window
+    Csv(PathBuf),
+    // a grayscale picture to redraw, see PICTURE_SIZE
+    Picture(PathBuf),
+    // a CSV or picture dropped on the window, with its bytes (the browser has no
+    // files to read the path from)
+    Dropped(PathBuf, Vec<u8>),
+}
+
+// Datasets made of images, they get the bigger MNIST_ and PICTURE_ networks
+#[derive(Clone, Debug)]
+enum Pixels {
+    // every sample is a (width, height) image (MNIST)
+    Inputs(usize, usize),
+    // every sample is one pixel of the picture, (x, y) -> brightness
+    Picture(usize, usize, Vec<f32>),
+}
+
+// The hidden layers and batch size for a dataset of these pixels
+fn network_size(pixels: Option<&Pixels>) -> (&'static [usize], usize) {
+    match pixels {
+        Some(Pixels::Inputs(..)) => (MNIST_HIDDEN, MNIST_BATCH_SIZE),
+        Some(Pixels::Picture(..)) => (PICTURE_HIDDEN, PICTURE_BATCH_SIZE),
+        None => (HIDDEN_LAYERS, BATCH_SIZE),
+    }
+}
+
+// The batch size of --descent, `default` from network_size without it or for mini-batches
+// of a dataset that has one
+fn batch_size(config: &Config, default: usize, samples: usize) -> usize {
+    match config.descent {
+        None => default,
+        Some(Descent::MiniBatch(size)) => mini_batch(default, size, samples),
+        Some(descent) => Descent::batch_size(descent),
+    }
+}
+
+// Mini-batches `default` samples big if that's less than all of them, `size` otherwise
+// but at least two of them an epoch
+fn mini_batch(default: usize, size: usize, samples: usize) -> usize {
+    if default > 1 && default < samples {
+        default
+    } else {
+        size.min(samples / 2).max(2)
+    }
+}
 
-#[derive(PartialEq)]
+#[derive(Clone)]
 enum Signal {
     Pause,
     Resume,
     Stop,
+    // A slider was moved
+    Set(Hyperparams),
+    // Train this many epochs while paused, then pause again
+    Step(u32),
+    // A saved network was loaded, continue training it. From the epoch and optimizer
+    // state it was saved at, when they were saved too.
+    Load(NN, Option<Box<TrainingState>>),
+    // Send back the network, the epoch training would continue with and the optimizer
+    // state, to save them
+    Save(Sender<(NN, usize, OptimizerState)>),
+    // Continue from this network of the timeline after the epoch it was taken at
+    Rewind(i32, NN),
+    // Publish the moving average of the weights instead of the trained ones
+    ShowEma(bool),
+    // Prune this fraction of every layer's weights (see NN::prune) and train on with
+    // them at zero, 0.0 lets them train again
+    Prune(f32),
+}
+
+// Seeds for runs without --seed. The browser's crypto is out of reach without
+// wasm-bindgen, and a seed only has to differ from the last one.
+#[cfg(target_arch = "wasm32")]
+fn browser_random(dest: &mut [u8]) -> Result<(), getrandom::Error> {
+    static CALLS: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+    let calls = CALLS.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    let mut x = (date::now() * 1000.0) as u64 ^ calls.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    for byte in dest {
+        // splitmix64
+        x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = x;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        *byte = (z ^ (z >> 31)) as u8;
+    }
+    Ok(())
+}
+#[cfg(target_arch = "wasm32")]
+getrandom::register_custom_getrandom!(browser_random);
+
+fn main() {
+    let config = Config::parse();
+    use_backend(&config);
+    // No window, e.g. on a server or in CI
+    if let Some(seconds) = config.bench {
+        bench::run(&config, seconds);
+        return;
+    }
+    if let Some(addr) = &config.serve {
+        serve::run(&config, addr);
+        return;
+    }
+    if let Some(fractions) = &config.curve {
+        curve::run(&config, fractions);
+        return;
+    }
+    if let Some(sweep) = &config.sweep {
+        sweep::run(&config, sweep);
+        return;
+    }
+    if config.headless {
+        headless::train(&config);
+        return;
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    if config.tui {
+        tui::run(&config);
+        return;
+    }
+    macroquad::Window::from_config(window_conf(), run(config));
+}
+
+// --backend gpu for every network trained below, training stays on the CPU when
+// there's no adapter or the gpu feature is off
+fn use_backend(config: &Config) {
+    if config.backend == Backend::Cpu {
+        return;
+    }
+    match set_backend(config.backend) {
+        Ok(adapter) => println!("Backpropagating on {}", adapter),
+        Err(e) => println!("Can't use the GPU ({}), training on the CPU", e),
+    }
 }
 
-#[macroquad::main(window_conf)]
-async fn main() {
-    let nn_structure = &[2, 4, 4, 1];
-    let nn = Arc::new(Mutex::new(NN::new(nn_structure)));
-    let gradient = NN::new(nn_structure);
+async fn run(config: Config) {
+    let mut source = Config::source(&config);
+    // From --layers or edited with the architecture keys, replaces HIDDEN_LAYERS
+    // (or MNIST_HIDDEN)
+    let mut hidden = Config::hidden(&config);
+    // Placed with the mouse in the dataset editor (D), kept for editing them again
+    let mut drawn: Vec<(f32, f32, usize)> = vec![];
+    let mut drawn_class = 0;
+    let mut editing = false;
+    // Hidden layer the architecture keys change
+    let mut edit_layer = 0;
+    let mut show_weights = SHOW_WEIGHTS;
+    let mut show_histograms = SHOW_HISTOGRAMS;
+    let mut show_norms = SHOW_NORMS;
+    let mut show_ema = SHOW_EMA;
+    let mut log_scale = LOG_SCALE;
+    let mut show_confusion = SHOW_CONFUSION;
+    let mut show_probe = SHOW_PROBE;
+    let mut show_runs = SHOW_RUNS;
+    // Every reset starts a new run, numbered from 1
+    let mut past_runs: Vec<PastRun> = vec![];
+    let mut run_id = 0;
+    let mut collapsed = Collapsed::default();
+    let mut themes = vec![Theme::DARK, Theme::LIGHT];
+    let stream = progress::stream(&config);
+    match Theme::load(THEME_PATH, Theme::DARK) {
+        Ok(custom) => themes.insert(0, custom),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => println!("Loading {} failed: {}", THEME_PATH, e),
+    }
+    let mut theme = 0;
+    // Keeps recording across resets
+    let mut recorder: Option<Recorder> = None;
+    // --import replaces the first network's initialization, its hidden layers are used
+    // in place of the configured ones
+    let mut imported =
+        config
+            .import
+            .as_ref()
+            .and_then(|path| match NN::import_keras(path, ACTIVATION) {
+                Ok(keras) => {
+                    println!("Imported {:?} from {}", NN::arch(&keras), path);
+                    hidden = Some(NN::arch(&keras)[1..keras.count - 1].to_vec());
+                    Some(keras)
+                }
+                Err(e) => {
+                    println!("Importing {} failed: {}", path, e);
+                    None
+                }
+            });
+    // The dropped files stay reported until the next drop
+    let mut last_dropped: Vec<PathBuf> = vec![];
 
     'reset: loop {
-        // XOR Example
-        let t_input = Mat::new(&[&[0.0, 0.0], &[0.0, 1.0], &[1.0, 0.0], &[1.0, 1.0]]);
-        let t_output = Mat::new(&[&[0.0], &[1.0], &[1.0], &[0.0]]);
-
-        // Opposite example
-        // let t_input = Mat::new(&[
-        //     &[1.0],
-        //     &[0.9],
-        //     &[0.8],
-        //     &[0.7],
-        //     &[0.6],
-        //     &[0.5],
-        //     &[0.4],
-        //     &[0.3],
-        //     &[0.2],
-        //     &[0.1],
-        //     &[0.0],
-        // ]);
-
-        // let t_output = Mat::new(&[
-        //     &[0.0],
-        //     &[0.1],
-        //     &[0.2],
-        //     &[0.3],
-        //     &[0.4],
-        //     &[0.5],
-        //     &[0.6],
-        //     &[0.7],
-        //     &[0.8],
-        //     &[0.9],
-        //     &[1.0],
-        // ]);
-
-        let mut gradient = gradient.clone();
-
-        let (tx, rx): (Sender<Signal>, Receiver<Signal>) = channel();
+        run_id += 1;
+        // A fixed --seed repeats the run on every reset, otherwise each one differs
+        let seed = config.seed.unwrap_or_else(random_seed);
+        let mut rng = seeded_rng(seed);
+        let mut error = None;
+        let (dataset_name, dataset, pixels) =
+            load_dataset(source.as_ref(), &drawn, &mut rng, &mut error);
+        let probe = Probe::new(&dataset.t_input, dataset.input_names, dataset.output_names);
+        let (t_input, t_output) = (dataset.t_input, dataset.t_output);
+        let (hidden_layers, dataset_batch) = network_size(pixels.as_ref());
+
+        let mut nn_structure = vec![t_input.cols];
+        nn_structure.extend_from_slice(hidden.as_deref().unwrap_or(hidden_layers));
+        nn_structure.push(t_output.cols);
+        Config::check_layers(&config, &nn_structure);
+        // The render thread's copy, the training thread trains its own
+        let (mut nn, optimizer) = build_nn(&nn_structure, &config);
+
+        let ((t_input, t_output), (v_input, v_output)) =
+            split(&t_input, &t_output, config.validation_split, &mut rng);
+        // what Tab switches to after full batches
+        let mini = mini_batch(dataset_batch, MINI_BATCH_SIZE, t_input.rows);
+
+        let params = Hyperparams {
+            learning_rate: config.learning_rate,
+            momentum: match config.method {
+                Method::Momentum { momentum, .. } => momentum,
+                _ => MOMENTUM,
+            },
+            batch_size: batch_size(&config, dataset_batch, t_input.rows),
+            epochs_per_frame: EPOCHS_PER_FRAME,
+            epochs_per_second: config.epochs_per_second,
+            noise: AUGMENT_NOISE,
+        };
 
         let mut paused = false;
-        let time_elapsed = chrono::Utc::now().timestamp_millis();
-
-        let info: Arc<Mutex<Renderinfo>>;
-        {
-            // Calculate first cost for creating the struct
-            let mut nn = nn.lock().unwrap();
-            NN::randomize(&mut nn, -1.0, 1.0);
-            let cost = NN::cost(&nn, &t_input, &t_output);
-            println!("Initial cost: {}", cost);
-            info = Arc::new(Mutex::new(Renderinfo {
-                epoch: 0,
-                cost,
-                t_input: t_input.clone(),
-                t_output: t_output.clone(),
-                training_time: 0.0,
-                cost_history: vec![cost],
-                paused,
-            }));
-        }
 
-        clear_background(BACKGROUND_COLOR);
-        {
-            let mut info = info.lock().unwrap();
-            draw_frame(&nn.lock().unwrap(), &mut info);
+        fit_data(&mut nn, &config, &t_input, &t_output);
+        // The compared run continues from here, so it starts from the same weights
+        // when it has the same layers
+        let compare_rng = rng.clone();
+        Init::apply(INIT, &mut nn, &mut rng);
+        if let Some(keras) = imported.take() {
+            if NN::arch(&keras) == nn_structure {
+                // trained on raw inputs
+                nn.norm = keras.norm;
+                nn.weights = keras.weights;
+                nn.biases = keras.biases;
+                nn.act = keras.act;
+            } else {
+                let e = Error::Architecture {
+                    expected: NN::arch(&keras),
+                    found: nn_structure.clone(),
+                };
+                println!("The imported network doesn't fit the dataset: {}", e);
+            }
         }
-        next_frame().await;
+        // Calculate first cost for creating the struct
+        let cost = NN::cost(&nn, &t_input, &t_output);
+        println!("Initial cost: {}", cost);
+        let mut info = Renderinfo {
+            epoch: 0,
+            cost,
+            accuracy: None,
+            t_input: t_input.clone(),
+            t_output: t_output.clone(),
+            v_input: v_input.clone(),
+            v_output: v_output.clone(),
+            training_time: 0.0,
+            cost_history: CostHistory {
+                train: vec![(0, cost / t_input.rows as f32)],
+                validation: vec![],
+                accuracy: vec![],
+                compare: vec![],
+                rate: vec![(0, config.learning_rate)],
+                norms: NN::layer_norms(&nn)
+                    .into_iter()
+                    .map(|norm| vec![(0, norm)])
+                    .collect(),
+                population: vec![],
+            },
+            graph_view: GraphView::default(),
+            paused,
+            learning_rate: config.learning_rate,
+            epochs: config.epochs,
+            method: config.method,
+            diverged: false,
+            converged: None,
+            error,
+            image: match pixels {
+                Some(Pixels::Inputs(width, height)) => Some((width, height)),
+                _ => None,
+            },
+            canvas: match &pixels {
+                Some(Pixels::Inputs(width, height)) => Some(Canvas::new(*width, *height)),
+                _ => None,
+            },
+            picture: match pixels {
+                Some(Pixels::Picture(width, height, brightness)) => {
+                    Some((width, height, brightness))
+                }
+                _ => None,
+            },
+            dataset: dataset_name,
+            seed,
+            show_weights,
+            show_histograms,
+            show_norms,
+            show_ema: show_ema && EMA_DECAY.is_some(),
+            lr_curve: None,
+            quantized: None,
+            prunes: vec![],
+            descents: vec![],
+            jump: None,
+            population: config.evolve.then_some(POPULATION),
+            grad_norms: vec![],
+            show_confusion,
+            show_probe,
+            probe,
+            run_id,
+            past_runs: std::mem::take(&mut past_runs),
+            show_runs,
+            log_scale,
+            theme: themes[theme],
+            collapsed,
+            params,
+            slider_drag: None,
+            frame: 0,
+            edit_layer,
+            sample: 0,
+            timeline: Timeline::new(TIMELINE_EVERY, TIMELINE_CAPACITY),
+        };
 
         // TRAINING
-        let nn_clone = Arc::clone(&nn);
-        let info_clone = Arc::clone(&info);
-
-        let _training_thread = thread::spawn(move || {
-            'training: for i in 0..=EPOCH_MAX {
-                if let Ok(signal) = rx.try_recv() {
-                    match signal {
-                        Signal::Pause => {
-                            let mut info = info_clone.lock().unwrap();
-                            info.paused = true;
-                            drop(info);
-
-                            while let Ok(signal) = rx.recv() {
-                                if signal == Signal::Resume {
-                                    let mut info = info_clone.lock().unwrap();
-                                    info.paused = false;
-                                    drop(info);
-                                    break;
-                                } else if signal == Signal::Stop {
-                                    break 'training;
-                                }
-                            }
-                        }
-                        Signal::Stop => {
-                            break 'training;
-                        }
-                        _ => {}
-                    }
-                }
+        let evolution = evolution(&config, &nn, &mut rng);
+        let trainer = Trainer {
+            name: String::new(),
+            nn: nn.clone(),
+            optimizer,
+            early_stopping: EarlyStopping::new(TARGET_COST, PATIENCE, MIN_DELTA),
+            divergence: Divergence::new(config.divergence_factor),
+            checkpointer: Checkpointer::new(&config.checkpoint_dir, CHECKPOINT_EVERY, true),
+            progress: Progress::new(&config, stream.clone()),
+            params,
+            rng,
+            t_input,
+            t_output,
+            v_input,
+            v_output,
+            epochs: config.epochs,
+            schedule: config.schedule,
+            ema: EMA_DECAY.map(Ema::new),
+            show_ema: show_ema && EMA_DECAY.is_some(),
+            quiet: false,
+            evolution,
+            augment: Augment {
+                noise: AUGMENT_NOISE,
+                image: info.image,
+                shift: IMAGE_SHIFT,
+            },
+        };
+        // The second run's network and Renderinfo, drawn next to the first
+        let mut compared = config.compare.as_ref().map(|compare| {
+            let compared = compared_trainer(&config, compare, &trainer, compare_rng);
+            let nn = compared.nn.clone();
+            let cost = NN::cost(&nn, &info.t_input, &info.t_output);
+            let per_sample = cost / info.t_input.rows as f32;
+            info.cost_history.compare.push((0, per_sample));
+            let info = Renderinfo {
+                cost,
+                cost_history: CostHistory {
+                    train: vec![(0, per_sample)],
+                    ..CostHistory::default()
+                },
+                learning_rate: compare.learning_rate,
+                ..info.clone()
+            };
+            (compared, nn, info)
+        });
 
-                {
-                    let mut info = info_clone.lock().unwrap();
-                    info.epoch = i;
-                    info.t_input = t_input.clone();
-                    info.t_output = t_output.clone();
-                    info.training_time =
-                        (chrono::Utc::now().timestamp_millis() - time_elapsed) as f32 / 1000.0;
-                }
+        clear_background(themes[theme].background);
+        draw_frame(
+            &nn,
+            &mut info,
+            compared.as_mut().map(|(_, nn, info)| (&*nn, info)),
+        );
+        next_frame().await;
 
-                {
-                    let mut nn = nn_clone.lock().unwrap();
-                    NN::backprop(&mut nn, &mut gradient, &t_input, &t_output);
-                    NN::learn(&mut nn, &gradient, LEARNING_RATE);
-                }
-            }
-            println!(
-                "Training time: {}",
-                info_clone.lock().unwrap().training_time
-            );
+        let mut runs = vec![training::spawn(trainer)];
+        let mut compared = compared.map(|(trainer, nn, info)| {
+            runs.push(training::spawn(trainer));
+            (nn, info)
         });
 
         loop {
+            training::tick(&runs, TRAIN_SLICE);
+            Snapshot::take(&runs[0].snapshot, &mut nn, &mut info);
+            if let Some((nn, compared)) = &mut compared {
+                // for its epochs per frame cap
+                compared.frame = info.frame;
+                Snapshot::take(&runs[1].snapshot, nn, compared);
+            }
+            // A run paused itself for a jump in the cost, pause the rest with it so P
+            // resumes them all
+            let jumped = |info: &Renderinfo| info.jump.is_some() && !info.diverged;
+            if !paused && (jumped(&info) || compared.as_ref().is_some_and(|(_, c)| jumped(c))) {
+                training::broadcast(&runs, Signal::Pause);
+                paused = true;
+                println!("Paused");
+            }
+
             // Quit?
             if is_key_pressed(KeyCode::Escape) || is_key_pressed(KeyCode::Q) {
                 std::process::exit(0);
@@ -161,38 +636,781 @@ async fn main() {
 
             // Reset?
             if is_key_pressed(KeyCode::R) {
-                // Stop the training thread
-                let _ = tx.send(Signal::Stop);
+                // Stop the training threads
+                past_runs = end_run(runs, info, &nn_structure);
                 println!("Reset");
                 // Restart the program
                 continue 'reset;
             }
 
+            // Switch dataset?
+            for (key, preset) in PRESET_KEYS.iter().zip(Preset::ALL) {
+                if is_key_pressed(*key) {
+                    past_runs = end_run(runs, info, &nn_structure);
+                    println!("Switched to {}", Preset::name(preset));
+                    source = Some(Source::Preset(preset));
+                    continue 'reset;
+                }
+            }
+
+            // Draw a dataset? Pressing D again trains on it
+            if is_key_pressed(KeyCode::D) {
+                editing = !editing;
+                if !editing && !drawn.is_empty() {
+                    past_runs = end_run(runs, info, &nn_structure);
+                    println!("Training on {} drawn points", drawn.len());
+                    source = Some(Source::Drawn);
+                    continue 'reset;
+                }
+            }
+
+            // Dropped a file? CSVs and pictures become the dataset, anything else is
+            // loaded as a saved network
+            let dropped = dropped_files();
+            if dropped != last_dropped {
+                last_dropped = dropped.clone();
+                for (i, path) in dropped.into_iter().enumerate() {
+                    let Some(bytes) = dropped_file(i) else {
+                        continue;
+                    };
+                    let csv = path.extension().is_some_and(|ext| ext == "csv");
+                    if csv || is_picture(&path) {
+                        past_runs = end_run(runs, info, &nn_structure);
+                        let verb = if csv { "Switched to" } else { "Redrawing" };
+                        println!("{} {}", verb, path.display());
+                        source = Some(Source::Dropped(path, bytes));
+                        continue 'reset;
+                    }
+                    info.error =
+                        load_model(&mut nn, &runs[0], &path, Some(&bytes), &nn_structure).err();
+                }
+            }
+
+            // Change the hidden layers? Restarts training with the new architecture
+            let mut layers = nn_structure[1..nn_structure.len() - 1].to_vec();
+            if edit_hidden(&mut layers, &mut edit_layer) {
+                past_runs = end_run(runs, info, &nn_structure);
+                println!("Hidden layers: {:?}", layers);
+                hidden = Some(layers);
+                continue 'reset;
+            }
+            info.edit_layer = edit_layer;
+
+            // Save?
+            if is_key_pressed(KeyCode::S) {
+                let saved = save_model(&nn, &runs[0], &info.cost_history, &config.model_path);
+                println!("{}", saved);
+            }
+
+            // Load?
+            if is_key_pressed(KeyCode::L) {
+                let path = Path::new(&config.model_path);
+                info.error = load_model(&mut nn, &runs[0], path, None, &nn_structure).err();
+            }
+
+            // Export?
+            if is_key_pressed(KeyCode::E) {
+                match NN::export_onnx(&nn, &config.onnx_path) {
+                    Ok(()) => println!("Exported to {}", config.onnx_path),
+                    Err(e) => println!("Exporting to {} failed: {}", config.onnx_path, e),
+                }
+            }
+
+            // Generate Rust source?
+            if is_key_pressed(KeyCode::G) {
+                match NN::export_rust(&nn, &config.rust_path) {
+                    Ok(()) => println!("Generated {}", config.rust_path),
+                    Err(e) => println!("Generating {} failed: {}", config.rust_path, e),
+                }
+            }
+
+            // Generate int8 Rust source?
+            if is_key_pressed(KeyCode::J) {
+                let q = QuantizedNN::new(&nn);
+                match QuantizedNN::export_rust(&q, &config.quantized_path) {
+                    Ok(()) => println!(
+                        "Generated {} ({} bytes of parameters)",
+                        config.quantized_path,
+                        QuantizedNN::size(&q)
+                    ),
+                    Err(e) => println!("Generating {} failed: {}", config.quantized_path, e),
+                }
+                info.quantized = Some(if is_classification(&info.t_output) {
+                    Quantized::Accuracy {
+                        epoch: info.epoch,
+                        exact: NN::accuracy(&nn, &info.t_input, &info.t_output),
+                        int8: QuantizedNN::accuracy(&q, &info.t_input, &info.t_output),
+                    }
+                } else {
+                    Quantized::Error {
+                        epoch: info.epoch,
+                        max: QuantizedNN::max_error(&q, &nn, &info.t_input),
+                    }
+                });
+            }
+
+            // Prune?
+            if is_key_pressed(KeyCode::X) || is_key_pressed(KeyCode::Z) {
+                let fraction = if is_key_pressed(KeyCode::X) {
+                    // from what the network has, a pruned model loaded with L included
+                    ((NN::sparsity(&nn) + PRUNE_STEP) * 100.).round() / 100.
+                } else {
+                    0.
+                };
+                let fraction = fraction.min(MAX_PRUNED);
+                info.prunes.push((info.epoch, fraction));
+                Run::send(&runs[0], Signal::Prune(fraction));
+            }
+
+            // Full batch, mini-batch or online gradient descent? The batch size slider
+            // follows, like a slider it only changes the first run
+            if is_key_pressed(KeyCode::Tab) {
+                let descent = Descent::of(info.params.batch_size, info.t_input.rows);
+                let descent = Descent::next(descent, mini);
+                info.params.batch_size = Descent::batch_size(descent);
+                info.descents.push((info.epoch, descent));
+                Run::send(&runs[0], Signal::Set(info.params));
+            }
+
+            // Cost plot?
+            if is_key_pressed(KeyCode::H) {
+                match capture_path("cost", "png") {
+                    Ok(path) => {
+                        export_plot(&info, &path);
+                        println!("Saved the cost plot to {}", path);
+                    }
+                    Err(e) => println!("Creating {} failed: {}", SCREENSHOT_DIR, e),
+                }
+            }
+
+            // Weight heatmaps?
+            if is_key_pressed(KeyCode::W) {
+                show_weights = !show_weights;
+                info.show_weights = show_weights;
+            }
+
+            // Weight and bias histograms?
+            if is_key_pressed(KeyCode::Y) {
+                show_histograms = !show_histograms;
+                info.show_histograms = show_histograms;
+            }
+
+            // Earlier runs on the cost graph?
+            if is_key_pressed(KeyCode::U) {
+                show_runs = !show_runs;
+                info.show_runs = show_runs;
+            }
+
+            // Probe panel?
+            if is_key_pressed(KeyCode::I) {
+                show_probe = !show_probe;
+                info.show_probe = show_probe;
+            }
+
+            // Moving average of the weights?
+            if is_key_pressed(KeyCode::A) && EMA_DECAY.is_some() {
+                show_ema = !show_ema;
+                info.show_ema = show_ema;
+                training::broadcast(&runs, Signal::ShowEma(show_ema));
+                if let Some((_, info)) = &mut compared {
+                    info.show_ema = show_ema;
+                }
+            }
+
+            // Parameter norms in the graph?
+            if is_key_pressed(KeyCode::K) {
+                show_norms = !show_norms;
+                info.show_norms = show_norms;
+            }
+
+            // Confusion matrices?
+            if is_key_pressed(KeyCode::M) {
+                show_confusion = !show_confusion;
+                info.show_confusion = show_confusion;
+            }
+
+            // Learning rate range test? Trains a copy of the network as it is now and
+            // plots the cost against the rate in place of the cost graph until F again
+            if is_key_pressed(KeyCode::F) {
+                info.lr_curve = match info.lr_curve {
+                    Some(_) => None,
+                    None => {
+                        let (_, optimizer) = build_nn(&nn_structure, &config);
+                        let curve = LrFinder::run(
+                            &LR_FINDER,
+                            &nn,
+                            &optimizer,
+                            &info.t_input,
+                            &info.t_output,
+                            info.params.batch_size,
+                            &mut seeded_rng(seed),
+                        );
+                        match LrFinder::suggest(&curve) {
+                            Some(rate) => println!("Learning rate range test suggests {}", rate),
+                            None => println!("The cost didn't fall at any tested rate"),
+                        }
+                        Some(curve)
+                    }
+                };
+            }
+
+            // Collapse/expand the help, graph, side, samples and slider panels?
+            let panels = [
+                (KeyCode::F1, &mut collapsed.help),
+                (KeyCode::F2, &mut collapsed.graph),
+                (KeyCode::F3, &mut collapsed.side),
+                (KeyCode::F4, &mut collapsed.samples),
+                (KeyCode::F5, &mut collapsed.controls),
+            ];
+            let mut toggled = false;
+            for (key, panel) in panels {
+                if is_key_pressed(key) {
+                    *panel = !*panel;
+                    toggled = true;
+                }
+            }
+            if toggled {
+                info.collapsed = collapsed;
+            }
+
+            // Next theme?
+            if is_key_pressed(KeyCode::T) {
+                theme = (theme + 1) % themes.len();
+                info.theme = themes[theme];
+            }
+
+            // Log scale cost graph?
+            if is_key_pressed(KeyCode::O) {
+                log_scale = !log_scale;
+                info.log_scale = log_scale;
+            }
+
+            // Previous/next sample on the diagram?
+            if is_key_pressed(KeyCode::Left) || is_key_pressed(KeyCode::Right) {
+                let rows = info.t_input.rows;
+                info.sample = if is_key_pressed(KeyCode::Left) {
+                    (info.sample + rows - 1) % rows
+                } else {
+                    (info.sample + 1) % rows
+                };
+            }
+
+            // Train on from the network picked on the timeline? The history after it
+            // goes once the training thread got there (see Snapshot::rewound)
+            if is_key_pressed(KeyCode::Enter) {
+                if let Some((epoch, rewound)) = Timeline::selected(&info.timeline) {
+                    println!("Rewinding to epoch {}", epoch);
+                    Run::send(&runs[0], Signal::Rewind(*epoch, rewound.clone()));
+                    info.timeline.selected = None;
+                }
+            }
+
+            // Step one or 100 epochs while paused?
+            if paused && is_key_pressed(KeyCode::N) {
+                training::broadcast(&runs, Signal::Step(1));
+            }
+            if paused && is_key_pressed(KeyCode::B) {
+                training::broadcast(&runs, Signal::Step(100));
+            }
+
             // Pause/Resume?
             if is_key_pressed(KeyCode::P) {
                 if paused {
-                    // Send a "resume" signal to the training thread
-                    let _ = tx.send(Signal::Resume);
+                    // Send a "resume" signal to the training threads
+                    training::broadcast(&runs, Signal::Resume);
                     paused = false;
+                    info.jump = None;
+                    if let Some((_, compared)) = &mut compared {
+                        compared.jump = None;
+                    }
                     println!("Resumed");
                 } else {
-                    // Send a "pause" signal to the training thread
-                    let _ = tx.send(Signal::Pause);
+                    // Send a "pause" signal to the training threads
+                    training::broadcast(&runs, Signal::Pause);
                     paused = true;
                     println!("Paused");
                 }
             }
 
-            clear_background(BACKGROUND_COLOR);
-            {
-                let mut info = info.lock().unwrap();
-                draw_frame(&nn.lock().unwrap(), &mut info);
+            clear_background(themes[theme].background);
+            let changed = if editing {
+                draw_editor(&mut drawn, &mut drawn_class, themes[theme]);
+                None
+            } else {
+                draw_frame(
+                    &nn,
+                    &mut info,
+                    compared.as_mut().map(|(nn, info)| (&*nn, info)),
+                )
+            };
+            if let Some(params) = changed {
+                // The sliders only change the first run
+                Run::send(&runs[0], Signal::Set(params));
+            }
+
+            // Screenshot? After drawing, so the whole frame is in it
+            if is_key_pressed(KeyCode::C) {
+                match capture_path("screenshot", "png") {
+                    Ok(path) => {
+                        get_screen_data().export_png(&path);
+                        println!("Saved a screenshot to {}", path);
+                    }
+                    Err(e) => println!("Creating {} failed: {}", SCREENSHOT_DIR, e),
+                }
+            }
+
+            // Recording?
+            let toggle = is_key_pressed(KeyCode::V);
+            match &mut recorder {
+                None if toggle => {
+                    recorder = Some(Recorder::new(
+                        RECORD_INTERVAL,
+                        RECORD_SCALE,
+                        RECORD_MAX_FRAMES,
+                    ));
+                    println!("Recording");
+                }
+                None => {}
+                Some(rec) => {
+                    // Stopped by the key or because it's full
+                    if toggle || !Recorder::capture(rec) {
+                        let saved = capture_path("recording", "gif")
+                            .and_then(|path| Recorder::save(rec, &path).map(|()| path));
+                        match saved {
+                            Ok(path) => {
+                                println!("Saved {} frames to {}", Recorder::frames(rec), path)
+                            }
+                            Err(e) => println!("Saving the recording failed: {}", e),
+                        }
+                        recorder = None;
+                    } else {
+                        // Drawn after capturing, so it's not in the recording
+                        draw_text(
+                            format!("REC {}", Recorder::frames(rec)).as_str(),
+                            screen_width() / 2. - 30.,
+                            20.,
+                            24.,
+                            themes[theme].bad,
+                        );
+                    }
+                }
             }
             next_frame().await;
         }
     }
 }
 
+// Stops the training threads for a reset and keeps the run's costs with the earlier
+// ones, dropping the oldest past MAX_PAST_RUNS
+fn end_run(runs: Vec<Run>, info: Renderinfo, arch: &[usize]) -> Vec<PastRun> {
+    training::stop_all(runs);
+    let mut past_runs = info.past_runs;
+    past_runs.push(PastRun {
+        id: info.run_id,
+        label: format!(
+            "{}, lr {:.4}, {:?}",
+            info.dataset,
+            info.params.learning_rate,
+            &arch[1..arch.len() - 1]
+        ),
+        train: info.cost_history.train,
+    });
+    if past_runs.len() > MAX_PAST_RUNS {
+        past_runs.remove(0);
+    }
+    past_runs
+}
+
+// Saves the training thread's network and next to it (see TrainingState::path) what
+// resuming it takes, or falls back to the render thread's copy alone. Returns what
+// happened, for the window to print and the TUI to show.
+fn save_model(nn: &NN, run: &Run, history: &CostHistory, path: &str) -> String {
+    let Some((trained, epoch, optimizer)) = Run::state(run) else {
+        return match NN::save(nn, path) {
+            Ok(()) => format!("Saved to {} without the training state", path),
+            Err(e) => format!("Saving to {} failed: {}", path, e),
+        };
+    };
+    if let Err(e) = NN::save(&trained, path) {
+        return format!("Saving to {} failed: {}", path, e);
+    }
+
+    let points = |points: &[(i32, f32)]| {
+        points
+            .iter()
+            .filter(|&&(e, _)| (e as usize) < epoch)
+            .map(|&(e, cost)| (e as usize, cost))
+            .collect()
+    };
+    let state = TrainingState {
+        epoch,
+        optimizer,
+        history: points(&history.train),
+        validation: points(&history.validation),
+    };
+    let state_path = TrainingState::path(path);
+    match TrainingState::save(&state, &state_path) {
+        Ok(()) => format!("Saved to {} and {}", path, state_path.display()),
+        Err(e) => format!("Saving to {} failed: {}", state_path.display(), e),
+    }
+}
+
+// Replaces the network's weights unless the file's architecture differs. A training
+// state saved with it resumes training where it was saved. A dropped file comes with
+// its bytes and without a state, there is only the one file.
+fn load_model(
+    nn: &mut NN,
+    run: &Run,
+    path: &Path,
+    dropped: Option<&[u8]>,
+    arch: &[usize],
+) -> Result<(), String> {
+    let failed = |msg: String| {
+        println!("{}", msg);
+        Err(msg)
+    };
+    let bytes = match dropped {
+        Some(bytes) => Ok(bytes.to_vec()),
+        None => std::fs::read(path),
+    };
+    // dropped Keras weights too
+    let loaded = bytes.and_then(|bytes| {
+        if path.extension().is_some_and(|ext| ext == "npz") {
+            NN::parse_npz(&bytes, ACTIVATION)
+        } else {
+            NN::parse(&bytes, path)
+        }
+    });
+    match loaded {
+        // The gradient and optimizer state are shaped after the architecture
+        Ok(loaded) if NN::arch(&loaded) != arch => {
+            let e = Error::Architecture {
+                expected: arch.to_vec(),
+                found: NN::arch(&loaded),
+            };
+            failed(format!("{} has {}", path.display(), e))
+        }
+        Ok(loaded) => {
+            let state_path = TrainingState::path(path);
+            let state = match TrainingState::load(&state_path) {
+                Ok(_) if dropped.is_some() => None,
+                Ok(state) => {
+                    println!("Resuming at epoch {}", state.epoch);
+                    Some(state)
+                }
+                Err(e) if e.kind() == ErrorKind::NotFound => None,
+                Err(e) => {
+                    println!("Loading {} failed: {}", state_path.display(), e);
+                    None
+                }
+            };
+            *nn = loaded.clone();
+            Run::send(run, Signal::Load(loaded, state.map(Box::new)));
+            println!("Loaded {}", path.display());
+            Ok(())
+        }
+        Err(e) => failed(format!("Loading {} failed: {}", path.display(), e)),
+    }
+}
+
+// Names of the files dropped on the window. miniquad 0.3 only reports drops in the
+// browser so far, its desktop backends never return any.
+fn dropped_files() -> Vec<PathBuf> {
+    let ctx = unsafe { get_internal_gl() }.quad_context;
+    (0..ctx.dropped_file_count())
+        .filter_map(|i| ctx.dropped_file_path(i))
+        .collect()
+}
+
+// What the i-th dropped file holds, the browser only gives the name besides
+fn dropped_file(i: usize) -> Option<Vec<u8>> {
+    unsafe { get_internal_gl() }
+        .quad_context
+        .dropped_file_bytes(i)
+}
+
+// [ and ] pick the layer to edit, up/down change its neuron count and = and - insert
+// a layer after it or remove it. Returns whether the layers changed.
+fn edit_hidden(layers: &mut Vec<usize>, edit: &mut usize) -> bool {
+    if is_key_pressed(KeyCode::LeftBracket) {
+        *edit = edit.saturating_sub(1);
+    }
+    if is_key_pressed(KeyCode::RightBracket) {
+        *edit += 1;
+    }
+    *edit = (*edit).min(layers.len().saturating_sub(1));
+
+    if is_key_pressed(KeyCode::Equal) {
+        // same size as the one it follows
+        let neurons = layers.get(*edit).copied().unwrap_or(HIDDEN_NEURONS);
+        if layers.is_empty() {
+            layers.push(neurons);
+        } else {
+            *edit += 1;
+            layers.insert(*edit, neurons);
+        }
+        return true;
+    }
+    if let Some(neurons) = layers.get_mut(*edit) {
+        if is_key_pressed(KeyCode::Minus) {
+            layers.remove(*edit);
+            *edit = (*edit).min(layers.len().saturating_sub(1));
+            return true;
+        }
+        if is_key_pressed(KeyCode::Up) && *neurons < MAX_HIDDEN_NEURONS {
+            *neurons += 1;
+            return true;
+        }
+        if is_key_pressed(KeyCode::Down) && *neurons > 1 {
+            *neurons -= 1;
+            return true;
+        }
+    }
+    false
+}
+
+// Local time for file names, seconds since 1970 in the browser where chrono can't
+// read the clock
+#[cfg(not(target_arch = "wasm32"))]
+fn timestamp() -> String {
+    chrono::Local::now().format("%Y%m%d-%H%M%S").to_string()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn timestamp() -> String {
+    (date::now() as u64).to_string()
+}
+
+// SCREENSHOT_DIR/<prefix>-<local time>.<ext>, creating the directory
+fn capture_path(prefix: &str, ext: &str) -> std::io::Result<String> {
+    std::fs::create_dir_all(SCREENSHOT_DIR)?;
+    Ok(format!(
+        "{}/{}-{}.{}",
+        SCREENSHOT_DIR,
+        prefix,
+        timestamp(),
+        ext
+    ))
+}
+
+// The second run of --compare-lr/--compare-hidden, on the same samples with the same
+// input scaling and random numbers as `first`. It doesn't write checkpoints or metrics.
+fn compared_trainer(
+    config: &Config,
+    compare: &Compare,
+    first: &Trainer,
+    mut rng: StdRng,
+) -> Trainer {
+    let arch = NN::arch(&first.nn);
+    let mut compared = vec![arch[0]];
+    compared.extend_from_slice(
+        compare
+            .hidden
+            .as_deref()
+            .unwrap_or(&arch[1..arch.len() - 1]),
+    );
+    compared.push(arch[arch.len() - 1]);
+    let config = Config {
+        learning_rate: compare.learning_rate,
+        metrics_path: None,
+        tensorboard_dir: None,
+        ..config.clone()
+    };
+    let (mut nn, optimizer) = build_nn(&compared, &config);
+    nn.norm = first.nn.norm.clone();
+    nn.class_weights = first.nn.class_weights.clone();
+    Init::apply(INIT, &mut nn, &mut rng);
+    let evolution = first
+        .evolution
+        .as_ref()
+        .map(|evolution| Evolution::new(&nn, evolution.population.len(), &mut rng));
+    println!(
+        "Comparing with rate {} and hidden layers {:?}",
+        compare.learning_rate,
+        &compared[1..compared.len() - 1]
+    );
+
+    Trainer {
+        name: "Compared run: ".to_owned(),
+        nn,
+        optimizer,
+        early_stopping: EarlyStopping::new(TARGET_COST, PATIENCE, MIN_DELTA),
+        divergence: first.divergence.clone(),
+        checkpointer: Checkpointer::new(&config.checkpoint_dir, 0, false),
+        progress: Progress::new(&config, None),
+        params: Hyperparams {
+            learning_rate: compare.learning_rate,
+            ..first.params
+        },
+        rng,
+        t_input: first.t_input.clone(),
+        t_output: first.t_output.clone(),
+        v_input: first.v_input.clone(),
+        v_output: first.v_output.clone(),
+        epochs: first.epochs,
+        schedule: first.schedule,
+        ema: first.ema.clone(),
+        show_ema: first.show_ema,
+        quiet: first.quiet,
+        evolution,
+        augment: first.augment,
+    }
+}
+
+// The population of --evolve, grown from the initialized network
+fn evolution(config: &Config, nn: &NN, rng: &mut StdRng) -> Option<Evolution> {
+    config.evolve.then(|| Evolution::new(nn, POPULATION, rng))
+}
+
+// Fits the input scaling to the training samples and, with --balance-classes, weighs
+// the classes so they count the same however many samples each has
+fn fit_data(nn: &mut NN, config: &Config, t_input: &Mat, t_output: &Mat) {
+    nn.norm = Normalization::fit(SCALING, t_input);
+    if config.balance_classes && is_classification(t_output) {
+        nn.class_weights = balanced_weights(t_output);
+    }
+}
+
+// The network from the consts above and the optimizer the config picks
+fn build_nn(arch: &[usize], config: &Config) -> (NN, Optimizer) {
+    let mut builder = NNBuilder::new().input(arch[0]);
+    for &size in &arch[1..arch.len() - 1] {
+        builder = builder.dense(size, ACTIVATION).dropout(DROPOUT);
+    }
+    // Momentum unless the experiment file picks Adam (works best with a lower rate,
+    // around 0.05) or plain gradient descent (needs a higher rate, around 1.0)
+    let mut optimizer = Optimizer::new(config.learning_rate, config.method);
+    optimizer.clip = CLIP;
+    builder
+        .dense(arch[arch.len() - 1], Activation::Sigmoid)
+        .loss(LOSS)
+        .regularization(Regularization {
+            l1: L1_LAMBDA,
+            l2: L2_LAMBDA,
+        })
+        .optimizer(optimizer)
+        .build()
+}
+
+// Display name, training data and how the samples are pixels, if they are.
+// Why a source couldn't be used goes to `error` as well as stdout.
+fn load_dataset(
+    source: Option<&Source>,
+    drawn: &[(f32, f32, usize)],
+    rng: &mut StdRng,
+    error: &mut Option<String>,
+) -> (String, Dataset, Option<Pixels>) {
+    let mut failed = |msg: String| {
+        println!("{}", msg);
+        *error = Some(msg);
+    };
+    match source {
+        Some(Source::Preset(preset)) => {
+            return (
+                Preset::name(*preset).to_owned(),
+                Preset::build(*preset, rng),
+                None,
+            );
+        }
+        Some(Source::Drawn) => {
+            return ("Drawn".to_owned(), Dataset::from_points(drawn), None);
+        }
+        Some(Source::Csv(path)) => match Dataset::load_csv(path) {
+            Ok(dataset) => return (path.display().to_string(), dataset, None),
+            Err(e) => failed(format!("Loading {} failed: {}", path.display(), e)),
+        },
+        Some(Source::Dropped(path, bytes)) if !is_picture(path) => {
+            match Dataset::parse_csv(bytes) {
+                Ok(dataset) => return (path.display().to_string(), dataset, None),
+                Err(e) => failed(format!("Loading {} failed: {}", path.display(), e)),
+            }
+        }
+        Some(file @ (Source::Picture(path) | Source::Dropped(path, _))) => match picture(file) {
+            Ok((width, height, pixels)) => {
+                println!(
+                    "Loaded {} as a {}x{} picture",
+                    path.display(),
+                    width,
+                    height
+                );
+                let dataset = Dataset::from_picture(width, height, &pixels);
+                let pixels = Pixels::Picture(width, height, pixels);
+                return (path.display().to_string(), dataset, Some(pixels));
+            }
+            Err(e) => failed(format!("Loading {} failed: {}", path.display(), e)),
+        },
+        None => {}
+    }
+
+    if let Some(images) = MNIST_IMAGES {
+        match Dataset::load_mnist(images, MNIST_LABELS, MNIST_SAMPLES) {
+            Ok(dataset) => {
+                println!("Loaded {} MNIST digits", dataset.t_input.rows);
+                return ("MNIST".to_owned(), dataset, Some(Pixels::Inputs(28, 28)));
+            }
+            Err(e) => failed(format!("Loading MNIST from {} failed: {}", images, e)),
+        }
+    }
+
+    if let Some(path) = DATASET_PATH {
+        match Dataset::load_csv(path) {
+            Ok(dataset) => {
+                println!(
+                    "Loaded {} samples from {} ({:?} -> {:?})",
+                    dataset.t_input.rows, path, dataset.input_names, dataset.output_names
+                );
+                return (path.to_owned(), dataset, None);
+            }
+            Err(e) => failed(format!("Loading {} failed, using XOR: {}", path, e)),
+        }
+    }
+
+    let preset = Preset::Xor;
+    (
+        Preset::name(preset).to_owned(),
+        Preset::build(preset, rng),
+        None,
+    )
+}
+
+fn is_picture(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext == "png" || ext == "tga")
+}
+
+// A Source::Picture read from its path or a dropped one
+fn picture(source: &Source) -> std::io::Result<(usize, usize, Vec<f32>)> {
+    match source {
+        Source::Dropped(_, bytes) => parse_picture(bytes),
+        Source::Picture(path) => parse_picture(&std::fs::read(path)?),
+        _ => Err(std::io::Error::new(
+            ErrorKind::InvalidInput,
+            "not a picture",
+        )),
+    }
+}
+
+// The picture's brightness in [0, 1] row by row, shrunk to fit PICTURE_SIZE
+fn parse_picture(bytes: &[u8]) -> std::io::Result<(usize, usize, Vec<f32>)> {
+    let picture = image::load_from_memory(bytes)
+        .map_err(|e| std::io::Error::new(ErrorKind::InvalidData, e))?
+        .to_luma8();
+    let (width, height) = picture.dimensions();
+    let scale = (PICTURE_SIZE as f32 / width.max(height) as f32).min(1.);
+    let (width, height) = (
+        ((width as f32 * scale).round() as u32).max(1),
+        ((height as f32 * scale).round() as u32).max(1),
+    );
+    let picture = image::imageops::resize(
+        &picture,
+        width,
+        height,
+        image::imageops::FilterType::Triangle,
+    );
+    let pixels = picture.pixels().map(|p| p.0[0] as f32 / 255.).collect();
+    Ok((width as usize, height as usize, pixels))
+}
+
 fn lerp(a: f32, b: f32, t: f32) -> f32 {
     a + (b - a) * t
 }
@@ -0,0 +1,127 @@
+use super::{
+    draw_rectangle, draw_rectangle_lines, draw_text, is_mouse_button_down, is_mouse_button_pressed,
+    mouse_position, vec2, Mat, MouseButton, Rect, Theme, NN,
+};
+
+// Space for the name and value above every input slider
+const SLIDER_HEIGHT: f32 = 34.;
+const OUTPUT_HEIGHT: f32 = 20.;
+
+// Inputs set by hand in the probe panel (I toggles) and run through the current
+// network every frame, they don't have to be anywhere in the training set.
+#[derive(Clone, Debug)]
+pub struct Probe {
+    pub inputs: Vec<f32>,
+    // slider range of every input, the training samples' widened a bit
+    ranges: Vec<(f32, f32)>,
+    input_names: Vec<String>,
+    output_names: Vec<String>,
+    // slider being dragged
+    drag: Option<usize>,
+}
+
+impl Probe {
+    // Starts in the middle of every input's range
+    pub fn new(t_input: &Mat, input_names: Vec<String>, output_names: Vec<String>) -> Probe {
+        let ranges: Vec<(f32, f32)> = (0..t_input.cols)
+            .map(|col| {
+                let column = t_input.data.iter().map(|row| row[col]);
+                let min = column.clone().fold(f32::INFINITY, f32::min);
+                let max = column.fold(f32::NEG_INFINITY, f32::max);
+                if !min.is_finite() || !max.is_finite() {
+                    return (0., 1.);
+                }
+                let margin = ((max - min) * 0.1).max(0.05);
+                (min - margin, max + margin)
+            })
+            .collect();
+        Probe {
+            inputs: ranges.iter().map(|&(min, max)| (min + max) / 2.).collect(),
+            ranges,
+            input_names,
+            output_names,
+            drag: None,
+        }
+    }
+
+    // One slider per input above the network's outputs for them. Dragging with the
+    // left mouse button moves a slider, a right click takes the inputs of `sample`
+    // (the one the arrow keys picked).
+    pub fn draw(probe: &mut Probe, nn: &NN, sample: &[f32], theme: Theme, rect: Rect) {
+        draw_rectangle(rect.x, rect.y, rect.w, rect.h, theme.panel);
+        let (mx, my) = mouse_position();
+        if !is_mouse_button_down(MouseButton::Left) {
+            probe.drag = None;
+        }
+        let over = rect.contains(vec2(mx, my));
+        if over && is_mouse_button_pressed(MouseButton::Right) && sample.len() == probe.inputs.len()
+        {
+            probe.inputs = sample.to_vec();
+        }
+
+        draw_text("Probe", rect.x + 5., rect.y + 15., 16., theme.text);
+        let mut y = rect.y + 20.;
+        for i in 0..probe.inputs.len() {
+            let (min, max) = probe.ranges[i];
+            let track = Rect::new(rect.x + 10., y + 20., rect.w - 20., 6.);
+            let grab = Rect::new(track.x - 5., track.y - 8., track.w + 10., track.h + 16.);
+            if is_mouse_button_pressed(MouseButton::Left) && grab.contains(vec2(mx, my)) {
+                probe.drag = Some(i);
+            }
+            if probe.drag == Some(i) {
+                let t = ((mx - track.x) / track.w).clamp(0., 1.);
+                probe.inputs[i] = min + (max - min) * t;
+            }
+
+            let name = probe
+                .input_names
+                .get(i)
+                .cloned()
+                .unwrap_or_else(|| format!("in {}", i));
+            draw_text(
+                format!("{}: {:.3}", name, probe.inputs[i]).as_str(),
+                rect.x + 10.,
+                y + 14.,
+                16.,
+                theme.text,
+            );
+            draw_rectangle(track.x, track.y, track.w, track.h, theme.background);
+            draw_rectangle_lines(track.x, track.y, track.w, track.h, 1., theme.inactive);
+            let t = ((probe.inputs[i] - min) / (max - min)).clamp(0., 1.);
+            draw_rectangle(
+                track.x + t * track.w - 3.,
+                track.y - 4.,
+                6.,
+                track.h + 8.,
+                theme.positive,
+            );
+            y += SLIDER_HEIGHT;
+        }
+
+        let mut nn = nn.clone();
+        nn.activations[0].data[0].clone_from(&probe.inputs);
+        NN::forward(&mut nn);
+        let output = &nn.activations[nn.count - 1].data[0];
+        for (i, &value) in output.iter().enumerate() {
+            if y + OUTPUT_HEIGHT > rect.y + rect.h {
+                break;
+            }
+            let name = probe
+                .output_names
+                .get(i)
+                .cloned()
+                .unwrap_or_else(|| format!("out {}", i));
+            // the output layer is a sigmoid, so the bar is full at 1
+            let bar = (rect.w - 20.) * value.clamp(0., 1.);
+            draw_rectangle(rect.x + 10., y + 4., bar, OUTPUT_HEIGHT - 6., theme.cost);
+            draw_text(
+                format!("{}: {:.4}", name, value).as_str(),
+                rect.x + 12.,
+                y + 15.,
+                16.,
+                theme.text,
+            );
+            y += OUTPUT_HEIGHT;
+        }
+    }
+}
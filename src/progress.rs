@@ -0,0 +1,82 @@
+use std::sync::Arc;
+
+use super::{Config, EventWriter, Metrics, MetricsLogger, MetricsServer, METRICS_EVERY};
+
+// Where the training loops report their metrics every METRICS_EVERY epochs: the
+// --metrics file, the --tensorboard event file and the --stream clients, each only
+// when configured
+pub struct Progress {
+    logger: Option<MetricsLogger>,
+    events: Option<EventWriter>,
+    stream: Option<Arc<MetricsServer>>,
+}
+
+// The --stream server, bound once and shared by the runs after a reset since the
+// address stays taken
+pub fn stream(config: &Config) -> Option<Arc<MetricsServer>> {
+    let addr = config.stream.as_ref()?;
+    match MetricsServer::bind(addr) {
+        Ok(server) => {
+            println!("Streaming metrics on ws://{}", server.addr);
+            Some(Arc::new(server))
+        }
+        Err(e) => {
+            println!("Can't stream metrics on {}: {}", addr, e);
+            None
+        }
+    }
+}
+
+impl Progress {
+    pub fn new(config: &Config, stream: Option<Arc<MetricsServer>>) -> Progress {
+        let events = config.tensorboard_dir.as_ref().and_then(|dir| {
+            EventWriter::create(dir)
+                .map_err(|e| println!("Can't write TensorBoard events to {}: {}", dir, e))
+                .ok()
+        });
+        Progress {
+            logger: config
+                .metrics_path
+                .as_ref()
+                .map(|path| MetricsLogger::new(path, METRICS_EVERY)),
+            events,
+            stream,
+        }
+    }
+
+    // Whether log writes anything for this epoch, to skip computing the metrics otherwise
+    pub fn due(progress: &Progress, epoch: usize) -> bool {
+        (progress.logger.is_some() || progress.events.is_some() || progress.stream.is_some())
+            && epoch.is_multiple_of(METRICS_EVERY)
+    }
+
+    // `grad_norms` per layer only go to TensorBoard
+    pub fn log(progress: &mut Progress, metrics: &Metrics, grad_norms: &[f32]) {
+        if let Some(logger) = &mut progress.logger {
+            if let Err(e) = MetricsLogger::log(logger, metrics) {
+                println!("Logging to {} failed: {}", logger.path.display(), e);
+            }
+        }
+
+        if let Some(server) = &progress.stream {
+            MetricsServer::send(server, metrics);
+        }
+
+        if let Some(events) = &mut progress.events {
+            let mut scalars = vec![
+                ("cost".to_owned(), metrics.cost),
+                ("learning_rate".to_owned(), metrics.learning_rate),
+            ];
+            if let Some(accuracy) = metrics.accuracy {
+                scalars.push(("accuracy".to_owned(), accuracy));
+            }
+            for (i, norm) in grad_norms.iter().enumerate() {
+                scalars.push((format!("grad_norm/layer_{}", i + 1), *norm));
+            }
+            let scalars: Vec<_> = scalars.iter().map(|(tag, v)| (tag.as_str(), *v)).collect();
+            if let Err(e) = EventWriter::scalars(events, metrics.epoch, &scalars) {
+                println!("Writing to {} failed: {}", events.path.display(), e);
+            }
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use std::{collections::HashMap, fs, io, path::Path};
+
+use super::{get_screen_data, get_time};
+
+// Records the window into an animated GIF. GIF is the only format, MP4 would need
+// a video encoder this project doesn't depend on. Frames are quantized to the 216
+// color web palette and LZW compressed by hand (like the protobuf in the ONNX export).
+pub struct Recorder {
+    // seconds between captured frames
+    interval: f64,
+    // every scale-th pixel of every scale-th row is kept
+    scale: usize,
+    max_frames: usize,
+    last: f64,
+    width: usize,
+    height: usize,
+    // palette indices, one byte per pixel
+    frames: Vec<Vec<u8>>,
+}
+
+impl Recorder {
+    pub fn new(interval: f64, scale: usize, max_frames: usize) -> Recorder {
+        Recorder {
+            interval,
+            scale: scale.max(1),
+            max_frames,
+            last: f64::NEG_INFINITY,
+            width: 0,
+            height: 0,
+            frames: vec![],
+        }
+    }
+
+    pub fn frames(rec: &Recorder) -> usize {
+        rec.frames.len()
+    }
+
+    // Grabs the drawn frame if the interval passed, call after drawing and before
+    // next_frame. Returns false once max_frames are recorded.
+    pub fn capture(rec: &mut Recorder) -> bool {
+        if rec.frames.len() >= rec.max_frames {
+            return false;
+        }
+        let now = get_time();
+        if now - rec.last < rec.interval {
+            return true;
+        }
+        rec.last = now;
+
+        let image = get_screen_data();
+        let (width, height) = (image.width as usize, image.height as usize);
+        let (out_width, out_height) = (width / rec.scale, height / rec.scale);
+        // a resized window would change the size mid recording, keep the first one
+        if rec.frames.is_empty() {
+            rec.width = out_width;
+            rec.height = out_height;
+        }
+
+        let pixels = image.get_image_data();
+        let mut frame = vec![0; rec.width * rec.height];
+        for y in 0..rec.height.min(out_height) {
+            // the screen data starts at the bottom row
+            let row = height - 1 - y * rec.scale;
+            for x in 0..rec.width.min(out_width) {
+                frame[y * rec.width + x] = palette_index(pixels[row * width + x * rec.scale]);
+            }
+        }
+        rec.frames.push(frame);
+        true
+    }
+
+    pub fn save(rec: &Recorder, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, encode(rec))
+    }
+}
+
+// Nearest of the 6x6x6 levels per channel
+fn palette_index([r, g, b, _]: [u8; 4]) -> u8 {
+    let level = |c: u8| (c as u16 * 5 + 127) / 255;
+    (level(r) * 36 + level(g) * 6 + level(b)) as u8
+}
+
+fn u16_le(buf: &mut Vec<u8>, v: usize) {
+    buf.extend_from_slice(&(v as u16).to_le_bytes());
+}
+
+fn encode(rec: &Recorder) -> Vec<u8> {
+    let mut buf = b"GIF89a".to_vec();
+    u16_le(&mut buf, rec.width);
+    u16_le(&mut buf, rec.height);
+    // global color table of 256 entries, 8 bits per channel
+    buf.extend_from_slice(&[0xf7, 0, 0]);
+    for i in 0..256 {
+        let (r, g, b) = if i < 216 {
+            (i / 36, i / 6 % 6, i % 6)
+        } else {
+            (0, 0, 0)
+        };
+        buf.extend_from_slice(&[r as u8 * 51, g as u8 * 51, b as u8 * 51]);
+    }
+
+    // loop forever
+    buf.extend_from_slice(b"\x21\xff\x0bNETSCAPE2.0\x03\x01\x00\x00\x00");
+
+    let delay = (rec.interval * 100.).round() as usize;
+    for frame in &rec.frames {
+        // graphic control extension with the frame delay in 1/100 s
+        buf.extend_from_slice(&[0x21, 0xf9, 4, 0]);
+        u16_le(&mut buf, delay);
+        buf.extend_from_slice(&[0, 0]);
+
+        // image descriptor covering the whole canvas
+        buf.push(0x2c);
+        u16_le(&mut buf, 0);
+        u16_le(&mut buf, 0);
+        u16_le(&mut buf, rec.width);
+        u16_le(&mut buf, rec.height);
+        buf.push(0);
+
+        buf.push(8);
+        for block in lzw(frame).chunks(255) {
+            buf.push(block.len() as u8);
+            buf.extend_from_slice(block);
+        }
+        buf.push(0);
+    }
+
+    buf.push(0x3b);
+    buf
+}
+
+// Variable width LZW as GIF uses it, for 8 bit indices
+fn lzw(data: &[u8]) -> Vec<u8> {
+    const CLEAR: u16 = 256;
+    const END: u16 = 257;
+
+    let mut out = vec![];
+    let mut acc = 0u32;
+    let mut bits = 0;
+    let mut emit = |code: u16, width: u32, out: &mut Vec<u8>| {
+        acc |= (code as u32) << bits;
+        bits += width;
+        while bits >= 8 {
+            out.push(acc as u8);
+            acc >>= 8;
+            bits -= 8;
+        }
+    };
+
+    let mut table: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut next = END + 1;
+    let mut width = 9;
+    emit(CLEAR, width, &mut out);
+
+    let mut prefix: Option<u16> = None;
+    for &byte in data {
+        let Some(code) = prefix else {
+            prefix = Some(byte as u16);
+            continue;
+        };
+        if let Some(&joined) = table.get(&(code, byte)) {
+            prefix = Some(joined);
+            continue;
+        }
+
+        emit(code, width, &mut out);
+        if next < 4096 {
+            table.insert((code, byte), next);
+            if next == 1 << width && width < 12 {
+                width += 1;
+            }
+            next += 1;
+        } else {
+            // table full, start over
+            emit(CLEAR, width, &mut out);
+            table.clear();
+            next = END + 1;
+            width = 9;
+        }
+        prefix = Some(byte as u16);
+    }
+    if let Some(code) = prefix {
+        emit(code, width, &mut out);
+        // the decoder adds an entry for this code too
+        if next == 1 << width && width < 12 {
+            width += 1;
+        }
+    }
+    emit(END, width, &mut out);
+    if bits > 0 {
+        out.push(acc as u8);
+    }
+    out
+}
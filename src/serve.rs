@@ -0,0 +1,136 @@
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    time::Duration,
+};
+
+use super::{Config, Mat, NN};
+
+// Requests bigger than this are turned away, an input vector is a few numbers
+const MAX_BODY: usize = 1 << 20;
+
+// `serve`: loads the model and answers HTTP requests one at a time until killed.
+//
+//     POST /  [0, 1]            -> {"output": [0.98]}
+//     POST /  [[0, 1], [1, 1]]  -> {"output": [[0.98], [0.02]]}
+//     GET /                     -> {"arch": [2, 4, 4, 1]}
+//
+// Errors come back as {"error": "..."} with a 4xx status.
+pub fn run(config: &Config, addr: &str) {
+    let mut nn = match NN::load(&config.model_path) {
+        Ok(nn) => nn,
+        Err(e) => {
+            println!("Can't load {}: {}", config.model_path, e);
+            return;
+        }
+    };
+    let listener = match TcpListener::bind(addr) {
+        Ok(listener) => listener,
+        Err(e) => {
+            println!("Can't listen on {}: {}", addr, e);
+            return;
+        }
+    };
+    println!(
+        "Serving {} {:?} on http://{}",
+        config.model_path,
+        NN::arch(&nn),
+        listener
+            .local_addr()
+            .map_or(addr.to_owned(), |a| a.to_string())
+    );
+
+    for stream in listener.incoming().flatten() {
+        // a client that stops sending doesn't hold up the others for long
+        let _ = stream.set_read_timeout(Some(Duration::from_secs(5)));
+        if let Err(e) = handle(&mut nn, stream) {
+            println!("Request failed: {}", e);
+        }
+    }
+}
+
+fn handle(nn: &mut NN, mut stream: TcpStream) -> io::Result<()> {
+    let mut reader = BufReader::new(&stream);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    let mut parts = request.split_whitespace();
+    let (method, path) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+
+    let mut length = 0;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+
+    let (status, body) = if path != "/" {
+        ("404 Not Found", error("no such path, use /"))
+    } else if method == "GET" {
+        let arch = NN::arch(nn);
+        ("200 OK", json::object! { arch: arch }.dump())
+    } else if method != "POST" {
+        ("405 Method Not Allowed", error("use GET or POST"))
+    } else if length > MAX_BODY {
+        ("413 Payload Too Large", error("request too big"))
+    } else {
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body)?;
+        match predict(nn, &String::from_utf8_lossy(&body)) {
+            Ok(output) => ("200 OK", json::object! { output: output }.dump()),
+            Err(e) => ("400 Bad Request", error(&e)),
+        }
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )
+}
+
+fn error(message: &str) -> String {
+    json::object! { error: message }.dump()
+}
+
+// The outputs for one input vector, or a list of outputs for a list of them
+fn predict(nn: &mut NN, body: &str) -> Result<json::JsonValue, String> {
+    let value = json::parse(body).map_err(|e| e.to_string())?;
+    let row = |nn: &mut NN, value: &json::JsonValue| -> Result<json::JsonValue, String> {
+        let input = value
+            .members()
+            .map(|x| x.as_f32().ok_or_else(|| format!("`{}` is not a number", x)))
+            .collect::<Result<Vec<f32>, String>>()?;
+        let input = Mat {
+            rows: 1,
+            cols: input.len(),
+            data: vec![input],
+        };
+        NN::try_forward(nn, &input).map_err(|e| e.to_string())?;
+        Ok(nn.activations[nn.count - 1].data[0].clone().into())
+    };
+
+    if !value.is_array() {
+        return Err("expected an array of inputs".to_owned());
+    }
+    if value.members().all(|x| x.is_array()) && !value.is_empty() {
+        let rows = value
+            .members()
+            .map(|v| row(nn, v))
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(rows.into())
+    } else {
+        row(nn, &value)
+    }
+}
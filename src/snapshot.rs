@@ -0,0 +1,139 @@
+use std::{mem, sync::Mutex};
+
+use super::{CostHistory, Jump, Renderinfo, Timeline, TrainingState, NN};
+
+// What the training thread hands the render thread after every epoch: the network
+// and the progress that goes with it. Each thread works on its own network and
+// only locks this to swap one in or out, so drawing a frame never holds up an epoch
+// and a slow epoch never holds up a frame.
+#[derive(Debug)]
+pub struct Snapshot {
+    nn: NN,
+    // whether nn is newer than the render thread's copy
+    fresh: bool,
+    pub epoch: i32,
+    pub learning_rate: f32,
+    pub grad_norms: Vec<f32>,
+    pub training_time: f32,
+    pub paused: bool,
+    pub diverged: bool,
+    // epoch and cost at which early stopping ended the training
+    pub converged: Option<(i32, f32)>,
+    // the cost jump training paused at, taken by the render thread
+    pub jump: Option<Jump>,
+    // validation costs not taken by the render thread yet
+    pub validation: Vec<(i32, f32)>,
+    // and the spread of the population's costs, see Evolution::spread
+    pub population: Vec<(i32, [f32; 5])>,
+    // frames drawn so far, for the epochs per frame cap
+    pub frame: usize,
+    // epoch training went back to with Signal::Rewind, the render thread drops the
+    // history after it
+    pub rewound: Option<i32>,
+    // saved with a loaded network, its cost history replaces the render thread's
+    pub restored: Option<TrainingState>,
+}
+
+impl Snapshot {
+    pub fn new(nn: NN, learning_rate: f32) -> Snapshot {
+        Snapshot {
+            nn,
+            fresh: false,
+            epoch: 0,
+            learning_rate,
+            grad_norms: vec![],
+            training_time: 0.0,
+            paused: false,
+            diverged: false,
+            converged: None,
+            jump: None,
+            validation: vec![],
+            population: vec![],
+            frame: 0,
+            rewound: None,
+            restored: None,
+        }
+    }
+
+    // Training thread: swaps in a copy of `nn` and updates the progress. The copy is
+    // made before locking and the old one dropped after.
+    pub fn publish(shared: &Mutex<Snapshot>, nn: &NN, update: impl FnOnce(&mut Snapshot)) {
+        let mut copy = nn.clone();
+        {
+            let mut snapshot = shared.lock().unwrap();
+            mem::swap(&mut snapshot.nn, &mut copy);
+            snapshot.fresh = true;
+            update(&mut snapshot);
+        }
+    }
+
+    // Swaps the newest network into `nn`, if there is one
+    pub fn swap(snapshot: &mut Snapshot, nn: &mut NN) {
+        if snapshot.fresh {
+            mem::swap(&mut snapshot.nn, nn);
+            snapshot.fresh = false;
+        }
+    }
+
+    // Render thread: swaps the newest network into `nn` (if there is one) and copies
+    // the progress into info
+    pub fn take(shared: &Mutex<Snapshot>, nn: &mut NN, info: &mut Renderinfo) {
+        let mut snapshot = shared.lock().unwrap();
+        Self::swap(&mut snapshot, nn);
+        if let Some(epoch) = snapshot.rewound.take() {
+            let history = &mut info.cost_history;
+            for points in [
+                &mut history.train,
+                &mut history.validation,
+                &mut history.accuracy,
+                &mut history.rate,
+            ] {
+                points.retain(|&(e, _)| e <= epoch);
+            }
+            for points in &mut history.norms {
+                points.retain(|&(e, _)| e <= epoch);
+            }
+            history.population.retain(|&(e, _)| e <= epoch);
+            Timeline::rewind(&mut info.timeline, epoch);
+            info.converged = None;
+            info.jump = None;
+        }
+        if let Some(state) = snapshot.restored.take() {
+            let points = |points: &[(usize, f32)]| {
+                points.iter().map(|&(e, cost)| (e as i32, cost)).collect()
+            };
+            info.cost_history = CostHistory {
+                train: points(&state.history),
+                validation: points(&state.validation),
+                accuracy: vec![],
+                compare: mem::take(&mut info.cost_history.compare),
+                rate: vec![],
+                norms: vec![],
+                population: vec![],
+            };
+            Timeline::clear(&mut info.timeline);
+            info.converged = None;
+            info.jump = None;
+        }
+        info.epoch = snapshot.epoch;
+        info.learning_rate = snapshot.learning_rate;
+        info.grad_norms.clone_from(&snapshot.grad_norms);
+        info.training_time = snapshot.training_time;
+        info.paused = snapshot.paused;
+        info.diverged = snapshot.diverged;
+        if let Some(jump) = snapshot.jump.take() {
+            info.jump = Some(jump);
+        }
+        if let Some((epoch, cost)) = snapshot.converged {
+            info.converged = Some(epoch);
+            info.cost = cost;
+        }
+        info.cost_history
+            .validation
+            .append(&mut snapshot.validation);
+        info.cost_history
+            .population
+            .append(&mut snapshot.population);
+        snapshot.frame = info.frame;
+    }
+}
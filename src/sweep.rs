@@ -0,0 +1,264 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+    time::Instant,
+};
+
+use super::{
+    batches, build_nn, fit_data, is_classification, load_dataset, rand_float, random_seed,
+    seeded_rng, split, Config, Dataset, EarlyStopping, Init, Mat, Optimizer, Schedule, StdRng,
+    INIT, MIN_DELTA, NN, PATIENCE, TARGET_COST,
+};
+
+// What `nn-rust sweep` tries: every combination of the lists, or `random` runs with a
+// log-uniform rate between the smallest and largest one and the rest picked from the lists
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sweep {
+    pub rates: Vec<f32>,
+    // hidden layers only, the dataset decides the input and output sizes
+    pub hidden: Vec<Vec<usize>>,
+    // 0 trains on the whole set at once
+    pub batch_sizes: Vec<usize>,
+    pub random: Option<usize>,
+    pub threads: usize,
+}
+
+#[derive(Clone, Debug)]
+struct Run {
+    rate: f32,
+    hidden: Vec<usize>,
+    batch_size: usize,
+}
+
+struct Outcome {
+    run: Run,
+    trained: Trained,
+    seconds: f32,
+}
+
+// How a network of `train` ended up, the costs per sample
+pub struct Trained {
+    pub epochs: i32,
+    // None when the weights became NaN/inf
+    pub cost: Option<f32>,
+    pub validation: Option<f32>,
+    // on the validation samples, on the training samples without any
+    pub accuracy: Option<f32>,
+}
+
+impl Sweep {
+    fn runs(sweep: &Sweep, rng: &mut StdRng) -> Vec<Run> {
+        if let Some(n) = sweep.random {
+            let (min, max) = sweep
+                .rates
+                .iter()
+                .fold((f32::INFINITY, 0.0f32), |(min, max), &r| {
+                    (min.min(r), max.max(r))
+                });
+            let pick = |rng: &mut StdRng, len: usize| {
+                (rand_float(rng, 0.0, len as f32) as usize).min(len - 1)
+            };
+            return (0..n)
+                .map(|_| Run {
+                    // a single rate is all there is to pick
+                    rate: if min < max {
+                        rand_float(rng, min.ln(), max.ln()).exp()
+                    } else {
+                        min
+                    },
+                    hidden: sweep.hidden[pick(rng, sweep.hidden.len())].clone(),
+                    batch_size: sweep.batch_sizes[pick(rng, sweep.batch_sizes.len())],
+                })
+                .collect();
+        }
+
+        let mut runs = vec![];
+        for hidden in &sweep.hidden {
+            for &batch_size in &sweep.batch_sizes {
+                for &rate in &sweep.rates {
+                    runs.push(Run {
+                        rate,
+                        hidden: hidden.clone(),
+                        batch_size,
+                    });
+                }
+            }
+        }
+        runs
+    }
+}
+
+// `sweep`: trains every run of the sweep without a window, `threads` at a time, on the
+// configured dataset with the same seed, then prints them best first
+pub fn run(config: &Config, sweep: &Sweep) {
+    let seed = config.seed.unwrap_or_else(random_seed);
+    let mut rng = seeded_rng(seed);
+    let (dataset_name, dataset, _) =
+        load_dataset(Config::source(config).as_ref(), &[], &mut rng, &mut None);
+    let runs = Sweep::runs(sweep, &mut rng);
+    println!(
+        "Sweeping {} runs of {} epochs on {} with seed {}, {} at a time",
+        runs.len(),
+        config.epochs,
+        dataset_name,
+        seed,
+        sweep.threads
+    );
+
+    let next = AtomicUsize::new(0);
+    let outcomes = Mutex::new(vec![]);
+    let start = Instant::now();
+    thread::scope(|s| {
+        for _ in 0..sweep.threads.max(1) {
+            s.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(run) = runs.get(i) else {
+                    break;
+                };
+                let outcome = sweep_run(config, &dataset, run.clone(), seed);
+                println!("{:>4}/{} {}", i + 1, runs.len(), describe(&outcome));
+                outcomes.lock().unwrap().push(outcome);
+            });
+        }
+    });
+
+    let mut outcomes = outcomes.into_inner().unwrap();
+    // diverged runs last
+    outcomes.sort_by(|a, b| {
+        let cost = |o: &Outcome| {
+            let trained = &o.trained;
+            trained.validation.or(trained.cost).unwrap_or(f32::INFINITY)
+        };
+        cost(a).total_cmp(&cost(b))
+    });
+    println!();
+    println!(
+        "{:>8} {:>12} {:>6} {:>7} {:>12} {:>12} {:>9} {:>8}",
+        "rate", "hidden", "batch", "epochs", "cost", "validation", "accuracy", "time"
+    );
+    for o in &outcomes {
+        let cost = |c: Option<f32>| c.map_or("-".to_owned(), |c| format!("{:.6}", c));
+        let trained = &o.trained;
+        println!(
+            "{:>8.4} {:>12} {:>6} {:>7} {:>12} {:>12} {:>9} {:>7.2}s",
+            o.run.rate,
+            format!("{:?}", o.run.hidden),
+            o.run.batch_size,
+            trained.epochs,
+            if trained.cost.is_some() {
+                cost(trained.cost)
+            } else {
+                "diverged".to_owned()
+            },
+            cost(trained.validation),
+            trained
+                .accuracy
+                .map_or("-".to_owned(), |a| format!("{:.1}%", a * 100.0)),
+            o.seconds
+        );
+    }
+    println!("Sweep took {:.2}s", start.elapsed().as_secs_f32());
+}
+
+fn describe(o: &Outcome) -> String {
+    format!(
+        "rate {:.4}, hidden {:?}, batch {}: {}",
+        o.run.rate,
+        o.run.hidden,
+        o.run.batch_size,
+        o.trained
+            .cost
+            .map_or("diverged".to_owned(), |c| format!("cost {}", c))
+    )
+}
+
+// One run of the sweep on the configured dataset
+fn sweep_run(config: &Config, dataset: &Dataset, run: Run, seed: u64) -> Outcome {
+    let start = Instant::now();
+    let mut rng = seeded_rng(seed);
+    let ((t_input, t_output), (v_input, v_output)) = split(
+        &dataset.t_input,
+        &dataset.t_output,
+        config.validation_split,
+        &mut rng,
+    );
+
+    let mut arch = vec![t_input.cols];
+    arch.extend_from_slice(&run.hidden);
+    arch.push(t_output.cols);
+    let config = Config {
+        learning_rate: run.rate,
+        ..config.clone()
+    };
+    let trained = train(
+        &config,
+        &arch,
+        (&t_input, &t_output),
+        (&v_input, &v_output),
+        run.batch_size,
+        seed,
+    );
+    Outcome {
+        run,
+        trained,
+        seconds: start.elapsed().as_secs_f32(),
+    }
+}
+
+// A network trained like `--headless` does it, with early stopping but without saving
+// anything, for `sweep` and `curve`. It stops on the cost per sample, so the stopping
+// point doesn't depend on how many samples there are.
+pub fn train(
+    config: &Config,
+    arch: &[usize],
+    (t_input, t_output): (&Mat, &Mat),
+    (v_input, v_output): (&Mat, &Mat),
+    batch_size: usize,
+    seed: u64,
+) -> Trained {
+    let mut rng = seeded_rng(seed);
+    let (mut nn, mut optimizer) = build_nn(arch, config);
+    fit_data(&mut nn, config, t_input, t_output);
+    Init::apply(INIT, &mut nn, &mut rng);
+    let mut gradient = NN::new(arch);
+    let mut early_stopping = EarlyStopping::new(TARGET_COST, PATIENCE, MIN_DELTA);
+
+    let mut epochs = 0;
+    let mut cost = None;
+    for i in 0..=config.epochs {
+        epochs = i;
+        optimizer.rate = Schedule::rate(&config.schedule, config.learning_rate, i as usize);
+        for (x, y) in batches(t_input, t_output, batch_size, &mut rng) {
+            NN::backprop(&mut nn, &mut gradient, &x, &y, &mut rng);
+            Optimizer::step(&mut optimizer, &mut nn, &gradient);
+        }
+        if !NN::is_finite(&nn) {
+            cost = None;
+            break;
+        }
+        let c = NN::cost(&nn, t_input, t_output) / t_input.rows as f32;
+        cost = Some(c);
+        if EarlyStopping::update(&mut early_stopping, c) {
+            break;
+        }
+    }
+
+    let finite = cost.is_some();
+    // the accuracy of the samples it didn't train on, when there are some
+    let (a_input, a_output) = if v_input.rows > 0 {
+        (v_input, v_output)
+    } else {
+        (t_input, t_output)
+    };
+    Trained {
+        epochs,
+        cost,
+        validation: (finite && v_input.rows > 0)
+            .then(|| NN::cost(&nn, v_input, v_output) / v_input.rows as f32),
+        accuracy: (finite && is_classification(a_output))
+            .then(|| NN::accuracy(&nn, a_input, a_output)),
+    }
+}
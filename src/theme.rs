@@ -0,0 +1,139 @@
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::Path,
+};
+
+use super::Color;
+
+// Colors of every drawn element
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Theme {
+    pub background: Color,
+    pub text: Color,
+    // panel backgrounds and empty cells
+    pub panel: Color,
+    // dropped or input neurons
+    pub inactive: Color,
+    // weights, biases and outputs are shaded from negative (or 0) to positive (or 1)
+    pub positive: Color,
+    pub negative: Color,
+    // cost plot lines
+    pub cost: Color,
+    pub validation: Color,
+    pub accuracy: Color,
+    // cost of the compared run
+    pub compare: Color,
+    // converged / correct and diverged / wrong
+    pub good: Color,
+    pub bad: Color,
+}
+
+const fn rgb(r: u8, g: u8, b: u8) -> Color {
+    Color {
+        r: r as f32 / 255.,
+        g: g as f32 / 255.,
+        b: b as f32 / 255.,
+        a: 1.,
+    }
+}
+
+impl Theme {
+    pub const DARK: Theme = Theme {
+        background: rgb(0, 0, 0),
+        text: rgb(255, 255, 255),
+        panel: Color {
+            r: 0.2,
+            g: 0.2,
+            b: 0.2,
+            a: 0.5,
+        },
+        inactive: rgb(130, 130, 130),
+        positive: rgb(255, 0, 255),
+        negative: rgb(0, 255, 0),
+        cost: rgb(230, 41, 55),
+        validation: rgb(102, 191, 255),
+        accuracy: rgb(255, 203, 0),
+        compare: rgb(200, 122, 255),
+        good: rgb(0, 228, 48),
+        bad: rgb(230, 41, 55),
+    };
+
+    pub const LIGHT: Theme = Theme {
+        background: rgb(245, 245, 245),
+        text: rgb(20, 20, 20),
+        panel: Color {
+            r: 0.8,
+            g: 0.8,
+            b: 0.8,
+            a: 0.6,
+        },
+        inactive: rgb(160, 160, 160),
+        positive: rgb(200, 30, 60),
+        negative: rgb(30, 90, 200),
+        cost: rgb(200, 30, 60),
+        validation: rgb(0, 120, 200),
+        accuracy: rgb(200, 140, 0),
+        compare: rgb(120, 60, 180),
+        good: rgb(0, 150, 40),
+        bad: rgb(200, 30, 60),
+    };
+
+    // JSON object of element names to "#rrggbb" or "#rrggbbaa" colors, e.g.
+    //
+    //     { "background": "#202020", "positive": "#ff8800" }
+    //
+    // Elements that are left out keep their color from `base`.
+    pub fn parse(text: &str, base: Theme) -> io::Result<Theme> {
+        let value = json::parse(text).map_err(|e| invalid(e.to_string()))?;
+        if !value.is_object() {
+            return Err(invalid("expected an object".to_owned()));
+        }
+
+        let mut theme = base;
+        for (name, color) in value.entries() {
+            let color = color
+                .as_str()
+                .and_then(hex)
+                .ok_or_else(|| invalid(format!("`{}` is not a #rrggbb color", name)))?;
+            let element = match name {
+                "background" => &mut theme.background,
+                "text" => &mut theme.text,
+                "panel" => &mut theme.panel,
+                "inactive" => &mut theme.inactive,
+                "positive" => &mut theme.positive,
+                "negative" => &mut theme.negative,
+                "cost" => &mut theme.cost,
+                "validation" => &mut theme.validation,
+                "accuracy" => &mut theme.accuracy,
+                "compare" => &mut theme.compare,
+                "good" => &mut theme.good,
+                "bad" => &mut theme.bad,
+                _ => return Err(invalid(format!("unknown element `{}`", name))),
+            };
+            *element = color;
+        }
+        Ok(theme)
+    }
+
+    pub fn load(path: impl AsRef<Path>, base: Theme) -> io::Result<Theme> {
+        Self::parse(&fs::read_to_string(path)?, base)
+    }
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, msg)
+}
+
+fn hex(s: &str) -> Option<Color> {
+    let digits = s.strip_prefix('#')?;
+    if !digits.is_ascii() || (digits.len() != 6 && digits.len() != 8) {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&digits[i..i + 2], 16).ok();
+    let alpha = if digits.len() == 8 { channel(6)? } else { 255 };
+    Some(Color {
+        a: alpha as f32 / 255.,
+        ..rgb(channel(0)?, channel(2)?, channel(4)?)
+    })
+}
@@ -0,0 +1,111 @@
+use std::collections::VecDeque;
+
+use super::{
+    draw_line, draw_rectangle, draw_text, is_mouse_button_down, is_mouse_button_pressed,
+    mouse_position, vec2, MouseButton, Rect, Theme, NN,
+};
+
+// Copies of the network taken by the render thread every `every` epochs, the oldest
+// dropped past `capacity`. Dragging along the timeline bar picks one to view instead
+// of the live network.
+#[derive(Clone, Debug)]
+pub struct Timeline {
+    snapshots: VecDeque<(i32, NN)>,
+    every: i32,
+    capacity: usize,
+    // index into snapshots, None follows the training
+    pub selected: Option<usize>,
+    drag: bool,
+}
+
+impl Timeline {
+    pub fn new(every: i32, capacity: usize) -> Timeline {
+        Timeline {
+            snapshots: VecDeque::new(),
+            every,
+            capacity,
+            selected: None,
+            drag: false,
+        }
+    }
+
+    // Keeps nn when it's the first one past the next multiple of `every`
+    pub fn record(timeline: &mut Timeline, epoch: i32, nn: &NN) {
+        let due = match timeline.snapshots.back() {
+            Some(&(last, _)) => epoch / timeline.every > last / timeline.every,
+            None => true,
+        };
+        // a diverged network isn't worth going back to
+        if !due || !NN::is_finite(nn) {
+            return;
+        }
+        if timeline.snapshots.len() == timeline.capacity {
+            timeline.snapshots.pop_front();
+            // keep showing the same snapshot
+            timeline.selected = timeline.selected.map(|i| i.saturating_sub(1));
+        }
+        timeline.snapshots.push_back((epoch, nn.clone()));
+    }
+
+    // The epoch and network being viewed, None when it's the live one
+    pub fn selected(timeline: &Timeline) -> Option<&(i32, NN)> {
+        timeline.snapshots.get(timeline.selected?)
+    }
+
+    // For a network that has nothing to do with the snapshots
+    pub fn clear(timeline: &mut Timeline) {
+        timeline.snapshots.clear();
+        timeline.selected = None;
+    }
+
+    // Back to the live network, forgetting the snapshots after `epoch`
+    pub fn rewind(timeline: &mut Timeline, epoch: i32) {
+        timeline.snapshots.retain(|&(e, _)| e <= epoch);
+        timeline.selected = None;
+    }
+
+    // The bar with a tick per snapshot. Dragging on it selects the closest one, right
+    // clicking goes back to the live network.
+    pub fn draw(timeline: &mut Timeline, theme: Theme, rect: Rect) {
+        let (mx, my) = mouse_position();
+        let count = timeline.snapshots.len();
+        let bar = Rect::new(rect.x + 10., rect.y + rect.h - 10., rect.w - 20., 6.);
+        let grab = Rect::new(bar.x - 5., rect.y, bar.w + 10., rect.h);
+        if !is_mouse_button_down(MouseButton::Left) {
+            timeline.drag = false;
+        }
+        if is_mouse_button_pressed(MouseButton::Left) && grab.contains(vec2(mx, my)) {
+            timeline.drag = true;
+        }
+        if is_mouse_button_pressed(MouseButton::Right) && grab.contains(vec2(mx, my)) {
+            timeline.selected = None;
+        }
+        if timeline.drag && count > 0 {
+            let t = ((mx - bar.x) / bar.w).clamp(0., 1.);
+            timeline.selected = Some((t * (count - 1) as f32).round() as usize);
+        }
+
+        draw_rectangle(bar.x, bar.y, bar.w, bar.h, theme.panel);
+        let to_x = |i: usize| {
+            if count < 2 {
+                bar.x + bar.w
+            } else {
+                bar.x + i as f32 / (count - 1) as f32 * bar.w
+            }
+        };
+        for i in 0..count {
+            draw_line(to_x(i), bar.y, to_x(i), bar.y + bar.h, 1., theme.inactive);
+        }
+        let handle = timeline.selected.map_or(bar.x + bar.w, to_x);
+        draw_rectangle(handle - 3., bar.y - 4., 6., bar.h + 8., theme.positive);
+
+        let text = match Self::selected(timeline) {
+            Some((epoch, _)) => format!(
+                "Viewing epoch {} (enter trains on from here, right click goes back)",
+                epoch
+            ),
+            None => format!("Timeline: {} snapshots, drag to look back", count),
+        };
+        draw_text(text.as_str(), bar.x, bar.y - 4., 16., theme.text);
+    }
+}
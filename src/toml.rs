@@ -0,0 +1,241 @@
+use std::{
+    collections::HashMap,
+    io::{self, ErrorKind},
+};
+
+// The part of TOML experiment files need: `[table]` headers, `key = value` lines and
+// # comments, with strings, integers, floats, booleans and (nested) arrays of those on
+// a single line as values. Keys are returned as "table.key".
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Str(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Array(Vec<Value>),
+}
+
+impl Value {
+    pub fn as_f64(value: &Value) -> Option<f64> {
+        match *value {
+            Value::Int(i) => Some(i as f64),
+            Value::Float(f) => Some(f),
+            _ => None,
+        }
+    }
+
+    pub fn as_usize(value: &Value) -> Option<usize> {
+        match *value {
+            Value::Int(i) => usize::try_from(i).ok(),
+            _ => None,
+        }
+    }
+}
+
+fn invalid(line: usize, msg: String) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, format!("line {}: {}", line, msg))
+}
+
+pub fn parse(text: &str) -> io::Result<HashMap<String, Value>> {
+    let mut values = HashMap::new();
+    let mut table = String::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(name) = line.strip_prefix('[') {
+            let name = name
+                .strip_suffix(']')
+                .ok_or_else(|| invalid(i + 1, "missing ] after the table name".to_owned()))?;
+            table = format!("{}.", name.trim());
+            continue;
+        }
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| invalid(i + 1, "expected key = value".to_owned()))?;
+        let key = format!("{}{}", table, key.trim());
+        let value = parse_value(value.trim()).map_err(|msg| invalid(i + 1, msg))?;
+        if values.insert(key.clone(), value).is_some() {
+            return Err(invalid(i + 1, format!("`{}` is set twice", key)));
+        }
+    }
+    Ok(values)
+}
+
+// The items of an array between its brackets, split at the commas that aren't in a
+// string or a nested array. None when a quote or bracket isn't closed.
+fn split_items(inner: &str) -> Option<Vec<&str>> {
+    let mut items = vec![];
+    let (mut in_string, mut depth, mut start) = (false, 0usize, 0);
+    for (i, c) in inner.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            _ if in_string => {}
+            '[' => depth += 1,
+            ']' => depth = depth.checked_sub(1)?,
+            ',' if depth == 0 => {
+                items.push(&inner[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if in_string || depth > 0 {
+        return None;
+    }
+    items.push(&inner[start..]);
+    Some(items)
+}
+
+// Everything from a # that isn't inside a string
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn parse_value(text: &str) -> Result<Value, String> {
+    if let Some(inner) = text.strip_prefix('"') {
+        // no escapes, paths and names don't need them
+        let inner = inner
+            .strip_suffix('"')
+            .ok_or_else(|| format!("unterminated string {}", text))?;
+        if inner.contains('"') {
+            return Err(format!("more than one string in {}", text));
+        }
+        return Ok(Value::Str(inner.to_owned()));
+    }
+    if let Some(inner) = text.strip_prefix('[') {
+        let inner = inner
+            .strip_suffix(']')
+            .ok_or_else(|| format!("unterminated array {}", text))?;
+        return split_items(inner)
+            .ok_or_else(|| format!("unbalanced brackets or quotes in {}", text))?
+            .into_iter()
+            .map(str::trim)
+            // allows a trailing comma
+            .filter(|item| !item.is_empty())
+            .map(parse_value)
+            .collect::<Result<_, _>>()
+            .map(Value::Array);
+    }
+    match text {
+        "true" => return Ok(Value::Bool(true)),
+        "false" => return Ok(Value::Bool(false)),
+        _ => {}
+    }
+    // TOML allows 1_000 for readability
+    let number = text.replace('_', "");
+    if let Ok(i) = number.parse() {
+        return Ok(Value::Int(i));
+    }
+    number
+        .parse()
+        .map(Value::Float)
+        .map_err(|_| format!("can't parse the value {}", text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Config, Method, Schedule};
+    use super::*;
+
+    #[test]
+    fn test_toml_tables() {
+        let values =
+            parse("epochs = 10\n\n[optimizer]\nmethod = \"sgd\"\n[ output ]\nmodel = \"a.json\"")
+                .unwrap();
+        assert_eq!(values["epochs"], Value::Int(10));
+        assert_eq!(values["optimizer.method"], Value::Str("sgd".to_owned()));
+        assert_eq!(values["output.model"], Value::Str("a.json".to_owned()));
+        assert_eq!(values.len(), 3);
+
+        assert!(parse("[optimizer\nmethod = \"sgd\"").is_err());
+        assert!(parse("a = 1\na = 2").is_err());
+    }
+
+    #[test]
+    fn test_toml_comments() {
+        let values = parse("# a comment\nname = \"a # b\" # a comment\nrate = 0.5#").unwrap();
+        assert_eq!(values["name"], Value::Str("a # b".to_owned()));
+        assert_eq!(values["rate"], Value::Float(0.5));
+    }
+
+    #[test]
+    fn test_toml_values() {
+        let values = parse("epochs = 20_000\nrate = 1_0.5\non = true\noff = false").unwrap();
+        assert_eq!(values["epochs"], Value::Int(20000));
+        assert_eq!(values["rate"], Value::Float(10.5));
+        assert_eq!(values["on"], Value::Bool(true));
+        assert_eq!(values["off"], Value::Bool(false));
+        assert!(parse("epochs = twenty").is_err());
+    }
+
+    #[test]
+    fn test_toml_arrays() {
+        let values =
+            parse("layers = [2, 8, 8, 1,]\nnested = [[1, 2], [\"a,]\", 3],]\nempty = []").unwrap();
+        let ints = |xs: &[i64]| Value::Array(xs.iter().map(|&x| Value::Int(x)).collect());
+        assert_eq!(values["layers"], ints(&[2, 8, 8, 1]));
+        assert_eq!(
+            values["nested"],
+            Value::Array(vec![
+                ints(&[1, 2]),
+                Value::Array(vec![Value::Str("a,]".to_owned()), Value::Int(3)]),
+            ])
+        );
+        assert_eq!(values["empty"], Value::Array(vec![]));
+    }
+
+    #[test]
+    fn test_toml_unterminated() {
+        for line in [
+            "name = \"moons",
+            "name = \"moons\" \"",
+            "layers = [2, 8",
+            "layers = [[2, 8]",
+            "layers = [2, 8]]",
+            "layers = [\"2, 8]",
+        ] {
+            assert!(parse(line).is_err(), "{}", line);
+        }
+    }
+
+    #[test]
+    fn test_toml_experiment() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/datasets/experiment.toml");
+        let config = Config::parse_from(["nn-rust", "--config", path]);
+        assert_eq!(
+            config,
+            Config {
+                epochs: 20000,
+                learning_rate: 0.5,
+                layers: Some(vec![2, 8, 8, 1]),
+                dataset: Some("moons".to_owned()),
+                method: Method::Momentum {
+                    momentum: 0.9,
+                    nesterov: true,
+                },
+                // over all epochs without schedule.epochs
+                schedule: Schedule::Cosine {
+                    epochs: 20000,
+                    min_rate: 0.01,
+                },
+                model_path: "moons.json".to_owned(),
+                onnx_path: "moons.onnx".to_owned(),
+                rust_path: "moons.rs".to_owned(),
+                checkpoint_dir: "checkpoints/moons".to_owned(),
+                ..Config::default()
+            }
+        );
+    }
+}
@@ -0,0 +1,547 @@
+#[cfg(target_arch = "wasm32")]
+use std::cell::RefCell;
+use std::sync::{mpsc::channel, Arc, Mutex};
+#[cfg(not(target_arch = "wasm32"))]
+use std::{
+    sync::mpsc::{Receiver, RecvTimeoutError, Sender, TryRecvError},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use super::{
+    batches, date, is_classification, Augment, Checkpointer, Divergence, EarlyStopping, Ema,
+    Evolution, Hyperparams, Jump, Mat, Method, Metrics, Optimizer, OptimizerState, Progress,
+    Schedule, Signal, Snapshot, StdRng, TrainingState, NN, VALIDATION_EVERY,
+};
+
+// Seconds of epochs the epochs per second cap lets training catch up on after it
+// fell behind, the rest are skipped
+const CATCH_UP: f64 = 0.1;
+
+// Everything a training thread owns: its network and optimizer, the samples and what
+// it reports to
+pub struct Trainer {
+    // put in front of what it prints, to tell compared runs apart
+    pub name: String,
+    pub nn: NN,
+    pub optimizer: Optimizer,
+    pub early_stopping: EarlyStopping,
+    // pauses training when the cost jumps
+    pub divergence: Divergence,
+    pub checkpointer: Checkpointer,
+    pub progress: Progress,
+    pub params: Hyperparams,
+    pub rng: StdRng,
+    pub t_input: Mat,
+    pub t_output: Mat,
+    pub v_input: Mat,
+    pub v_output: Mat,
+    pub epochs: i32,
+    pub schedule: Schedule,
+    // moving average of the weights, updated after every step
+    pub ema: Option<Ema>,
+    // publish the average instead of the trained network
+    pub show_ema: bool,
+    // don't print how training went, the TUI shows it (and prints would land in it)
+    pub quiet: bool,
+    // evolve this population instead of training nn by backprop, nn is its best
+    pub evolution: Option<Evolution>,
+    // applied to every batch, the noise follows the params
+    pub augment: Augment,
+}
+
+// The render thread's end of a training thread. The browser has no threads, there
+// the session is trained a slice at a time in the frame loop (see tick).
+pub struct Run {
+    pub snapshot: Arc<Mutex<Snapshot>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    tx: Sender<Signal>,
+    #[cfg(not(target_arch = "wasm32"))]
+    thread: JoinHandle<()>,
+    #[cfg(target_arch = "wasm32")]
+    session: RefCell<Session>,
+}
+
+impl Run {
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn send(run: &Run, signal: Signal) {
+        // only fails once the thread was stopped
+        let _ = run.tx.send(signal);
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn send(run: &Run, signal: Signal) {
+        Session::handle(&mut run.session.borrow_mut(), &run.snapshot, signal);
+    }
+
+    // Waits for the thread to finish, the next run may replace the network with one
+    // of another shape
+    pub fn stop(run: Run) {
+        Self::send(&run, Signal::Stop);
+        #[cfg(not(target_arch = "wasm32"))]
+        let _ = run.thread.join();
+    }
+
+    // The trained network, the epoch training would continue with and the optimizer
+    // state, None when the training thread doesn't answer
+    pub fn state(run: &Run) -> Option<(NN, usize, OptimizerState)> {
+        let (reply, answer) = channel();
+        Self::send(run, Signal::Save(reply));
+        // answered right away without a thread
+        #[cfg(target_arch = "wasm32")]
+        return answer.try_recv().ok();
+        #[cfg(not(target_arch = "wasm32"))]
+        answer.recv_timeout(Duration::from_secs(5)).ok()
+    }
+}
+
+// Pause, Resume and Step go to every run
+pub fn broadcast(runs: &[Run], signal: Signal) {
+    for run in runs {
+        Run::send(run, signal.clone());
+    }
+}
+
+pub fn stop_all(runs: Vec<Run>) {
+    for run in runs {
+        Run::stop(run);
+    }
+}
+
+// Starts training on a new thread, which publishes its progress to the snapshot after
+// every epoch and takes Signals until Signal::Stop. After the last epoch (or early
+// stopping) it waits for a Signal::Rewind or Signal::Load.
+pub fn spawn(trainer: Trainer) -> Run {
+    let snapshot = Arc::new(Mutex::new(Snapshot::new(
+        trainer.nn.clone(),
+        trainer.params.learning_rate,
+    )));
+    let session = Session::new(trainer);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let shared = Arc::clone(&snapshot);
+        let (tx, rx) = channel();
+        let thread = thread::spawn(move || train(session, &shared, &rx));
+        Run {
+            snapshot,
+            tx,
+            thread,
+        }
+    }
+    #[cfg(target_arch = "wasm32")]
+    Run {
+        snapshot,
+        session: RefCell::new(session),
+    }
+}
+
+// Trains the runs in turn for up to `seconds`, called once a frame. Only the browser
+// needs it, everywhere else the threads train by themselves.
+pub fn tick(runs: &[Run], seconds: f64) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let end = date::now() + seconds;
+        let mut busy = true;
+        while busy && date::now() < end {
+            busy = false;
+            for run in runs {
+                let mut session = run.session.borrow_mut();
+                if Session::next(&mut session, &run.snapshot) == Next::Epoch {
+                    Session::epoch(&mut session, &run.snapshot);
+                    busy = true;
+                }
+            }
+        }
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    let _ = (runs, seconds);
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn train(mut session: Session, shared: &Mutex<Snapshot>, rx: &Receiver<Signal>) {
+    while !session.stopped {
+        let signal = match Session::next(&mut session, shared) {
+            // Take whatever was sent before training
+            Next::Epoch => match rx.try_recv() {
+                Ok(signal) => signal,
+                Err(TryRecvError::Empty) => {
+                    Session::epoch(&mut session, shared);
+                    continue;
+                }
+                Err(TryRecvError::Disconnected) => break,
+            },
+            // Wait for the next frame or epoch, still listening for signals
+            Next::Wait(seconds) => match rx.recv_timeout(Duration::from_secs_f64(seconds)) {
+                Ok(signal) => signal,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => break,
+            },
+            Next::Signal => match rx.recv() {
+                Ok(signal) => signal,
+                Err(_) => break,
+            },
+        };
+        Session::handle(&mut session, shared, signal);
+    }
+}
+
+// What a session can do next
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Next {
+    Epoch,
+    // the epochs per frame or per second cap allows the next one in this many seconds
+    Wait(f64),
+    // paused or done training
+    Signal,
+}
+
+// A Trainer part way through training, advanced one epoch at a time by the thread or
+// the frame loop
+struct Session {
+    trainer: Trainer,
+    gradient: NN,
+    // date::now() when training started
+    start: f64,
+    training_time: f32,
+    // frame the epochs_per_frame count is for and the epochs trained in it
+    frame: usize,
+    trained: usize,
+    // when the epochs_per_second cap allows the next epoch
+    next_epoch: f64,
+    // Epochs left to step through while paused, Some(0) waits for a signal
+    steps: Option<u32>,
+    // Next epoch to train, Signal::Rewind moves it back
+    i: i32,
+    // Converged, diverged or out of epochs, only a rewind or a load trains again
+    done: bool,
+    stopped: bool,
+}
+
+impl Session {
+    fn new(trainer: Trainer) -> Session {
+        Session {
+            gradient: NN::new(&NN::arch(&trainer.nn)),
+            trainer,
+            start: date::now(),
+            training_time: 0.0,
+            frame: 0,
+            trained: 0,
+            next_epoch: date::now(),
+            steps: None,
+            i: 0,
+            done: false,
+            stopped: false,
+        }
+    }
+
+    fn next(s: &mut Session, shared: &Mutex<Snapshot>) -> Next {
+        if s.stopped || s.done {
+            return Next::Signal;
+        }
+        if s.steps == Some(0) {
+            shared.lock().unwrap().paused = true;
+            return Next::Signal;
+        }
+        if s.i > s.trainer.epochs {
+            Self::finish(s);
+            return Next::Signal;
+        }
+
+        let params = s.trainer.params;
+        let frame_done = params.epochs_per_frame > 0 && {
+            let drawn = shared.lock().unwrap().frame;
+            if drawn != s.frame {
+                s.frame = drawn;
+                s.trained = 0;
+            }
+            s.trained >= params.epochs_per_frame
+        };
+        let now = date::now();
+        if frame_done {
+            Next::Wait(0.001)
+        } else if params.epochs_per_second > 0. && s.next_epoch > now {
+            Next::Wait(s.next_epoch - now)
+        } else {
+            Next::Epoch
+        }
+    }
+
+    fn finish(s: &mut Session) {
+        s.done = true;
+        say(&s.trainer, format!("Training time: {}", s.training_time));
+    }
+
+    fn handle(s: &mut Session, shared: &Mutex<Snapshot>, signal: Signal) {
+        let paused = s.steps == Some(0);
+        match signal {
+            // Waits in next()
+            Signal::Pause => s.steps = Some(0),
+            Signal::Resume => s.steps = None,
+            Signal::Stop => s.stopped = true,
+            Signal::Set(new) => s.trainer.params = new,
+            Signal::Step(n) => match &mut s.steps {
+                Some(0) if n > 0 => s.steps = Some(n),
+                // Pressed again before the last steps were done
+                Some(left) => *left += n,
+                None => {}
+            },
+            Signal::Load(loaded, state) => {
+                s.trainer.nn = loaded;
+                Self::reset_ema(s);
+                Divergence::reset(&mut s.trainer.divergence);
+                // a network swapped in mid training keeps the early stopping counts
+                let restart = s.done || state.is_some();
+                match state {
+                    Some(state) => resume(shared, s, state),
+                    None if s.done => rewind(shared, &s.trainer.nn, s.i - 1),
+                    None => Snapshot::publish(shared, &s.trainer.nn, |_| {}),
+                }
+                if restart {
+                    reset(&mut s.trainer.early_stopping);
+                }
+                s.done = false;
+            }
+            Signal::Rewind(epoch, rewound) => {
+                s.trainer.nn = rewound;
+                Self::reset_ema(s);
+                Divergence::reset(&mut s.trainer.divergence);
+                s.i = epoch + 1;
+                rewind(shared, &s.trainer.nn, epoch);
+                reset(&mut s.trainer.early_stopping);
+                if s.done {
+                    say(&s.trainer, format!("Rewound to epoch {}", epoch));
+                }
+                s.done = false;
+            }
+            Signal::ShowEma(show) => {
+                s.trainer.show_ema = show;
+                Snapshot::publish(shared, Self::shown(&s.trainer), |_| {});
+            }
+            Signal::Prune(fraction) => {
+                if fraction > 0.0 {
+                    NN::prune(&mut s.trainer.nn, fraction);
+                } else {
+                    NN::unprune(&mut s.trainer.nn);
+                }
+                Self::reset_ema(s);
+                Divergence::reset(&mut s.trainer.divergence);
+                // a finished run fine-tunes what's left, like after a load
+                if s.done {
+                    rewind(shared, &s.trainer.nn, s.i - 1);
+                    reset(&mut s.trainer.early_stopping);
+                    s.done = false;
+                } else {
+                    Snapshot::publish(shared, &s.trainer.nn, |_| {});
+                }
+            }
+            Signal::Save(reply) => {
+                let state = Optimizer::state(&s.trainer.optimizer).clone();
+                let _ = reply.send((s.trainer.nn.clone(), s.i as usize, state));
+            }
+        }
+        // So the epochs stepped through reach the cost history
+        if paused && s.steps != Some(0) {
+            shared.lock().unwrap().paused = false;
+        }
+    }
+
+    // The network the render thread gets, the moving average once there is one and
+    // it's shown
+    fn shown(t: &Trainer) -> &NN {
+        match &t.ema {
+            Some(Ema {
+                nn: Some(average), ..
+            }) if t.show_ema => average,
+            _ => &t.nn,
+        }
+    }
+
+    fn reset_ema(s: &mut Session) {
+        if let Some(ema) = &mut s.trainer.ema {
+            Ema::reset(ema);
+        }
+    }
+
+    // Trains epoch i and publishes the result
+    fn epoch(s: &mut Session, shared: &Mutex<Snapshot>) {
+        let i = s.i;
+        let params = s.trainer.params;
+        s.trained += 1;
+        if let Some(left) = &mut s.steps {
+            *left -= 1;
+        }
+        if params.epochs_per_second > 0. {
+            // Epochs that fell behind are caught up on right away, but at most
+            // CATCH_UP seconds of them so a pause or a stall isn't followed by a burst
+            s.next_epoch =
+                s.next_epoch.max(date::now() - CATCH_UP) + 1. / params.epochs_per_second as f64;
+        }
+
+        let t = &mut s.trainer;
+        let gradient = &mut s.gradient;
+        t.optimizer.rate = Schedule::rate(&t.schedule, params.learning_rate, i as usize);
+        if let Method::Momentum { momentum, .. } = &mut t.optimizer.method {
+            *momentum = params.momentum;
+        }
+
+        t.augment.noise = params.noise;
+
+        let nn = &mut t.nn;
+        if let Some(evolution) = &mut t.evolution {
+            // a generation is an epoch
+            Evolution::generation(evolution, &t.t_input, &t.t_output, &mut t.rng);
+            nn.clone_from(Evolution::best(evolution));
+        } else {
+            for (x, y) in batches(&t.t_input, &t.t_output, params.batch_size, &mut t.rng) {
+                let x = if Augment::is_off(&t.augment) {
+                    x
+                } else {
+                    Augment::apply(&t.augment, &x, &mut t.rng)
+                };
+                NN::backprop(nn, gradient, &x, &y, &mut t.rng);
+                Optimizer::step(&mut t.optimizer, nn, gradient);
+                if let Some(ema) = &mut t.ema {
+                    Ema::update(ema, nn);
+                }
+            }
+        }
+        let training_time = (date::now() - s.start) as f32;
+        s.training_time = training_time;
+
+        // without backprop there is no gradient to blame
+        let culprit = |norms: &[f32]| {
+            t.evolution
+                .is_none()
+                .then(|| Divergence::worst_layer(norms))
+                .flatten()
+        };
+        if !NN::is_finite(nn) {
+            let mut snapshot = shared.lock().unwrap();
+            snapshot.diverged = true;
+            // the gradient that blew up, for the diagram
+            snapshot.grad_norms = NN::layer_norms(gradient);
+            snapshot.jump = Some(Jump {
+                epoch: i,
+                factor: f32::INFINITY,
+                layer: culprit(&snapshot.grad_norms),
+            });
+            drop(snapshot);
+            say(t, format!("Diverged at epoch {}", i));
+            Self::finish(s);
+            return;
+        }
+        let cost = NN::cost(nn, &t.t_input, &t.t_output);
+
+        if let Err(e) = Checkpointer::update(&mut t.checkpointer, nn, i as usize, cost) {
+            // nn still borrows the trainer
+            if !t.quiet {
+                println!("{}Checkpoint at epoch {} failed: {}", t.name, i, e);
+            }
+        }
+
+        if Progress::due(&t.progress, i as usize) {
+            let metrics = Metrics {
+                epoch: i as usize,
+                cost,
+                accuracy: is_classification(&t.t_output)
+                    .then(|| NN::accuracy(nn, &t.t_input, &t.t_output)),
+                learning_rate: t.optimizer.rate,
+                time: training_time,
+            };
+            Progress::log(&mut t.progress, &metrics, &NN::layer_norms(gradient));
+        }
+
+        let validation = (t.v_input.rows > 0 && i % VALIDATION_EVERY == 0)
+            .then(|| NN::cost(nn, &t.v_input, &t.v_output) / t.v_input.rows as f32);
+        let converged = EarlyStopping::update(&mut t.early_stopping, cost);
+        let grad_norms = NN::layer_norms(gradient);
+        let jump = Divergence::update(&mut t.divergence, cost).map(|factor| Jump {
+            epoch: i,
+            factor,
+            layer: culprit(&grad_norms),
+        });
+
+        Snapshot::publish(shared, Self::shown(t), |snapshot| {
+            snapshot.epoch = i;
+            snapshot.learning_rate = t.optimizer.rate;
+            snapshot.grad_norms = grad_norms;
+            if jump.is_some() {
+                snapshot.jump = jump;
+            }
+            snapshot.training_time = training_time;
+            if let Some(evolution) = &t.evolution {
+                let rows = t.t_input.rows as f32;
+                let spread = Evolution::spread(evolution).map(|cost| cost / rows);
+                snapshot.population.push((i, spread));
+            }
+            if let Some(v_cost) = validation {
+                snapshot.validation.push((i, v_cost));
+            }
+            if converged {
+                snapshot.converged = Some((i, cost));
+            }
+        });
+        s.i += 1;
+        if let Some(jump) = jump {
+            // waits in next() like Signal::Pause, for a lower rate and Signal::Resume
+            s.steps = Some(0);
+            let cost = if jump.factor.is_finite() {
+                format!("Cost jumped {:.1}x to {}", jump.factor, cost)
+            } else {
+                format!("Cost became {}", cost)
+            };
+            say(
+                &s.trainer,
+                format!("{} at epoch {}, paused: lower the learning rate", cost, i),
+            );
+        }
+        if converged {
+            say(
+                &s.trainer,
+                format!("Converged at epoch {} with cost {}", i, cost),
+            );
+            Self::finish(s);
+        }
+    }
+}
+
+fn say(t: &Trainer, message: String) {
+    if !t.quiet {
+        println!("{}{}", t.name, message);
+    }
+}
+
+// Forgets the costs seen so far, so a rewound or loaded network isn't stopped right away
+fn reset(early_stopping: &mut EarlyStopping) {
+    *early_stopping = EarlyStopping::new(
+        early_stopping.target_cost,
+        early_stopping.patience,
+        early_stopping.min_delta,
+    );
+}
+
+// Continues a loaded network from the epoch and optimizer state saved with it
+fn resume(shared: &Mutex<Snapshot>, s: &mut Session, state: Box<TrainingState>) {
+    s.i = state.epoch as i32;
+    Optimizer::restore(&mut s.trainer.optimizer, state.optimizer.clone());
+    let epoch = (s.i - 1).max(0);
+    Snapshot::publish(shared, &s.trainer.nn, |snapshot| {
+        snapshot.epoch = epoch;
+        snapshot.diverged = false;
+        snapshot.converged = None;
+        snapshot.validation.clear();
+        snapshot.restored = Some(*state);
+    });
+}
+
+// Publishes the network rewound to `epoch` and forgets how training ended
+fn rewind(shared: &Mutex<Snapshot>, nn: &NN, epoch: i32) {
+    Snapshot::publish(shared, nn, |snapshot| {
+        snapshot.epoch = epoch;
+        snapshot.diverged = false;
+        snapshot.converged = None;
+        snapshot.validation.retain(|&(e, _)| e <= epoch);
+        snapshot.rewound = Some(epoch);
+    });
+}
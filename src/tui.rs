@@ -0,0 +1,353 @@
+use std::{io, sync::Arc, time::Duration};
+
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Color, Style},
+    text::Line,
+    widgets::{Block, Gauge, Paragraph, Sparkline},
+    DefaultTerminal, Frame,
+};
+
+use super::{
+    batch_size, build_nn, evolution, fit_data, is_classification, load_dataset, network_size,
+    random_seed, save_model, seeded_rng, split, training, Augment, Checkpointer, Config,
+    CostHistory, Divergence, EarlyStopping, Hyperparams, Init, Jump, Mat, Method, MetricsServer,
+    Pixels, Progress, Run, Signal, Snapshot, Trainer, AUGMENT_NOISE, CHECKPOINT_EVERY,
+    EPOCHS_PER_FRAME, IMAGE_SHIFT, INIT, MIN_DELTA, MOMENTUM, NN, PATIENCE, TARGET_COST,
+};
+
+// How long a frame waits for a key before redrawing
+const FRAME: Duration = Duration::from_millis(100);
+// Up and down change the learning rate by this factor
+const RATE_STEP: f32 = 1.5;
+
+const KEYS: &str = "q quit | r reset | p pause | n/b step 1/100 | up/down rate | s save";
+
+// What the terminal shows of a run, from its snapshot and the costs computed here
+struct View {
+    dataset: String,
+    arch: Vec<usize>,
+    seed: u64,
+    // for the costs, the training thread only sends the network
+    t_input: Mat,
+    t_output: Mat,
+    params: Hyperparams,
+    paused: bool,
+    epoch: i32,
+    epochs: i32,
+    cost: f32,
+    accuracy: Option<f32>,
+    learning_rate: f32,
+    training_time: f32,
+    diverged: bool,
+    converged: Option<i32>,
+    // the cost jump training paused at, until p resumes
+    jump: Option<Jump>,
+    history: CostHistory,
+    // the last save or a dataset that couldn't be loaded
+    message: Option<String>,
+}
+
+enum Exit {
+    Quit,
+    Reset,
+}
+
+// `--tui`: the window's training in the terminal, for machines without a display.
+// Trains on the same thread the window uses (see training::spawn) and shows the
+// cost sparkline, the metrics and the keys, until q.
+pub fn run(config: &Config) {
+    let stream = super::progress::stream(config);
+    let mut terminal = ratatui::init();
+    let result = loop {
+        let (run, nn, view) = start(config, stream.clone());
+        // whatever loading the dataset printed
+        if let Err(e) = terminal.clear() {
+            break Err(e);
+        }
+        let exit = show(&mut terminal, config, &run, nn, view);
+        Run::stop(run);
+        match exit {
+            Ok(Exit::Reset) => continue,
+            Ok(Exit::Quit) => break Ok(()),
+            Err(e) => break Err(e),
+        }
+    };
+    ratatui::restore();
+    if let Err(e) = result {
+        println!("The terminal failed: {}", e);
+    }
+}
+
+// Loads the dataset and starts training a new network on it, like a reset of the window
+fn start(config: &Config, stream: Option<Arc<MetricsServer>>) -> (Run, NN, View) {
+    let seed = config.seed.unwrap_or_else(random_seed);
+    let mut rng = seeded_rng(seed);
+    let mut error = None;
+    let (dataset_name, dataset, pixels) =
+        load_dataset(Config::source(config).as_ref(), &[], &mut rng, &mut error);
+    let (t_input, t_output) = (dataset.t_input, dataset.t_output);
+    let (hidden_layers, dataset_batch) = network_size(pixels.as_ref());
+
+    let mut arch = vec![t_input.cols];
+    arch.extend_from_slice(Config::hidden(config).as_deref().unwrap_or(hidden_layers));
+    arch.push(t_output.cols);
+    Config::check_layers(config, &arch);
+    let (mut nn, optimizer) = build_nn(&arch, config);
+
+    let ((t_input, t_output), (v_input, v_output)) =
+        split(&t_input, &t_output, config.validation_split, &mut rng);
+    fit_data(&mut nn, config, &t_input, &t_output);
+    Init::apply(INIT, &mut nn, &mut rng);
+
+    let params = Hyperparams {
+        learning_rate: config.learning_rate,
+        momentum: match config.method {
+            Method::Momentum { momentum, .. } => momentum,
+            _ => MOMENTUM,
+        },
+        batch_size: batch_size(config, dataset_batch, t_input.rows),
+        epochs_per_frame: EPOCHS_PER_FRAME,
+        epochs_per_second: config.epochs_per_second,
+        noise: AUGMENT_NOISE,
+    };
+    let cost = NN::cost(&nn, &t_input, &t_output);
+    let view = View {
+        dataset: dataset_name,
+        arch,
+        seed,
+        t_input: t_input.clone(),
+        t_output: t_output.clone(),
+        params,
+        paused: false,
+        epoch: 0,
+        epochs: config.epochs,
+        cost,
+        accuracy: None,
+        learning_rate: config.learning_rate,
+        training_time: 0.0,
+        diverged: false,
+        converged: None,
+        jump: None,
+        history: CostHistory {
+            train: vec![(0, cost / t_input.rows as f32)],
+            ..CostHistory::default()
+        },
+        message: error,
+    };
+    let evolution = evolution(config, &nn, &mut rng);
+    let run = training::spawn(Trainer {
+        name: String::new(),
+        nn: nn.clone(),
+        optimizer,
+        early_stopping: EarlyStopping::new(TARGET_COST, PATIENCE, MIN_DELTA),
+        divergence: Divergence::new(config.divergence_factor),
+        checkpointer: Checkpointer::new(&config.checkpoint_dir, CHECKPOINT_EVERY, true),
+        progress: Progress::new(config, stream),
+        params,
+        rng,
+        t_input,
+        t_output,
+        v_input,
+        v_output,
+        epochs: config.epochs,
+        schedule: config.schedule,
+        ema: None,
+        show_ema: false,
+        quiet: true,
+        evolution,
+        augment: Augment {
+            noise: AUGMENT_NOISE,
+            image: match pixels {
+                Some(Pixels::Inputs(width, height)) => Some((width, height)),
+                _ => None,
+            },
+            shift: IMAGE_SHIFT,
+        },
+    });
+    (run, nn, view)
+}
+
+fn show(
+    terminal: &mut DefaultTerminal,
+    config: &Config,
+    run: &Run,
+    mut nn: NN,
+    mut view: View,
+) -> io::Result<Exit> {
+    let mut frame = 0;
+    loop {
+        {
+            let mut snapshot = run.snapshot.lock().unwrap();
+            Snapshot::swap(&mut snapshot, &mut nn);
+            view.epoch = snapshot.epoch;
+            view.learning_rate = snapshot.learning_rate;
+            view.training_time = snapshot.training_time;
+            view.diverged = snapshot.diverged;
+            view.converged = snapshot.converged.map(|(epoch, _)| epoch);
+            if let Some(jump) = snapshot.jump.take() {
+                // training paused itself
+                view.paused = !snapshot.diverged;
+                view.jump = Some(jump);
+            }
+            view.history.validation.append(&mut snapshot.validation);
+            snapshot.frame = frame;
+        }
+        frame += 1;
+        let recorded = view.history.train.last().map_or(-1, |&(epoch, _)| epoch);
+        if view.epoch > recorded {
+            view.cost = NN::cost(&nn, &view.t_input, &view.t_output);
+            if view.cost.is_finite() {
+                let per_sample = view.cost / view.t_input.rows as f32;
+                view.history.train.push((view.epoch, per_sample));
+            }
+            view.accuracy = is_classification(&view.t_output)
+                .then(|| NN::accuracy(&nn, &view.t_input, &view.t_output));
+        }
+
+        terminal.draw(|f| draw(f, &view))?;
+
+        if !event::poll(FRAME)? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(Exit::Quit),
+            KeyCode::Char('r') => return Ok(Exit::Reset),
+            KeyCode::Char('p') => {
+                view.paused = !view.paused;
+                view.jump = None;
+                Run::send(
+                    run,
+                    if view.paused {
+                        Signal::Pause
+                    } else {
+                        Signal::Resume
+                    },
+                );
+            }
+            KeyCode::Char('n') if view.paused => Run::send(run, Signal::Step(1)),
+            KeyCode::Char('b') if view.paused => Run::send(run, Signal::Step(100)),
+            KeyCode::Up | KeyCode::Down => {
+                if key.code == KeyCode::Up {
+                    view.params.learning_rate *= RATE_STEP;
+                } else {
+                    view.params.learning_rate /= RATE_STEP;
+                }
+                Run::send(run, Signal::Set(view.params));
+            }
+            KeyCode::Char('s') => {
+                view.message = Some(save_model(&nn, run, &view.history, &config.model_path));
+            }
+            _ => {}
+        }
+    }
+}
+
+// The run at the top, the metrics and the progress through the epochs under it, the
+// cost sparkline in the rest and the keys at the bottom
+fn draw(frame: &mut Frame, view: &View) {
+    let [title, metrics, progress, costs, keys] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Length(5),
+        Constraint::Length(1),
+        Constraint::Min(3),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    frame.render_widget(
+        Line::from(format!(
+            "nn-rust | {} | {:?} | seed {}",
+            view.dataset, view.arch, view.seed
+        )),
+        title,
+    );
+
+    let state = if view.diverged {
+        "diverged - lower the rate and reset".to_owned()
+    } else if let Some(epoch) = view.converged {
+        format!("converged at epoch {}", epoch)
+    } else if let Some(jump) = view.jump {
+        let cost = if jump.factor.is_finite() {
+            format!("the cost jumped {:.1}x", jump.factor)
+        } else {
+            "the cost became NaN/inf".to_owned()
+        };
+        let layer = jump.layer.map_or(String::new(), |i| {
+            format!(", largest gradient into layer {}", i + 1)
+        });
+        format!(
+            "paused, {} at epoch {}{} - lower the rate, p resumes",
+            cost, jump.epoch, layer
+        )
+    } else if view.paused {
+        "paused".to_owned()
+    } else if view.epoch >= view.epochs {
+        "done".to_owned()
+    } else {
+        "training".to_owned()
+    };
+    let mut lines = vec![
+        Line::from(format!("cost       {:.6}", view.cost)),
+        Line::from(format!(
+            "rate       {:.5} (base {:.4})",
+            view.learning_rate, view.params.learning_rate
+        )),
+        Line::from(format!(
+            "time       {:.1}s, {:.0} epochs/s",
+            view.training_time,
+            view.epoch as f32 / view.training_time.max(1e-6)
+        )),
+    ];
+    let mut scores = vec![];
+    if let Some(accuracy) = view.accuracy {
+        scores.push(format!("accuracy {:.1}%", accuracy * 100.));
+    }
+    if let Some(&(_, cost)) = view.history.validation.last() {
+        scores.push(format!("validation {:.6}", cost));
+    }
+    if !scores.is_empty() {
+        lines.push(Line::from(scores.join(" | ")));
+    }
+    lines.push(Line::from(state));
+    frame.render_widget(Paragraph::new(lines), metrics);
+
+    let done = (view.epoch.max(0) as f64 / view.epochs.max(1) as f64).min(1.);
+    frame.render_widget(
+        Gauge::default()
+            .gauge_style(Style::default().fg(Color::Green))
+            .ratio(done)
+            .label(format!("epoch {}/{}", view.epoch, view.epochs)),
+        progress,
+    );
+
+    // the latest costs, one per column, scaled to the highest of them
+    let width = costs.width.saturating_sub(2) as usize;
+    let shown = &view.history.train[view.history.train.len().saturating_sub(width)..];
+    let max = shown.iter().map(|&(_, cost)| cost).fold(0., f32::max);
+    let data: Vec<u64> = shown
+        .iter()
+        .map(|&(_, cost)| (cost / max.max(f32::MIN_POSITIVE) * 1000.) as u64)
+        .collect();
+    frame.render_widget(
+        Sparkline::default()
+            .block(Block::bordered().title(format!("cost (max {:.6})", max)))
+            .style(Style::default().fg(Color::Yellow))
+            .max(1000)
+            .data(&data),
+        costs,
+    );
+
+    let bottom = match &view.message {
+        Some(message) => format!("{} | {}", message, KEYS),
+        None => KEYS.to_owned(),
+    };
+    frame.render_widget(Line::from(bottom), keys);
+}